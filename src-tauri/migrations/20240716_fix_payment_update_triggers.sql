@@ -0,0 +1,311 @@
+-- =====================================================
+-- PAYMENT UPDATE FIXES
+-- create_invoice links sale payments to the invoice through
+-- payments.invoice_id as well as invoice_sales, so the direct
+-- invoice sum must skip payments already counted via their sale.
+-- Editing a payment must also respect the same reference rules
+-- as inserting one.
+-- =====================================================
+
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_insert;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_update;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_delete;
+DROP TRIGGER IF EXISTS update_invoice_status_after_sale_update;
+DROP TRIGGER IF EXISTS validate_payment_references_on_update;
+
+-- =====================================================
+-- VALIDATION TRIGGER - Prevent Invalid References On Edit
+-- =====================================================
+
+CREATE TRIGGER validate_payment_references_on_update
+BEFORE UPDATE OF sale_id, invoice_id ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT OLD.sale_id OR NEW.invoice_id IS NOT OLD.invoice_id
+BEGIN
+  -- Validate a changed sale_id exists
+  SELECT CASE
+    WHEN NEW.sale_id IS NOT NULL AND NEW.sale_id IS NOT OLD.sale_id AND 
+         (SELECT COUNT(*) FROM sales WHERE id = NEW.sale_id) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent sale')
+  END;
+  
+  -- Validate a changed invoice_id exists and is not deleted
+  SELECT CASE
+    WHEN NEW.invoice_id IS NOT NULL AND NEW.invoice_id IS NOT OLD.invoice_id AND 
+         (SELECT COUNT(*) FROM invoices WHERE id = NEW.invoice_id AND is_deleted = 0) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent or deleted invoice')
+  END;
+END;
+
+-- =====================================================
+-- INVOICE PAYMENT STATUS TRIGGERS
+-- Handles both sale-linked and direct invoice payments
+-- =====================================================
+
+-- Trigger: After INSERT on payments - Update Invoice Status
+CREATE TRIGGER update_invoice_payment_status_after_payment_insert
+AFTER INSERT ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices linked through sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          -- Sum of payments through linked sales
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          -- Plus direct payments to invoice
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = NEW.sale_id
+    UNION
+    SELECT NEW.invoice_id
+    WHERE NEW.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+-- Trigger: After UPDATE on payments - Update Invoice Status
+CREATE TRIGGER update_invoice_payment_status_after_payment_update
+AFTER UPDATE ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR OLD.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices for all affected cases
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id IN (NEW.sale_id, OLD.sale_id)
+    UNION
+    SELECT NEW.invoice_id WHERE NEW.invoice_id IS NOT NULL
+    UNION
+    SELECT OLD.invoice_id WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+-- Trigger: After DELETE on payments - Update Invoice Status
+CREATE TRIGGER update_invoice_payment_status_after_payment_delete
+AFTER DELETE ON payments
+FOR EACH ROW
+WHEN OLD.sale_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = OLD.sale_id
+    UNION
+    SELECT OLD.invoice_id
+    WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+-- Trigger: When sale payment status is manually changed, update linked invoices
+CREATE TRIGGER update_invoice_status_after_sale_update
+AFTER UPDATE OF is_paid, paid_at ON sales
+FOR EACH ROW
+WHEN OLD.is_paid != NEW.is_paid OR (OLD.paid_at IS NULL) != (NEW.paid_at IS NULL)
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT invoice_id FROM invoice_sales WHERE sale_id = NEW.id
+  ) AND is_deleted = 0;
+END;
@@ -0,0 +1,66 @@
+-- =====================================================
+-- INVOICE TOTAL TRIGGER
+-- Recalculate payment status when an invoice's total changes,
+-- e.g. after update_invoice re-links sales and recomputes totals
+-- =====================================================
+
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_total_update;
+
+CREATE TRIGGER update_invoice_payment_status_after_total_update
+AFTER UPDATE OF total_amount_ttc ON invoices
+FOR EACH ROW
+WHEN NEW.is_deleted = 0 OR NEW.is_deleted IS NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.id;
+END;
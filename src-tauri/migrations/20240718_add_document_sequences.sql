@@ -0,0 +1,22 @@
+-- Migration: Server-side document numbering (2024-07-18)
+-- One gap-free counter per document type and year, handed out inside the
+-- same transaction that creates the document.
+CREATE TABLE IF NOT EXISTS document_sequences (
+    document_type TEXT NOT NULL,
+    year INTEGER NOT NULL,
+    last_value INTEGER NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (document_type, year)
+);
+
+ALTER TABLE settings ADD COLUMN invoice_number_format TEXT DEFAULT 'FAC-{YY}/{SEQ:05}';
+
+-- Start each year's invoice counter after the highest number already issued
+-- that year, read from the trailing digits of invoice_number (FAC-24/00042 -> 42).
+-- Hard deletes may have left gaps, so the row count is not enough.
+INSERT INTO document_sequences (document_type, year, last_value)
+SELECT 'invoice', CAST(strftime('%Y', date) AS INTEGER),
+       MAX(CAST(substr(invoice_number, length(rtrim(invoice_number, '0123456789')) + 1) AS INTEGER))
+FROM invoices
+WHERE strftime('%Y', date) IS NOT NULL
+GROUP BY strftime('%Y', date);
@@ -0,0 +1,41 @@
+-- =====================================================
+-- SALE TOTAL TRIGGER
+-- Recalculate payment status when a sale's total changes,
+-- e.g. after update_sale or recalculate_all_sale_totals.
+-- Sales without payments keep their manually set status.
+-- =====================================================
+
+DROP TRIGGER IF EXISTS update_sale_payment_status_after_total_update;
+
+CREATE TRIGGER update_sale_payment_status_after_total_update
+AFTER UPDATE OF total_amount_ttc ON sales
+FOR EACH ROW
+WHEN OLD.total_amount_ttc IS NOT NEW.total_amount_ttc
+  AND EXISTS (SELECT 1 FROM payments p WHERE p.sale_id = NEW.id AND p.is_deleted = 0)
+BEGIN
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = NEW.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.id;
+END;
@@ -0,0 +1,765 @@
+-- Migration: Store money as integer centimes (2024-07-20)
+-- Every money column is rebuilt as INTEGER holding minor units, the same way
+-- 20240607_update_credit_balance_type.sql rebuilt credit_balance:
+-- add a temporary column, copy ROUND(value * 100), drop the old column and
+-- rename. Triggers reference these columns, so they are dropped first and
+-- recreated unchanged at the end: their SUM(...) >= total comparisons are now
+-- exact integer comparisons.
+-- Non-money REAL columns (tax_rate, weights, widths, quantities) are untouched.
+
+-- 1. Drop the triggers that reference money columns
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_delete;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_insert;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_update;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_total_update;
+DROP TRIGGER IF EXISTS update_invoice_status_after_sale_update;
+DROP TRIGGER IF EXISTS update_sale_payment_status_after_payment_delete;
+DROP TRIGGER IF EXISTS update_sale_payment_status_after_payment_insert;
+DROP TRIGGER IF EXISTS update_sale_payment_status_after_payment_update;
+DROP TRIGGER IF EXISTS update_sale_payment_status_after_total_update;
+DROP TRIGGER IF EXISTS update_sales_status_after_invoice_update;
+DROP TRIGGER IF EXISTS update_status_after_bulk_payment_change;
+DROP TRIGGER IF EXISTS validate_payment_references;
+DROP TRIGGER IF EXISTS validate_payment_references_on_update;
+
+-- 2. Convert each column to centimes
+
+-- clients
+ALTER TABLE clients ADD COLUMN credit_balance_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE clients SET credit_balance_cents = CAST(ROUND(credit_balance * 100) AS INTEGER);
+ALTER TABLE clients DROP COLUMN credit_balance;
+ALTER TABLE clients RENAME COLUMN credit_balance_cents TO credit_balance;
+
+-- sales
+ALTER TABLE sales ADD COLUMN total_amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE sales SET total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE sales DROP COLUMN total_amount;
+ALTER TABLE sales RENAME COLUMN total_amount_cents TO total_amount;
+ALTER TABLE sales ADD COLUMN total_amount_ttc_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE sales SET total_amount_ttc_cents = CAST(ROUND(total_amount_ttc * 100) AS INTEGER);
+ALTER TABLE sales DROP COLUMN total_amount_ttc;
+ALTER TABLE sales RENAME COLUMN total_amount_ttc_cents TO total_amount_ttc;
+ALTER TABLE sales ADD COLUMN transportation_fee_cents INTEGER;
+UPDATE sales SET transportation_fee_cents = CAST(ROUND(transportation_fee * 100) AS INTEGER) WHERE transportation_fee IS NOT NULL;
+ALTER TABLE sales DROP COLUMN transportation_fee;
+ALTER TABLE sales RENAME COLUMN transportation_fee_cents TO transportation_fee;
+
+-- sale_items
+ALTER TABLE sale_items ADD COLUMN price_per_ton_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE sale_items SET price_per_ton_cents = CAST(ROUND(price_per_ton * 100) AS INTEGER);
+ALTER TABLE sale_items DROP COLUMN price_per_ton;
+ALTER TABLE sale_items RENAME COLUMN price_per_ton_cents TO price_per_ton;
+ALTER TABLE sale_items ADD COLUMN total_amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE sale_items SET total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE sale_items DROP COLUMN total_amount;
+ALTER TABLE sale_items RENAME COLUMN total_amount_cents TO total_amount;
+
+-- invoices
+ALTER TABLE invoices ADD COLUMN total_amount_ht_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE invoices SET total_amount_ht_cents = CAST(ROUND(total_amount_ht * 100) AS INTEGER);
+ALTER TABLE invoices DROP COLUMN total_amount_ht;
+ALTER TABLE invoices RENAME COLUMN total_amount_ht_cents TO total_amount_ht;
+ALTER TABLE invoices ADD COLUMN total_amount_ttc_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE invoices SET total_amount_ttc_cents = CAST(ROUND(total_amount_ttc * 100) AS INTEGER);
+ALTER TABLE invoices DROP COLUMN total_amount_ttc;
+ALTER TABLE invoices RENAME COLUMN total_amount_ttc_cents TO total_amount_ttc;
+ALTER TABLE invoices ADD COLUMN transportation_fee_cents INTEGER;
+UPDATE invoices SET transportation_fee_cents = CAST(ROUND(transportation_fee * 100) AS INTEGER) WHERE transportation_fee IS NOT NULL;
+ALTER TABLE invoices DROP COLUMN transportation_fee;
+ALTER TABLE invoices RENAME COLUMN transportation_fee_cents TO transportation_fee;
+ALTER TABLE invoices ADD COLUMN transportation_fee_ttc_cents INTEGER;
+UPDATE invoices SET transportation_fee_ttc_cents = CAST(ROUND(transportation_fee_ttc * 100) AS INTEGER) WHERE transportation_fee_ttc IS NOT NULL;
+ALTER TABLE invoices DROP COLUMN transportation_fee_ttc;
+ALTER TABLE invoices RENAME COLUMN transportation_fee_ttc_cents TO transportation_fee_ttc;
+
+-- payments
+ALTER TABLE payments ADD COLUMN amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE payments SET amount_cents = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE payments DROP COLUMN amount;
+ALTER TABLE payments RENAME COLUMN amount_cents TO amount;
+
+-- bulk_payments
+ALTER TABLE bulk_payments ADD COLUMN total_amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE bulk_payments SET total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE bulk_payments DROP COLUMN total_amount;
+ALTER TABLE bulk_payments RENAME COLUMN total_amount_cents TO total_amount;
+
+-- credit_transactions
+ALTER TABLE credit_transactions ADD COLUMN amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE credit_transactions SET amount_cents = CAST(ROUND(amount * 100) AS INTEGER);
+ALTER TABLE credit_transactions DROP COLUMN amount;
+ALTER TABLE credit_transactions RENAME COLUMN amount_cents TO amount;
+
+-- corrugated_sheet_items
+ALTER TABLE corrugated_sheet_items ADD COLUMN price_per_unit_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE corrugated_sheet_items SET price_per_unit_cents = CAST(ROUND(price_per_unit * 100) AS INTEGER);
+ALTER TABLE corrugated_sheet_items DROP COLUMN price_per_unit;
+ALTER TABLE corrugated_sheet_items RENAME COLUMN price_per_unit_cents TO price_per_unit;
+ALTER TABLE corrugated_sheet_items ADD COLUMN total_amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE corrugated_sheet_items SET total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE corrugated_sheet_items DROP COLUMN total_amount;
+ALTER TABLE corrugated_sheet_items RENAME COLUMN total_amount_cents TO total_amount;
+
+-- steel_slitting_strip_items
+ALTER TABLE steel_slitting_strip_items ADD COLUMN price_per_unit_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE steel_slitting_strip_items SET price_per_unit_cents = CAST(ROUND(price_per_unit * 100) AS INTEGER);
+ALTER TABLE steel_slitting_strip_items DROP COLUMN price_per_unit;
+ALTER TABLE steel_slitting_strip_items RENAME COLUMN price_per_unit_cents TO price_per_unit;
+ALTER TABLE steel_slitting_strip_items ADD COLUMN total_amount_cents INTEGER NOT NULL DEFAULT 0;
+UPDATE steel_slitting_strip_items SET total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER);
+ALTER TABLE steel_slitting_strip_items DROP COLUMN total_amount;
+ALTER TABLE steel_slitting_strip_items RENAME COLUMN total_amount_cents TO total_amount;
+
+-- 3. Recreate the triggers
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_delete
+AFTER DELETE ON payments
+FOR EACH ROW
+WHEN OLD.sale_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = OLD.sale_id
+    UNION
+    SELECT OLD.invoice_id
+    WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_insert
+AFTER INSERT ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices linked through sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          -- Sum of payments through linked sales
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          -- Plus direct payments to invoice
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = NEW.sale_id
+    UNION
+    SELECT NEW.invoice_id
+    WHERE NEW.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_update
+AFTER UPDATE ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR OLD.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices for all affected cases
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id IN (NEW.sale_id, OLD.sale_id)
+    UNION
+    SELECT NEW.invoice_id WHERE NEW.invoice_id IS NOT NULL
+    UNION
+    SELECT OLD.invoice_id WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_total_update
+AFTER UPDATE OF total_amount_ttc ON invoices
+FOR EACH ROW
+WHEN NEW.is_deleted = 0 OR NEW.is_deleted IS NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_invoice_status_after_sale_update
+AFTER UPDATE OF is_paid, paid_at ON sales
+FOR EACH ROW
+WHEN OLD.is_paid != NEW.is_paid OR (OLD.paid_at IS NULL) != (NEW.paid_at IS NULL)
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT invoice_id FROM invoice_sales WHERE sale_id = NEW.id
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_sale_payment_status_after_payment_delete
+AFTER DELETE ON payments
+FOR EACH ROW
+WHEN OLD.sale_id IS NOT NULL
+BEGIN
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = OLD.sale_id;
+END;
+
+CREATE TRIGGER update_sale_payment_status_after_payment_insert
+AFTER INSERT ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL
+BEGIN
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.sale_id;
+END;
+
+CREATE TRIGGER update_sale_payment_status_after_payment_update
+AFTER UPDATE ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR OLD.sale_id IS NOT NULL
+BEGIN
+  -- Update the new sale if sale_id changed or was updated
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = NEW.sale_id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.sale_id AND NEW.sale_id IS NOT NULL;
+
+  -- Update the old sale if sale_id was changed
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = OLD.sale_id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = OLD.sale_id AND OLD.sale_id IS NOT NULL AND OLD.sale_id != NEW.sale_id;
+END;
+
+CREATE TRIGGER update_sale_payment_status_after_total_update
+AFTER UPDATE OF total_amount_ttc ON sales
+FOR EACH ROW
+WHEN OLD.total_amount_ttc IS NOT NEW.total_amount_ttc
+  AND EXISTS (SELECT 1 FROM payments p WHERE p.sale_id = NEW.id AND p.is_deleted = 0)
+BEGIN
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = NEW.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = NEW.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_sales_status_after_invoice_update
+AFTER UPDATE OF is_deleted ON invoices
+FOR EACH ROW
+WHEN OLD.is_deleted != NEW.is_deleted
+BEGIN
+  -- Recalculate all sales linked to this invoice
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = sales.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT sale_id FROM invoice_sales WHERE invoice_id = NEW.id
+  );
+END;
+
+CREATE TRIGGER update_status_after_bulk_payment_change
+AFTER UPDATE OF is_deleted ON bulk_payments
+FOR EACH ROW
+WHEN OLD.is_deleted != NEW.is_deleted
+BEGIN
+  -- Update all sales with payments linked to this bulk payment
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = sales.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT sale_id 
+    FROM payments 
+    WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+  );
+
+  -- Update all invoices linked to affected sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ), 0)
+        ) >= invoices.total_amount_ttc THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id
+    FROM invoice_sales s
+    WHERE s.sale_id IN (
+      SELECT DISTINCT sale_id 
+      FROM payments 
+      WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+    )
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER validate_payment_references
+BEFORE INSERT ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL
+BEGIN
+  -- Validate sale_id exists and is not deleted
+  SELECT CASE
+    WHEN NEW.sale_id IS NOT NULL AND 
+         (SELECT COUNT(*) FROM sales WHERE id = NEW.sale_id) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent sale')
+  END;
+  
+  -- Validate invoice_id exists and is not deleted
+  SELECT CASE
+    WHEN NEW.invoice_id IS NOT NULL AND 
+         (SELECT COUNT(*) FROM invoices WHERE id = NEW.invoice_id AND is_deleted = 0) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent or deleted invoice')
+  END;
+END;
+
+CREATE TRIGGER validate_payment_references_on_update
+BEFORE UPDATE OF sale_id, invoice_id ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT OLD.sale_id OR NEW.invoice_id IS NOT OLD.invoice_id
+BEGIN
+  -- Validate a changed sale_id exists
+  SELECT CASE
+    WHEN NEW.sale_id IS NOT NULL AND NEW.sale_id IS NOT OLD.sale_id AND 
+         (SELECT COUNT(*) FROM sales WHERE id = NEW.sale_id) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent sale')
+  END;
+  
+  -- Validate a changed invoice_id exists and is not deleted
+  SELECT CASE
+    WHEN NEW.invoice_id IS NOT NULL AND NEW.invoice_id IS NOT OLD.invoice_id AND 
+         (SELECT COUNT(*) FROM invoices WHERE id = NEW.invoice_id AND is_deleted = 0) = 0
+    THEN RAISE(ABORT, 'Payment references non-existent or deleted invoice')
+  END;
+END;
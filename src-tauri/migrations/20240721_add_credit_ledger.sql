@@ -0,0 +1,70 @@
+-- Client credit is now driven by the credit_transactions ledger:
+-- clients.credit_balance is kept equal to SUM(credit) - SUM(debit) by triggers.
+
+-- Existing balances were free numbers: record whatever the ledger does not
+-- already explain as an opening manual adjustment, so nothing is lost
+INSERT INTO credit_transactions (id, client_id, amount, type, source_type, source_id, notes, created_at, updated_at)
+SELECT
+  lower(hex(randomblob(16))),
+  c.id,
+  ABS(c.credit_balance - IFNULL(l.balance, 0)),
+  CASE WHEN c.credit_balance - IFNULL(l.balance, 0) > 0 THEN 'credit' ELSE 'debit' END,
+  'manual_adjustment',
+  NULL,
+  'Opening balance',
+  CURRENT_TIMESTAMP,
+  CURRENT_TIMESTAMP
+FROM clients c
+LEFT JOIN (
+  SELECT client_id, SUM(CASE WHEN type = 'credit' THEN amount ELSE -amount END) AS balance
+  FROM credit_transactions
+  GROUP BY client_id
+) l ON l.client_id = c.id
+WHERE c.credit_balance != IFNULL(l.balance, 0);
+
+CREATE INDEX IF NOT EXISTS idx_credit_transactions_source_id ON credit_transactions(source_id);
+
+CREATE TRIGGER update_client_credit_balance_after_credit_insert
+AFTER INSERT ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = NEW.client_id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.client_id;
+END;
+
+CREATE TRIGGER update_client_credit_balance_after_credit_delete
+AFTER DELETE ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = OLD.client_id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = OLD.client_id;
+END;
+
+CREATE TRIGGER update_client_credit_balance_after_credit_update
+AFTER UPDATE OF amount, type, client_id ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = clients.id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (NEW.client_id, OLD.client_id);
+END;
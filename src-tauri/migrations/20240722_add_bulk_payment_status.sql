@@ -0,0 +1,8 @@
+-- update_status_after_bulk_payment_change already watches bulk_payments.is_deleted,
+-- but the column was never created
+ALTER TABLE bulk_payments ADD COLUMN is_deleted BOOLEAN DEFAULT 0;
+ALTER TABLE bulk_payments ADD COLUMN deleted_at DATETIME NULL;
+ALTER TABLE bulk_payments ADD COLUMN check_number TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_bulk_payments_client_id ON bulk_payments(client_id);
+CREATE INDEX IF NOT EXISTS idx_payments_bulk_payment_id ON payments(bulk_payment_id);
@@ -0,0 +1,125 @@
+-- Full-text search indexes used by global_search. Each table keeps its own copy
+-- of the searchable text next to the id of the row it came from; the triggers
+-- below keep them in sync. Soft-deleted rows stay indexed and are filtered out
+-- when searching, so restoring them needs no reindex.
+
+CREATE VIRTUAL TABLE IF NOT EXISTS clients_fts USING fts5(
+    id UNINDEXED, name, company, nif, rc,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS sale_items_fts USING fts5(
+    id UNINDEXED, description, coil_ref, top_coat_ral, back_coat_ral,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS invoices_fts USING fts5(
+    id UNINDEXED, invoice_number, notes,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS payments_fts USING fts5(
+    id UNINDEXED, check_number, notes,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Clients
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_insert
+AFTER INSERT ON clients
+BEGIN
+    INSERT INTO clients_fts (id, name, company, nif, rc)
+    VALUES (NEW.id, NEW.name, NEW.company, NEW.nif, NEW.rc);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_update
+AFTER UPDATE OF id, name, company, nif, rc ON clients
+BEGIN
+    DELETE FROM clients_fts WHERE id = OLD.id;
+    INSERT INTO clients_fts (id, name, company, nif, rc)
+    VALUES (NEW.id, NEW.name, NEW.company, NEW.nif, NEW.rc);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_delete
+AFTER DELETE ON clients
+BEGIN
+    DELETE FROM clients_fts WHERE id = OLD.id;
+END;
+
+-- Sale items
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_insert
+AFTER INSERT ON sale_items
+BEGIN
+    INSERT INTO sale_items_fts (id, description, coil_ref, top_coat_ral, back_coat_ral)
+    VALUES (NEW.id, NEW.description, NEW.coil_ref, NEW.top_coat_ral, NEW.back_coat_ral);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_update
+AFTER UPDATE OF id, description, coil_ref, top_coat_ral, back_coat_ral ON sale_items
+BEGIN
+    DELETE FROM sale_items_fts WHERE id = OLD.id;
+    INSERT INTO sale_items_fts (id, description, coil_ref, top_coat_ral, back_coat_ral)
+    VALUES (NEW.id, NEW.description, NEW.coil_ref, NEW.top_coat_ral, NEW.back_coat_ral);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_delete
+AFTER DELETE ON sale_items
+BEGIN
+    DELETE FROM sale_items_fts WHERE id = OLD.id;
+END;
+
+-- Invoices
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_insert
+AFTER INSERT ON invoices
+BEGIN
+    INSERT INTO invoices_fts (id, invoice_number, notes)
+    VALUES (NEW.id, NEW.invoice_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_update
+AFTER UPDATE OF id, invoice_number, notes ON invoices
+BEGIN
+    DELETE FROM invoices_fts WHERE id = OLD.id;
+    INSERT INTO invoices_fts (id, invoice_number, notes)
+    VALUES (NEW.id, NEW.invoice_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_delete
+AFTER DELETE ON invoices
+BEGIN
+    DELETE FROM invoices_fts WHERE id = OLD.id;
+END;
+
+-- Payments
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_insert
+AFTER INSERT ON payments
+BEGIN
+    INSERT INTO payments_fts (id, check_number, notes)
+    VALUES (NEW.id, NEW.check_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_update
+AFTER UPDATE OF id, check_number, notes ON payments
+BEGIN
+    DELETE FROM payments_fts WHERE id = OLD.id;
+    INSERT INTO payments_fts (id, check_number, notes)
+    VALUES (NEW.id, NEW.check_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_delete
+AFTER DELETE ON payments
+BEGIN
+    DELETE FROM payments_fts WHERE id = OLD.id;
+END;
+
+-- Index the existing data
+INSERT INTO clients_fts (id, name, company, nif, rc)
+SELECT id, name, company, nif, rc FROM clients;
+
+INSERT INTO sale_items_fts (id, description, coil_ref, top_coat_ral, back_coat_ral)
+SELECT id, description, coil_ref, top_coat_ral, back_coat_ral FROM sale_items;
+
+INSERT INTO invoices_fts (id, invoice_number, notes)
+SELECT id, invoice_number, notes FROM invoices;
+
+INSERT INTO payments_fts (id, check_number, notes)
+SELECT id, check_number, notes FROM payments;
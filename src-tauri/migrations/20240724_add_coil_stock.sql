@@ -0,0 +1,57 @@
+-- Coil stock. Weights are in tons, like sale_items.coil_weight.
+CREATE TABLE IF NOT EXISTS coils (
+    id TEXT PRIMARY KEY,
+    coil_ref TEXT NOT NULL UNIQUE,
+    received_at TEXT NOT NULL,
+    supplier TEXT,
+    thickness REAL,
+    width REAL,
+    top_coat_ral TEXT,
+    back_coat_ral TEXT,
+    gross_weight REAL NOT NULL,
+    net_weight REAL NOT NULL,
+    remaining_weight REAL NOT NULL DEFAULT 0,
+    cost_per_ton INTEGER NOT NULL DEFAULT 0,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT
+);
+
+-- Every change to a coil's weight. coils.remaining_weight is kept equal to
+-- SUM(weight) by the triggers below: receipts and releases are positive,
+-- sales negative, adjustments either.
+CREATE TABLE IF NOT EXISTS coil_movements (
+    id TEXT PRIMARY KEY,
+    coil_id TEXT NOT NULL REFERENCES coils(id) ON DELETE CASCADE,
+    movement_type TEXT NOT NULL CHECK (movement_type IN ('receipt', 'sale', 'sale_release', 'adjustment')),
+    weight REAL NOT NULL,
+    sale_id TEXT,
+    sale_item_id TEXT,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_coil_movements_coil_id ON coil_movements(coil_id);
+CREATE INDEX IF NOT EXISTS idx_coil_movements_sale_id ON coil_movements(sale_id);
+
+CREATE TRIGGER IF NOT EXISTS update_coil_remaining_weight_after_movement_insert
+AFTER INSERT ON coil_movements
+FOR EACH ROW
+BEGIN
+  UPDATE coils
+  SET
+    remaining_weight = IFNULL((SELECT SUM(m.weight) FROM coil_movements m WHERE m.coil_id = NEW.coil_id), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.coil_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS update_coil_remaining_weight_after_movement_delete
+AFTER DELETE ON coil_movements
+FOR EACH ROW
+BEGIN
+  UPDATE coils
+  SET
+    remaining_weight = IFNULL((SELECT SUM(m.weight) FROM coil_movements m WHERE m.coil_id = OLD.coil_id), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = OLD.coil_id;
+END;
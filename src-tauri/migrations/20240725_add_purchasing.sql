@@ -0,0 +1,95 @@
+-- Purchasing: suppliers, the coils ordered from them, their invoices and what
+-- we paid them. Amounts are integer centimes, weights are tons.
+CREATE TABLE IF NOT EXISTS suppliers (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    company TEXT,
+    email TEXT,
+    phone TEXT,
+    address TEXT,
+    notes TEXT,
+    nif TEXT,
+    nis TEXT,
+    rc TEXT,
+    ai TEXT,
+    rib TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS purchase_orders (
+    id TEXT PRIMARY KEY,
+    supplier_id TEXT NOT NULL REFERENCES suppliers(id),
+    -- The supplier's or our own order reference
+    reference TEXT,
+    date TEXT NOT NULL,
+    total_amount INTEGER NOT NULL DEFAULT 0,
+    total_amount_ttc INTEGER NOT NULL DEFAULT 0,
+    tax_rate REAL NOT NULL DEFAULT 0.19,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+-- One ordered coil per line; coil_id is set once the coil has been received.
+CREATE TABLE IF NOT EXISTS purchase_order_items (
+    id TEXT PRIMARY KEY,
+    purchase_order_id TEXT NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    coil_ref TEXT,
+    thickness REAL,
+    width REAL,
+    top_coat_ral TEXT,
+    back_coat_ral TEXT,
+    weight REAL NOT NULL,
+    price_per_ton INTEGER NOT NULL,
+    total_amount INTEGER NOT NULL,
+    coil_id TEXT REFERENCES coils(id),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_order_items_order_id ON purchase_order_items(purchase_order_id);
+
+CREATE TABLE IF NOT EXISTS supplier_invoices (
+    id TEXT PRIMARY KEY,
+    supplier_id TEXT NOT NULL REFERENCES suppliers(id),
+    purchase_order_id TEXT REFERENCES purchase_orders(id),
+    -- The number printed on the supplier's invoice
+    invoice_number TEXT NOT NULL,
+    date TEXT NOT NULL,
+    due_date TEXT,
+    total_amount_ht INTEGER NOT NULL,
+    total_amount_ttc INTEGER NOT NULL,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS supplier_payments (
+    id TEXT PRIMARY KEY,
+    supplier_id TEXT NOT NULL REFERENCES suppliers(id),
+    supplier_invoice_id TEXT REFERENCES supplier_invoices(id),
+    amount INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    method TEXT NOT NULL,
+    check_number TEXT,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_supplier_invoices_supplier_id ON supplier_invoices(supplier_id);
+CREATE INDEX IF NOT EXISTS idx_supplier_payments_supplier_id ON supplier_payments(supplier_id);
+CREATE INDEX IF NOT EXISTS idx_supplier_payments_invoice_id ON supplier_payments(supplier_invoice_id);
+
+-- Coils received against a purchase order keep a link to their line
+ALTER TABLE coils ADD COLUMN supplier_id TEXT REFERENCES suppliers(id);
+ALTER TABLE coils ADD COLUMN purchase_order_item_id TEXT REFERENCES purchase_order_items(id);
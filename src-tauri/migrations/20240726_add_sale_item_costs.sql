@@ -0,0 +1,13 @@
+-- Cost side of a sale item, in integer centimes. unit_cost is priced in the
+-- same unit as price_per_ton; cost_amount is the line's cost, computed like
+-- total_amount. Both stay NULL while the cost is unknown.
+ALTER TABLE sale_items ADD COLUMN unit_cost INTEGER;
+ALTER TABLE sale_items ADD COLUMN cost_amount INTEGER;
+
+-- Weight-priced items cut from a coil already in stock take its cost per ton
+UPDATE sale_items
+SET
+  unit_cost = (SELECT coil.cost_per_ton FROM coils coil WHERE coil.coil_ref = TRIM(sale_items.coil_ref)),
+  cost_amount = CAST(ROUND((SELECT coil.cost_per_ton FROM coils coil WHERE coil.coil_ref = TRIM(sale_items.coil_ref)) * IFNULL(coil_weight, 0)) AS INTEGER)
+WHERE product_type IN ('coil', 'steel_slitting')
+  AND EXISTS (SELECT 1 FROM coils coil WHERE coil.coil_ref = TRIM(sale_items.coil_ref));
@@ -0,0 +1,28 @@
+-- VAT per item and stamp duty on cash sales, amounts in integer centimes.
+-- sale_items.tax_rate overrides the sale's rate for that line and stays NULL
+-- when the line follows it; tax_amount is the line's VAT rounded on its own.
+ALTER TABLE sale_items ADD COLUMN tax_rate REAL;
+ALTER TABLE sale_items ADD COLUMN tax_amount INTEGER NOT NULL DEFAULT 0;
+
+-- tax_amount is the sale's VAT, rounded once per rate. total_amount_ttc is
+-- total_amount + tax_amount + stamp_duty. A sale with a tax_exemption reason
+-- (export, investment scheme...) charges no VAT on any line.
+ALTER TABLE sales ADD COLUMN tax_amount INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sales ADD COLUMN stamp_duty INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sales ADD COLUMN tax_exemption TEXT;
+
+-- Droit de timbre charged on sales paid in cash: a share of the amount due,
+-- at least stamp_duty_min and at most stamp_duty_max (no cap when NULL or 0)
+ALTER TABLE settings ADD COLUMN stamp_duty_rate REAL DEFAULT 0.01;
+ALTER TABLE settings ADD COLUMN stamp_duty_min INTEGER DEFAULT 500;
+ALTER TABLE settings ADD COLUMN stamp_duty_max INTEGER;
+
+-- Existing sales were all taxed at their own rate with no stamp duty
+UPDATE sale_items
+SET tax_amount = CAST(ROUND(total_amount * COALESCE(
+  (SELECT s.tax_rate FROM sales s WHERE s.id = sale_items.sale_id),
+  (SELECT tax_rate FROM settings LIMIT 1),
+  0
+)) AS INTEGER);
+
+UPDATE sales SET tax_amount = total_amount_ttc - total_amount;
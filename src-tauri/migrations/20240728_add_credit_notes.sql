@@ -0,0 +1,545 @@
+-- Credit notes (avoirs): an issued invoice is never deleted, it is cancelled
+-- in full or in part by credit notes numbered in their own sequence.
+-- Amounts are integer centimes, like everywhere else.
+CREATE TABLE IF NOT EXISTS credit_notes (
+    id TEXT PRIMARY KEY,
+    credit_note_number TEXT NOT NULL UNIQUE,
+    invoice_id TEXT NOT NULL,
+    client_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    reason TEXT,
+    total_amount_ht INTEGER NOT NULL,
+    tax_amount INTEGER NOT NULL,
+    stamp_duty INTEGER NOT NULL DEFAULT 0,
+    total_amount_ttc INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id),
+    FOREIGN KEY (client_id) REFERENCES clients(id)
+);
+
+-- What each credit note gives back, line by line. sale_item_id is NULL for
+-- the part of a sale not carried by its items (transportation fee).
+-- tax_amount adds up to the credit note's tax_amount.
+CREATE TABLE IF NOT EXISTS credit_note_items (
+    id TEXT PRIMARY KEY,
+    credit_note_id TEXT NOT NULL,
+    sale_id TEXT NOT NULL,
+    sale_item_id TEXT,
+    description TEXT NOT NULL,
+    tax_rate REAL NOT NULL,
+    total_amount INTEGER NOT NULL,
+    tax_amount INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (credit_note_id) REFERENCES credit_notes(id) ON DELETE CASCADE,
+    FOREIGN KEY (sale_id) REFERENCES sales(id),
+    FOREIGN KEY (sale_item_id) REFERENCES sale_items(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_credit_notes_invoice_id ON credit_notes(invoice_id);
+CREATE INDEX IF NOT EXISTS idx_credit_notes_client_id ON credit_notes(client_id);
+CREATE INDEX IF NOT EXISTS idx_credit_note_items_credit_note_id ON credit_note_items(credit_note_id);
+CREATE INDEX IF NOT EXISTS idx_credit_note_items_sale_item_id ON credit_note_items(sale_item_id);
+
+-- TTC credited on the invoice so far: the invoice is settled once its
+-- payments reach total_amount_ttc - credited_amount
+ALTER TABLE invoices ADD COLUMN credited_amount INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE settings ADD COLUMN credit_note_number_format TEXT DEFAULT 'AV-{YY}/{SEQ:05}';
+
+-- The invoice payment status triggers are recreated unchanged except that
+-- they compare payments to what is left after credit notes
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_delete;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_insert;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_payment_update;
+DROP TRIGGER IF EXISTS update_invoice_payment_status_after_total_update;
+DROP TRIGGER IF EXISTS update_invoice_status_after_sale_update;
+DROP TRIGGER IF EXISTS update_status_after_bulk_payment_change;
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_delete
+AFTER DELETE ON payments
+FOR EACH ROW
+WHEN OLD.sale_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = OLD.sale_id
+    UNION
+    SELECT OLD.invoice_id
+    WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_insert
+AFTER INSERT ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices linked through sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          -- Sum of payments through linked sales
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          -- Plus direct payments to invoice
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id = NEW.sale_id
+    UNION
+    SELECT NEW.invoice_id
+    WHERE NEW.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_payment_update
+AFTER UPDATE ON payments
+FOR EACH ROW
+WHEN NEW.sale_id IS NOT NULL OR OLD.sale_id IS NOT NULL OR NEW.invoice_id IS NOT NULL OR OLD.invoice_id IS NOT NULL
+BEGIN
+  -- Update invoices for all affected cases
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id 
+    FROM invoice_sales 
+    WHERE sale_id IN (NEW.sale_id, OLD.sale_id)
+    UNION
+    SELECT NEW.invoice_id WHERE NEW.invoice_id IS NOT NULL
+    UNION
+    SELECT OLD.invoice_id WHERE OLD.invoice_id IS NOT NULL
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_invoice_payment_status_after_total_update
+AFTER UPDATE OF total_amount_ttc, credited_amount ON invoices
+FOR EACH ROW
+WHEN NEW.is_deleted = 0 OR NEW.is_deleted IS NULL
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_invoice_status_after_sale_update
+AFTER UPDATE OF is_paid, paid_at ON sales
+FOR EACH ROW
+WHEN OLD.is_paid != NEW.is_paid OR (OLD.paid_at IS NULL) != (NEW.paid_at IS NULL)
+BEGIN
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT invoice_id FROM invoice_sales WHERE sale_id = NEW.id
+  ) AND is_deleted = 0;
+END;
+
+CREATE TRIGGER update_status_after_bulk_payment_change
+AFTER UPDATE OF is_deleted ON bulk_payments
+FOR EACH ROW
+WHEN OLD.is_deleted != NEW.is_deleted
+BEGIN
+  -- Update all sales with payments linked to this bulk payment
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = sales.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT sale_id 
+    FROM payments 
+    WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+  );
+
+  -- Update all invoices linked to affected sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id
+    FROM invoice_sales s
+    WHERE s.sale_id IN (
+      SELECT DISTINCT sale_id 
+      FROM payments 
+      WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+    )
+  ) AND is_deleted = 0;
+END;
+
+-- Paid amounts a credit note gives back are booked on the client ledger;
+-- SQLite cannot widen a CHECK constraint, so the table is rebuilt
+CREATE TABLE credit_transactions_new (
+    id TEXT PRIMARY KEY DEFAULT (lower(hex(randomblob(16)))),
+    client_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    type TEXT NOT NULL CHECK (type IN ('credit', 'debit')),
+    source_type TEXT NOT NULL CHECK (source_type IN ('payment', 'refund', 'manual_adjustment', 'credit_use', 'credit_note')),
+    source_id TEXT,
+    notes TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (client_id) REFERENCES clients(id)
+);
+INSERT INTO credit_transactions_new (id, client_id, amount, type, source_type, source_id, notes, created_at, updated_at)
+SELECT id, client_id, amount, type, source_type, source_id, notes, created_at, updated_at FROM credit_transactions;
+DROP TABLE credit_transactions;
+ALTER TABLE credit_transactions_new RENAME TO credit_transactions;
+
+CREATE INDEX idx_credit_transactions_client_id ON credit_transactions(client_id);
+CREATE INDEX idx_credit_transactions_created_at ON credit_transactions(created_at);
+CREATE INDEX idx_credit_transactions_source_id ON credit_transactions(source_id);
+
+CREATE TRIGGER update_client_credit_balance_after_credit_insert
+AFTER INSERT ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = NEW.client_id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = NEW.client_id;
+END;
+
+CREATE TRIGGER update_client_credit_balance_after_credit_delete
+AFTER DELETE ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = OLD.client_id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id = OLD.client_id;
+END;
+
+CREATE TRIGGER update_client_credit_balance_after_credit_update
+AFTER UPDATE OF amount, type, client_id ON credit_transactions
+FOR EACH ROW
+BEGIN
+  UPDATE clients
+  SET
+    credit_balance = IFNULL((
+      SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+      FROM credit_transactions ct
+      WHERE ct.client_id = clients.id
+    ), 0),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (NEW.client_id, OLD.client_id);
+END;
@@ -0,0 +1,57 @@
+-- Quotes (proformas) sent before a sale. Their items have the same shape as
+-- sale_items so an accepted quote becomes a sale without retyping anything.
+-- Amounts are integer centimes, like everywhere else.
+CREATE TABLE IF NOT EXISTS quotes (
+    id TEXT PRIMARY KEY,
+    quote_number TEXT NOT NULL UNIQUE,
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    date TEXT NOT NULL,
+    valid_until TEXT NOT NULL,
+    -- draft and sent quotes read as expired once valid_until has passed
+    status TEXT NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'sent', 'accepted', 'expired')),
+    notes TEXT,
+    payment_method TEXT,
+    transportation_fee INTEGER,
+    tax_rate REAL NOT NULL DEFAULT 0.19,
+    tax_exemption TEXT,
+    total_amount INTEGER NOT NULL DEFAULT 0,
+    tax_amount INTEGER NOT NULL DEFAULT 0,
+    stamp_duty INTEGER NOT NULL DEFAULT 0,
+    total_amount_ttc INTEGER NOT NULL DEFAULT 0,
+    -- The sale the quote was converted to
+    sale_id TEXT REFERENCES sales(id),
+    converted_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS quote_items (
+    id TEXT PRIMARY KEY,
+    quote_id TEXT NOT NULL REFERENCES quotes(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    coil_ref TEXT,
+    coil_thickness REAL,
+    coil_width REAL,
+    top_coat_ral TEXT,
+    back_coat_ral TEXT,
+    coil_weight REAL,
+    quantity REAL NOT NULL,
+    price_per_ton INTEGER NOT NULL,
+    total_amount INTEGER NOT NULL,
+    unit_cost INTEGER,
+    tax_rate REAL,
+    tax_amount INTEGER NOT NULL DEFAULT 0,
+    product_type TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_quotes_client_id ON quotes(client_id);
+CREATE INDEX IF NOT EXISTS idx_quotes_date ON quotes(date);
+CREATE INDEX IF NOT EXISTS idx_quote_items_quote_id ON quote_items(quote_id);
+
+-- The quote a sale was converted from
+ALTER TABLE sales ADD COLUMN quote_id TEXT REFERENCES quotes(id);
+
+ALTER TABLE settings ADD COLUMN quote_number_format TEXT DEFAULT 'PRO-{YY}/{SEQ:05}';
@@ -0,0 +1,42 @@
+-- Delivery notes (bons de livraison): one per trip, each carrying part or
+-- all of a sale's items. Coils and steel slitting are delivered by weight
+-- (tons), every other product by quantity.
+CREATE TABLE IF NOT EXISTS delivery_notes (
+    id TEXT PRIMARY KEY,
+    delivery_note_number TEXT NOT NULL UNIQUE,
+    sale_id TEXT NOT NULL REFERENCES sales(id),
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    date TEXT NOT NULL,
+    truck TEXT,
+    driver TEXT,
+    plate TEXT,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    is_deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS delivery_note_items (
+    id TEXT PRIMARY KEY,
+    delivery_note_id TEXT NOT NULL REFERENCES delivery_notes(id) ON DELETE CASCADE,
+    -- Sales with live delivery notes cannot be edited, so only the lines of
+    -- deleted notes go when a sale's items are replaced
+    sale_item_id TEXT NOT NULL REFERENCES sale_items(id) ON DELETE CASCADE,
+    -- Tons loaded; required for coils and steel slitting
+    weight REAL,
+    -- Units loaded; required for the other products
+    quantity REAL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_delivery_notes_sale_id ON delivery_notes(sale_id);
+CREATE INDEX IF NOT EXISTS idx_delivery_notes_client_id ON delivery_notes(client_id);
+CREATE INDEX IF NOT EXISTS idx_delivery_note_items_delivery_note_id ON delivery_note_items(delivery_note_id);
+CREATE INDEX IF NOT EXISTS idx_delivery_note_items_sale_item_id ON delivery_note_items(sale_item_id);
+
+-- Sales recorded before delivery notes existed were delivered on the spot:
+-- only sales created from now on wait for their delivery notes
+ALTER TABLE sales ADD COLUMN tracks_delivery INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE settings ADD COLUMN delivery_note_number_format TEXT DEFAULT 'BL-{YY}/{SEQ:05}';
@@ -0,0 +1,35 @@
+-- Cheques and traites (bills of exchange) received from clients, followed
+-- from receipt to the bank: received -> deposited -> cleared or bounced.
+-- A payment or bulk payment made with one points at it; a bounced
+-- instrument takes its payments back. Amounts are integer centimes.
+CREATE TABLE IF NOT EXISTS payment_instruments (
+    id TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    kind TEXT NOT NULL CHECK (kind IN ('check', 'traite')),
+    number TEXT,
+    bank TEXT,
+    amount INTEGER NOT NULL,
+    received_date TEXT NOT NULL,
+    -- When the instrument can be cashed: the échéance of a traite, the
+    -- received date of an ordinary cheque
+    maturity_date TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'received' CHECK (status IN ('received', 'deposited', 'cleared', 'bounced')),
+    -- The bank remittance (bordereau de remise) it was deposited with
+    deposit_batch TEXT,
+    deposited_at TEXT,
+    cleared_at TEXT,
+    bounced_at TEXT,
+    bounce_reason TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_instruments_client_id ON payment_instruments(client_id);
+CREATE INDEX IF NOT EXISTS idx_payment_instruments_maturity ON payment_instruments(status, maturity_date);
+
+-- Cheques recorded before this migration were handled outside the app and
+-- are not tracked
+ALTER TABLE payments ADD COLUMN instrument_id TEXT REFERENCES payment_instruments(id);
+ALTER TABLE bulk_payments ADD COLUMN instrument_id TEXT REFERENCES payment_instruments(id);
+
+CREATE INDEX IF NOT EXISTS idx_payments_instrument_id ON payments(instrument_id);
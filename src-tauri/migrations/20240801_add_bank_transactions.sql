@@ -0,0 +1,25 @@
+-- Lines of the bank statements imported for reconciliation (CSV, OFX or
+-- CAMT.053). Credits are positive, debits negative, in integer centimes.
+-- A credit is reconciled against one client payment or one bulk payment.
+CREATE TABLE IF NOT EXISTS bank_transactions (
+    id TEXT PRIMARY KEY,
+    -- The bank's id for the line, or its date, amount and text: importing
+    -- overlapping statements does not duplicate lines
+    fingerprint TEXT NOT NULL UNIQUE,
+    source_file TEXT NOT NULL,
+    format TEXT NOT NULL CHECK (format IN ('csv', 'ofx', 'camt053')),
+    date TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    description TEXT,
+    reference TEXT,
+    payment_id TEXT REFERENCES payments(id),
+    bulk_payment_id TEXT REFERENCES bulk_payments(id),
+    match_method TEXT CHECK (match_method IN ('auto', 'manual')),
+    matched_at TEXT,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (payment_id IS NULL OR bulk_payment_id IS NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_bank_transactions_date ON bank_transactions(date);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bank_transactions_payment_id ON bank_transactions(payment_id) WHERE payment_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bank_transactions_bulk_payment_id ON bank_transactions(bulk_payment_id) WHERE bulk_payment_id IS NOT NULL;
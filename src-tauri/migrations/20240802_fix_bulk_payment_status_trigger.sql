@@ -0,0 +1,104 @@
+-- update_status_after_bulk_payment_change summed an invoice's direct
+-- payments without skipping those also linked to one of its sales, so a
+-- payment carrying both sale_id and invoice_id counted twice (bulk payment
+-- children always do). Same guard as the other payment triggers.
+DROP TRIGGER IF EXISTS update_status_after_bulk_payment_change;
+
+CREATE TRIGGER update_status_after_bulk_payment_change
+AFTER UPDATE OF is_deleted ON bulk_payments
+FOR EACH ROW
+WHEN OLD.is_deleted != NEW.is_deleted
+BEGIN
+  -- Update all sales with payments linked to this bulk payment
+  UPDATE sales
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN IFNULL((
+          SELECT SUM(p.amount)
+          FROM payments p
+          WHERE p.sale_id = sales.id AND p.is_deleted = 0
+        ), 0) >= sales.total_amount_ttc THEN 
+          (SELECT MIN(p.created_at) FROM payments p WHERE p.sale_id = sales.id AND p.is_deleted = 0)
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT sale_id 
+    FROM payments 
+    WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+  );
+
+  -- Update all invoices linked to affected sales
+  UPDATE invoices
+  SET
+    is_paid = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 1
+        ELSE 0
+      END
+    ),
+    paid_at = (
+      SELECT CASE
+        WHEN (
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = invoices.id AND s.sale_id = p.sale_id)
+          ), 0)
+        ) >= invoices.total_amount_ttc - invoices.credited_amount THEN 
+          (SELECT MIN(earliest_payment) FROM (
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = invoices.id
+            UNION ALL
+            SELECT MIN(p.created_at) as earliest_payment
+            FROM payments p
+            WHERE p.invoice_id = invoices.id AND p.is_deleted = 0
+          ))
+        ELSE NULL
+      END
+    ),
+    updated_at = CURRENT_TIMESTAMP
+  WHERE id IN (
+    SELECT DISTINCT invoice_id
+    FROM invoice_sales s
+    WHERE s.sale_id IN (
+      SELECT DISTINCT sale_id 
+      FROM payments 
+      WHERE bulk_payment_id = NEW.id AND sale_id IS NOT NULL
+    )
+  ) AND is_deleted = 0;
+END;
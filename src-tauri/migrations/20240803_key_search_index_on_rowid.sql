@@ -0,0 +1,157 @@
+-- The search index triggers deleted by `id`, an UNINDEXED column, so every
+-- update or delete scanned the whole index. Each index now has a side table
+-- giving the index rowid of every row, and the triggers delete by rowid.
+-- Rowids are handed out by the index itself rather than copied from the
+-- indexed table, whose rowids a VACUUM may renumber.
+
+-- Clients
+DROP TRIGGER IF EXISTS clients_fts_after_insert;
+DROP TRIGGER IF EXISTS clients_fts_after_update;
+DROP TRIGGER IF EXISTS clients_fts_after_delete;
+
+CREATE TABLE IF NOT EXISTS clients_fts_rowids (
+    id TEXT PRIMARY KEY,
+    fts_rowid INTEGER NOT NULL
+);
+
+INSERT INTO clients_fts_rowids (id, fts_rowid)
+SELECT id, MAX(rowid) FROM clients_fts GROUP BY id;
+
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_insert
+AFTER INSERT ON clients
+BEGIN
+    INSERT INTO clients_fts_rowids (id, fts_rowid)
+    VALUES (NEW.id, (SELECT IFNULL(MAX(rowid), 0) + 1 FROM clients_fts));
+    INSERT INTO clients_fts (rowid, id, name, company, nif, rc)
+    VALUES ((SELECT fts_rowid FROM clients_fts_rowids WHERE id = NEW.id), NEW.id, NEW.name, NEW.company, NEW.nif, NEW.rc);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_update
+AFTER UPDATE OF id, name, company, nif, rc ON clients
+BEGIN
+    UPDATE clients_fts_rowids SET id = NEW.id WHERE id = OLD.id;
+    DELETE FROM clients_fts WHERE rowid = (SELECT fts_rowid FROM clients_fts_rowids WHERE id = NEW.id);
+    INSERT INTO clients_fts (rowid, id, name, company, nif, rc)
+    VALUES ((SELECT fts_rowid FROM clients_fts_rowids WHERE id = NEW.id), NEW.id, NEW.name, NEW.company, NEW.nif, NEW.rc);
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_fts_after_delete
+AFTER DELETE ON clients
+BEGIN
+    DELETE FROM clients_fts WHERE rowid = (SELECT fts_rowid FROM clients_fts_rowids WHERE id = OLD.id);
+    DELETE FROM clients_fts_rowids WHERE id = OLD.id;
+END;
+
+-- Sale items
+DROP TRIGGER IF EXISTS sale_items_fts_after_insert;
+DROP TRIGGER IF EXISTS sale_items_fts_after_update;
+DROP TRIGGER IF EXISTS sale_items_fts_after_delete;
+
+CREATE TABLE IF NOT EXISTS sale_items_fts_rowids (
+    id TEXT PRIMARY KEY,
+    fts_rowid INTEGER NOT NULL
+);
+
+INSERT INTO sale_items_fts_rowids (id, fts_rowid)
+SELECT id, MAX(rowid) FROM sale_items_fts GROUP BY id;
+
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_insert
+AFTER INSERT ON sale_items
+BEGIN
+    INSERT INTO sale_items_fts_rowids (id, fts_rowid)
+    VALUES (NEW.id, (SELECT IFNULL(MAX(rowid), 0) + 1 FROM sale_items_fts));
+    INSERT INTO sale_items_fts (rowid, id, description, coil_ref, top_coat_ral, back_coat_ral)
+    VALUES ((SELECT fts_rowid FROM sale_items_fts_rowids WHERE id = NEW.id), NEW.id, NEW.description, NEW.coil_ref, NEW.top_coat_ral, NEW.back_coat_ral);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_update
+AFTER UPDATE OF id, description, coil_ref, top_coat_ral, back_coat_ral ON sale_items
+BEGIN
+    UPDATE sale_items_fts_rowids SET id = NEW.id WHERE id = OLD.id;
+    DELETE FROM sale_items_fts WHERE rowid = (SELECT fts_rowid FROM sale_items_fts_rowids WHERE id = NEW.id);
+    INSERT INTO sale_items_fts (rowid, id, description, coil_ref, top_coat_ral, back_coat_ral)
+    VALUES ((SELECT fts_rowid FROM sale_items_fts_rowids WHERE id = NEW.id), NEW.id, NEW.description, NEW.coil_ref, NEW.top_coat_ral, NEW.back_coat_ral);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sale_items_fts_after_delete
+AFTER DELETE ON sale_items
+BEGIN
+    DELETE FROM sale_items_fts WHERE rowid = (SELECT fts_rowid FROM sale_items_fts_rowids WHERE id = OLD.id);
+    DELETE FROM sale_items_fts_rowids WHERE id = OLD.id;
+END;
+
+-- Invoices
+DROP TRIGGER IF EXISTS invoices_fts_after_insert;
+DROP TRIGGER IF EXISTS invoices_fts_after_update;
+DROP TRIGGER IF EXISTS invoices_fts_after_delete;
+
+CREATE TABLE IF NOT EXISTS invoices_fts_rowids (
+    id TEXT PRIMARY KEY,
+    fts_rowid INTEGER NOT NULL
+);
+
+INSERT INTO invoices_fts_rowids (id, fts_rowid)
+SELECT id, MAX(rowid) FROM invoices_fts GROUP BY id;
+
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_insert
+AFTER INSERT ON invoices
+BEGIN
+    INSERT INTO invoices_fts_rowids (id, fts_rowid)
+    VALUES (NEW.id, (SELECT IFNULL(MAX(rowid), 0) + 1 FROM invoices_fts));
+    INSERT INTO invoices_fts (rowid, id, invoice_number, notes)
+    VALUES ((SELECT fts_rowid FROM invoices_fts_rowids WHERE id = NEW.id), NEW.id, NEW.invoice_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_update
+AFTER UPDATE OF id, invoice_number, notes ON invoices
+BEGIN
+    UPDATE invoices_fts_rowids SET id = NEW.id WHERE id = OLD.id;
+    DELETE FROM invoices_fts WHERE rowid = (SELECT fts_rowid FROM invoices_fts_rowids WHERE id = NEW.id);
+    INSERT INTO invoices_fts (rowid, id, invoice_number, notes)
+    VALUES ((SELECT fts_rowid FROM invoices_fts_rowids WHERE id = NEW.id), NEW.id, NEW.invoice_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS invoices_fts_after_delete
+AFTER DELETE ON invoices
+BEGIN
+    DELETE FROM invoices_fts WHERE rowid = (SELECT fts_rowid FROM invoices_fts_rowids WHERE id = OLD.id);
+    DELETE FROM invoices_fts_rowids WHERE id = OLD.id;
+END;
+
+-- Payments
+DROP TRIGGER IF EXISTS payments_fts_after_insert;
+DROP TRIGGER IF EXISTS payments_fts_after_update;
+DROP TRIGGER IF EXISTS payments_fts_after_delete;
+
+CREATE TABLE IF NOT EXISTS payments_fts_rowids (
+    id TEXT PRIMARY KEY,
+    fts_rowid INTEGER NOT NULL
+);
+
+INSERT INTO payments_fts_rowids (id, fts_rowid)
+SELECT id, MAX(rowid) FROM payments_fts GROUP BY id;
+
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_insert
+AFTER INSERT ON payments
+BEGIN
+    INSERT INTO payments_fts_rowids (id, fts_rowid)
+    VALUES (NEW.id, (SELECT IFNULL(MAX(rowid), 0) + 1 FROM payments_fts));
+    INSERT INTO payments_fts (rowid, id, check_number, notes)
+    VALUES ((SELECT fts_rowid FROM payments_fts_rowids WHERE id = NEW.id), NEW.id, NEW.check_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_update
+AFTER UPDATE OF id, check_number, notes ON payments
+BEGIN
+    UPDATE payments_fts_rowids SET id = NEW.id WHERE id = OLD.id;
+    DELETE FROM payments_fts WHERE rowid = (SELECT fts_rowid FROM payments_fts_rowids WHERE id = NEW.id);
+    INSERT INTO payments_fts (rowid, id, check_number, notes)
+    VALUES ((SELECT fts_rowid FROM payments_fts_rowids WHERE id = NEW.id), NEW.id, NEW.check_number, NEW.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS payments_fts_after_delete
+AFTER DELETE ON payments
+BEGIN
+    DELETE FROM payments_fts WHERE rowid = (SELECT fts_rowid FROM payments_fts_rowids WHERE id = OLD.id);
+    DELETE FROM payments_fts_rowids WHERE id = OLD.id;
+END;
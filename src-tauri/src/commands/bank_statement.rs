@@ -0,0 +1,288 @@
+use super::Money;
+use chrono::NaiveDate;
+
+/// One booked line of a bank statement. Credits are positive, debits negative.
+#[derive(Debug, Clone)]
+pub struct StatementLine {
+    /// Booking date, YYYY-MM-DD
+    pub date: String,
+    pub amount: Money,
+    pub description: Option<String>,
+    /// Cheque number or bank reference
+    pub reference: Option<String>,
+    /// The bank's own id for the line (OFX FITID, CAMT AcctSvcrRef), when it gives one
+    pub bank_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    /// 'csv', 'ofx' or 'camt053'
+    pub format: &'static str,
+    pub lines: Vec<StatementLine>,
+}
+
+/// Decodes a statement file: UTF-8 when valid, Latin-1 otherwise (what most
+/// banking portals still export), without a byte order mark.
+pub fn decode(bytes: &[u8]) -> String {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    };
+    text.trim_start_matches('\u{feff}').to_string()
+}
+
+/// Recognises the format from the content rather than the file extension.
+pub fn parse(content: &str) -> Result<Statement, String> {
+    let head: String = content.chars().take(2000).collect::<String>().to_uppercase();
+    let (format, lines) = if head.contains("OFXHEADER") || head.contains("<OFX>") {
+        ("ofx", parse_ofx(content)?)
+    } else if head.contains("CAMT.053") || head.contains("<BKTOCSTMRSTMT>") {
+        ("camt053", parse_camt053(content)?)
+    } else {
+        ("csv", parse_csv(content)?)
+    };
+    if lines.is_empty() {
+        return Err("The statement contains no transactions".to_string());
+    }
+    Ok(Statement { format, lines })
+}
+
+/// Accepts ISO dates, day-first dates with '/', '-' or '.', and the compact
+/// YYYYMMDD[hhmmss...] form of OFX.
+pub fn parse_date(value: &str) -> Option<String> {
+    let value = value.trim();
+    let compact: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let date = if compact.len() >= 8 {
+        NaiveDate::parse_from_str(&compact[..8], "%Y%m%d").ok()
+    } else {
+        let day = value.split(['T', ' ']).next().unwrap_or(value);
+        ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%Y", "%d/%m/%y"]
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(day, format).ok())
+    };
+    date.map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+/// Reads "1 234,56", "1.234,56", "1,234.56" or "-1234.56": the last of ',' and
+/// '.' is the decimal separator, the other one groups thousands.
+pub fn parse_amount(value: &str) -> Option<Money> {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\u{a0}' && *c != '\u{202f}' && *c != '\'')
+        .collect();
+    let cleaned = cleaned.trim_end_matches(|c: char| c.is_alphabetic());
+    let normalized = match (cleaned.rfind(','), cleaned.rfind('.')) {
+        (Some(comma), Some(dot)) if comma > dot => cleaned.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => cleaned.replace(',', ""),
+        (Some(_), None) => cleaned.replace(',', "."),
+        _ => cleaned.to_string(),
+    };
+    let negative = normalized.starts_with('(') && normalized.ends_with(')');
+    let units: f64 = normalized.trim_matches(['(', ')']).parse().ok()?;
+    let amount = Money::from_units(units);
+    Some(if negative { -amount } else { amount })
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// --- CSV ---
+
+/// Splits one CSV record, honouring double-quoted fields.
+fn csv_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Lowercased header without accents, so "Libellé" and "libelle" compare equal.
+fn header_key(header: &str) -> String {
+    header
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'à' | 'â' => 'a',
+            'ô' => 'o',
+            'û' | 'ù' => 'u',
+            'î' | 'ï' => 'i',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    names
+        .iter()
+        .find_map(|name| headers.iter().position(|h| h == name))
+        .or_else(|| names.iter().find_map(|name| headers.iter().position(|h| h.starts_with(name))))
+}
+
+/// CSV exports differ per bank: the header row names the columns, and the
+/// amount is either one signed column or separate debit and credit columns.
+fn parse_csv(content: &str) -> Result<Vec<StatementLine>, String> {
+    let mut records = content.lines().filter(|line| !line.trim().is_empty());
+    let header_line = records.next().ok_or("The statement file is empty")?;
+    let delimiter = [';', '\t', ',']
+        .into_iter()
+        .max_by_key(|d| header_line.matches(*d).count())
+        .unwrap_or(',');
+    let headers: Vec<String> = csv_record(header_line, delimiter).iter().map(|h| header_key(h)).collect();
+
+    let date = find_column(&headers, &["date operation", "booking date", "date comptable", "date"])
+        .ok_or("The CSV statement has no date column")?;
+    let amount = find_column(&headers, &["montant", "amount"]);
+    let debit = find_column(&headers, &["debit"]);
+    let credit = find_column(&headers, &["credit"]);
+    if amount.is_none() && debit.is_none() && credit.is_none() {
+        return Err("The CSV statement has no amount, debit or credit column".to_string());
+    }
+    let description = find_column(&headers, &["libelle", "description", "label", "details", "operation"]);
+    let reference = find_column(&headers, &["reference", "n cheque", "no cheque", "cheque", "check number", "ref"]);
+
+    let mut lines = Vec::new();
+    for (i, record) in records.enumerate() {
+        let fields = csv_record(record, delimiter);
+        let field = |column: Option<usize>| column.and_then(|c| fields.get(c)).map(|f| f.trim()).unwrap_or("");
+        let row = i + 2;
+        let date_text = field(Some(date));
+        // Balance and total rows carry no date
+        if date_text.is_empty() {
+            continue;
+        }
+        let date = parse_date(date_text).ok_or_else(|| format!("Line {}: invalid date '{}'", row, date_text))?;
+        let amount = if amount.is_some() {
+            parse_amount(field(amount)).ok_or_else(|| format!("Line {}: invalid amount '{}'", row, field(amount)))?
+        } else {
+            let debit = parse_amount(field(debit)).unwrap_or_default().abs();
+            let credit = parse_amount(field(credit)).unwrap_or_default().abs();
+            credit - debit
+        };
+        if amount.is_zero() {
+            continue;
+        }
+        lines.push(StatementLine {
+            date,
+            amount,
+            description: non_empty(field(description)),
+            reference: non_empty(field(reference)),
+            bank_id: None,
+        });
+    }
+    Ok(lines)
+}
+
+// --- OFX ---
+
+/// Value of an OFX element; SGML-style OFX 1.x leaves elements unclosed, so
+/// the value runs to the next tag or line break.
+fn ofx_value(block: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let start = block.find(&open)? + open.len();
+    let rest = &block[start..];
+    let end = rest.find(['<', '\n', '\r']).unwrap_or(rest.len());
+    non_empty(&unescape_xml(&rest[..end]))
+}
+
+fn parse_ofx(content: &str) -> Result<Vec<StatementLine>, String> {
+    let mut lines = Vec::new();
+    for block in content.split("<STMTTRN>").skip(1) {
+        let block = block.split("</STMTTRN>").next().unwrap_or(block);
+        let posted = ofx_value(block, "DTPOSTED").ok_or("OFX transaction without DTPOSTED")?;
+        let date = parse_date(&posted).ok_or_else(|| format!("Invalid OFX date '{}'", posted))?;
+        let amount_text = ofx_value(block, "TRNAMT").ok_or("OFX transaction without TRNAMT")?;
+        let amount = parse_amount(&amount_text).ok_or_else(|| format!("Invalid OFX amount '{}'", amount_text))?;
+        let description = match (ofx_value(block, "NAME"), ofx_value(block, "MEMO")) {
+            (Some(name), Some(memo)) if name != memo => Some(format!("{} {}", name, memo)),
+            (name, memo) => name.or(memo),
+        };
+        lines.push(StatementLine {
+            date,
+            amount,
+            description,
+            reference: ofx_value(block, "CHECKNUM").or_else(|| ofx_value(block, "REFNUM")),
+            bank_id: ofx_value(block, "FITID"),
+        });
+    }
+    Ok(lines)
+}
+
+// --- CAMT.053 ---
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Text of the first `tag` element in `xml`, attributes allowed.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}", tag);
+    let mut from = 0;
+    while let Some(found) = xml[from..].find(&open) {
+        let start = from + found + open.len();
+        let rest = &xml[start..];
+        // Skip longer tags sharing the prefix, e.g. <Dt> and <DtTm>
+        if rest.starts_with('>') || rest.starts_with(' ') {
+            let body = &rest[rest.find('>')? + 1..];
+            let end = body.find(&format!("</{}>", tag))?;
+            return Some(&body[..end]);
+        }
+        from = start;
+    }
+    None
+}
+
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    xml_text(xml, tag).and_then(|text| non_empty(&unescape_xml(text)))
+}
+
+fn parse_camt053(content: &str) -> Result<Vec<StatementLine>, String> {
+    let mut lines = Vec::new();
+    for entry in content.split("<Ntry>").skip(1) {
+        let entry = entry.split("</Ntry>").next().unwrap_or(entry);
+        let amount_text = xml_value(entry, "Amt").ok_or("CAMT entry without Amt")?;
+        let amount = parse_amount(&amount_text).ok_or_else(|| format!("Invalid CAMT amount '{}'", amount_text))?;
+        let amount = match xml_value(entry, "CdtDbtInd").as_deref() {
+            Some("DBIT") => -amount.abs(),
+            _ => amount.abs(),
+        };
+        let booked = xml_text(entry, "BookgDt").or_else(|| xml_text(entry, "ValDt")).ok_or("CAMT entry without BookgDt")?;
+        let date_text = xml_value(booked, "Dt").or_else(|| xml_value(booked, "DtTm")).unwrap_or_default();
+        let date = parse_date(&date_text).ok_or_else(|| format!("Invalid CAMT date '{}'", date_text))?;
+        let description = xml_value(entry, "AddtlNtryInf")
+            .or_else(|| xml_value(entry, "Ustrd"))
+            .or_else(|| xml_value(entry, "AddtlTxInf"));
+        let reference = xml_value(entry, "ChqNb")
+            .or_else(|| xml_value(entry, "EndToEndId").filter(|id| id != "NOTPROVIDED"));
+        lines.push(StatementLine {
+            date,
+            amount,
+            description,
+            reference,
+            bank_id: xml_value(entry, "AcctSvcrRef").or_else(|| xml_value(entry, "NtryRef")),
+        });
+    }
+    Ok(lines)
+}
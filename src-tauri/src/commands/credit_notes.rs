@@ -0,0 +1,444 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+use chrono::Utc;
+
+use super::{
+    insert_audit_log, insert_credit_transaction, invoice_payment_totals, next_document_number, vat_by_rate, Money,
+    CREDIT_NOTE_NUMBERING,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditNoteItem {
+    pub id: String,
+    pub credit_note_id: String,
+    pub sale_id: String,
+    /// None for the part of the sale not carried by its items
+    pub sale_item_id: Option<String>,
+    pub description: String,
+    pub tax_rate: f64,
+    /// HT credited
+    pub total_amount: Money,
+    pub tax_amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditNote {
+    pub id: String,
+    pub credit_note_number: String,
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub client_id: String,
+    pub date: String,
+    pub reason: Option<String>,
+    pub total_amount_ht: Money,
+    pub tax_amount: Money,
+    pub stamp_duty: Money,
+    pub total_amount_ttc: Money,
+    pub created_at: String,
+    pub items: Vec<CreditNoteItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditNoteLineRequest {
+    pub sale_item_id: String,
+    /// HT to credit on the item; the whole of what is left when None
+    pub amount: Option<Money>,
+}
+
+/// Credits the listed `lines`, or `amount` TTC spread over the invoice pro
+/// rata, or everything left on the invoice when neither is given.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCreditNoteRequest {
+    pub invoice_id: String,
+    pub date: String,
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub lines: Vec<CreditNoteLineRequest>,
+    pub amount: Option<Money>,
+}
+
+/// A line of the invoice that can still be credited: a sale item, or the
+/// part of a sale its items do not carry.
+struct CreditableLine {
+    sale_id: String,
+    sale_item_id: Option<String>,
+    description: String,
+    tax_rate: f64,
+    remaining: Money,
+}
+
+/// What is left to credit on the invoice's live sales, line by line, and
+/// the stamp duty not credited yet.
+async fn invoice_creditable_lines(conn: &mut sqlx::SqliteConnection, invoice_id: &str) -> Result<(Vec<CreditableLine>, Money), String> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id AS sale_id, si.id AS sale_item_id, si.description, si.total_amount AS amount,
+               CASE WHEN s.tax_exemption IS NOT NULL THEN 0.0
+                    ELSE COALESCE(si.tax_rate, s.tax_rate, (SELECT tax_rate FROM settings LIMIT 1), 0.0) END AS tax_rate,
+               IFNULL((SELECT SUM(ci.total_amount) FROM credit_note_items ci WHERE ci.sale_item_id = si.id), 0) AS credited
+        FROM invoice_sales l
+        JOIN sales s ON s.id = l.sale_id AND (s.is_deleted = 0 OR s.is_deleted IS NULL)
+        JOIN sale_items si ON si.sale_id = s.id
+        WHERE l.invoice_id = ?1
+        UNION ALL
+        SELECT s.id, NULL,
+               CASE WHEN IFNULL(s.transportation_fee, 0) <> 0 THEN 'Transportation fee' ELSE 'Sale of ' || substr(s.date, 1, 10) END,
+               s.total_amount - IFNULL((SELECT SUM(si.total_amount) FROM sale_items si WHERE si.sale_id = s.id), 0),
+               CASE WHEN s.tax_exemption IS NOT NULL THEN 0.0
+                    ELSE COALESCE(s.tax_rate, (SELECT tax_rate FROM settings LIMIT 1), 0.0) END,
+               IFNULL((SELECT SUM(ci.total_amount) FROM credit_note_items ci WHERE ci.sale_id = s.id AND ci.sale_item_id IS NULL), 0)
+        FROM invoice_sales l
+        JOIN sales s ON s.id = l.sale_id AND (s.is_deleted = 0 OR s.is_deleted IS NULL)
+        WHERE l.invoice_id = ?1
+        "#
+    )
+    .bind(invoice_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    let lines = rows.iter()
+        .map(|row| CreditableLine {
+            sale_id: row.get("sale_id"),
+            sale_item_id: row.get("sale_item_id"),
+            description: row.get("description"),
+            tax_rate: row.get("tax_rate"),
+            remaining: row.get::<Money, _>("amount") - row.get::<Money, _>("credited"),
+        })
+        .filter(|line| line.remaining.is_positive())
+        .collect();
+    let stamp_duty: Money = sqlx::query_scalar(
+        r#"
+        SELECT IFNULL((SELECT SUM(s.stamp_duty) FROM invoice_sales l JOIN sales s ON s.id = l.sale_id
+                       WHERE l.invoice_id = ?1 AND (s.is_deleted = 0 OR s.is_deleted IS NULL)), 0)
+             - IFNULL((SELECT SUM(cn.stamp_duty) FROM credit_notes cn WHERE cn.invoice_id = ?1), 0)
+        "#
+    )
+    .bind(invoice_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok((lines, stamp_duty.max(Money::ZERO)))
+}
+
+/// Splits `total` over `weights` pro rata; the last share takes the rounding.
+fn allocate_pro_rata(total: Money, weights: &[Money]) -> Vec<Money> {
+    let sum: Money = weights.iter().sum();
+    let mut shares = Vec::with_capacity(weights.len());
+    let mut allocated = Money::ZERO;
+    for (i, weight) in weights.iter().enumerate() {
+        let share = if i + 1 == weights.len() {
+            total - allocated
+        } else if sum.is_zero() {
+            Money::ZERO
+        } else {
+            total.mul_f64(weight.centimes() as f64 / sum.centimes() as f64)
+        };
+        allocated += share;
+        shares.push(share);
+    }
+    shares
+}
+
+/// Turns the part of an invoice's credit notes that gives back money already
+/// paid into client credit, newest note first. Re-run whenever the invoice's
+/// payments or credit notes change.
+pub(super) async fn sync_invoice_credit_notes(conn: &mut sqlx::SqliteConnection, invoice_id: &str) -> Result<(), String> {
+    let Some((total, paid)) = invoice_payment_totals(conn, invoice_id).await? else {
+        return Ok(());
+    };
+    sqlx::query("DELETE FROM credit_transactions WHERE source_type = 'credit_note' AND source_id IN (SELECT id FROM credit_notes WHERE invoice_id = ?)")
+        .bind(invoice_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let notes = sqlx::query("SELECT id, credit_note_number, client_id, total_amount_ttc FROM credit_notes WHERE invoice_id = ? ORDER BY date DESC, created_at DESC")
+        .bind(invoice_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let credited: Money = notes.iter().map(|note| note.get::<Money, _>("total_amount_ttc")).sum();
+    // Payments beyond the total are already credited as overpayments
+    let mut refundable = (paid.min(total) + credited - total).max(Money::ZERO);
+    for note in &notes {
+        let amount = refundable.min(note.get("total_amount_ttc"));
+        if !amount.is_positive() {
+            break;
+        }
+        let id: String = note.get("id");
+        let client_id: String = note.get("client_id");
+        let notes = format!("Credit note {}", note.get::<String, _>("credit_note_number"));
+        insert_credit_transaction(conn, &client_id, amount, "credit", "credit_note", Some(&id), Some(&notes)).await?;
+        refundable -= amount;
+    }
+    Ok(())
+}
+
+/// Rejects changes to a sale whose invoice has credit notes: they point at
+/// the sale's items. `action` says what was attempted.
+pub(super) async fn ensure_no_credit_notes(conn: &mut sqlx::SqliteConnection, sale_id: &str, action: &str) -> Result<(), String> {
+    let numbers: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT cn.credit_note_number FROM credit_notes cn
+        WHERE cn.invoice_id IN (SELECT invoice_id FROM invoice_sales WHERE sale_id = ?1)
+           OR cn.id IN (SELECT credit_note_id FROM credit_note_items WHERE sale_id = ?1)
+        ORDER BY cn.credit_note_number
+        "#
+    )
+    .bind(sale_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if numbers.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("The sale's invoice has credit notes ({}) and the sale cannot be {}", numbers.join(", "), action))
+    }
+}
+
+fn credit_note_item_from_row(row: &sqlx::sqlite::SqliteRow) -> CreditNoteItem {
+    CreditNoteItem {
+        id: row.get("id"),
+        credit_note_id: row.get("credit_note_id"),
+        sale_id: row.get("sale_id"),
+        sale_item_id: row.get("sale_item_id"),
+        description: row.get("description"),
+        tax_rate: row.get("tax_rate"),
+        total_amount: row.get("total_amount"),
+        tax_amount: row.get("tax_amount"),
+    }
+}
+
+async fn fetch_credit_notes(conn: &mut sqlx::SqliteConnection, where_clause: &str, params: &[&str]) -> Result<Vec<CreditNote>, String> {
+    let query = format!(
+        r#"
+        SELECT cn.*, i.invoice_number
+        FROM credit_notes cn
+        JOIN invoices i ON i.id = cn.invoice_id
+        {}
+        ORDER BY cn.date, cn.credit_note_number
+        "#,
+        where_clause
+    );
+    let mut q = sqlx::query(&query);
+    for param in params {
+        q = q.bind(*param);
+    }
+    let rows = q.fetch_all(&mut *conn).await.map_err(|e| e.to_string())?;
+    let mut notes = Vec::new();
+    for row in rows {
+        let id: String = row.get("id");
+        let items = sqlx::query("SELECT * FROM credit_note_items WHERE credit_note_id = ? ORDER BY rowid")
+            .bind(&id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        notes.push(CreditNote {
+            id,
+            credit_note_number: row.get("credit_note_number"),
+            invoice_id: row.get("invoice_id"),
+            invoice_number: row.get("invoice_number"),
+            client_id: row.get("client_id"),
+            date: row.get("date"),
+            reason: row.get("reason"),
+            total_amount_ht: row.get("total_amount_ht"),
+            tax_amount: row.get("tax_amount"),
+            stamp_duty: row.get("stamp_duty"),
+            total_amount_ttc: row.get("total_amount_ttc"),
+            created_at: row.get("created_at"),
+            items: items.iter().map(credit_note_item_from_row).collect(),
+        });
+    }
+    Ok(notes)
+}
+
+/// Issues a credit note against an invoice: in full, on some of its lines,
+/// or for an amount spread over its lines. The invoice's balance drops by
+/// the credit note's TTC, and whatever the client had already paid of it
+/// becomes client credit.
+#[tauri::command]
+pub async fn create_credit_note(
+    credit_note: CreateCreditNoteRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<CreditNote, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let invoice = sqlx::query("SELECT invoice_number, client_id, total_amount_ttc, credited_amount FROM invoices WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
+        .bind(&credit_note.invoice_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Invoice not found".to_string())?;
+    let invoice_number: String = invoice.get("invoice_number");
+    let client_id: String = invoice.get("client_id");
+    let left = invoice.get::<Money, _>("total_amount_ttc") - invoice.get::<Money, _>("credited_amount");
+    if !left.is_positive() {
+        return Err(format!("Invoice {} is already fully credited", invoice_number));
+    }
+    if !credit_note.lines.is_empty() && credit_note.amount.is_some() {
+        return Err("A credit note is either for lines or for an amount, not both".to_string());
+    }
+
+    let (creditable, stamp_duty_left) = invoice_creditable_lines(&mut tx, &credit_note.invoice_id).await?;
+    // (line, HT credited), the stamp duty given back, and the TTC to reach exactly
+    let mut lines: Vec<(&CreditableLine, Money)> = Vec::new();
+    let mut stamp_duty = Money::ZERO;
+    let mut target = None;
+    if !credit_note.lines.is_empty() {
+        for request in &credit_note.lines {
+            let line = creditable.iter()
+                .find(|line| line.sale_item_id.as_deref() == Some(request.sale_item_id.as_str()))
+                .ok_or_else(|| format!("Item {} is not on invoice {} or is already fully credited", request.sale_item_id, invoice_number))?;
+            if lines.iter().any(|(l, _)| l.sale_item_id == line.sale_item_id) {
+                return Err(format!("Item {} is listed twice", request.sale_item_id));
+            }
+            let amount = request.amount.unwrap_or(line.remaining);
+            if !amount.is_positive() || amount > line.remaining {
+                return Err(format!("Item {} can be credited for at most {}", request.sale_item_id, line.remaining));
+            }
+            lines.push((line, amount));
+        }
+    } else {
+        let amount = credit_note.amount.unwrap_or(left);
+        if !amount.is_positive() || amount > left {
+            return Err(format!("Invoice {} can be credited for at most {}", invoice_number, left));
+        }
+        let vat = vat_by_rate(creditable.iter().map(|line| (line.tax_rate, line.remaining)));
+        let creditable_ttc = vat.iter().map(|g| g.taxable_amount + g.tax_amount).sum::<Money>() + stamp_duty_left;
+        let share = if creditable_ttc.is_positive() { (amount.centimes() as f64 / creditable_ttc.centimes() as f64).min(1.0) } else { 0.0 };
+        lines = creditable.iter().map(|line| (line, line.remaining.mul_f64(share))).collect();
+        stamp_duty = stamp_duty_left.mul_f64(share);
+        target = Some(amount);
+    }
+
+    let mut vat = vat_by_rate(lines.iter().map(|(line, amount)| (line.tax_rate, *amount)));
+    if let Some(target) = target {
+        // Rounding per rate may miss the amount by a few centimes: the VAT of
+        // the largest taxed rate absorbs it, or the largest line when none is taxed
+        let drift = target - vat.iter().map(|g| g.taxable_amount + g.tax_amount).sum::<Money>() - stamp_duty;
+        if !drift.is_zero() {
+            match vat.iter_mut().filter(|g| g.tax_rate > 0.0).max_by_key(|g| g.taxable_amount) {
+                Some(group) => group.tax_amount += drift,
+                None => {
+                    if let Some((_, amount)) = lines.iter_mut().max_by_key(|(_, amount)| *amount) {
+                        *amount += drift;
+                    }
+                    vat = vat_by_rate(lines.iter().map(|(line, amount)| (line.tax_rate, *amount)));
+                }
+            }
+        }
+    }
+    lines.retain(|(_, amount)| !amount.is_zero());
+    let total_amount_ht: Money = vat.iter().map(|g| g.taxable_amount).sum();
+    let tax_amount: Money = vat.iter().map(|g| g.tax_amount).sum();
+    let total_amount_ttc = total_amount_ht + tax_amount + stamp_duty;
+    if !total_amount_ttc.is_positive() {
+        return Err(format!("Invoice {} has nothing left to credit", invoice_number));
+    }
+    if total_amount_ttc > left {
+        return Err(format!("Invoice {} can be credited for at most {}", invoice_number, left));
+    }
+
+    // Each rate's VAT is shared among its lines so the lines add up to the header
+    let mut line_taxes = vec![Money::ZERO; lines.len()];
+    for group in &vat {
+        let members: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].0.tax_rate == group.tax_rate).collect();
+        let weights: Vec<Money> = members.iter().map(|&i| lines[i].1).collect();
+        for (i, tax) in members.into_iter().zip(allocate_pro_rata(group.tax_amount, &weights)) {
+            line_taxes[i] = tax;
+        }
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let number = next_document_number(&mut tx, &CREDIT_NOTE_NUMBERING, &credit_note.date).await?;
+    let reason = credit_note.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    sqlx::query(
+        r#"
+        INSERT INTO credit_notes (id, credit_note_number, invoice_id, client_id, date, reason, total_amount_ht, tax_amount, stamp_duty, total_amount_ttc, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&id)
+    .bind(&number)
+    .bind(&credit_note.invoice_id)
+    .bind(&client_id)
+    .bind(&credit_note.date)
+    .bind(reason)
+    .bind(total_amount_ht)
+    .bind(tax_amount)
+    .bind(stamp_duty)
+    .bind(total_amount_ttc)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    for ((line, amount), tax) in lines.iter().zip(&line_taxes) {
+        sqlx::query(
+            r#"
+            INSERT INTO credit_note_items (id, credit_note_id, sale_id, sale_item_id, description, tax_rate, total_amount, tax_amount, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&id)
+        .bind(&line.sale_id)
+        .bind(&line.sale_item_id)
+        .bind(&line.description)
+        .bind(line.tax_rate)
+        .bind(*amount)
+        .bind(*tax)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    // Fires update_invoice_payment_status_after_total_update
+    sqlx::query("UPDATE invoices SET credited_amount = credited_amount + ?, updated_at = ? WHERE id = ?")
+        .bind(total_amount_ttc)
+        .bind(&now)
+        .bind(&credit_note.invoice_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sync_invoice_credit_notes(&mut tx, &credit_note.invoice_id).await?;
+
+    let details = format!("Credit note {} of {} on invoice {}", number, total_amount_ttc, invoice_number);
+    insert_audit_log(&mut *tx, "create", "credit_note", &id, None, Some(&details)).await?;
+    let created = fetch_credit_notes(&mut tx, "WHERE cn.id = ?", &[&id]).await?
+        .pop()
+        .ok_or_else(|| "Credit note not found".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+/// Credit notes of an invoice or a client, oldest first.
+#[tauri::command]
+pub async fn get_credit_notes(
+    invoice_id: Option<String>,
+    client_id: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<CreditNote>, String> {
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    if let Some(invoice_id) = invoice_id.as_deref() {
+        conditions.push("cn.invoice_id = ?");
+        params.push(invoice_id);
+    }
+    if let Some(client_id) = client_id.as_deref() {
+        conditions.push("cn.client_id = ?");
+        params.push(client_id);
+    }
+    let where_clause = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_credit_notes(&mut conn, &where_clause, &params).await
+}
+
+#[tauri::command]
+pub async fn get_credit_note(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Option<CreditNote>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    Ok(fetch_credit_notes(&mut conn, "WHERE cn.id = ?", &[&id]).await?.pop())
+}
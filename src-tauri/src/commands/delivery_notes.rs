@@ -0,0 +1,462 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+use chrono::Utc;
+
+use super::{
+    document_client, document_letterhead, insert_audit_log, item_details_from_row, next_document_number, pdf, write_document,
+    DELIVERY_NOTE_NUMBERING,
+};
+
+/// Values of `delivery_status` on sales.
+pub const DELIVERY_STATUSES: [&str; 3] = ["undelivered", "partial", "delivered"];
+
+/// Float drift allowed when adding up delivered tons and units.
+const DELIVERY_TOLERANCE: f64 = 1e-6;
+
+/// Coils and steel slitting are delivered by weight, everything else by quantity.
+fn delivered_by_weight(product_type: &str) -> bool {
+    matches!(product_type, "coil" | "steel_slitting")
+}
+
+/// What the sale item aliased `si` is for, in the measure delivery notes use.
+const ITEM_ORDERED_SQL: &str = "CASE WHEN si.product_type IN ('coil', 'steel_slitting') THEN IFNULL(si.coil_weight, 0.0) ELSE si.quantity END";
+
+/// What the live delivery notes of the sale item aliased `si` carried, in the
+/// measure of `ITEM_ORDERED_SQL`.
+pub(super) const ITEM_DELIVERED_SQL: &str = r#"IFNULL((
+          SELECT SUM(CASE WHEN si.product_type IN ('coil', 'steel_slitting') THEN IFNULL(dni.weight, 0.0) ELSE IFNULL(dni.quantity, 0.0) END)
+          FROM delivery_note_items dni
+          JOIN delivery_notes dn ON dn.id = dni.delivery_note_id AND dn.is_deleted = 0
+          WHERE dni.sale_item_id = si.id
+        ), 0.0)"#;
+
+/// The delivery status of the sale aliased `s`. Sales without items, and
+/// sales recorded before delivery notes existed, count as delivered.
+pub(super) fn sale_delivery_status_sql() -> String {
+    format!(
+        r#"(SELECT CASE
+              WHEN s.tracks_delivery = 0 OR COUNT(*) = 0 OR MIN(d.delivered - d.ordered) > -{tolerance} THEN 'delivered'
+              WHEN MAX(d.delivered) > 0 THEN 'partial'
+              ELSE 'undelivered'
+            END
+           FROM (SELECT {ordered} AS ordered, {delivered} AS delivered FROM sale_items si WHERE si.sale_id = s.id) d)"#,
+        tolerance = DELIVERY_TOLERANCE,
+        ordered = ITEM_ORDERED_SQL,
+        delivered = ITEM_DELIVERED_SQL,
+    )
+}
+
+/// Invoices carry whole sales, so a sale is invoiced once all of it is delivered.
+pub(super) async fn ensure_sale_delivered(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<(), String> {
+    let status: Option<String> = sqlx::query_scalar(&format!("SELECT {} FROM sales s WHERE s.id = ?", sale_delivery_status_sql()))
+        .bind(sale_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match status.as_deref() {
+        Some("partial") | Some("undelivered") => Err(format!("Sale {} is not fully delivered and cannot be invoiced yet", sale_id)),
+        _ => Ok(()),
+    }
+}
+
+/// Rejects changes to a sale that has live delivery notes; `action` says what
+/// was attempted.
+pub(super) async fn ensure_no_delivery_notes(conn: &mut sqlx::SqliteConnection, sale_id: &str, action: &str) -> Result<(), String> {
+    let numbers: Vec<String> = sqlx::query_scalar("SELECT delivery_note_number FROM delivery_notes WHERE sale_id = ? AND is_deleted = 0 ORDER BY delivery_note_number")
+        .bind(sale_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if numbers.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("The sale has delivery notes ({}) and cannot be {}; delete them first", numbers.join(", "), action))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliveryNoteItem {
+    pub id: String,
+    pub delivery_note_id: String,
+    pub sale_item_id: String,
+    pub description: String,
+    pub product_type: String,
+    /// Tons loaded; what counts for coils and steel slitting
+    pub weight: Option<f64>,
+    /// Units loaded; what counts for the other products
+    pub quantity: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliveryNote {
+    pub id: String,
+    pub delivery_note_number: String,
+    pub sale_id: String,
+    pub client_id: String,
+    pub date: String,
+    pub truck: Option<String>,
+    pub driver: Option<String>,
+    pub plate: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub items: Vec<DeliveryNoteItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliveryNoteLineRequest {
+    pub sale_item_id: String,
+    /// Tons loaded; coils and steel slitting take what is left when None
+    pub weight: Option<f64>,
+    /// Units loaded; the other products take what is left when None
+    pub quantity: Option<f64>,
+}
+
+/// Delivers the listed `lines`, or everything the sale still has to deliver
+/// when none are given.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateDeliveryNoteRequest {
+    pub sale_id: String,
+    pub date: String,
+    pub truck: Option<String>,
+    pub driver: Option<String>,
+    pub plate: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub lines: Vec<DeliveryNoteLineRequest>,
+}
+
+/// A sale item and how much of it is left to deliver.
+struct DeliverableLine {
+    sale_item_id: String,
+    by_weight: bool,
+    remaining: f64,
+}
+
+async fn sale_deliverable_lines(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<Vec<DeliverableLine>, String> {
+    let rows = sqlx::query(&format!(
+        "SELECT si.id, si.product_type, {} AS ordered, {} AS delivered FROM sale_items si WHERE si.sale_id = ? ORDER BY si.created_at, si.rowid",
+        ITEM_ORDERED_SQL, ITEM_DELIVERED_SQL
+    ))
+    .bind(sale_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows.iter()
+        .map(|row| DeliverableLine {
+            sale_item_id: row.get("id"),
+            by_weight: delivered_by_weight(row.get("product_type")),
+            remaining: (row.get::<f64, _>("ordered") - row.get::<f64, _>("delivered")).max(0.0),
+        })
+        .collect())
+}
+
+fn delivery_note_item_from_row(row: &sqlx::sqlite::SqliteRow) -> DeliveryNoteItem {
+    DeliveryNoteItem {
+        id: row.get("id"),
+        delivery_note_id: row.get("delivery_note_id"),
+        sale_item_id: row.get("sale_item_id"),
+        description: row.get("description"),
+        product_type: row.get("product_type"),
+        weight: row.get("weight"),
+        quantity: row.get("quantity"),
+    }
+}
+
+async fn fetch_delivery_notes(conn: &mut sqlx::SqliteConnection, where_clause: &str, params: &[&str]) -> Result<Vec<DeliveryNote>, String> {
+    let query = format!("SELECT dn.* FROM delivery_notes dn {} ORDER BY dn.date, dn.delivery_note_number", where_clause);
+    let mut q = sqlx::query(&query);
+    for param in params {
+        q = q.bind(*param);
+    }
+    let rows = q.fetch_all(&mut *conn).await.map_err(|e| e.to_string())?;
+    let mut notes = Vec::new();
+    for row in rows {
+        let id: String = row.get("id");
+        let items = sqlx::query(
+            r#"
+            SELECT dni.*, si.description, si.product_type
+            FROM delivery_note_items dni
+            JOIN sale_items si ON si.id = dni.sale_item_id
+            WHERE dni.delivery_note_id = ?
+            ORDER BY dni.rowid
+            "#
+        )
+        .bind(&id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        notes.push(DeliveryNote {
+            id,
+            delivery_note_number: row.get("delivery_note_number"),
+            sale_id: row.get("sale_id"),
+            client_id: row.get("client_id"),
+            date: row.get("date"),
+            truck: row.get("truck"),
+            driver: row.get("driver"),
+            plate: row.get("plate"),
+            notes: row.get("notes"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            items: items.iter().map(delivery_note_item_from_row).collect(),
+        });
+    }
+    Ok(notes)
+}
+
+/// Records a trip delivering part or all of a sale. Coils and steel slitting
+/// are delivered by weight, other products by quantity; the other measure
+/// may be given for the record. Nothing can be delivered beyond the sale.
+#[tauri::command]
+pub async fn create_delivery_note(
+    delivery_note: CreateDeliveryNoteRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<DeliveryNote, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let client_id: String = sqlx::query_scalar("SELECT client_id FROM sales WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
+        .bind(&delivery_note.sale_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Sale not found".to_string())?;
+
+    let deliverable = sale_deliverable_lines(&mut tx, &delivery_note.sale_id).await?;
+    // (sale item, weight, quantity)
+    let mut lines: Vec<(&str, Option<f64>, Option<f64>)> = Vec::new();
+    if delivery_note.lines.is_empty() {
+        for line in deliverable.iter().filter(|line| line.remaining > DELIVERY_TOLERANCE) {
+            let (weight, quantity) = if line.by_weight { (Some(line.remaining), None) } else { (None, Some(line.remaining)) };
+            lines.push((&line.sale_item_id, weight, quantity));
+        }
+    } else {
+        for request in &delivery_note.lines {
+            let line = deliverable.iter()
+                .find(|line| line.sale_item_id == request.sale_item_id)
+                .ok_or_else(|| format!("Item {} is not on this sale", request.sale_item_id))?;
+            if lines.iter().any(|(id, _, _)| *id == line.sale_item_id) {
+                return Err(format!("Item {} is listed twice", request.sale_item_id));
+            }
+            let (weight, quantity) = if line.by_weight {
+                (Some(request.weight.unwrap_or(line.remaining)), request.quantity)
+            } else {
+                (request.weight, Some(request.quantity.unwrap_or(line.remaining)))
+            };
+            let delivered = if line.by_weight { weight } else { quantity }.unwrap_or(0.0);
+            if !delivered.is_finite() || delivered <= 0.0 || delivered > line.remaining + DELIVERY_TOLERANCE {
+                return Err(format!("Item {} can be delivered for at most {}", request.sale_item_id, line.remaining));
+            }
+            if weight.into_iter().chain(quantity).any(|value| !value.is_finite() || value < 0.0) {
+                return Err(format!("Item {} has a negative weight or quantity", request.sale_item_id));
+            }
+            lines.push((&line.sale_item_id, weight, quantity));
+        }
+    }
+    if lines.is_empty() {
+        return Err("The sale is already fully delivered".to_string());
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let number = next_document_number(&mut tx, &DELIVERY_NOTE_NUMBERING, &delivery_note.date).await?;
+    let text = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    sqlx::query(
+        r#"
+        INSERT INTO delivery_notes (id, delivery_note_number, sale_id, client_id, date, truck, driver, plate, notes, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&id)
+    .bind(&number)
+    .bind(&delivery_note.sale_id)
+    .bind(&client_id)
+    .bind(&delivery_note.date)
+    .bind(text(&delivery_note.truck))
+    .bind(text(&delivery_note.driver))
+    .bind(text(&delivery_note.plate))
+    .bind(text(&delivery_note.notes))
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    for (sale_item_id, weight, quantity) in &lines {
+        sqlx::query("INSERT INTO delivery_note_items (id, delivery_note_id, sale_item_id, weight, quantity, created_at) VALUES (?, ?, ?, ?, ?, ?)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&id)
+            .bind(sale_item_id)
+            .bind(weight)
+            .bind(quantity)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let details = format!("Delivery note {} for sale {}", number, delivery_note.sale_id);
+    insert_audit_log(&mut *tx, "create", "delivery_note", &id, None, Some(&details)).await?;
+    let created = fetch_delivery_notes(&mut tx, "WHERE dn.id = ?", &[&id]).await?
+        .pop()
+        .ok_or_else(|| "Delivery note not found".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+/// Live delivery notes of a sale or a client, oldest first.
+#[tauri::command]
+pub async fn get_delivery_notes(
+    sale_id: Option<String>,
+    client_id: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<DeliveryNote>, String> {
+    let mut conditions = vec!["dn.is_deleted = 0"];
+    let mut params = Vec::new();
+    if let Some(sale_id) = sale_id.as_deref() {
+        conditions.push("dn.sale_id = ?");
+        params.push(sale_id);
+    }
+    if let Some(client_id) = client_id.as_deref() {
+        conditions.push("dn.client_id = ?");
+        params.push(client_id);
+    }
+    let where_clause = format!("WHERE {}", conditions.join(" AND "));
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_delivery_notes(&mut conn, &where_clause, &params).await
+}
+
+#[tauri::command]
+pub async fn get_delivery_note(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Option<DeliveryNote>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    Ok(fetch_delivery_notes(&mut conn, "WHERE dn.id = ? AND dn.is_deleted = 0", &[&id]).await?.pop())
+}
+
+/// Cancels a delivery note that was not carried out. Once the sale is
+/// invoiced its deliveries are final.
+#[tauri::command]
+pub async fn delete_delivery_note(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let note = sqlx::query(
+        r#"
+        SELECT dn.delivery_note_number, s.invoice_id
+        FROM delivery_notes dn
+        JOIN sales s ON s.id = dn.sale_id
+        WHERE dn.id = ? AND dn.is_deleted = 0
+        "#
+    )
+    .bind(&id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Delivery note not found".to_string())?;
+    let number: String = note.get("delivery_note_number");
+    if note.get::<Option<String>, _>("invoice_id").is_some() {
+        return Err(format!("Delivery note {} belongs to an invoiced sale and cannot be deleted", number));
+    }
+    sqlx::query("UPDATE delivery_notes SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(Utc::now().to_rfc3339())
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = format!("Delivery note {} soft-deleted", number);
+    insert_audit_log(&mut *tx, "soft_delete", "delivery_note", &id, None, Some(&details)).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// A delivered measure as printed: tons to the kilo, units to two decimals.
+fn delivery_measure_text(by_weight: bool, value: f64) -> String {
+    if by_weight {
+        format!("{} T", pdf::format_quantity(value, 3))
+    } else {
+        pdf::format_quantity(value, 2)
+    }
+}
+
+#[tauri::command]
+pub async fn generate_delivery_note_pdf(
+    delivery_note_id: String,
+    output_path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let note = sqlx::query("SELECT delivery_note_number, client_id, date, truck, driver, plate, notes FROM delivery_notes WHERE id = ? AND is_deleted = 0")
+        .bind(&delivery_note_id)
+        .fetch_optional(&*pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Delivery note not found".to_string())?;
+    let client_id: String = note.get("client_id");
+
+    let client = document_client(&pool, &client_id).await?;
+    let (company, logo, _) = document_letterhead(&pool).await?;
+
+    // What is left is counted up to this note, so reprints read the same
+    let items = sqlx::query(&format!(
+        r#"
+        SELECT si.description, si.coil_ref, si.coil_thickness, si.coil_width, si.top_coat_ral, si.back_coat_ral, si.product_type,
+               {ordered} AS ordered, dni.weight, dni.quantity,
+               IFNULL((
+                 SELECT SUM(CASE WHEN si.product_type IN ('coil', 'steel_slitting') THEN IFNULL(x.weight, 0.0) ELSE IFNULL(x.quantity, 0.0) END)
+                 FROM delivery_note_items x
+                 JOIN delivery_notes n ON n.id = x.delivery_note_id AND n.is_deleted = 0
+                 WHERE x.sale_item_id = si.id AND n.rowid <= dn.rowid
+               ), 0.0) AS delivered_to_date
+        FROM delivery_note_items dni
+        JOIN delivery_notes dn ON dn.id = dni.delivery_note_id
+        JOIN sale_items si ON si.id = dni.sale_item_id
+        WHERE dni.delivery_note_id = ?
+        ORDER BY dni.rowid
+        "#,
+        ordered = ITEM_ORDERED_SQL
+    ))
+    .bind(&delivery_note_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let lines = items.iter()
+        .map(|row| {
+            let by_weight = delivered_by_weight(row.get("product_type"));
+            let weight: Option<f64> = row.get("weight");
+            let quantity: Option<f64> = row.get("quantity");
+            let ordered: f64 = row.get("ordered");
+            let mut details = item_details_from_row(row);
+            // The measure that does not count is printed for the record
+            match (by_weight, weight, quantity) {
+                (true, _, Some(quantity)) => details.push(format!("{} pièce(s)", pdf::format_quantity(quantity, 0))),
+                (false, Some(weight), _) => details.push(format!("{} T", pdf::format_quantity(weight, 3))),
+                _ => {}
+            }
+            let delivered = if by_weight { weight } else { quantity }.unwrap_or(0.0);
+            let remaining = (ordered - row.get::<f64, _>("delivered_to_date")).max(0.0);
+            pdf::DeliveryLine {
+                description: row.get("description"),
+                details: details.join(", "),
+                ordered: delivery_measure_text(by_weight, ordered),
+                delivered: delivery_measure_text(by_weight, delivered),
+                remaining: delivery_measure_text(by_weight, if remaining > DELIVERY_TOLERANCE { remaining } else { 0.0 }),
+            }
+        })
+        .collect();
+
+    let document = pdf::DeliveryNoteDocument {
+        number: note.get("delivery_note_number"),
+        date: note.get("date"),
+        truck: note.get("truck"),
+        driver: note.get("driver"),
+        plate: note.get("plate"),
+        notes: note.get("notes"),
+        logo,
+        company,
+        client,
+        lines,
+    };
+    let bytes = pdf::render_delivery_note(&document)?;
+    write_document(&output_path, &bytes)
+}
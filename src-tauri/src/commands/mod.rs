@@ -1,20 +1,34 @@
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use sqlx::Row;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc};
 use uuid::Uuid;
 use serde_json;
 use serde_json::Value;
 use chrono::NaiveDateTime;
 use std::env;
 use std::fs;
-use std::path::Path;
 use sqlx::Connection;
 use dirs;
 use tokio::sync::oneshot;
 use tauri_plugin_dialog::DialogExt;
 use std::path::PathBuf;
 
+mod money;
+mod pdf;
+mod bank_statement;
+mod purchasing;
+mod credit_notes;
+mod quotes;
+mod delivery_notes;
+mod reconciliation;
+pub use money::Money;
+pub use pdf::amount_in_words;
+pub use purchasing::*;
+pub use credit_notes::*;
+pub use quotes::*;
+pub use delivery_notes::*;
+pub use reconciliation::*;
 
 
 // Client structs
@@ -32,7 +46,7 @@ pub struct Client {
     pub rc: Option<String>,
     pub ai: Option<String>,
     pub rib: Option<String>,
-    pub credit_balance: f64,
+    pub credit_balance: Money,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -80,9 +94,19 @@ pub struct SaleItem {
     pub back_coat_ral: Option<String>,
     pub coil_weight: Option<f64>,
     pub quantity: f64,
-    pub price_per_ton: f64,
-    pub total_amount: f64,
+    pub price_per_ton: Money,
+    pub total_amount: Money,
+    /// Cost in the unit price_per_ton is expressed in; None while unknown
+    pub unit_cost: Option<Money>,
+    /// The line's cost, computed like total_amount
+    pub cost_amount: Option<Money>,
+    /// VAT rate set on this line; None when it follows the sale's rate
+    pub tax_rate: Option<f64>,
+    /// The line's VAT, rounded on its own
+    pub tax_amount: Money,
     pub product_type: String,
+    /// Tons (coils, steel slitting) or units carried by delivery notes so far
+    pub delivered: f64,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -92,18 +116,33 @@ pub struct Sale {
     pub id: String,
     pub client_id: String,
     pub date: DateTime<Utc>,
-    pub total_amount: f64,
-    pub total_amount_ttc: f64,
+    pub total_amount: Money,
+    pub total_amount_ttc: Money,
     pub is_invoiced: bool,
     pub invoice_id: Option<String>,
     pub notes: Option<String>,
     pub payment_method: Option<String>,
-    pub transportation_fee: Option<f64>,
+    pub transportation_fee: Option<Money>,
     pub tax_rate: f64,
+    /// VAT on the lines and the transportation fee, rounded once per rate
+    pub tax_amount: Money,
+    /// Droit de timbre on cash sales, included in total_amount_ttc
+    pub stamp_duty: Money,
+    /// Why the sale is exempt from VAT; None when VAT applies
+    pub tax_exemption: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub is_paid: bool,
     pub paid_at: Option<DateTime<Utc>>,
+    pub amount_paid: Money,
+    /// What is still owed, never negative: an overpayment shows as `overpaid`
+    pub balance_due: Money,
+    /// unpaid, partial, paid or overpaid
+    pub payment_status: String,
+    /// The quote the sale was converted from
+    pub quote_id: Option<String>,
+    /// undelivered, partial or delivered
+    pub delivery_status: String,
     pub items: Vec<SaleItem>,
 }
 
@@ -111,17 +150,23 @@ pub struct Sale {
 pub struct CreateSaleRequest {
     pub client_id: String,
     pub date: DateTime<Utc>,
-    pub total_amount: f64,
-    pub total_amount_ttc: f64,
+    pub total_amount: Money,
+    pub total_amount_ttc: Money,
     pub is_invoiced: bool,
     pub invoice_id: Option<String>,
     pub notes: Option<String>,
     pub payment_method: Option<String>,
-    pub transportation_fee: Option<f64>,
+    pub transportation_fee: Option<Money>,
     pub tax_rate: f64,
+    /// Why the sale is exempt from VAT (export, ...); every line is then taxed at 0
+    #[serde(default)]
+    pub tax_exemption: Option<String>,
     pub is_paid: Option<bool>,
     pub paid_at: Option<DateTime<Utc>>,
     pub items: Vec<CreateSaleItemRequest>,
+    /// Lets the sale take more weight from a coil than is left in stock
+    #[serde(default)]
+    pub allow_oversell: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -134,9 +179,226 @@ pub struct CreateSaleItemRequest {
     pub back_coat_ral: Option<String>,
     pub coil_weight: Option<f64>,
     pub quantity: f64,
-    pub price_per_ton: f64,
-    pub total_amount: f64,
+    pub price_per_ton: Money,
+    pub total_amount: Money,
     pub product_type: String,
+    /// Cost in the unit price_per_ton is expressed in. When left out, coil and
+    /// steel slitting items take the cost per ton of their coil in stock.
+    #[serde(default)]
+    pub unit_cost: Option<Money>,
+    /// VAT rate of this line when it differs from the sale's
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+}
+
+// --- List filters ---
+
+/// Search, filter and sort options shared by the paginated list commands.
+/// A list ignores the fields it has no column for.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListFilter {
+    /// Case-insensitive match on client name or company, invoice number,
+    /// coil_ref and check_number
+    pub search: Option<String>,
+    /// Inclusive, YYYY-MM-DD
+    pub start_date: Option<String>,
+    /// Inclusive, YYYY-MM-DD
+    pub end_date: Option<String>,
+    pub client_id: Option<String>,
+    pub supplier_id: Option<String>,
+    pub amount_min: Option<Money>,
+    pub amount_max: Option<Money>,
+    /// unpaid, partial, paid or overpaid; "all" means no filter
+    pub payment_status: Option<String>,
+    pub is_invoiced: Option<bool>,
+    /// One of the sort keys of the list
+    pub sort_by: Option<String>,
+    /// "asc" or "desc"
+    pub sort_direction: Option<String>,
+}
+
+/// How a list applies a `ListFilter` to its query.
+struct ListColumns {
+    date: &'static str,
+    client_id: Option<&'static str>,
+    supplier_id: Option<&'static str>,
+    amount: Option<&'static str>,
+    /// Conditions with a single `?` bound to the search pattern
+    search: &'static [&'static str],
+    payment_status: Option<&'static str>,
+    is_invoiced: Option<&'static str>,
+    /// Sort keys and the expression each one orders by
+    sorts: &'static [(&'static str, &'static str)],
+    /// Sort key and direction used when the filter has none
+    default_sort: (&'static str, &'static str),
+    /// Last ORDER BY term, so pages do not overlap when sort values tie
+    tie_breaker: &'static str,
+}
+
+const CLIENT_LIST: ListColumns = ListColumns {
+    date: "c.created_at",
+    client_id: Some("c.id"),
+    supplier_id: None,
+    amount: None,
+    search: &["c.name LIKE ?", "c.company LIKE ?"],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("name", "c.name"),
+        ("company", "c.company"),
+        ("created_at", "c.created_at"),
+        ("credit_balance", "c.credit_balance"),
+    ],
+    default_sort: ("name", "ASC"),
+    tie_breaker: "c.id",
+};
+
+/// Used with `sales_with_payment_progress() s`, `clients c` and the sale's invoice `inv`.
+const SALE_LIST: ListColumns = ListColumns {
+    date: "s.date",
+    client_id: Some("s.client_id"),
+    supplier_id: None,
+    amount: Some("s.total_amount_ttc"),
+    search: &[
+        "c.name LIKE ?",
+        "c.company LIKE ?",
+        "inv.invoice_number LIKE ?",
+        "EXISTS (SELECT 1 FROM sale_items si WHERE si.sale_id = s.id AND si.coil_ref LIKE ?)",
+    ],
+    payment_status: Some("s.payment_status"),
+    is_invoiced: Some("s.is_invoiced"),
+    sorts: &[
+        ("date", "s.date"),
+        ("client_name", "c.name"),
+        ("invoice_number", "inv.invoice_number"),
+        ("total_amount", "s.total_amount"),
+        ("total_amount_ttc", "s.total_amount_ttc"),
+        ("balance_due", "s.balance_due"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "s.id",
+};
+
+/// Used with `invoices_with_payment_progress() i` and `clients c`.
+const INVOICE_LIST: ListColumns = ListColumns {
+    date: "i.date",
+    client_id: Some("i.client_id"),
+    supplier_id: None,
+    amount: Some("i.total_amount_ttc"),
+    search: &[
+        "c.name LIKE ?",
+        "c.company LIKE ?",
+        "i.invoice_number LIKE ?",
+        "EXISTS (SELECT 1 FROM sales sl JOIN sale_items si ON si.sale_id = sl.id WHERE sl.invoice_id = i.id AND si.coil_ref LIKE ?)",
+    ],
+    payment_status: Some("i.payment_status"),
+    is_invoiced: None,
+    sorts: &[
+        ("date", "i.date"),
+        ("due_date", "i.due_date"),
+        ("invoice_number", "i.invoice_number"),
+        ("client_name", "c.name"),
+        ("total_amount_ttc", "i.total_amount_ttc"),
+        ("balance_due", "i.balance_due"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "i.id",
+};
+
+/// Used with `payments p`, `clients c` and the invoice `inv` the payment counts towards.
+const PAYMENT_LIST: ListColumns = ListColumns {
+    date: "p.date",
+    client_id: Some("p.client_id"),
+    supplier_id: None,
+    amount: Some("p.amount"),
+    search: &[
+        "c.name LIKE ?",
+        "c.company LIKE ?",
+        "inv.invoice_number LIKE ?",
+        "p.check_number LIKE ?",
+    ],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("date", "p.date"),
+        ("amount", "p.amount"),
+        ("client_name", "c.name"),
+        ("method", "p.method"),
+        ("check_number", "p.check_number"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "p.id",
+};
+
+fn list_filter_date(date: &str) -> Result<String, String> {
+    date.get(..10)
+        .filter(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+        .map(str::to_string)
+        .ok_or_else(|| format!("Invalid filter date '{}'", date))
+}
+
+/// Appends the filter's conditions to `where_clause` and `params`, and returns
+/// the ORDER BY clause.
+fn apply_list_filter(
+    filter: &ListFilter,
+    columns: &ListColumns,
+    where_clause: &mut String,
+    params: &mut Vec<String>,
+) -> Result<String, String> {
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        where_clause.push_str(&format!(" AND ({})", columns.search.join(" OR ")));
+        let pattern = format!("%{}%", search);
+        params.extend(columns.search.iter().map(|_| pattern.clone()));
+    }
+    if let Some(ref start) = filter.start_date {
+        where_clause.push_str(&format!(" AND substr({}, 1, 10) >= ?", columns.date));
+        params.push(list_filter_date(start)?);
+    }
+    if let Some(ref end) = filter.end_date {
+        where_clause.push_str(&format!(" AND substr({}, 1, 10) <= ?", columns.date));
+        params.push(list_filter_date(end)?);
+    }
+    if let (Some(cid), Some(column)) = (&filter.client_id, columns.client_id) {
+        where_clause.push_str(&format!(" AND {} = ?", column));
+        params.push(cid.clone());
+    }
+    if let (Some(sid), Some(column)) = (&filter.supplier_id, columns.supplier_id) {
+        where_clause.push_str(&format!(" AND {} = ?", column));
+        params.push(sid.clone());
+    }
+    if let Some(amount) = columns.amount {
+        if let Some(min) = filter.amount_min {
+            where_clause.push_str(&format!(" AND {} >= CAST(? AS INTEGER)", amount));
+            params.push(min.centimes().to_string());
+        }
+        if let Some(max) = filter.amount_max {
+            where_clause.push_str(&format!(" AND {} <= CAST(? AS INTEGER)", amount));
+            params.push(max.centimes().to_string());
+        }
+    }
+    if let Some(status) = payment_status_filter(filter.payment_status.as_deref())? {
+        if let Some(column) = columns.payment_status {
+            where_clause.push_str(&format!(" AND {} = ?", column));
+            params.push(status);
+        }
+    }
+    if let (Some(is_invoiced), Some(column)) = (filter.is_invoiced, columns.is_invoiced) {
+        where_clause.push_str(&format!(" AND COALESCE({}, 0) = {}", column, if is_invoiced { 1 } else { 0 }));
+    }
+    let (default_key, default_direction) = columns.default_sort;
+    let key = filter.sort_by.as_deref().unwrap_or(default_key);
+    let expression = columns.sorts.iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, e)| *e)
+        .ok_or_else(|| format!("Cannot sort by '{}'", key))?;
+    let direction = match filter.sort_direction.as_deref() {
+        None if filter.sort_by.is_none() => default_direction,
+        None => "ASC",
+        Some(d) if d.eq_ignore_ascii_case("asc") => "ASC",
+        Some(d) if d.eq_ignore_ascii_case("desc") => "DESC",
+        Some(d) => return Err(format!("Unknown sort direction '{}'", d)),
+    };
+    Ok(format!(" ORDER BY {} {}, {} {}", expression, direction, columns.tie_breaker, direction))
 }
 
 // Client commands
@@ -150,25 +412,37 @@ pub struct PaginatedClientsResult {
 pub async fn get_clients(
     page: Option<u32>,
     page_size: Option<u32>,
+    filter: Option<ListFilter>,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<PaginatedClientsResult, String> {
-    let page = page.unwrap_or(1);
+    let page = page.unwrap_or(1).max(1);
     let page_size = page_size.unwrap_or(5);
     let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE (c.is_deleted = 0 OR c.is_deleted IS NULL)");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &CLIENT_LIST, &mut where_clause, &mut params)?;
     // Total count
-    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clients WHERE 1=1")
-        .fetch_one(&*pool)
+    let count_query = format!("SELECT COUNT(*) FROM clients c {}", where_clause);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!(
+        "SELECT c.id, c.name, c.company, c.email, c.phone, c.address, c.notes, c.nif, c.nis, c.rc, c.ai, c.rib, c.credit_balance, c.created_at, c.updated_at FROM clients c {}{} LIMIT ? OFFSET ?",
+        where_clause, order_by
+    );
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let clients = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
         .await
         .map_err(|e| e.to_string())?;
-    // Paginated rows
-    let clients = sqlx::query(
-        r#"SELECT id, name, company, email, phone, address, notes, nif, nis, rc, ai, rib, credit_balance, created_at, updated_at FROM clients ORDER BY name LIMIT ? OFFSET ?"#
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
     let rows: Vec<Client> = clients
         .into_iter()
         .map(|row| Client {
@@ -245,7 +519,7 @@ pub async fn create_client(
         INSERT INTO clients (
             id, name, company, email, phone, address, notes, nif, nis, rc, ai, rib,
             credit_balance, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0.0, ?, ?)
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?)
         "#
     )
     .bind(&id)
@@ -323,16 +597,148 @@ pub async fn delete_client(
     id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
     sqlx::query("DELETE FROM clients WHERE id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // Insert audit log entry for client deletion
-    insert_audit_log(&*pool, "delete", "client", &id, None, Some("Client deleted")).await?;
+    insert_audit_log(&mut *tx, "delete", "client", &id, None, Some("Client deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
+// --- Payment progress ---
+
+/// Values of `payment_status` on sales and invoices.
+pub const PAYMENT_STATUSES: [&str; 4] = ["unpaid", "partial", "paid", "overpaid"];
+
+/// Non-deleted payments received on the sale aliased `s`.
+const SALE_AMOUNT_PAID_SQL: &str = "IFNULL((SELECT SUM(p.amount) FROM payments p WHERE p.sale_id = s.id AND p.is_deleted = 0), 0)";
+
+/// Non-deleted payments received on the invoice aliased `i`: payments on its
+/// sales, plus payments made on the invoice itself and not on one of them.
+const INVOICE_AMOUNT_PAID_SQL: &str = r#"(
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales l
+            JOIN payments p ON p.sale_id = l.sale_id AND p.is_deleted = 0
+            WHERE l.invoice_id = i.id
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = i.id AND p.is_deleted = 0
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales l WHERE l.invoice_id = i.id AND l.sale_id = p.sale_id)
+          ), 0))"#;
+
+/// Wraps `table` (aliased `alias`) with `amount_paid`, `balance_due` and
+/// `payment_status` columns, for use as `FROM {} alias`. `amount_due_sql`
+/// is what has to be paid, over the columns of `alias`.
+fn with_payment_progress(table: &str, alias: &str, amount_paid_sql: &str, amount_due_sql: &str) -> String {
+    format!(
+        r#"(SELECT progress.*,
+              MAX(progress.amount_due - progress.amount_paid, 0) AS balance_due,
+              CASE
+                WHEN progress.amount_paid > progress.amount_due THEN 'overpaid'
+                WHEN progress.amount_paid = progress.amount_due THEN 'paid'
+                WHEN progress.amount_paid > 0 THEN 'partial'
+                ELSE 'unpaid'
+              END AS payment_status
+           FROM (SELECT {alias}.*, {paid} AS amount_paid, {due} AS amount_due FROM {table} {alias}) progress)"#,
+        alias = alias,
+        paid = amount_paid_sql,
+        due = amount_due_sql,
+        table = table,
+    )
+}
+
+/// `sales` with their payment progress columns, and their `delivery_status`.
+fn sales_with_payment_progress() -> String {
+    let sales = format!("(SELECT s.*, {} AS delivery_status FROM sales s)", delivery_notes::sale_delivery_status_sql());
+    with_payment_progress(&sales, "s", SALE_AMOUNT_PAID_SQL, "s.total_amount_ttc")
+}
+
+/// `invoices` with their payment progress columns. Credit notes lower what
+/// is left to pay.
+fn invoices_with_payment_progress() -> String {
+    with_payment_progress("invoices", "i", INVOICE_AMOUNT_PAID_SQL, "i.total_amount_ttc - i.credited_amount")
+}
+
+/// Rejects unknown statuses; `None` and "all" mean no filter.
+fn payment_status_filter(status: Option<&str>) -> Result<Option<String>, String> {
+    match status {
+        None | Some("") | Some("all") => Ok(None),
+        Some(status) if PAYMENT_STATUSES.contains(&status) => Ok(Some(status.to_string())),
+        Some(status) => Err(format!("Unknown payment status: {}", status)),
+    }
+}
+
+fn sale_item_from_row(row: &sqlx::sqlite::SqliteRow) -> SaleItem {
+    SaleItem {
+        id: row.get("id"),
+        sale_id: row.get("sale_id"),
+        description: row.get("description"),
+        coil_ref: row.get("coil_ref"),
+        coil_thickness: row.get("coil_thickness"),
+        coil_width: row.get("coil_width"),
+        top_coat_ral: row.get("top_coat_ral"),
+        back_coat_ral: row.get("back_coat_ral"),
+        coil_weight: row.get("coil_weight"),
+        quantity: row.get("quantity"),
+        price_per_ton: row.get("price_per_ton"),
+        total_amount: row.get("total_amount"),
+        unit_cost: row.get("unit_cost"),
+        cost_amount: row.get("cost_amount"),
+        tax_rate: row.get("tax_rate"),
+        tax_amount: row.get("tax_amount"),
+        product_type: row.get("product_type"),
+        delivered: row.get("delivered"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+/// Maps a row of `sales_with_payment_progress()`.
+fn sale_from_row(sale_row: &sqlx::sqlite::SqliteRow, items: Vec<SaleItem>) -> Sale {
+    Sale {
+        id: sale_row.get("id"),
+        client_id: sale_row.get("client_id"),
+        date: sale_row.get("date"),
+        total_amount: sale_row.get("total_amount"),
+        total_amount_ttc: sale_row.get("total_amount_ttc"),
+        is_invoiced: sale_row.get("is_invoiced"),
+        invoice_id: sale_row.get("invoice_id"),
+        notes: sale_row.get("notes"),
+        payment_method: sale_row.get("payment_method"),
+        transportation_fee: sale_row.get("transportation_fee"),
+        tax_rate: sale_row.get("tax_rate"),
+        tax_amount: sale_row.get("tax_amount"),
+        stamp_duty: sale_row.get("stamp_duty"),
+        tax_exemption: sale_row.get("tax_exemption"),
+        created_at: sale_row.get("created_at"),
+        updated_at: sale_row.get("updated_at"),
+        is_paid: sale_row.get("is_paid"),
+        paid_at: sale_row.get("paid_at"),
+        amount_paid: sale_row.get("amount_paid"),
+        balance_due: sale_row.get("balance_due"),
+        payment_status: sale_row.get("payment_status"),
+        quote_id: sale_row.get("quote_id"),
+        delivery_status: sale_row.get("delivery_status"),
+        items,
+    }
+}
+
+async fn fetch_sale_items(pool: &SqlitePool, sale_id: &str) -> Result<Vec<SaleItem>, String> {
+    let rows = sqlx::query(&format!("SELECT si.*, {} AS delivered FROM sale_items si WHERE si.sale_id = ?", delivery_notes::ITEM_DELIVERED_SQL))
+        .bind(sale_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(sale_item_from_row).collect())
+}
+
 // Sale commands
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct PaginatedSalesResult {
@@ -344,70 +750,44 @@ pub struct PaginatedSalesResult {
 pub async fn get_sales(
     page: Option<u32>,
     page_size: Option<u32>,
+    filter: Option<ListFilter>,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<PaginatedSalesResult, String> {
-    let page = page.unwrap_or(1);
+    let page = page.unwrap_or(1).max(1);
     let page_size = page_size.unwrap_or(5);
     let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE (s.is_deleted = 0 OR s.is_deleted IS NULL)");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &SALE_LIST, &mut where_clause, &mut params)?;
+    let from = format!(
+        "FROM {} s LEFT JOIN clients c ON c.id = s.client_id LEFT JOIN invoices inv ON inv.id = s.invoice_id {}",
+        sales_with_payment_progress(),
+        where_clause
+    );
     // Total count
-    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales WHERE is_deleted = 0 OR is_deleted IS NULL")
-        .fetch_one(&*pool)
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated sales
+    let rows_query = format!("SELECT s.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let sales_rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
         .await
         .map_err(|e| e.to_string())?;
-    // Paginated sales
-    let sales_rows = sqlx::query(
-        r#"SELECT * FROM sales WHERE is_deleted = 0 OR is_deleted IS NULL ORDER BY date DESC LIMIT ? OFFSET ?"#
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
     let mut rows = Vec::new();
     for sale_row in sales_rows {
         let sale_id: String = sale_row.get("id");
-        let items_rows = sqlx::query(
-            r#"SELECT * FROM sale_items WHERE sale_id = ?"#
-        )
-        .bind(&sale_id)
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-        let items = items_rows.into_iter().map(|row| SaleItem {
-            id: row.get("id"),
-            sale_id: row.get("sale_id"),
-            description: row.get("description"),
-            coil_ref: row.get("coil_ref"),
-            coil_thickness: row.get("coil_thickness"),
-            coil_width: row.get("coil_width"),
-            top_coat_ral: row.get("top_coat_ral"),
-            back_coat_ral: row.get("back_coat_ral"),
-            coil_weight: row.get("coil_weight"),
-            quantity: row.get("quantity"),
-            price_per_ton: row.get("price_per_ton"),
-            total_amount: row.get("total_amount"),
-            product_type: row.get("product_type"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        }).collect();
-        rows.push(Sale {
-            id: sale_row.get("id"),
-            client_id: sale_row.get("client_id"),
-            date: sale_row.get("date"),
-            total_amount: sale_row.get("total_amount"),
-            total_amount_ttc: sale_row.get("total_amount_ttc"),
-            is_invoiced: sale_row.get("is_invoiced"),
-            invoice_id: sale_row.get("invoice_id"),
-            notes: sale_row.get("notes"),
-            payment_method: sale_row.get("payment_method"),
-            transportation_fee: sale_row.get("transportation_fee"),
-            tax_rate: sale_row.get("tax_rate"),
-            created_at: sale_row.get("created_at"),
-            updated_at: sale_row.get("updated_at"),
-            is_paid: sale_row.get("is_paid"),
-            paid_at: sale_row.get("paid_at"),
-            items,
-        });
+        let items = fetch_sale_items(&pool, &sale_id).await?;
+        rows.push(sale_from_row(&sale_row, items));
     }
     Ok(PaginatedSalesResult { rows, total })
 }
@@ -417,79 +797,267 @@ pub async fn get_sale_by_id(
     id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<Option<Sale>, String> {
-    let sale_row = sqlx::query(
-        r#"SELECT * FROM sales WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)"#
-    )
+    let sale_row = sqlx::query(&format!(
+        "SELECT s.* FROM {} s WHERE s.id = ? AND (s.is_deleted = 0 OR s.is_deleted IS NULL)",
+        sales_with_payment_progress()
+    ))
     .bind(&id)
     .fetch_optional(&*pool)
     .await
     .map_err(|e| e.to_string())?;
     if let Some(sale_row) = sale_row {
-        let items_rows = sqlx::query(
-            r#"SELECT * FROM sale_items WHERE sale_id = ?"#
-        )
-        .bind(&id)
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-        let items = items_rows.into_iter().map(|row| SaleItem {
-            id: row.get("id"),
-            sale_id: row.get("sale_id"),
-            description: row.get("description"),
-            coil_ref: row.get("coil_ref"),
-            coil_thickness: row.get("coil_thickness"),
-            coil_width: row.get("coil_width"),
-            top_coat_ral: row.get("top_coat_ral"),
-            back_coat_ral: row.get("back_coat_ral"),
-            coil_weight: row.get("coil_weight"),
-            quantity: row.get("quantity"),
-            price_per_ton: row.get("price_per_ton"),
-            total_amount: row.get("total_amount"),
-            product_type: row.get("product_type"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        }).collect();
-        Ok(Some(Sale {
-            id: sale_row.get("id"),
-            client_id: sale_row.get("client_id"),
-            date: sale_row.get("date"),
-            total_amount: sale_row.get("total_amount"),
-            total_amount_ttc: sale_row.get("total_amount_ttc"),
-            is_invoiced: sale_row.get("is_invoiced"),
-            invoice_id: sale_row.get("invoice_id"),
-            notes: sale_row.get("notes"),
-            payment_method: sale_row.get("payment_method"),
-            transportation_fee: sale_row.get("transportation_fee"),
-            tax_rate: sale_row.get("tax_rate"),
-            created_at: sale_row.get("created_at"),
-            updated_at: sale_row.get("updated_at"),
-            is_paid: sale_row.get("is_paid"),
-            paid_at: sale_row.get("paid_at"),
-            items,
-        }))
+        let items = fetch_sale_items(&pool, &id).await?;
+        Ok(Some(sale_from_row(&sale_row, items)))
     } else {
         Ok(None)
     }
 }
 
-fn calculate_total_amount(item: &CreateSaleItemRequest) -> f64 {
-    match item.product_type.as_str() {
-        "coil" => {
-            let weight = item.coil_weight.unwrap_or(0.0);
-            item.price_per_ton * weight
+/// How many units of price_per_ton the item is made of: tons for coils and
+/// steel slitting, meters for corrugated sheets. Other products carry their
+/// own total.
+fn priced_units(item: &CreateSaleItemRequest) -> Option<f64> {
+    units_priced_by(&item.product_type, item.quantity, item.coil_width, item.coil_weight)
+}
+
+fn units_priced_by(product_type: &str, quantity: f64, coil_width: Option<f64>, coil_weight: Option<f64>) -> Option<f64> {
+    match product_type {
+        "coil" | "steel_slitting" => Some(coil_weight.unwrap_or(0.0)),
+        "corrugated_sheet" => Some(quantity * coil_width.unwrap_or(0.0)),
+        _ => None,
+    }
+}
+
+/// Item totals are rounded to the centime by `Money::mul_f64`.
+fn calculate_total_amount(item: &CreateSaleItemRequest) -> Money {
+    priced_units(item)
+        .map(|units| item.price_per_ton.mul_f64(units))
+        .unwrap_or(item.total_amount)
+}
+
+/// The item's (unit_cost, cost_amount): the cost entered on the item, or else
+/// the cost per ton of the coil a weight-priced item is cut from.
+async fn sale_item_cost(conn: &mut sqlx::SqliteConnection, item: &CreateSaleItemRequest) -> Result<(Option<Money>, Option<Money>), String> {
+    let unit_cost = match (item.unit_cost, item.coil_ref.as_deref().map(str::trim)) {
+        (Some(cost), _) => Some(cost),
+        (None, Some(coil_ref)) if matches!(item.product_type.as_str(), "coil" | "steel_slitting") => {
+            sqlx::query_scalar("SELECT cost_per_ton FROM coils WHERE coil_ref = ?")
+                .bind(coil_ref)
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?
         }
-        "corrugated_sheet" => {
-            let length = item.coil_width.unwrap_or(0.0);
-            item.quantity * length * item.price_per_ton
+        _ => None,
+    };
+    let cost_amount = unit_cost.map(|cost| cost.mul_f64(priced_units(item).unwrap_or(item.quantity)));
+    Ok((unit_cost, cost_amount))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VatLine {
+    pub tax_rate: f64,
+    /// HT amount taxed at tax_rate
+    pub taxable_amount: Money,
+    pub tax_amount: Money,
+}
+
+/// Groups HT amounts by VAT rate, rounding the tax once per rate. Rates come
+/// out in ascending order.
+fn vat_by_rate(lines: impl IntoIterator<Item = (f64, Money)>) -> Vec<VatLine> {
+    let mut groups: Vec<VatLine> = Vec::new();
+    for (tax_rate, amount) in lines {
+        match groups.iter_mut().find(|g| g.tax_rate == tax_rate) {
+            Some(group) => group.taxable_amount += amount,
+            None => groups.push(VatLine { tax_rate, taxable_amount: amount, tax_amount: Money::ZERO }),
         }
-        "steel_slitting" => {
-            let weight = item.coil_weight.unwrap_or(0.0);
-            item.price_per_ton * weight
+    }
+    for group in &mut groups {
+        group.tax_amount = group.taxable_amount.mul_f64(group.tax_rate);
+    }
+    groups.sort_by(|a, b| a.tax_rate.total_cmp(&b.tax_rate));
+    groups
+}
+
+/// How the droit de timbre is charged on cash sales, from settings.
+#[derive(Debug, Clone, Copy)]
+struct StampDutyRule {
+    rate: f64,
+    min: Money,
+    max: Option<Money>,
+}
+
+impl Default for StampDutyRule {
+    fn default() -> Self {
+        StampDutyRule { rate: 0.01, min: Money::from_units(5.0), max: None }
+    }
+}
+
+impl StampDutyRule {
+    /// The stamp duty on `amount` when it is paid with `payment_method`.
+    fn charge(&self, payment_method: Option<&str>, amount: Money) -> Money {
+        if payment_method != Some("cash") || !amount.is_positive() {
+            return Money::ZERO;
+        }
+        let duty = amount.mul_f64(self.rate).max(self.min);
+        match self.max {
+            Some(max) => duty.min(max),
+            None => duty,
         }
-        _ => item.total_amount
     }
 }
 
+async fn stamp_duty_rule(conn: &mut sqlx::SqliteConnection) -> Result<StampDutyRule, String> {
+    let row = sqlx::query("SELECT stamp_duty_rate, stamp_duty_min, stamp_duty_max FROM settings LIMIT 1")
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let default = StampDutyRule::default();
+    Ok(match row {
+        Some(row) => StampDutyRule {
+            rate: row.get::<Option<f64>, _>("stamp_duty_rate").unwrap_or(default.rate),
+            min: row.get::<Option<Money>, _>("stamp_duty_min").unwrap_or(default.min),
+            max: row.get::<Option<Money>, _>("stamp_duty_max").filter(|max| max.is_positive()),
+        },
+        None => default,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SaleTotals {
+    total_amount: Money,
+    tax_amount: Money,
+    stamp_duty: Money,
+    total_amount_ttc: Money,
+}
+
+/// Computes a sale's totals from its (VAT rate, HT amount) lines, the
+/// transportation fee included as one of them. HT is the sum of the lines,
+/// the VAT is rounded once per rate, and TTC adds both plus the stamp duty
+/// when `stamp_duty` applies.
+fn compute_sale_totals(lines: impl IntoIterator<Item = (f64, Money)>, stamp_duty: Option<(&StampDutyRule, Option<&str>)>) -> SaleTotals {
+    let vat = vat_by_rate(lines);
+    let total_amount = vat.iter().map(|g| g.taxable_amount).sum::<Money>();
+    let tax_amount = vat.iter().map(|g| g.tax_amount).sum::<Money>();
+    let stamp_duty = stamp_duty
+        .map(|(rule, payment_method)| rule.charge(payment_method, total_amount + tax_amount))
+        .unwrap_or_default();
+    SaleTotals {
+        total_amount,
+        tax_amount,
+        stamp_duty,
+        total_amount_ttc: total_amount + tax_amount + stamp_duty,
+    }
+}
+
+/// The VAT rate an item is taxed at: none on exempt sales, else its own rate
+/// or the sale's.
+fn item_tax_rate(item_rate: Option<f64>, sale_rate: f64, exempt: bool) -> f64 {
+    if exempt {
+        0.0
+    } else {
+        item_rate.unwrap_or(sale_rate)
+    }
+}
+
+/// The (VAT rate, HT amount) lines of a sale or quote: its items, then the
+/// transportation fee at the document's rate.
+fn charge_lines(items: &[CreateSaleItemRequest], tax_rate: f64, exempt: bool, transportation_fee: Option<Money>) -> Vec<(f64, Money)> {
+    let mut lines: Vec<(f64, Money)> = items.iter()
+        .map(|item| (item_tax_rate(item.tax_rate, tax_rate, exempt), calculate_total_amount(item)))
+        .collect();
+    if let Some(fee) = transportation_fee.filter(|fee| !fee.is_zero()) {
+        lines.push((item_tax_rate(None, tax_rate, exempt), fee));
+    }
+    lines
+}
+
+fn sale_tax_lines(sale: &CreateSaleRequest) -> Vec<(f64, Money)> {
+    charge_lines(&sale.items, sale.tax_rate, sale.tax_exemption.is_some(), sale.transportation_fee)
+}
+
+fn validate_charges(tax_rate: f64, items: &[CreateSaleItemRequest], transportation_fee: Option<Money>) -> Result<(), String> {
+    if !(0.0..1.0).contains(&tax_rate) {
+        return Err("Tax rate must be between 0 and 1".to_string());
+    }
+    if items.iter().any(|item| item.tax_rate.is_some_and(|rate| !(0.0..1.0).contains(&rate))) {
+        return Err("Item tax rates must be between 0 and 1".to_string());
+    }
+    if transportation_fee.unwrap_or_default().is_negative() {
+        return Err("Transportation fee cannot be negative".to_string());
+    }
+    Ok(())
+}
+
+fn validate_sale_charges(sale: &CreateSaleRequest) -> Result<(), String> {
+    validate_charges(sale.tax_rate, &sale.items, sale.transportation_fee)
+}
+
+/// Replaces the client-sent header totals with the ones computed from the
+/// items and returns them with the VAT and stamp duty to store.
+fn apply_sale_totals(sale: &mut CreateSaleRequest, stamp_duty: &StampDutyRule, context: &str) -> SaleTotals {
+    sale.tax_exemption = sale.tax_exemption.as_deref().map(str::trim).filter(|r| !r.is_empty()).map(str::to_string);
+    let totals = compute_sale_totals(sale_tax_lines(sale), Some((stamp_duty, sale.payment_method.as_deref())));
+    if sale.total_amount != totals.total_amount || sale.total_amount_ttc != totals.total_amount_ttc {
+        log::warn!("[{}] Correcting totals: received HT={} TTC={}, computed HT={} TTC={}",
+            context, sale.total_amount, sale.total_amount_ttc, totals.total_amount, totals.total_amount_ttc);
+    }
+    sale.total_amount = totals.total_amount;
+    sale.total_amount_ttc = totals.total_amount_ttc;
+    totals
+}
+
+/// Recomputes an invoice's totals from its linked, non-deleted sales.
+/// Updating the totals fires update_invoice_payment_status_after_total_update.
+async fn refresh_invoice_totals(conn: &mut sqlx::SqliteConnection, invoice_id: &str) -> Result<(), String> {
+    sqlx::query(
+        r#"
+        UPDATE invoices SET
+            total_amount_ht = (
+                SELECT COALESCE(SUM(s.total_amount), 0)
+                FROM invoice_sales l
+                JOIN sales s ON s.id = l.sale_id
+                WHERE l.invoice_id = invoices.id AND (s.is_deleted = 0 OR s.is_deleted IS NULL)
+            ),
+            total_amount_ttc = (
+                SELECT COALESCE(SUM(s.total_amount_ttc), 0)
+                FROM invoice_sales l
+                JOIN sales s ON s.id = l.sale_id
+                WHERE l.invoice_id = invoices.id AND (s.is_deleted = 0 OR s.is_deleted IS NULL)
+            ),
+            updated_at = ?
+        WHERE id = ?
+        "#
+    )
+    .bind(Utc::now())
+    .bind(invoice_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    // Credit notes already issued cannot end up giving back more than the invoice is worth
+    let overcredited: Option<String> = sqlx::query_scalar("SELECT invoice_number FROM invoices WHERE id = ? AND credited_amount > total_amount_ttc")
+        .bind(invoice_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(number) = overcredited {
+        return Err(format!("Invoice {} would total less than its credit notes", number));
+    }
+    Ok(())
+}
+
+/// Refreshes the totals of every invoice the sale is linked to.
+async fn refresh_sale_invoice_totals(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<(), String> {
+    let invoice_ids: Vec<String> = sqlx::query_scalar("SELECT invoice_id FROM invoice_sales WHERE sale_id = ?")
+        .bind(sale_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    for invoice_id in invoice_ids {
+        refresh_invoice_totals(&mut *conn, &invoice_id).await?;
+    }
+    Ok(())
+}
+
 fn validate_sale_item(item: &CreateSaleItemRequest) -> Result<(), String> {
     if item.description.trim().is_empty() {
         return Err("Description is required".to_string());
@@ -527,35 +1095,34 @@ fn validate_sale_item(item: &CreateSaleItemRequest) -> Result<(), String> {
         }
         _ => {}
     }
+    if item.unit_cost.unwrap_or_default().is_negative() {
+        return Err("Unit cost cannot be negative".to_string());
+    }
     Ok(())
 }
 
-#[tauri::command]
-pub async fn create_sale(
-    sale: CreateSaleRequest,
-    pool: tauri::State<'_, SqlitePool>
-) -> Result<Sale, String> {
-    // Debug: print the received sale JSON and fields
-    match serde_json::to_string(&sale) {
-        Ok(json) => println!("[create_sale] Received sale JSON: {}", json),
-        Err(e) => println!("[create_sale] Failed to serialize received sale: {}", e),
-    }
-    println!("[create_sale] Fields: client_id={:?}, date={:?}, total_amount={:?}, total_amount_ttc={:?}, is_invoiced={:?}, invoice_id={:?}, notes={:?}, payment_method={:?}, transportation_fee={:?}, tax_rate={:?}, is_paid={:?}, paid_at={:?}, items.len={}",
-        sale.client_id, sale.date, sale.total_amount, sale.total_amount_ttc, sale.is_invoiced, sale.invoice_id, sale.notes, sale.payment_method, sale.transportation_fee, sale.tax_rate, sale.is_paid, sale.paid_at, sale.items.len());
+/// Writes a new sale and its items, then takes their weight from coil stock.
+/// Header totals are always derived from the items, never trusted from the client.
+async fn insert_sale(conn: &mut sqlx::SqliteConnection, sale: &mut CreateSaleRequest, context: &str) -> Result<String, String> {
     let sale_id = Uuid::new_v4().to_string();
     let now = Utc::now();
     let is_paid = sale.is_paid.unwrap_or(false);
     let paid_at = sale.paid_at;
+    let stamp_duty = stamp_duty_rule(&mut *conn).await?;
+    let totals = apply_sale_totals(sale, &stamp_duty, context);
     sqlx::query(
         r#"INSERT INTO sales (
-            id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, notes, payment_method, transportation_fee, tax_rate, is_paid, paid_at, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+            id, client_id, date, total_amount, total_amount_ttc, tax_amount, stamp_duty, tax_exemption, is_invoiced, invoice_id, notes, payment_method, transportation_fee, tax_rate, is_paid, paid_at, tracks_delivery, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"#
     )
     .bind(&sale_id)
     .bind(&sale.client_id)
     .bind(sale.date)
     .bind(sale.total_amount)
     .bind(sale.total_amount_ttc)
+    .bind(totals.tax_amount)
+    .bind(totals.stamp_duty)
+    .bind(&sale.tax_exemption)
     .bind(sale.is_invoiced)
     .bind(&sale.invoice_id)
     .bind(&sale.notes)
@@ -566,7 +1133,7 @@ pub async fn create_sale(
     .bind(paid_at)
     .bind(now)
     .bind(now)
-    .execute(&*pool)
+    .execute(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
     // Insert sale items
@@ -574,10 +1141,12 @@ pub async fn create_sale(
         validate_sale_item(item)?;
         let item_id = Uuid::new_v4().to_string();
         let total_amount = calculate_total_amount(item);
+        let tax_amount = total_amount.mul_f64(item_tax_rate(item.tax_rate, sale.tax_rate, sale.tax_exemption.is_some()));
+        let (unit_cost, cost_amount) = sale_item_cost(&mut *conn, item).await?;
         sqlx::query(
             r#"INSERT INTO sale_items (
-                id, sale_id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral, coil_weight, quantity, price_per_ton, total_amount, product_type, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+                id, sale_id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral, coil_weight, quantity, price_per_ton, total_amount, unit_cost, cost_amount, tax_rate, tax_amount, product_type, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
         )
         .bind(&item_id)
         .bind(&sale_id)
@@ -591,13 +1160,40 @@ pub async fn create_sale(
         .bind(item.quantity)
         .bind(item.price_per_ton)
         .bind(total_amount)
+        .bind(unit_cost)
+        .bind(cost_amount)
+        .bind(item.tax_rate)
+        .bind(tax_amount)
         .bind(&item.product_type)
         .bind(now)
         .bind(now)
-        .execute(&*pool)
+        .execute(&mut *conn)
         .await
         .map_err(|e| e.to_string())?;
     }
+    consume_sale_stock(&mut *conn, &sale_id, sale.allow_oversell).await?;
+    Ok(sale_id)
+}
+
+#[tauri::command]
+pub async fn create_sale(
+    mut sale: CreateSaleRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Sale, String> {
+    // Debug: print the received sale JSON and fields
+    match serde_json::to_string(&sale) {
+        Ok(json) => println!("[create_sale] Received sale JSON: {}", json),
+        Err(e) => println!("[create_sale] Failed to serialize received sale: {}", e),
+    }
+    println!("[create_sale] Fields: client_id={:?}, date={:?}, total_amount={:?}, total_amount_ttc={:?}, is_invoiced={:?}, invoice_id={:?}, notes={:?}, payment_method={:?}, transportation_fee={:?}, tax_rate={:?}, is_paid={:?}, paid_at={:?}, items.len={}",
+        sale.client_id, sale.date, sale.total_amount, sale.total_amount_ttc, sale.is_invoiced, sale.invoice_id, sale.notes, sale.payment_method, sale.transportation_fee, sale.tax_rate, sale.is_paid, sale.paid_at, sale.items.len());
+    validate_sale_charges(&sale)?;
+    // The sale, its items and the audit entry are written atomically
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let sale_id = insert_sale(&mut tx, &mut sale, "create_sale").await?;
+    // Insert audit log entry for sale creation
+    insert_audit_log(&mut *tx, "create", "sale", &sale_id, None, Some("Sale created")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     // Fetch and return the created sale
     get_sale_by_id(sale_id, pool).await.and_then(|opt| opt.ok_or_else(|| "Failed to retrieve created sale".to_string()))
 }
@@ -605,7 +1201,7 @@ pub async fn create_sale(
 #[tauri::command]
 pub async fn update_sale(
     id: String,
-    sale: CreateSaleRequest,
+    mut sale: CreateSaleRequest,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<Sale, String> {
     // Log the received sale for debugging
@@ -613,16 +1209,27 @@ pub async fn update_sale(
         Ok(json) => println!("[update_sale] Received sale JSON: {}", json),
         Err(e) => println!("[update_sale] Failed to serialize received sale: {}", e),
     }
+    validate_sale_charges(&sale)?;
     let now = Utc::now();
     let is_paid = sale.is_paid.unwrap_or(false);
     let paid_at = sale.paid_at;
+    // Old items are only replaced if every new item is written successfully
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    // Credit and delivery notes point at the items this would replace
+    credit_notes::ensure_no_credit_notes(&mut tx, &id, "changed").await?;
+    delivery_notes::ensure_no_delivery_notes(&mut tx, &id, "changed").await?;
+    let stamp_duty = stamp_duty_rule(&mut tx).await?;
+    let totals = apply_sale_totals(&mut sale, &stamp_duty, "update_sale");
     sqlx::query(
-        r#"UPDATE sales SET client_id = ?, date = ?, total_amount = ?, total_amount_ttc = ?, is_invoiced = ?, invoice_id = ?, notes = ?, payment_method = ?, transportation_fee = ?, tax_rate = ?, is_paid = ?, paid_at = ?, updated_at = ? WHERE id = ?"#
+        r#"UPDATE sales SET client_id = ?, date = ?, total_amount = ?, total_amount_ttc = ?, tax_amount = ?, stamp_duty = ?, tax_exemption = ?, is_invoiced = ?, invoice_id = ?, notes = ?, payment_method = ?, transportation_fee = ?, tax_rate = ?, is_paid = ?, paid_at = ?, updated_at = ? WHERE id = ?"#
     )
     .bind(&sale.client_id)
     .bind(sale.date)
     .bind(sale.total_amount)
     .bind(sale.total_amount_ttc)
+    .bind(totals.tax_amount)
+    .bind(totals.stamp_duty)
+    .bind(&sale.tax_exemption)
     .bind(sale.is_invoiced)
     .bind(&sale.invoice_id)
     .bind(&sale.notes)
@@ -633,16 +1240,18 @@ pub async fn update_sale(
     .bind(paid_at)
     .bind(now)
     .bind(&id)
-    .execute(&*pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| {
         println!("[update_sale] SQL error: {}", e);
         e.to_string()
     })?;
+    // The old items give their coil weight back before the new ones take theirs
+    release_sale_stock(&mut tx, &id).await?;
     // Delete old items
     sqlx::query("DELETE FROM sale_items WHERE sale_id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             println!("[update_sale] SQL error (delete items): {}", e);
@@ -656,10 +1265,12 @@ pub async fn update_sale(
         }
         let item_id = Uuid::new_v4().to_string();
         let total_amount = calculate_total_amount(item);
+        let tax_amount = total_amount.mul_f64(item_tax_rate(item.tax_rate, sale.tax_rate, sale.tax_exemption.is_some()));
+        let (unit_cost, cost_amount) = sale_item_cost(&mut tx, item).await?;
         sqlx::query(
             r#"INSERT INTO sale_items (
-                id, sale_id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral, coil_weight, quantity, price_per_ton, total_amount, product_type, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+                id, sale_id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral, coil_weight, quantity, price_per_ton, total_amount, unit_cost, cost_amount, tax_rate, tax_amount, product_type, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
         )
         .bind(&item_id)
         .bind(&id)
@@ -673,16 +1284,31 @@ pub async fn update_sale(
         .bind(item.quantity)
         .bind(item.price_per_ton)
         .bind(total_amount)
+        .bind(unit_cost)
+        .bind(cost_amount)
+        .bind(item.tax_rate)
+        .bind(tax_amount)
         .bind(&item.product_type)
         .bind(now)
         .bind(now)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             println!("[update_sale] SQL error (insert item): {}", e);
             e.to_string()
         })?;
     }
+    consume_sale_stock(&mut tx, &id, sale.allow_oversell).await?;
+    // Invoices built from this sale must follow its new totals
+    refresh_sale_invoice_totals(&mut tx, &id).await?;
+    // A new total changes what the payments overpay
+    sync_sale_credit(&mut tx, &id).await?;
+    // Insert audit log entry for sale update
+    insert_audit_log(&mut *tx, "update", "sale", &id, None, Some("Sale updated")).await?;
+    tx.commit().await.map_err(|e| {
+        println!("[update_sale] SQL error (commit): {}", e);
+        e.to_string()
+    })?;
     // Fetch and return the updated sale
     get_sale_by_id(id, pool).await.and_then(|opt| opt.ok_or_else(|| "Failed to retrieve updated sale".to_string()))
 }
@@ -692,11 +1318,14 @@ pub async fn delete_sale(
     id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
     let now = chrono::Utc::now().to_rfc3339();
+    // Delivered goods cannot go back to stock with the sale
+    delivery_notes::ensure_no_delivery_notes(&mut tx, &id, "deleted").await?;
     // 1. Find all affected invoices via invoice_sales
     let invoice_ids: Vec<String> = sqlx::query("SELECT invoice_id FROM invoice_sales WHERE sale_id = ?")
         .bind(&id)
-        .fetch_all(&*pool)
+        .fetch_all(&mut *tx)
         .await
         .map_err(|e| e.to_string())?
         .into_iter()
@@ -706,7 +1335,7 @@ pub async fn delete_sale(
     for invoice_id in &invoice_ids {
         let is_paid: Option<bool> = sqlx::query_scalar("SELECT is_paid FROM invoices WHERE id = ?")
             .bind(invoice_id)
-            .fetch_one(&*pool)
+            .fetch_one(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
         if is_paid.unwrap_or(false) {
@@ -717,29 +1346,40 @@ pub async fn delete_sale(
     sqlx::query("UPDATE sales SET is_deleted = 1, deleted_at = ? WHERE id = ?")
         .bind(&now)
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // 4. Soft delete all related payments
+    let payment_ids: Vec<String> = sqlx::query_scalar("SELECT id FROM payments WHERE sale_id = ?")
+        .bind(&id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
     sqlx::query("UPDATE payments SET is_deleted = 1, deleted_at = ? WHERE sale_id = ?")
         .bind(&now)
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // 5. Remove all invoice_sales rows for this sale
     sqlx::query("DELETE FROM invoice_sales WHERE sale_id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
+    // The payments take their ledger rows with them, and the invoices'
+    // remaining payments may no longer overpay
+    sync_payments_credit(&mut tx, &payment_ids, None).await?;
+    for invoice_id in &invoice_ids {
+        sync_settlement_credit(&mut tx, Some(invoice_id), None).await?;
+    }
     // 6. For each affected invoice, check if it has any remaining non-deleted sales
     for invoice_id in invoice_ids {
         let count: i64 = sqlx::query_scalar(
             "SELECT COUNT(*) FROM invoice_sales s JOIN sales ON s.sale_id = sales.id WHERE s.invoice_id = ? AND (sales.is_deleted = 0 OR sales.is_deleted IS NULL)"
         )
         .bind(&invoice_id)
-        .fetch_one(&*pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
         if count == 0 {
@@ -747,13 +1387,16 @@ pub async fn delete_sale(
             sqlx::query("UPDATE invoices SET is_deleted = 1, deleted_at = ? WHERE id = ?")
                 .bind(&now)
                 .bind(&invoice_id)
-                .execute(&*pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| e.to_string())?;
         }
     }
+    // 7. Return the sale's coil weight to stock
+    release_sale_stock(&mut tx, &id).await?;
     // Insert audit log entry for sale soft delete
-    insert_audit_log(&*pool, "soft_delete", "sale", &id, None, Some("Sale soft-deleted")).await?;
+    insert_audit_log(&mut *tx, "soft_delete", "sale", &id, None, Some("Sale soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -762,23 +1405,30 @@ pub async fn restore_sale(
     id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let was_deleted: Option<bool> = sqlx::query_scalar("SELECT is_deleted FROM sales WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
     // 1. Restore the sale
     sqlx::query("UPDATE sales SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // 2. Restore all related payments
     sqlx::query("UPDATE payments SET is_deleted = 0, deleted_at = NULL WHERE sale_id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // 3. Restore invoice_sales rows (not possible if deleted, but if you use is_deleted, restore here)
     // 4. For each affected invoice, check if all its sales are now restored (not deleted)
     let invoice_ids: Vec<String> = sqlx::query("SELECT invoice_id FROM invoice_sales WHERE sale_id = ?")
         .bind(&id)
-        .fetch_all(&*pool)
+        .fetch_all(&mut *tx)
         .await
         .map_err(|e| e.to_string())?
         .into_iter()
@@ -789,34 +1439,194 @@ pub async fn restore_sale(
             "SELECT COUNT(*) FROM invoice_sales s JOIN sales ON s.sale_id = sales.id WHERE s.invoice_id = ? AND (sales.is_deleted = 1)"
         )
         .bind(&invoice_id)
-        .fetch_one(&*pool)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
         if count == 0 {
             // All sales are restored, so restore the invoice
             sqlx::query("UPDATE invoices SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
                 .bind(&invoice_id)
-                .execute(&*pool)
+                .execute(&mut *tx)
                 .await
                 .map_err(|e| e.to_string())?;
         }
     }
+    // 5. Take the coil weight again; the stock may have been sold in the meantime
+    if was_deleted.unwrap_or(false) {
+        consume_sale_stock(&mut tx, &id, false).await?;
+    }
+    // The restored payments count again
+    sync_sale_credit(&mut tx, &id).await?;
     // Insert audit log entry for sale restore
-    insert_audit_log(&*pool, "restore", "sale", &id, None, Some("Sale restored")).await?;
+    insert_audit_log(&mut *tx, "restore", "sale", &id, None, Some("Sale restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Invoice {
-    pub id: String,
-    pub invoice_number: String,
-    pub client_id: String,
-    pub date: String,
-    pub due_date: String,
-    pub total_amount_ht: f64,
-    pub total_amount_ttc: f64,
+pub struct SaleTotalsCorrection {
+    pub sale_id: String,
+    pub old_total_amount: Money,
+    pub new_total_amount: Money,
+    pub old_total_amount_ttc: Money,
+    pub new_total_amount_ttc: Money,
+    pub corrected_items: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecalculateSaleTotalsResult {
+    pub checked: i64,
+    pub dry_run: bool,
+    pub corrections: Vec<SaleTotalsCorrection>,
+}
+
+/// Maintenance command: recomputes the item and header totals of every
+/// non-deleted sale and fixes the rows that drifted. With `dry_run` the
+/// corrections are only reported. Sales without a tax rate use the one from
+/// settings; the stamp duty charged when the sale was saved is kept.
+#[tauri::command]
+pub async fn recalculate_all_sale_totals(
+    dry_run: Option<bool>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<RecalculateSaleTotalsResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let now = Utc::now();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let default_tax_rate: Option<f64> = sqlx::query_scalar("SELECT tax_rate FROM settings LIMIT 1")
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
+    let sales = sqlx::query("SELECT id, total_amount, total_amount_ttc, tax_amount, stamp_duty, tax_exemption, transportation_fee, tax_rate FROM sales WHERE is_deleted = 0 OR is_deleted IS NULL ORDER BY date")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut corrections = Vec::new();
+    for sale in &sales {
+        let sale_id: String = sale.get("id");
+        let items = sqlx::query(
+            r#"
+            SELECT id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral,
+                   coil_weight, quantity, price_per_ton, total_amount, product_type, unit_cost, tax_rate, tax_amount
+            FROM sale_items
+            WHERE sale_id = ?
+            "#
+        )
+        .bind(&sale_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let tax_rate: Option<f64> = sale.get("tax_rate");
+        let tax_rate = tax_rate.or(default_tax_rate)
+            .ok_or_else(|| format!("Sale {} has no tax rate and no default is configured in settings", sale_id))?;
+        let exempt = sale.get::<Option<String>, _>("tax_exemption").is_some();
+        let mut lines = Vec::with_capacity(items.len() + 1);
+        let mut corrected_items = 0;
+        for row in &items {
+            let item = CreateSaleItemRequest {
+                description: row.get("description"),
+                coil_ref: row.get("coil_ref"),
+                coil_thickness: row.get("coil_thickness"),
+                coil_width: row.get("coil_width"),
+                top_coat_ral: row.get("top_coat_ral"),
+                back_coat_ral: row.get("back_coat_ral"),
+                coil_weight: row.get("coil_weight"),
+                quantity: row.get("quantity"),
+                price_per_ton: row.get("price_per_ton"),
+                total_amount: row.get("total_amount"),
+                product_type: row.get("product_type"),
+                unit_cost: row.get("unit_cost"),
+                tax_rate: row.get("tax_rate"),
+            };
+            let total_amount = calculate_total_amount(&item);
+            let item_rate = item_tax_rate(item.tax_rate, tax_rate, exempt);
+            let tax_amount = total_amount.mul_f64(item_rate);
+            if item.total_amount != total_amount || row.get::<Money, _>("tax_amount") != tax_amount {
+                corrected_items += 1;
+                if !dry_run {
+                    sqlx::query("UPDATE sale_items SET total_amount = ?, tax_amount = ?, updated_at = ? WHERE id = ?")
+                        .bind(total_amount)
+                        .bind(tax_amount)
+                        .bind(now)
+                        .bind(row.get::<String, _>("id"))
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            lines.push((item_rate, total_amount));
+        }
+        let transportation_fee: Option<Money> = sale.get("transportation_fee");
+        if let Some(fee) = transportation_fee.filter(|fee| !fee.is_zero()) {
+            lines.push((item_tax_rate(None, tax_rate, exempt), fee));
+        }
+
+        let old_total_amount: Money = sale.get("total_amount");
+        let old_total_amount_ttc: Money = sale.get("total_amount_ttc");
+        let old_tax_amount: Money = sale.get("tax_amount");
+        let stamp_duty: Money = sale.get("stamp_duty");
+        let totals = compute_sale_totals(lines, None);
+        let new_total_amount = totals.total_amount;
+        let new_total_amount_ttc = totals.total_amount_ttc + stamp_duty;
+        if corrected_items == 0 && old_total_amount == new_total_amount && old_total_amount_ttc == new_total_amount_ttc && old_tax_amount == totals.tax_amount {
+            continue;
+        }
+
+        if !dry_run {
+            sqlx::query("UPDATE sales SET total_amount = ?, total_amount_ttc = ?, tax_amount = ?, updated_at = ? WHERE id = ?")
+                .bind(new_total_amount)
+                .bind(new_total_amount_ttc)
+                .bind(totals.tax_amount)
+                .bind(now)
+                .bind(&sale_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+            refresh_sale_invoice_totals(&mut tx, &sale_id).await?;
+            let details = format!(
+                "Totals recalculated: HT {} -> {}, TTC {} -> {}",
+                old_total_amount, new_total_amount, old_total_amount_ttc, new_total_amount_ttc
+            );
+            insert_audit_log(&mut *tx, "recalculate", "sale", &sale_id, None, Some(&details)).await?;
+        }
+        corrections.push(SaleTotalsCorrection {
+            sale_id,
+            old_total_amount,
+            new_total_amount,
+            old_total_amount_ttc,
+            new_total_amount_ttc,
+            corrected_items,
+        });
+    }
+    if !dry_run {
+        tx.commit().await.map_err(|e| e.to_string())?;
+    }
+    Ok(RecalculateSaleTotalsResult {
+        checked: sales.len() as i64,
+        dry_run,
+        corrections,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: String,
+    pub invoice_number: String,
+    pub client_id: String,
+    pub date: String,
+    pub due_date: String,
+    pub total_amount_ht: Money,
+    pub total_amount_ttc: Money,
     pub is_paid: bool,
     pub paid_at: Option<String>,
+    /// TTC given back by credit notes
+    pub credited_amount: Money,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+    /// unpaid, partial, paid or overpaid
+    pub payment_status: String,
+    pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: Option<String>,
     pub deleted_at: Option<String>,
@@ -824,14 +1634,17 @@ pub struct Invoice {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateInvoiceRequest {
-    pub invoice_number: String,
+    /// Ignored by create_invoice, which assigns the next number in sequence.
+    /// When set, update_invoice renumbers the invoice.
+    pub invoice_number: Option<String>,
     pub client_id: String,
     pub date: String,
     pub due_date: String,
-    pub total_amount_ht: f64,
-    pub total_amount_ttc: f64,
+    pub total_amount_ht: Money,
+    pub total_amount_ttc: Money,
     pub is_paid: bool,
     pub paid_at: Option<String>,
+    pub notes: Option<String>,
     pub sales_ids: Vec<String>,
 }
 
@@ -841,40 +1654,200 @@ pub struct PaginatedInvoicesResult {
     pub total: i64,
 }
 
+const DEFAULT_INVOICE_NUMBER_FORMAT: &str = "FAC-{YY}/{SEQ:05}";
+const DEFAULT_CREDIT_NOTE_NUMBER_FORMAT: &str = "AV-{YY}/{SEQ:05}";
+const DEFAULT_QUOTE_NUMBER_FORMAT: &str = "PRO-{YY}/{SEQ:05}";
+const DEFAULT_DELIVERY_NOTE_NUMBER_FORMAT: &str = "BL-{YY}/{SEQ:05}";
+
+/// Expands a numbering pattern such as `FAC-{YYYY}-{SEQ:05}`.
+/// Supported tokens are `{YYYY}`, `{YY}`, `{MM}`, `{SEQ}` and `{SEQ:0N}`.
+fn format_document_number(pattern: &str, year: i32, month: u32, seq: i64) -> Result<String, String> {
+    let mut result = String::new();
+    let mut has_seq = false;
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| format!("Unclosed token in number format '{}'", pattern))? + start;
+        let token = &rest[start + 1..end];
+        match token {
+            "YYYY" => result.push_str(&format!("{:04}", year)),
+            "YY" => result.push_str(&format!("{:02}", year.rem_euclid(100))),
+            "MM" => result.push_str(&format!("{:02}", month)),
+            "SEQ" => {
+                has_seq = true;
+                result.push_str(&seq.to_string());
+            }
+            _ if token.starts_with("SEQ:") => {
+                let width: usize = token[4..].parse().map_err(|_| format!("Invalid sequence width in '{}'", token))?;
+                has_seq = true;
+                result.push_str(&format!("{:0width$}", seq, width = width));
+            }
+            _ => return Err(format!("Unknown token '{{{}}}' in number format", token)),
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    if !has_seq {
+        return Err("Number format must contain a {SEQ} token".to_string());
+    }
+    Ok(result)
+}
+
+/// Year and month a document is numbered under, taken from its own date.
+fn document_period(date: &str) -> Result<(i32, u32), String> {
+    date.get(..10)
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| (d.year(), d.month()))
+        .ok_or_else(|| format!("Invalid document date '{}'", date))
+}
+
+/// How a numbered document type draws its numbers: its counter in
+/// document_sequences, the settings column holding its pattern, and where
+/// its numbers are stored.
+struct DocumentNumbering {
+    document_type: &'static str,
+    format_setting: &'static str,
+    default_format: &'static str,
+    table: &'static str,
+    number_column: &'static str,
+}
+
+const INVOICE_NUMBERING: DocumentNumbering = DocumentNumbering {
+    document_type: "invoice",
+    format_setting: "invoice_number_format",
+    default_format: DEFAULT_INVOICE_NUMBER_FORMAT,
+    table: "invoices",
+    number_column: "invoice_number",
+};
+
+const CREDIT_NOTE_NUMBERING: DocumentNumbering = DocumentNumbering {
+    document_type: "credit_note",
+    format_setting: "credit_note_number_format",
+    default_format: DEFAULT_CREDIT_NOTE_NUMBER_FORMAT,
+    table: "credit_notes",
+    number_column: "credit_note_number",
+};
+
+const QUOTE_NUMBERING: DocumentNumbering = DocumentNumbering {
+    document_type: "quote",
+    format_setting: "quote_number_format",
+    default_format: DEFAULT_QUOTE_NUMBER_FORMAT,
+    table: "quotes",
+    number_column: "quote_number",
+};
+
+const DELIVERY_NOTE_NUMBERING: DocumentNumbering = DocumentNumbering {
+    document_type: "delivery_note",
+    format_setting: "delivery_note_number_format",
+    default_format: DEFAULT_DELIVERY_NOTE_NUMBER_FORMAT,
+    table: "delivery_notes",
+    number_column: "delivery_note_number",
+};
+
+async fn document_number_format(conn: &mut sqlx::SqliteConnection, numbering: &DocumentNumbering) -> Result<String, String> {
+    let format: Option<String> = sqlx::query_scalar(&format!("SELECT {} FROM settings LIMIT 1", numbering.format_setting))
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
+    Ok(format.unwrap_or_else(|| numbering.default_format.to_string()))
+}
+
+/// Hands out the next number for a document type. Must run inside the
+/// transaction that inserts the document so a rollback gives the number back.
+async fn next_document_number(conn: &mut sqlx::SqliteConnection, numbering: &DocumentNumbering, date: &str) -> Result<String, String> {
+    let (year, month) = document_period(date)?;
+    let format = document_number_format(conn, numbering).await?;
+    loop {
+        // Bump the counter first: the write lock serialises concurrent callers
+        let seq: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO document_sequences (document_type, year, last_value, updated_at)
+            VALUES (?, ?, 1, CURRENT_TIMESTAMP)
+            ON CONFLICT(document_type, year) DO UPDATE SET last_value = last_value + 1, updated_at = CURRENT_TIMESTAMP
+            RETURNING last_value
+            "#
+        )
+        .bind(numbering.document_type)
+        .bind(year)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        let number = format_document_number(&format, year, month, seq)?;
+        // Numbers issued before the counter existed are skipped, not reused
+        let taken: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE {} = ?", numbering.table, numbering.number_column))
+            .bind(&number)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        if taken == 0 {
+            return Ok(number);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn preview_next_invoice_number(
+    date: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let date = date.unwrap_or_else(|| Utc::now().to_rfc3339());
+    // Draw the number exactly as create_invoice would, then give it back
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let number = next_document_number(&mut tx, &INVOICE_NUMBERING, &date).await?;
+    tx.rollback().await.map_err(|e| e.to_string())?;
+    Ok(number)
+}
+
 #[tauri::command]
 pub async fn get_invoices(
     page: Option<u32>,
     page_size: Option<u32>,
+    filter: Option<ListFilter>,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<PaginatedInvoicesResult, String> {
-    let page = page.unwrap_or(1);
+    let page = page.unwrap_or(1).max(1);
     let page_size = page_size.unwrap_or(5);
     let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE (i.is_deleted = 0 OR i.is_deleted IS NULL)");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &INVOICE_LIST, &mut where_clause, &mut params)?;
+    let progress = invoices_with_payment_progress();
     // Total count
-    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM invoices WHERE is_deleted = 0 OR is_deleted IS NULL")
-        .fetch_one(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
+    let count_query = format!("SELECT COUNT(*) FROM {} i LEFT JOIN clients c ON c.id = i.client_id {}", progress, where_clause);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
     // Paginated rows
-    let invoices = sqlx::query(
+    let rows_query = format!(
         r#"
-        SELECT i.id, i.invoice_number, i.client_id, i.date, i.due_date, 
-               i.total_amount_ht, i.total_amount_ttc, i.is_paid, i.paid_at, 
-               i.created_at, i.updated_at,
+        SELECT i.id, i.invoice_number, i.client_id, i.date, i.due_date,
+               i.total_amount_ht, i.total_amount_ttc, i.is_paid, i.paid_at, i.credited_amount,
+               i.amount_paid, i.balance_due, i.payment_status,
+               i.notes, i.created_at, i.updated_at,
                GROUP_CONCAT(s.id) as sales_ids
-        FROM invoices i
+        FROM {} i
+        LEFT JOIN clients c ON c.id = i.client_id
         LEFT JOIN sales s ON s.invoice_id = i.id
-        WHERE i.is_deleted = 0 OR i.is_deleted IS NULL
+        {}
         GROUP BY i.id
-        ORDER BY i.date DESC
+        {}
         LIMIT ? OFFSET ?
-        "#
-    )
-    .bind(page_size as i64)
-    .bind(offset as i64)
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+        "#,
+        progress, where_clause, order_by
+    );
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let invoices = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
     let rows: Vec<serde_json::Value> = invoices
         .into_iter()
         .map(|row| {
@@ -888,10 +1861,15 @@ pub async fn get_invoices(
                 "client_id": row.get::<String, _>("client_id"),
                 "date": row.get::<String, _>("date"),
                 "due_date": row.get::<String, _>("due_date"),
-                "total_amount_ht": row.get::<f64, _>("total_amount_ht"),
-                "total_amount_ttc": row.get::<f64, _>("total_amount_ttc"),
+                "total_amount_ht": row.get::<Money, _>("total_amount_ht"),
+                "total_amount_ttc": row.get::<Money, _>("total_amount_ttc"),
                 "is_paid": row.get::<bool, _>("is_paid"),
                 "paid_at": row.get::<Option<String>, _>("paid_at"),
+                "credited_amount": row.get::<Money, _>("credited_amount"),
+                "amount_paid": row.get::<Money, _>("amount_paid"),
+                "balance_due": row.get::<Money, _>("balance_due"),
+                "payment_status": row.get::<String, _>("payment_status"),
+                "notes": row.get::<Option<String>, _>("notes"),
                 "created_at": row.get::<String, _>("created_at"),
                 "updated_at": row.get::<Option<String>, _>("updated_at"),
                 "sales_ids": sales_ids_array
@@ -906,16 +1884,22 @@ pub async fn create_invoice(
     invoice: CreateInvoiceRequest,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<Invoice, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
+    let invoice_number = next_document_number(&mut tx, &INVOICE_NUMBERING, &invoice.date).await?;
+    // Clients may echo the previewed number, but never choose their own
+    if let Some(requested) = invoice.invoice_number.as_deref().filter(|n| !n.is_empty() && *n != invoice_number) {
+        return Err(format!("Invoice number {} is no longer available, the next number is {}", requested, invoice_number));
+    }
 
     sqlx::query(
         r#"INSERT INTO invoices (
-            id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, paid_at, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+            id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, paid_at, notes, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
     )
     .bind(&id)
-    .bind(&invoice.invoice_number)
+    .bind(&invoice_number)
     .bind(&invoice.client_id)
     .bind(&invoice.date)
     .bind(&invoice.due_date)
@@ -923,19 +1907,21 @@ pub async fn create_invoice(
     .bind(invoice.total_amount_ttc)
     .bind(false) // is_paid always false at creation
     .bind(Option::<String>::None) // paid_at always null at creation
+    .bind(&invoice.notes)
     .bind(&now)
     .bind(&now)
-    .execute(&*pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| e.to_string())?;
 
     // After creating the invoice, update the sales and their payments
     for sale_id in &invoice.sales_ids {
+        delivery_notes::ensure_sale_delivered(&mut tx, sale_id).await?;
         // Mark sale as invoiced
         sqlx::query("UPDATE sales SET is_invoiced = 1, invoice_id = ? WHERE id = ?")
         .bind(&id)
         .bind(sale_id)
-            .execute(&*pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -946,7 +1932,7 @@ pub async fn create_invoice(
             .bind(&id)
             .bind(sale_id)
             .bind(&now)
-            .execute(&*pool)
+            .execute(&mut *tx)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -954,14 +1940,29 @@ pub async fn create_invoice(
         sqlx::query("UPDATE payments SET invoice_id = ? WHERE sale_id = ?")
             .bind(&id)
             .bind(sale_id)
-            .execute(&*pool)
+            .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     }
 
+    // The sales' payments now settle the invoice as a whole
+    sync_settlement_credit(&mut tx, Some(&id), None).await?;
+    // Insert audit log entry for invoice creation
+    insert_audit_log(&mut *tx, "create", "invoice", &id, None, Some("Invoice created")).await?;
+    // Payments already made on the sales now count towards the invoice
+    let progress = sqlx::query(&format!(
+        "SELECT amount_paid, balance_due, payment_status FROM {} i WHERE i.id = ?",
+        invoices_with_payment_progress()
+    ))
+    .bind(&id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
     Ok(Invoice {
         id,
-        invoice_number: invoice.invoice_number,
+        invoice_number,
         client_id: invoice.client_id,
         date: invoice.date,
         due_date: invoice.due_date,
@@ -969,21 +1970,211 @@ pub async fn create_invoice(
         total_amount_ttc: invoice.total_amount_ttc,
         is_paid: false, // always false at creation
         paid_at: None,  // always None at creation
+        credited_amount: Money::ZERO,
+        amount_paid: progress.get("amount_paid"),
+        balance_due: progress.get("balance_due"),
+        payment_status: progress.get("payment_status"),
+        notes: invoice.notes,
         created_at: now.clone(),
         updated_at: Some(now),
         deleted_at: None,// always None at creation
     })
 }
 
+#[tauri::command]
+pub async fn update_invoice(
+    id: String,
+    mut invoice: CreateInvoiceRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Invoice, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+
+    let current = sqlx::query("SELECT invoice_number, client_id, credited_amount FROM invoices WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Invoice not found".to_string())?;
+    let current_number: String = current.get("invoice_number");
+    let current_client_id: String = current.get("client_id");
+    // Credit notes are drawn on the invoice as it stands
+    if current.get::<Money, _>("credited_amount").is_positive() {
+        return Err(format!("Invoice {} has credit notes and can no longer be changed", current_number));
+    }
+    // Numbers come from the gap-free sequence and are fixed once assigned
+    if invoice.invoice_number.as_deref().is_some_and(|number| number != current_number) {
+        return Err("Invoice numbers cannot be changed once assigned".to_string());
+    }
+    // A sale listed twice is linked once
+    let mut seen = std::collections::HashSet::new();
+    invoice.sales_ids.retain(|sale_id| seen.insert(sale_id.clone()));
+    if invoice.sales_ids.is_empty() {
+        return Err("An invoice must be linked to at least one sale".to_string());
+    }
+    if invoice.client_id != current_client_id {
+        // Payments made against the invoice rather than one of its sales belong to the old client
+        let direct_payments: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM payments p
+            WHERE p.invoice_id = ? AND (p.is_deleted = 0 OR p.is_deleted IS NULL)
+              AND (p.sale_id IS NULL OR NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = ? AND s.sale_id = p.sale_id))
+            "#
+        )
+        .bind(&id)
+        .bind(&id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if direct_payments > 0 {
+            return Err("Cannot change the client of an invoice that has direct payments".to_string());
+        }
+    }
+
+    // Every sale must belong to the invoice's client and not be invoiced elsewhere
+    for sale_id in &invoice.sales_ids {
+        let sale_row = sqlx::query("SELECT client_id, invoice_id FROM sales WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
+            .bind(sale_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Sale {} not found", sale_id))?;
+        let client_id: String = sale_row.get("client_id");
+        let linked_invoice: Option<String> = sale_row.get("invoice_id");
+        if client_id != invoice.client_id {
+            return Err(format!("Sale {} belongs to another client", sale_id));
+        }
+        if linked_invoice.as_deref().is_some_and(|linked| linked != id) {
+            return Err(format!("Sale {} is already invoiced", sale_id));
+        }
+    }
+
+    let current_sales_ids: Vec<String> = sqlx::query_scalar("SELECT sale_id FROM invoice_sales WHERE invoice_id = ?")
+        .bind(&id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Unlink sales that were removed from the invoice
+    for sale_id in current_sales_ids.iter().filter(|s| !invoice.sales_ids.contains(s)) {
+        sqlx::query("DELETE FROM invoice_sales WHERE invoice_id = ? AND sale_id = ?")
+            .bind(&id)
+            .bind(sale_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::query("UPDATE sales SET is_invoiced = 0, invoice_id = NULL WHERE id = ? AND invoice_id = ?")
+            .bind(sale_id)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::query("UPDATE payments SET invoice_id = NULL WHERE sale_id = ? AND invoice_id = ?")
+            .bind(sale_id)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Link sales that were added to the invoice
+    for sale_id in invoice.sales_ids.iter().filter(|s| !current_sales_ids.contains(s)) {
+        delivery_notes::ensure_sale_delivered(&mut tx, sale_id).await?;
+        sqlx::query("UPDATE sales SET is_invoiced = 1, invoice_id = ? WHERE id = ?")
+            .bind(&id)
+            .bind(sale_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        let link_id = Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&link_id)
+            .bind(&id)
+            .bind(sale_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        sqlx::query("UPDATE payments SET invoice_id = ? WHERE sale_id = ?")
+            .bind(&id)
+            .bind(sale_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    sqlx::query(
+        r#"UPDATE invoices SET client_id = ?, date = ?, due_date = ?, notes = ?, updated_at = ? WHERE id = ?"#
+    )
+    .bind(&invoice.client_id)
+    .bind(&invoice.date)
+    .bind(&invoice.due_date)
+    .bind(&invoice.notes)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Totals always come from the linked sales, never from the client, and
+    // setting them recalculates is_paid/paid_at against the final set of sales
+    refresh_invoice_totals(&mut tx, &id).await?;
+    // What each payment overpays follows the new set of sales
+    sync_settlement_credit(&mut tx, Some(&id), None).await?;
+    for sale_id in current_sales_ids.iter().filter(|s| !invoice.sales_ids.contains(s)) {
+        sync_sale_credit(&mut tx, sale_id).await?;
+    }
+
+    // Insert audit log entry for invoice update
+    insert_audit_log(&mut *tx, "update", "invoice", &id, None, Some("Invoice updated")).await?;
+
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc,
+               is_paid, paid_at, credited_amount, amount_paid, balance_due, payment_status, notes, created_at, updated_at, deleted_at
+        FROM {} i
+        WHERE id = ?
+        "#,
+        invoices_with_payment_progress()
+    ))
+    .bind(&id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(Invoice {
+        id: row.get("id"),
+        invoice_number: row.get("invoice_number"),
+        client_id: row.get("client_id"),
+        date: row.get("date"),
+        due_date: row.get("due_date"),
+        total_amount_ht: row.get("total_amount_ht"),
+        total_amount_ttc: row.get("total_amount_ttc"),
+        is_paid: row.get("is_paid"),
+        paid_at: row.get("paid_at"),
+        credited_amount: row.get("credited_amount"),
+        amount_paid: row.get("amount_paid"),
+        balance_due: row.get("balance_due"),
+        payment_status: row.get("payment_status"),
+        notes: row.get("notes"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        deleted_at: row.get("deleted_at"),
+    })
+}
+
 #[tauri::command]
 pub async fn restore_invoice(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
     sqlx::query("UPDATE invoices SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
         .bind(&id)
-        .execute(&*pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
     // Insert audit log entry for invoice restore
-    insert_audit_log(&*pool, "restore", "invoice", &id, None, Some("Invoice restored")).await?;
+    insert_audit_log(&mut *tx, "restore", "invoice", &id, None, Some("Invoice restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -997,8 +2188,8 @@ pub struct CorrugatedSheetItem {
     pub length: Option<f64>,
     pub color: Option<String>,
     pub quantity: i64,
-    pub price_per_unit: f64,
-    pub total_amount: f64,
+    pub price_per_unit: Money,
+    pub total_amount: Money,
     pub created_at: String,
     pub updated_at: Option<String>,
 }
@@ -1012,8 +2203,8 @@ pub struct CreateCorrugatedSheetItemRequest {
     pub length: Option<f64>,
     pub color: Option<String>,
     pub quantity: i64,
-    pub price_per_unit: f64,
-    pub total_amount: f64,
+    pub price_per_unit: Money,
+    pub total_amount: Money,
 }
 
 #[tauri::command]
@@ -1104,8 +2295,8 @@ pub struct SteelSlittingStripItem {
     pub width: Option<f64>,
     pub coil_weight: Option<f64>,
     pub quantity: i64,
-    pub price_per_unit: f64,
-    pub total_amount: f64,
+    pub price_per_unit: Money,
+    pub total_amount: Money,
     pub created_at: String,
     pub updated_at: Option<String>,
 }
@@ -1118,8 +2309,8 @@ pub struct CreateSteelSlittingStripItemRequest {
     pub width: Option<f64>,
     pub coil_weight: Option<f64>,
     pub quantity: i64,
-    pub price_per_unit: f64,
-    pub total_amount: f64,
+    pub price_per_unit: Money,
+    pub total_amount: Money,
 }
 
 #[tauri::command]
@@ -1204,13 +2395,14 @@ pub async fn mark_sale_invoiced(
     invoice_id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<(), String> {
-    
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    delivery_notes::ensure_sale_delivered(&mut conn, &sale_id).await?;
     sqlx::query!(
         "UPDATE sales SET is_invoiced = 1, invoice_id = ? WHERE id = ?",
         invoice_id,
         sale_id
     )
-    .execute(&*pool)
+    .execute(&mut *conn)
     .await
     .map_err(|e| e.to_string())?;
     Ok(())
@@ -1243,14 +2435,17 @@ pub struct AuditLog {
     pub details: Option<String>,
 }
 
-async fn insert_audit_log(
-    pool: &SqlitePool,
+async fn insert_audit_log<'e, E>(
+    executor: E,
     action: &str,
     entity_type: &str,
     entity_id: &str,
     user_id: Option<&str>,
     details: Option<&str>,
-) -> Result<(), String> {
+) -> Result<(), String>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
     let now = chrono::Utc::now().to_rfc3339();
     sqlx::query(
         "INSERT INTO audit_log (action, entity_type, entity_id, user_id, timestamp, details) VALUES (?, ?, ?, ?, ?, ?)"
@@ -1261,7 +2456,7 @@ async fn insert_audit_log(
     .bind(user_id)
     .bind(&now)
     .bind(details)
-    .execute(pool)
+    .execute(executor)
     .await
     .map_err(|e| e.to_string())?;
     Ok(())
@@ -1272,61 +2467,45 @@ pub async fn delete_invoice(
     id: String,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<(), String> {
-    // Check if invoice is a draft and has no payments
-    let invoice_row = sqlx::query!("SELECT is_paid FROM invoices WHERE id = ?", id)
-        .fetch_one(&*pool)
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let invoice = sqlx::query("SELECT invoice_number, total_amount_ttc FROM invoices WHERE id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Invoice not found".to_string())?;
+    // An invoice that bills anything is cancelled by a credit note, never
+    // deleted. Only an empty one can be voided.
+    if !invoice.get::<Money, _>("total_amount_ttc").is_zero() {
+        return Err(format!(
+            "Invoice {} has been issued and cannot be deleted; cancel it with a credit note instead",
+            invoice.get::<String, _>("invoice_number")
+        ));
+    }
+    // Every invoice carries a number from the gap-free sequence, so it is
+    // never hard-deleted: it stays on record as soft-deleted
+    let sales = sqlx::query!("SELECT id FROM sales WHERE invoice_id = ?", id)
+        .fetch_all(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
-    let is_paid = invoice_row.is_paid;
-    // Treat NULL as false (unpaid) for draft logic
-    let is_paid = is_paid.unwrap_or(false);
-    let payment_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM payments WHERE invoice_id = ? AND (is_deleted = 0 OR is_deleted IS NULL)")
-        .bind(&id)
-        .fetch_one(&*pool)
+    for sale in &sales {
+        sqlx::query!(
+            "UPDATE sales SET is_invoiced = 0, invoice_id = NULL WHERE id = ?",
+            sale.id
+        )
+        .execute(&mut *tx)
         .await
         .map_err(|e| e.to_string())?;
-    if !is_paid && payment_count == 0 {
-        // Hard delete: remove invoice, invoice_sales, and unmark sales
-        sqlx::query("DELETE FROM invoice_sales WHERE invoice_id = ?")
-            .bind(&id)
-            .execute(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        sqlx::query("UPDATE sales SET is_invoiced = 0, invoice_id = NULL WHERE invoice_id = ?")
-            .bind(&id)
-            .execute(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        sqlx::query("DELETE FROM invoices WHERE id = ?")
-            .bind(&id)
-            .execute(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        insert_audit_log(&*pool, "hard_delete", "invoice", &id, None, Some("Invoice hard-deleted (draft, no payments)")).await?;
-    } else {
-        // Soft delete as before
-        let sales = sqlx::query!("SELECT id FROM sales WHERE invoice_id = ?", id)
-            .fetch_all(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        for sale in &sales {
-            sqlx::query!(
-                "UPDATE sales SET is_invoiced = 0, invoice_id = NULL WHERE id = ?",
-                sale.id
-            )
-            .execute(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        }
-        let now = chrono::Utc::now().to_rfc3339();
-        sqlx::query("UPDATE invoices SET is_deleted = 1, deleted_at = ? WHERE id = ?")
-            .bind(&now)
-            .bind(&id)
-            .execute(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
-        insert_audit_log(&*pool, "soft_delete", "invoice", &id, None, Some("Invoice soft-deleted")).await?;
     }
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("UPDATE invoices SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "soft_delete", "invoice", &id, None, Some("Invoice soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
     Ok(())
 }
 
@@ -1336,11 +2515,13 @@ pub struct Payment {
     pub sale_id: Option<String>,
     pub invoice_id: Option<String>,
     pub client_id: String,
-    pub amount: f64,
+    pub amount: Money,
     pub date: String,
     pub method: String,
     pub notes: Option<String>,
     pub check_number: Option<String>,
+    /// The cheque or traite the payment was made with
+    pub instrument_id: Option<String>,
     pub created_at: String,
     pub updated_at: Option<String>,
     pub is_deleted: Option<bool>,
@@ -1352,11 +2533,30 @@ pub struct CreatePaymentRequest {
     pub sale_id: Option<String>,
     pub invoice_id: Option<String>,
     pub client_id: String,
-    pub amount: f64,
+    pub amount: Money,
     pub date: String,
     pub method: String,
     pub notes: Option<String>,
+    /// Number of the cheque or traite
     pub check_number: Option<String>,
+    /// Bank the cheque or traite is drawn on
+    #[serde(default)]
+    pub bank: Option<String>,
+    /// Échéance of a traite or a post-dated cheque
+    #[serde(default)]
+    pub maturity_date: Option<String>,
+}
+
+/// Shared by create_payment and update_payment: a payment needs a positive
+/// amount and a method, and only cheques and traites keep a check_number.
+fn validate_payment(payment: &mut CreatePaymentRequest) -> Result<(), String> {
+    if !payment.amount.is_positive() {
+        return Err("Payment amount must be positive".to_string());
+    }
+    if payment.method.trim().is_empty() {
+        return Err("Payment method is required".to_string());
+    }
+    validate_instrument_fields(&payment.method, &mut payment.check_number, &mut payment.bank, &mut payment.maturity_date)
 }
 
 #[tauri::command]
@@ -1367,15 +2567,17 @@ pub async fn create_payment(
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
 
-    // Ensure check_number is None if method is not 'check'
-    if payment.method != "check" {
-        payment.check_number = None;
-    }
+    validate_payment(&mut payment)?;
 
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let instrument_id = match payment_instrument_request(&payment) {
+        Some(instrument) => Some(insert_payment_instrument(&mut tx, &instrument).await?),
+        None => None,
+    };
     sqlx::query(
         r#"
-        INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, notes, check_number, created_at, updated_at, is_deleted)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+        INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, notes, check_number, instrument_id, created_at, updated_at, is_deleted)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
         "#,
     )
     .bind(&id)
@@ -1387,11 +2589,14 @@ pub async fn create_payment(
     .bind(&payment.method)
     .bind(&payment.notes)
     .bind(&payment.check_number)
+    .bind(&instrument_id)
     .bind(&now)
     .bind(&now)
-    .execute(&*pool)
+    .execute(&mut *tx)
     .await
     .map_err(|e| e.to_string())?;
+    sync_payment_credit(&mut tx, &id).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
 
     let new_payment = Payment {
         id,
@@ -1403,6 +2608,7 @@ pub async fn create_payment(
         method: payment.method,
         notes: payment.notes,
         check_number: payment.check_number,
+        instrument_id,
         created_at: now.clone(),
         updated_at: Some(now),
         is_deleted: Some(false),
@@ -1413,61 +2619,3128 @@ pub async fn create_payment(
 }
 
 #[tauri::command]
-pub async fn get_payments(
+pub async fn update_payment(
+    id: String,
+    mut payment: CreatePaymentRequest,
     pool: tauri::State<'_, SqlitePool>
-) -> Result<Vec<Payment>, String> {
-    let rows = sqlx::query(
-        r#"SELECT * FROM payments WHERE is_deleted = 0 OR is_deleted IS NULL ORDER BY date DESC"#
-    )
+) -> Result<Payment, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+
+    validate_payment(&mut payment)?;
+
+    // Deleted payments must be restored before they can be edited
+    let current = sqlx::query(
+        r#"
+        SELECT p.bulk_payment_id, p.instrument_id, pi.status AS instrument_status
+        FROM payments p LEFT JOIN payment_instruments pi ON pi.id = p.instrument_id
+        WHERE p.id = ? AND (p.is_deleted = 0 OR p.is_deleted IS NULL)
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Payment not found".to_string())?;
+
+    // The cheque or traite follows the payment until it goes to the bank
+    let previous_instrument: Option<String> = current.get("instrument_id");
+    if previous_instrument.is_some() {
+        let status: String = current.get("instrument_status");
+        if status != "received" {
+            return Err(format!("The cheque or traite of this payment is already {} and can no longer be changed", status));
+        }
+        if current.get::<Option<String>, _>("bulk_payment_id").is_some() {
+            return Err("This payment is part of a bulk cheque or traite; change the bulk payment instead".to_string());
+        }
+    }
+    let instrument_id = match payment_instrument_request(&payment) {
+        Some(instrument) => Some(save_payment_instrument(&mut tx, previous_instrument.as_deref(), &instrument).await?),
+        None => None,
+    };
+    let previous_settlement = payment_settlement(&mut tx, &id).await?;
+
+    // The update_*_payment_status_after_payment_update triggers recalculate
+    // is_paid/paid_at for both the old and the new sale/invoice links
+    sqlx::query(
+        r#"
+        UPDATE payments
+        SET sale_id = ?, invoice_id = ?, client_id = ?, amount = ?, date = ?, method = ?, notes = ?, check_number = ?, instrument_id = ?, updated_at = ?
+        WHERE id = ?
+        "#,
+    )
+    .bind(&payment.sale_id)
+    .bind(&payment.invoice_id)
+    .bind(&payment.client_id)
+    .bind(payment.amount)
+    .bind(&payment.date)
+    .bind(&payment.method)
+    .bind(&payment.notes)
+    .bind(&payment.check_number)
+    .bind(&instrument_id)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sync_payment_credit(&mut tx, &id).await?;
+    // The payments left behind on the old invoice or sale may no longer overpay
+    if payment_settlement(&mut tx, &id).await? != previous_settlement {
+        let (invoice_id, sale_id) = previous_settlement;
+        sync_settlement_credit(&mut tx, invoice_id.as_deref(), sale_id.as_deref()).await?;
+    }
+    // Paid otherwise now: the cheque or traite was never really received
+    if instrument_id.is_none() {
+        if let Some(ref previous) = previous_instrument {
+            sqlx::query("DELETE FROM payment_instruments WHERE id = ?")
+                .bind(previous)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    // Insert audit log entry for payment update
+    insert_audit_log(&mut *tx, "update", "payment", &id, None, Some("Payment updated")).await?;
+
+    let row = sqlx::query("SELECT * FROM payments WHERE id = ?")
+        .bind(&id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(Payment {
+        id: row.get("id"),
+        sale_id: row.get("sale_id"),
+        invoice_id: row.get("invoice_id"),
+        client_id: row.get("client_id"),
+        amount: row.get("amount"),
+        date: row.get("date"),
+        method: row.get("method"),
+        notes: row.get("notes"),
+        check_number: row.get("check_number"),
+        instrument_id: row.get("instrument_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+    })
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedPaymentsResult {
+    pub rows: Vec<Payment>,
+    pub total: i64,
+}
+
+#[tauri::command]
+pub async fn get_payments(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedPaymentsResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE (p.is_deleted = 0 OR p.is_deleted IS NULL)");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &PAYMENT_LIST, &mut where_clause, &mut params)?;
+    // A payment on an invoiced sale counts towards the sale's invoice
+    let from = format!(
+        "FROM payments p LEFT JOIN clients c ON c.id = p.client_id LEFT JOIN sales ps ON ps.id = p.sale_id LEFT JOIN invoices inv ON inv.id = COALESCE(p.invoice_id, ps.invoice_id) {}",
+        where_clause
+    );
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT p.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let payments = rows.into_iter().map(|row| Payment {
+        id: row.get("id"),
+        sale_id: row.get("sale_id"),
+        invoice_id: row.get("invoice_id"),
+        client_id: row.get("client_id"),
+        amount: row.get("amount"),
+        date: row.get("date"),
+        method: row.get("method"),
+        notes: row.get("notes"),
+        check_number: row.get("check_number"),
+        instrument_id: row.get("instrument_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+    }).collect();
+    Ok(PaginatedPaymentsResult { rows: payments, total })
+}
+
+#[tauri::command]
+pub async fn delete_payment(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("UPDATE payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sync_payment_credit(&mut tx, &id).await?;
+    // Insert audit log entry for payment soft delete
+    insert_audit_log(&mut *tx, "soft_delete", "payment", &id, None, Some("Payment soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_payment(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let bounced: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM payments p JOIN payment_instruments pi ON pi.id = p.instrument_id WHERE p.id = ? AND pi.status = 'bounced'"
+    )
+    .bind(&id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if bounced > 0 {
+        return Err("This payment's cheque or traite bounced; record a new payment instead".to_string());
+    }
+    sqlx::query("UPDATE payments SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sync_payment_credit(&mut tx, &id).await?;
+    // Insert audit log entry for payment restore
+    insert_audit_log(&mut *tx, "restore", "payment", &id, None, Some("Payment restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// --- Payment instruments ---
+
+/// Payment methods whose cheque or traite is followed until the bank clears it.
+pub const INSTRUMENT_METHODS: [&str; 2] = ["check", "traite"];
+pub const INSTRUMENT_STATUSES: [&str; 4] = ["received", "deposited", "cleared", "bounced"];
+
+/// The instrument aliased `pi` still backs a live payment or bulk payment.
+const INSTRUMENT_IN_USE_SQL: &str = r#"(
+    EXISTS (SELECT 1 FROM payments p WHERE p.instrument_id = pi.id AND (p.is_deleted = 0 OR p.is_deleted IS NULL))
+    OR EXISTS (SELECT 1 FROM bulk_payments b WHERE b.instrument_id = pi.id AND (b.is_deleted = 0 OR b.is_deleted IS NULL))
+)"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentInstrument {
+    pub id: String,
+    pub client_id: String,
+    pub client_name: Option<String>,
+    /// 'check' or 'traite'
+    pub kind: String,
+    pub number: Option<String>,
+    pub bank: Option<String>,
+    pub amount: Money,
+    pub received_date: String,
+    pub maturity_date: String,
+    /// One of INSTRUMENT_STATUSES
+    pub status: String,
+    pub deposit_batch: Option<String>,
+    pub deposited_at: Option<String>,
+    pub cleared_at: Option<String>,
+    pub bounced_at: Option<String>,
+    pub bounce_reason: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub bulk_payment_id: Option<String>,
+    /// The payments made with it, reversed ones included
+    pub payments: Vec<Payment>,
+}
+
+/// What a payment says about the cheque or traite it was made with.
+struct InstrumentRequest<'a> {
+    client_id: &'a str,
+    kind: &'a str,
+    number: Option<&'a str>,
+    bank: Option<&'a str>,
+    amount: Money,
+    received_date: &'a str,
+    maturity_date: Option<&'a str>,
+}
+
+fn payment_instrument_request(payment: &CreatePaymentRequest) -> Option<InstrumentRequest<'_>> {
+    INSTRUMENT_METHODS.contains(&payment.method.as_str()).then(|| InstrumentRequest {
+        client_id: &payment.client_id,
+        kind: &payment.method,
+        number: payment.check_number.as_deref(),
+        bank: payment.bank.as_deref(),
+        amount: payment.amount,
+        received_date: &payment.date,
+        maturity_date: payment.maturity_date.as_deref(),
+    })
+}
+
+fn bulk_instrument_request(bulk: &CreateBulkPaymentRequest) -> Option<InstrumentRequest<'_>> {
+    INSTRUMENT_METHODS.contains(&bulk.method.as_str()).then(|| InstrumentRequest {
+        client_id: &bulk.client_id,
+        kind: &bulk.method,
+        number: bulk.check_number.as_deref(),
+        bank: bulk.bank.as_deref(),
+        amount: bulk.total_amount,
+        received_date: &bulk.date,
+        maturity_date: bulk.maturity_date.as_deref(),
+    })
+}
+
+fn parse_instrument_date(date: &str) -> Result<chrono::NaiveDate, String> {
+    date.get(..10)
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(|| format!("Invalid date '{}'", date))
+}
+
+/// Payments made otherwise drop the cheque fields; a traite cannot be
+/// recorded without its maturity date.
+fn validate_instrument_fields(
+    method: &str,
+    number: &mut Option<String>,
+    bank: &mut Option<String>,
+    maturity_date: &mut Option<String>,
+) -> Result<(), String> {
+    if !INSTRUMENT_METHODS.contains(&method) {
+        *number = None;
+        *bank = None;
+        *maturity_date = None;
+        return Ok(());
+    }
+    *bank = bank.as_deref().map(str::trim).filter(|b| !b.is_empty()).map(str::to_string);
+    match maturity_date.as_deref() {
+        Some(date) => {
+            parse_instrument_date(date)?;
+        }
+        None if method == "traite" => return Err("A traite needs a maturity date".to_string()),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Records a received cheque or traite, or rewrites `existing` while it is
+/// still in hand. A cheque without a maturity date can be cashed on receipt.
+async fn save_payment_instrument(
+    conn: &mut sqlx::SqliteConnection,
+    existing: Option<&str>,
+    instrument: &InstrumentRequest<'_>,
+) -> Result<String, String> {
+    let maturity_date = instrument.maturity_date.unwrap_or(instrument.received_date);
+    let now = Utc::now().to_rfc3339();
+    let id = existing.map(str::to_string).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let sql = if existing.is_some() {
+        r#"
+        UPDATE payment_instruments
+        SET client_id = ?, kind = ?, number = ?, bank = ?, amount = ?, received_date = ?, maturity_date = ?, updated_at = ?
+        WHERE id = ?
+        "#
+    } else {
+        r#"
+        INSERT INTO payment_instruments (client_id, kind, number, bank, amount, received_date, maturity_date, updated_at, id, status)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 'received')
+        "#
+    };
+    sqlx::query(sql)
+        .bind(instrument.client_id)
+        .bind(instrument.kind)
+        .bind(instrument.number)
+        .bind(instrument.bank)
+        .bind(instrument.amount)
+        .bind(instrument.received_date)
+        .bind(maturity_date)
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+async fn insert_payment_instrument(conn: &mut sqlx::SqliteConnection, instrument: &InstrumentRequest<'_>) -> Result<String, String> {
+    save_payment_instrument(conn, None, instrument).await
+}
+
+async fn fetch_payment_instruments(
+    conn: &mut sqlx::SqliteConnection,
+    where_clause: &str,
+    params: &[String],
+) -> Result<Vec<PaymentInstrument>, String> {
+    let sql = format!(
+        r#"
+        SELECT pi.*, c.name AS client_name,
+               (SELECT b.id FROM bulk_payments b WHERE b.instrument_id = pi.id) AS bulk_payment_id
+        FROM payment_instruments pi
+        LEFT JOIN clients c ON c.id = pi.client_id
+        {}
+        ORDER BY date(pi.maturity_date) ASC, pi.created_at ASC
+        "#,
+        where_clause
+    );
+    let mut query = sqlx::query(&sql);
+    for param in params {
+        query = query.bind(param);
+    }
+    let rows = query.fetch_all(&mut *conn).await.map_err(|e| e.to_string())?;
+
+    let mut instruments = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: String = row.get("id");
+        let payments = sqlx::query("SELECT * FROM payments WHERE instrument_id = ? ORDER BY created_at ASC, rowid ASC")
+            .bind(&id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(payment_from_row)
+            .collect();
+        instruments.push(PaymentInstrument {
+            id,
+            client_id: row.get("client_id"),
+            client_name: row.get("client_name"),
+            kind: row.get("kind"),
+            number: row.get("number"),
+            bank: row.get("bank"),
+            amount: row.get("amount"),
+            received_date: row.get("received_date"),
+            maturity_date: row.get("maturity_date"),
+            status: row.get("status"),
+            deposit_batch: row.get("deposit_batch"),
+            deposited_at: row.get("deposited_at"),
+            cleared_at: row.get("cleared_at"),
+            bounced_at: row.get("bounced_at"),
+            bounce_reason: row.get("bounce_reason"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            bulk_payment_id: row.get("bulk_payment_id"),
+            payments,
+        });
+    }
+    Ok(instruments)
+}
+
+async fn fetch_payment_instrument(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<PaymentInstrument, String> {
+    fetch_payment_instruments(conn, "WHERE pi.id = ?", &[id.to_string()])
+        .await?
+        .pop()
+        .ok_or_else(|| "Cheque or traite not found".to_string())
+}
+
+/// Loads an instrument for a status change, refusing it unless it is in `from`.
+async fn instrument_in_status(conn: &mut sqlx::SqliteConnection, id: &str, from: &str) -> Result<PaymentInstrument, String> {
+    let instrument = fetch_payment_instrument(conn, id).await?;
+    if instrument.status != from {
+        return Err(format!(
+            "{} {} is {}, not {}",
+            if instrument.kind == "traite" { "Traite" } else { "Cheque" },
+            instrument.number.as_deref().unwrap_or(&instrument.id),
+            instrument.status,
+            from
+        ));
+    }
+    Ok(instrument)
+}
+
+#[tauri::command]
+pub async fn get_payment_instruments(
+    client_id: Option<String>,
+    status: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<PaymentInstrument>, String> {
+    let mut where_clause = String::from("WHERE 1 = 1");
+    let mut params = Vec::new();
+    if let Some(client_id) = client_id {
+        where_clause.push_str(" AND pi.client_id = ?");
+        params.push(client_id);
+    }
+    if let Some(status) = status {
+        if !INSTRUMENT_STATUSES.contains(&status.as_str()) {
+            return Err(format!("Unknown cheque status '{}'", status));
+        }
+        where_clause.push_str(" AND pi.status = ?");
+        params.push(status);
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_payment_instruments(&mut conn, &where_clause, &params).await
+}
+
+/// Cheques and traites still in the portfolio (received or deposited, not
+/// yet cleared) that mature between `from` and `to`, soonest first.
+#[tauri::command]
+pub async fn get_cheques_due(
+    from: Option<String>,
+    to: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<PaymentInstrument>, String> {
+    let mut where_clause = format!("WHERE pi.status IN ('received', 'deposited') AND {}", INSTRUMENT_IN_USE_SQL);
+    let mut params = Vec::new();
+    if let Some(from) = from {
+        parse_instrument_date(&from)?;
+        where_clause.push_str(" AND date(pi.maturity_date) >= date(?)");
+        params.push(from);
+    }
+    if let Some(to) = to {
+        parse_instrument_date(&to)?;
+        where_clause.push_str(" AND date(pi.maturity_date) <= date(?)");
+        params.push(to);
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_payment_instruments(&mut conn, &where_clause, &params).await
+}
+
+/// Hands received cheques and traites to the bank under one remittance slip.
+#[tauri::command]
+pub async fn deposit_payment_instruments(
+    ids: Vec<String>,
+    deposit_batch: String,
+    date: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<PaymentInstrument>, String> {
+    let deposit_batch = deposit_batch.trim().to_string();
+    if ids.is_empty() {
+        return Err("Select at least one cheque or traite to deposit".to_string());
+    }
+    if deposit_batch.is_empty() {
+        return Err("Deposit batch reference is required".to_string());
+    }
+    parse_instrument_date(&date)?;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+    let mut deposited = Vec::with_capacity(ids.len());
+    for id in &ids {
+        instrument_in_status(&mut tx, id, "received").await?;
+        sqlx::query("UPDATE payment_instruments SET status = 'deposited', deposit_batch = ?, deposited_at = ?, updated_at = ? WHERE id = ?")
+            .bind(&deposit_batch)
+            .bind(&date)
+            .bind(&now)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        let details = format!("Deposited with batch {}", deposit_batch);
+        insert_audit_log(&mut *tx, "deposit", "payment_instrument", id, None, Some(&details)).await?;
+        deposited.push(fetch_payment_instrument(&mut tx, id).await?);
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(deposited)
+}
+
+#[tauri::command]
+pub async fn clear_payment_instrument(
+    id: String,
+    date: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaymentInstrument, String> {
+    parse_instrument_date(&date)?;
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    instrument_in_status(&mut tx, &id, "deposited").await?;
+    sqlx::query("UPDATE payment_instruments SET status = 'cleared', cleared_at = ?, updated_at = ? WHERE id = ?")
+        .bind(&date)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "clear", "payment_instrument", &id, None, Some("Cleared by the bank")).await?;
+    let cleared = fetch_payment_instrument(&mut tx, &id).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(cleared)
+}
+
+/// Records an unpaid cheque or traite returned by the bank and takes back
+/// the payments made with it, so the sales and invoices they settled are
+/// due again.
+#[tauri::command]
+pub async fn bounce_payment_instrument(
+    id: String,
+    date: String,
+    reason: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaymentInstrument, String> {
+    parse_instrument_date(&date)?;
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let instrument = instrument_in_status(&mut tx, &id, "deposited").await?;
+
+    let live_bulk: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM bulk_payments WHERE instrument_id = ? AND (is_deleted = 0 OR is_deleted IS NULL)"
+    )
+    .bind(&id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    let live: Vec<&Payment> = instrument.payments.iter().filter(|p| p.is_deleted != Some(true)).collect();
+    match live_bulk {
+        Some(bulk_id) => {
+            soft_delete_bulk_payment(&mut tx, &bulk_id).await?;
+            insert_audit_log(&mut *tx, "soft_delete", "bulk_payment", &bulk_id, None, Some("Bulk payment reversed: its cheque or traite bounced")).await?;
+        }
+        None => {
+            let now = Utc::now().to_rfc3339();
+            for payment in &live {
+                sqlx::query("UPDATE payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+                    .bind(&now)
+                    .bind(&payment.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                sync_payment_credit(&mut tx, &payment.id).await?;
+                insert_audit_log(&mut *tx, "soft_delete", "payment", &payment.id, None, Some("Payment reversed: its cheque or traite bounced")).await?;
+            }
+        }
+    }
+
+    sqlx::query("UPDATE payment_instruments SET status = 'bounced', bounced_at = ?, bounce_reason = ?, updated_at = ? WHERE id = ?")
+        .bind(&date)
+        .bind(&reason)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = format!(
+        "{} of {} bounced{}; {} payment(s) reversed",
+        instrument.number.as_deref().unwrap_or(&instrument.kind),
+        instrument.amount,
+        reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default(),
+        live.len()
+    );
+    insert_audit_log(&mut *tx, "bounce", "payment_instrument", &id, None, Some(&details)).await?;
+    let bounced = fetch_payment_instrument(&mut tx, &id).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(bounced)
+}
+
+// --- Client credit ledger ---
+
+/// Payment method that spends the client's credit balance instead of new money.
+pub const CREDIT_PAYMENT_METHOD: &str = "credit";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditTransaction {
+    pub id: String,
+    pub client_id: String,
+    pub amount: Money,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub source_type: String,
+    pub source_id: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+    /// Client credit balance after this entry
+    pub balance: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditAdjustmentRequest {
+    pub client_id: String,
+    /// Positive adds credit, negative removes it
+    pub amount: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditBalanceCorrection {
+    pub client_id: String,
+    pub old_balance: Money,
+    pub ledger_balance: Money,
+}
+
+/// The credit_transactions triggers keep clients.credit_balance in sync with the ledger.
+async fn insert_credit_transaction(
+    conn: &mut sqlx::SqliteConnection,
+    client_id: &str,
+    amount: Money,
+    kind: &str,
+    source_type: &str,
+    source_id: Option<&str>,
+    notes: Option<&str>,
+) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        r#"
+        INSERT INTO credit_transactions (id, client_id, amount, type, source_type, source_id, notes, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(client_id)
+    .bind(amount)
+    .bind(kind)
+    .bind(source_type)
+    .bind(source_id)
+    .bind(notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+async fn client_credit_balance(conn: &mut sqlx::SqliteConnection, client_id: &str) -> Result<Money, String> {
+    sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = ?")
+        .bind(client_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Client not found".to_string())
+}
+
+/// (TTC total, amount paid) of an invoice, counting payments the same way as
+/// the invoice payment status triggers.
+async fn invoice_payment_totals(conn: &mut sqlx::SqliteConnection, invoice_id: &str) -> Result<Option<(Money, Money)>, String> {
+    let row = sqlx::query(&format!(
+        "SELECT i.total_amount_ttc AS total, {} AS paid FROM invoices i WHERE i.id = ?",
+        INVOICE_AMOUNT_PAID_SQL
+    ))
+    .bind(invoice_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| (row.get("total"), row.get("paid"))))
+}
+
+/// (TTC total, amount paid) of a sale.
+async fn sale_payment_totals(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<Option<(Money, Money)>, String> {
+    let row = sqlx::query(&format!(
+        "SELECT s.total_amount_ttc AS total, {} AS paid FROM sales s WHERE s.id = ?",
+        SALE_AMOUNT_PAID_SQL
+    ))
+    .bind(sale_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| (row.get("total"), row.get("paid"))))
+}
+
+/// What a payment settles: (invoice, sale). The invoice, when there is one,
+/// directly or through the payment's sale; otherwise the sale alone.
+async fn payment_settlement(conn: &mut sqlx::SqliteConnection, payment_id: &str) -> Result<(Option<String>, Option<String>), String> {
+    let row = sqlx::query(
+        "SELECT COALESCE(p.invoice_id, (SELECT s.invoice_id FROM invoice_sales s WHERE s.sale_id = p.sale_id)) AS invoice_id, p.sale_id FROM payments p WHERE p.id = ?"
+    )
+    .bind(payment_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Payment not found".to_string())?;
+    Ok((row.get("invoice_id"), row.get("sale_id")))
+}
+
+/// Payments counted towards an invoice (on its sales or on the invoice
+/// itself) or, without an invoice, towards a sale, in the order they were
+/// received. Deleted payments come too when `include_deleted` is set.
+async fn settlement_payments(
+    conn: &mut sqlx::SqliteConnection,
+    invoice_id: Option<&str>,
+    sale_id: Option<&str>,
+    include_deleted: bool,
+) -> Result<Vec<(String, Money)>, String> {
+    let live = if include_deleted { "1 = 1" } else { "(p.is_deleted = 0 OR p.is_deleted IS NULL)" };
+    let (filter, id) = match (invoice_id, sale_id) {
+        (Some(invoice_id), _) => ("(p.invoice_id = ?1 OR p.sale_id IN (SELECT sale_id FROM invoice_sales WHERE invoice_id = ?1))", invoice_id),
+        (None, Some(sale_id)) => ("p.sale_id = ?1", sale_id),
+        (None, None) => return Ok(Vec::new()),
+    };
+    let rows = sqlx::query(&format!(
+        "SELECT p.id, p.amount FROM payments p WHERE {} AND {} ORDER BY p.date ASC, p.created_at ASC, p.rowid ASC",
+        filter, live
+    ))
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(|row| (row.get("id"), row.get("amount"))).collect())
+}
+
+/// How much of a payment lands beyond its invoice's (or sale's) TTC total.
+/// Payments fill the total in the order they were received, so only the
+/// latest ones overpay and the invoice's overpayment is credited once.
+async fn payment_overpayment(conn: &mut sqlx::SqliteConnection, payment_id: &str) -> Result<Money, String> {
+    let (invoice_id, sale_id) = payment_settlement(conn, payment_id).await?;
+    let totals = if let Some(ref invoice_id) = invoice_id {
+        invoice_payment_totals(conn, invoice_id).await?
+    } else if let Some(ref sale_id) = sale_id {
+        sale_payment_totals(conn, sale_id).await?
+    } else {
+        None
+    };
+    let Some((total, _)) = totals else {
+        return Ok(Money::ZERO);
+    };
+    let mut before = Money::ZERO;
+    for (id, amount) in settlement_payments(conn, invoice_id.as_deref(), sale_id.as_deref(), false).await? {
+        if id == payment_id {
+            return Ok((before + amount - total.max(before)).max(Money::ZERO));
+        }
+        before += amount;
+    }
+    Ok(Money::ZERO)
+}
+
+/// Re-derives the ledger rows one payment produces: a debit when it is paid
+/// with credit, a credit for whatever it overpays.
+async fn derive_payment_credit(conn: &mut sqlx::SqliteConnection, payment_id: &str) -> Result<(), String> {
+    let payment = sqlx::query("SELECT client_id, method, amount, COALESCE(is_deleted, 0) AS is_deleted FROM payments WHERE id = ?")
+        .bind(payment_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Payment not found".to_string())?;
+    let client_id: String = payment.get("client_id");
+    let method: String = payment.get("method");
+    let amount: Money = payment.get("amount");
+    let is_deleted: i64 = payment.get("is_deleted");
+
+    sqlx::query("DELETE FROM credit_transactions WHERE source_id = ? AND source_type IN ('payment', 'credit_use')")
+        .bind(payment_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if is_deleted != 0 {
+        return Ok(());
+    }
+    let overpayment = payment_overpayment(conn, payment_id).await?;
+    if method == CREDIT_PAYMENT_METHOD {
+        if overpayment.is_positive() {
+            return Err("A credit payment cannot exceed the amount due".to_string());
+        }
+        insert_credit_transaction(conn, &client_id, amount, "debit", "credit_use", Some(payment_id), Some("Paid with credit")).await?;
+        if client_credit_balance(conn, &client_id).await?.is_negative() {
+            return Err("Insufficient credit balance".to_string());
+        }
+    } else if overpayment.is_positive() {
+        insert_credit_transaction(conn, &client_id, overpayment, "credit", "payment", Some(payment_id), Some("Overpayment")).await?;
+    }
+    Ok(())
+}
+
+/// Re-derives the ledger rows of every payment on an invoice or, without an
+/// invoice, on a sale: what one payment overpays depends on the others and
+/// on the total. Called whenever a payment or a total changes.
+async fn sync_settlement_credit(
+    conn: &mut sqlx::SqliteConnection,
+    invoice_id: Option<&str>,
+    sale_id: Option<&str>,
+) -> Result<(), String> {
+    let payment_ids: Vec<String> = settlement_payments(conn, invoice_id, sale_id, true)
+        .await?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    sync_payments_credit(conn, &payment_ids, invoice_id).await
+}
+
+async fn sync_payments_credit(
+    conn: &mut sqlx::SqliteConnection,
+    payment_ids: &[String],
+    invoice_id: Option<&str>,
+) -> Result<(), String> {
+    let mut client_ids: Vec<String> = Vec::new();
+    for payment_id in payment_ids {
+        let ids: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT client_id FROM credit_transactions WHERE source_id = ? AND source_type IN ('payment', 'credit_use')
+            UNION SELECT client_id FROM payments WHERE id = ?
+            "#
+        )
+        .bind(payment_id)
+        .bind(payment_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        for id in ids {
+            if !client_ids.contains(&id) {
+                client_ids.push(id);
+            }
+        }
+    }
+    let mut balances_before = Vec::new();
+    for id in &client_ids {
+        balances_before.push(client_credit_balance(conn, id).await?);
+    }
+
+    for payment_id in payment_ids {
+        derive_payment_credit(conn, payment_id).await?;
+    }
+    // What the invoice's credit notes give back depends on how much was paid
+    if let Some(invoice_id) = invoice_id {
+        credit_notes::sync_invoice_credit_notes(conn, invoice_id).await?;
+    }
+
+    // Removing an overpayment credit that was already spent would leave a debt behind
+    for (id, before) in client_ids.iter().zip(balances_before) {
+        let after = client_credit_balance(conn, id).await?;
+        if after.is_negative() && after < before {
+            return Err("This change would make the client's credit balance negative".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Re-syncs the ledger of everything the payment settles. Called after every
+/// payment insert, update, soft delete and restore.
+async fn sync_payment_credit(conn: &mut sqlx::SqliteConnection, payment_id: &str) -> Result<(), String> {
+    match payment_settlement(conn, payment_id).await? {
+        (None, None) => sync_payments_credit(conn, &[payment_id.to_string()], None).await,
+        (invoice_id, sale_id) => sync_settlement_credit(conn, invoice_id.as_deref(), sale_id.as_deref()).await,
+    }
+}
+
+/// Re-syncs the ledger of a sale's payments after its total or invoice changed.
+async fn sync_sale_credit(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<(), String> {
+    let invoice_id: Option<String> = sqlx::query_scalar("SELECT invoice_id FROM invoice_sales WHERE sale_id = ?")
+        .bind(sale_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    sync_settlement_credit(conn, invoice_id.as_deref(), Some(sale_id)).await
+}
+
+#[tauri::command]
+pub async fn create_credit_adjustment(
+    adjustment: CreditAdjustmentRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<CreditTransaction, String> {
+    if adjustment.amount.is_zero() {
+        return Err("Adjustment amount cannot be zero".to_string());
+    }
+    let kind = if adjustment.amount.is_positive() { "credit" } else { "debit" };
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let id = insert_credit_transaction(
+        &mut tx,
+        &adjustment.client_id,
+        adjustment.amount.abs(),
+        kind,
+        "manual_adjustment",
+        None,
+        adjustment.notes.as_deref(),
+    )
+    .await?;
+    let balance = client_credit_balance(&mut tx, &adjustment.client_id).await?;
+    if balance.is_negative() {
+        return Err("Adjustment would make the credit balance negative".to_string());
+    }
+    let details = format!("{} {} (balance {})", kind, adjustment.amount.abs(), balance);
+    insert_audit_log(&mut *tx, "credit_adjustment", "client", &adjustment.client_id, None, Some(&details)).await?;
+    let row = sqlx::query("SELECT * FROM credit_transactions WHERE id = ?")
+        .bind(&id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(CreditTransaction {
+        id: row.get("id"),
+        client_id: row.get("client_id"),
+        amount: row.get("amount"),
+        kind: row.get("type"),
+        source_type: row.get("source_type"),
+        source_id: row.get("source_id"),
+        notes: row.get("notes"),
+        created_at: row.get("created_at"),
+        balance,
+    })
+}
+
+#[tauri::command]
+pub async fn get_client_credit_ledger(
+    client_id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<CreditTransaction>, String> {
+    let rows = sqlx::query(
+        r#"SELECT * FROM credit_transactions WHERE client_id = ? ORDER BY created_at ASC, rowid ASC"#
+    )
+    .bind(&client_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut balance = Money::ZERO;
+    let ledger = rows.into_iter().map(|row| {
+        let amount: Money = row.get("amount");
+        let kind: String = row.get("type");
+        if kind == "credit" {
+            balance += amount;
+        } else {
+            balance -= amount;
+        }
+        CreditTransaction {
+            id: row.get("id"),
+            client_id: row.get("client_id"),
+            amount,
+            kind,
+            source_type: row.get("source_type"),
+            source_id: row.get("source_id"),
+            notes: row.get("notes"),
+            created_at: row.get("created_at"),
+            balance,
+        }
+    }).collect();
+    Ok(ledger)
+}
+
+/// Resets every clients.credit_balance that drifted from its ledger total.
+#[tauri::command]
+pub async fn reconcile_credit_balances(
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<CreditBalanceCorrection>, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let rows = sqlx::query(
+        r#"
+        SELECT c.id, c.credit_balance,
+          IFNULL((
+            SELECT SUM(CASE WHEN ct.type = 'credit' THEN ct.amount ELSE -ct.amount END)
+            FROM credit_transactions ct
+            WHERE ct.client_id = c.id
+          ), 0) AS ledger_balance
+        FROM clients c
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut corrections = Vec::new();
+    for row in rows {
+        let correction = CreditBalanceCorrection {
+            client_id: row.get("id"),
+            old_balance: row.get("credit_balance"),
+            ledger_balance: row.get("ledger_balance"),
+        };
+        if correction.old_balance == correction.ledger_balance {
+            continue;
+        }
+        sqlx::query("UPDATE clients SET credit_balance = ? WHERE id = ?")
+            .bind(correction.ledger_balance)
+            .bind(&correction.client_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        let details = format!("credit_balance {} -> {}", correction.old_balance, correction.ledger_balance);
+        insert_audit_log(&mut *tx, "reconcile_credit", "client", &correction.client_id, None, Some(&details)).await?;
+        corrections.push(correction);
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(corrections)
+}
+
+// --- Bulk payments ---
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPaymentAllocation {
+    /// Exactly one of invoice_id / sale_id
+    pub invoice_id: Option<String>,
+    pub sale_id: Option<String>,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBulkPaymentRequest {
+    pub client_id: String,
+    pub total_amount: Money,
+    pub date: String,
+    pub method: String,
+    pub notes: Option<String>,
+    pub check_number: Option<String>,
+    #[serde(default)]
+    pub bank: Option<String>,
+    #[serde(default)]
+    pub maturity_date: Option<String>,
+    /// 'oldest_due_first' or 'explicit'
+    pub strategy: String,
+    /// Required for the 'explicit' strategy
+    pub allocations: Option<Vec<BulkPaymentAllocation>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPayment {
+    pub id: String,
+    pub client_id: String,
+    pub total_amount: Money,
+    pub date: String,
+    pub method: String,
+    pub notes: Option<String>,
+    pub check_number: Option<String>,
+    /// The cheque or traite covering the whole total; its child payments point at it too
+    pub instrument_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub is_deleted: Option<bool>,
+    pub deleted_at: Option<String>,
+    /// Part of the total that did not settle any sale or invoice and went to client credit
+    pub credited_amount: Money,
+    pub payments: Vec<Payment>,
+}
+
+fn payment_from_row(row: &sqlx::sqlite::SqliteRow) -> Payment {
+    Payment {
+        id: row.get("id"),
+        sale_id: row.get("sale_id"),
+        invoice_id: row.get("invoice_id"),
+        client_id: row.get("client_id"),
+        amount: row.get("amount"),
+        date: row.get("date"),
+        method: row.get("method"),
+        notes: row.get("notes"),
+        check_number: row.get("check_number"),
+        instrument_id: row.get("instrument_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Unpaid invoices and uninvoiced sales of a client, oldest due date first.
+async fn open_receivables(conn: &mut sqlx::SqliteConnection, client_id: &str) -> Result<Vec<BulkPaymentAllocation>, String> {
+    let rows = sqlx::query(
+        r#"
+        SELECT 'invoice' AS kind, i.id, date(i.due_date) AS due
+        FROM invoices i
+        WHERE i.client_id = ? AND i.is_deleted = 0 AND (i.is_paid = 0 OR i.is_paid IS NULL)
+        UNION ALL
+        SELECT 'sale' AS kind, s.id, date(s.date) AS due
+        FROM sales s
+        WHERE s.client_id = ? AND (s.is_deleted = 0 OR s.is_deleted IS NULL) AND (s.is_paid = 0 OR s.is_paid IS NULL)
+          AND NOT EXISTS (
+            SELECT 1 FROM invoice_sales x JOIN invoices i ON i.id = x.invoice_id AND i.is_deleted = 0
+            WHERE x.sale_id = s.id
+          )
+        ORDER BY due ASC, id ASC
+        "#,
+    )
+    .bind(client_id)
+    .bind(client_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut receivables = Vec::new();
+    for row in rows {
+        let kind: String = row.get("kind");
+        let id: String = row.get("id");
+        let totals = if kind == "invoice" {
+            invoice_payment_totals(conn, &id).await?
+        } else {
+            sale_payment_totals(conn, &id).await?
+        };
+        let due = totals.map(|(total, paid)| total - paid).unwrap_or_default();
+        if !due.is_positive() {
+            continue;
+        }
+        let (invoice_id, sale_id) = if kind == "invoice" { (Some(id), None) } else { (None, Some(id)) };
+        receivables.push(BulkPaymentAllocation { invoice_id, sale_id, amount: due });
+    }
+    Ok(receivables)
+}
+
+/// Inserts the child payments settling one allocation. Payments always
+/// reference a sale, so an invoice allocation is spread over its sales.
+async fn insert_bulk_allocation(
+    conn: &mut sqlx::SqliteConnection,
+    bulk: &CreateBulkPaymentRequest,
+    bulk_id: &str,
+    allocation: &BulkPaymentAllocation,
+) -> Result<(), String> {
+    let mut parts: Vec<(String, Money)> = Vec::new();
+    if let Some(ref invoice_id) = allocation.invoice_id {
+        let sale_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT s.id FROM invoice_sales x JOIN sales s ON s.id = x.sale_id WHERE x.invoice_id = ? ORDER BY s.date ASC, s.id ASC"
+        )
+        .bind(invoice_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+        if sale_ids.is_empty() {
+            return Err(format!("Invoice {} has no sales to allocate a payment to", invoice_id));
+        }
+        let mut remaining = allocation.amount;
+        for (i, sale_id) in sale_ids.iter().enumerate() {
+            let share = if i + 1 == sale_ids.len() {
+                remaining
+            } else {
+                let due = sale_payment_totals(conn, sale_id).await?
+                    .map(|(total, paid)| total - paid)
+                    .unwrap_or_default();
+                remaining.min(due.max(Money::ZERO))
+            };
+            if share.is_positive() {
+                parts.push((sale_id.clone(), share));
+                remaining -= share;
+            }
+        }
+    } else if let Some(ref sale_id) = allocation.sale_id {
+        parts.push((sale_id.clone(), allocation.amount));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    for (sale_id, amount) in parts {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            r#"
+            INSERT INTO payments (id, sale_id, invoice_id, client_id, bulk_payment_id, amount, date, method, notes, check_number, created_at, updated_at, is_deleted)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+            "#,
+        )
+        .bind(&id)
+        .bind(&sale_id)
+        .bind(&allocation.invoice_id)
+        .bind(&bulk.client_id)
+        .bind(bulk_id)
+        .bind(amount)
+        .bind(&bulk.date)
+        .bind(&bulk.method)
+        .bind(&bulk.notes)
+        .bind(&bulk.check_number)
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn fetch_bulk_payment(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<BulkPayment, String> {
+    let row = sqlx::query("SELECT * FROM bulk_payments WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Bulk payment not found".to_string())?;
+    let payments: Vec<Payment> = sqlx::query("SELECT * FROM payments WHERE bulk_payment_id = ? ORDER BY created_at ASC, rowid ASC")
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(payment_from_row)
+        .collect();
+    let total_amount: Money = row.get("total_amount");
+    let allocated: Money = payments.iter().map(|p| p.amount).sum();
+    Ok(BulkPayment {
+        id: row.get("id"),
+        client_id: row.get("client_id"),
+        total_amount,
+        date: row.get("date"),
+        method: row.get("method"),
+        notes: row.get("notes"),
+        check_number: row.get("check_number"),
+        instrument_id: row.get("instrument_id"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+        credited_amount: total_amount - allocated,
+        payments,
+    })
+}
+
+#[tauri::command]
+pub async fn create_bulk_payment(
+    mut bulk: CreateBulkPaymentRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<BulkPayment, String> {
+    if !bulk.total_amount.is_positive() {
+        return Err("Payment amount must be positive".to_string());
+    }
+    if bulk.method.trim().is_empty() {
+        return Err("Payment method is required".to_string());
+    }
+    if bulk.method == CREDIT_PAYMENT_METHOD {
+        return Err("A bulk payment cannot be paid with credit".to_string());
+    }
+    validate_instrument_fields(&bulk.method, &mut bulk.check_number, &mut bulk.bank, &mut bulk.maturity_date)?;
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    client_credit_balance(&mut tx, &bulk.client_id).await?;
+
+    let allocations = match bulk.strategy.as_str() {
+        "oldest_due_first" => {
+            let mut remaining = bulk.total_amount;
+            let mut allocations = Vec::new();
+            for mut receivable in open_receivables(&mut tx, &bulk.client_id).await? {
+                if remaining.is_zero() {
+                    break;
+                }
+                receivable.amount = receivable.amount.min(remaining);
+                remaining -= receivable.amount;
+                allocations.push(receivable);
+            }
+            allocations
+        }
+        "explicit" => {
+            let allocations = bulk.allocations.take().unwrap_or_default();
+            if allocations.is_empty() {
+                return Err("Explicit allocation requires at least one allocation".to_string());
+            }
+            let open = open_receivables(&mut tx, &bulk.client_id).await?;
+            for allocation in &allocations {
+                if !allocation.amount.is_positive() {
+                    return Err("Allocation amounts must be positive".to_string());
+                }
+                if allocation.invoice_id.is_some() == allocation.sale_id.is_some() {
+                    return Err("Each allocation needs exactly one of invoice_id or sale_id".to_string());
+                }
+                let target = open
+                    .iter()
+                    .find(|r| r.invoice_id == allocation.invoice_id && r.sale_id == allocation.sale_id);
+                let label = allocation.invoice_id.as_deref().or(allocation.sale_id.as_deref()).unwrap_or_default();
+                match target {
+                    None => return Err(format!("{} has nothing due for this client", label)),
+                    Some(target) if allocation.amount > target.amount => {
+                        return Err(format!("Allocation of {} exceeds the {} due on {}", allocation.amount, target.amount, label));
+                    }
+                    Some(_) => {}
+                }
+            }
+            let allocated: Money = allocations.iter().map(|a| a.amount).sum();
+            if allocated > bulk.total_amount {
+                return Err(format!("Allocations total {} but the payment is only {}", allocated, bulk.total_amount));
+            }
+            allocations
+        }
+        other => return Err(format!("Unknown allocation strategy '{}'", other)),
+    };
+
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let instrument_id = match bulk_instrument_request(&bulk) {
+        Some(instrument) => Some(insert_payment_instrument(&mut tx, &instrument).await?),
+        None => None,
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO bulk_payments (id, client_id, total_amount, date, method, notes, check_number, instrument_id, created_at, updated_at, is_deleted)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+        "#,
+    )
+    .bind(&id)
+    .bind(&bulk.client_id)
+    .bind(bulk.total_amount)
+    .bind(&bulk.date)
+    .bind(&bulk.method)
+    .bind(&bulk.notes)
+    .bind(&bulk.check_number)
+    .bind(&instrument_id)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for allocation in &allocations {
+        insert_bulk_allocation(&mut tx, &bulk, &id, allocation).await?;
+    }
+    if instrument_id.is_some() {
+        sqlx::query("UPDATE payments SET instrument_id = ? WHERE bulk_payment_id = ?")
+            .bind(&instrument_id)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    let allocated: Money = allocations.iter().map(|a| a.amount).sum();
+    let remainder = bulk.total_amount - allocated;
+    if remainder.is_positive() {
+        insert_credit_transaction(&mut tx, &bulk.client_id, remainder, "credit", "payment", Some(&id), Some("Bulk payment remainder")).await?;
+    }
+
+    let details = format!("{} allocated over {} target(s), {} to credit", allocated, allocations.len(), remainder);
+    insert_audit_log(&mut *tx, "create", "bulk_payment", &id, None, Some(&details)).await?;
+    let created = fetch_bulk_payment(&mut tx, &id).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn get_bulk_payments(
+    client_id: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<BulkPayment>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let ids: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM bulk_payments
+        WHERE (is_deleted = 0 OR is_deleted IS NULL) AND (? IS NULL OR client_id = ?)
+        ORDER BY date DESC, created_at DESC
+        "#
+    )
+    .bind(&client_id)
+    .bind(&client_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut bulk_payments = Vec::with_capacity(ids.len());
+    for id in ids {
+        bulk_payments.push(fetch_bulk_payment(&mut conn, &id).await?);
+    }
+    Ok(bulk_payments)
+}
+
+/// Soft-deletes a bulk payment together with its child payments and the
+/// remainder it put into client credit.
+async fn soft_delete_bulk_payment(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<(), String> {
+    let bulk = fetch_bulk_payment(conn, id).await?;
+    if bulk.is_deleted == Some(true) {
+        return Err("Bulk payment not found".to_string());
+    }
+    let now = Utc::now().to_rfc3339();
+    for payment in &bulk.payments {
+        sqlx::query("UPDATE payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+            .bind(&now)
+            .bind(&payment.id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        sync_payment_credit(conn, &payment.id).await?;
+    }
+    let balance_before = client_credit_balance(conn, &bulk.client_id).await?;
+    sqlx::query("DELETE FROM credit_transactions WHERE source_id = ? AND source_type = 'payment'")
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let balance = client_credit_balance(conn, &bulk.client_id).await?;
+    if balance.is_negative() && balance < balance_before {
+        return Err("This change would make the client's credit balance negative".to_string());
+    }
+    // Fires update_status_after_bulk_payment_change
+    sqlx::query("UPDATE bulk_payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_bulk_payment(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    soft_delete_bulk_payment(&mut tx, &id).await?;
+    insert_audit_log(&mut *tx, "soft_delete", "bulk_payment", &id, None, Some("Bulk payment soft-deleted with its payments")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// --- Invoice PDF ---
+
+/// The printed dimensions, colours and coil reference of a sale item.
+fn item_details_from_row(row: &sqlx::sqlite::SqliteRow) -> Vec<String> {
+    let product_type: String = row.get("product_type");
+    let thickness: Option<f64> = row.get("coil_thickness");
+    let width: Option<f64> = row.get("coil_width");
+    let coil_ref: Option<String> = row.get("coil_ref");
+    let top_coat: Option<String> = row.get("top_coat_ral");
+    let back_coat: Option<String> = row.get("back_coat_ral");
+
+    let mut details = Vec::new();
+    if let (Some(thickness), Some(width)) = (thickness, width) {
+        if product_type != "corrugated_sheet" {
+            details.push(format!("{} × {} mm", pdf::format_quantity(thickness, 3), pdf::format_quantity(width, 2)));
+        } else {
+            details.push(format!("ép. {} mm", pdf::format_quantity(thickness, 3)));
+        }
+    }
+    match (top_coat.as_deref().filter(|r| !r.is_empty()), back_coat.as_deref().filter(|r| !r.is_empty())) {
+        (Some(top), Some(back)) => details.push(format!("RAL {}/{}", top, back)),
+        (Some(ral), None) | (None, Some(ral)) => details.push(format!("RAL {}", ral)),
+        (None, None) => {}
+    }
+    if let Some(coil_ref) = coil_ref.filter(|r| !r.is_empty()) {
+        details.push(format!("Réf. {}", coil_ref));
+    }
+    details
+}
+
+/// Builds the printed line for a sale item according to its product type.
+/// The quantity printed on an invoice line: the units price_per_ton is
+/// charged for, so quantity × unit price is the line total.
+pub fn invoice_line_quantity(product_type: &str, quantity: f64, coil_width: Option<f64>, coil_weight: Option<f64>) -> String {
+    match (product_type, units_priced_by(product_type, quantity, coil_width, coil_weight)) {
+        ("corrugated_sheet", Some(meters)) => format!("{} m", pdf::format_quantity(meters, 2)),
+        (_, Some(tons)) => format!("{} T", pdf::format_quantity(tons, 3)),
+        (_, None) => pdf::format_quantity(quantity, 2),
+    }
+}
+
+fn invoice_line_from_row(row: &sqlx::sqlite::SqliteRow) -> pdf::InvoiceLine {
+    let product_type: String = row.get("product_type");
+    let quantity: f64 = row.get("quantity");
+    let width: Option<f64> = row.get("coil_width");
+    let weight: Option<f64> = row.get("coil_weight");
+    let mut details = item_details_from_row(row);
+
+    match product_type.as_str() {
+        "corrugated_sheet" => {
+            details.insert(0, format!("{} × {} m", pdf::format_quantity(quantity, 2), pdf::format_quantity(width.unwrap_or(0.0), 2)));
+        }
+        // Priced by weight: the piece count is printed for the record
+        "steel_slitting" => details.push(format!("{} pièce(s)", pdf::format_quantity(quantity, 0))),
+        _ => {}
+    }
+
+    pdf::InvoiceLine {
+        description: row.get("description"),
+        details: details.join(", "),
+        quantity: invoice_line_quantity(&product_type, quantity, width, weight),
+        unit_price: row.get("price_per_ton"),
+        total: row.get("total_amount"),
+    }
+}
+
+/// Company block, logo and currency of printed documents, from the settings row.
+async fn document_letterhead(pool: &SqlitePool) -> Result<(pdf::DocumentParty, Option<String>, String), String> {
+    let settings = sqlx::query(
+        "SELECT company_name, company_address, company_phone, company_email, company_logo, currency, nif, nis, rc, ai, rib FROM settings LIMIT 1"
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let setting = |column: &str| -> Option<String> { settings.as_ref().and_then(|row| row.get(column)) };
+    let company = pdf::DocumentParty {
+        name: setting("company_name").unwrap_or_default(),
+        company: None,
+        address: setting("company_address"),
+        phone: setting("company_phone"),
+        email: setting("company_email"),
+        nif: setting("nif"),
+        nis: setting("nis"),
+        rc: setting("rc"),
+        ai: setting("ai"),
+        rib: setting("rib"),
+    };
+    Ok((company, setting("company_logo"), setting("currency").unwrap_or_else(|| "DZD".to_string())))
+}
+
+async fn document_client(pool: &SqlitePool, client_id: &str) -> Result<pdf::DocumentParty, String> {
+    let client = sqlx::query("SELECT name, company, address, phone, email, nif, nis, rc, ai FROM clients WHERE id = ?")
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Client not found".to_string())?;
+    Ok(pdf::DocumentParty {
+        name: client.get("name"),
+        company: client.get("company"),
+        address: client.get("address"),
+        phone: client.get("phone"),
+        email: client.get("email"),
+        nif: client.get("nif"),
+        nis: client.get("nis"),
+        rc: client.get("rc"),
+        ai: client.get("ai"),
+        rib: None,
+    })
+}
+
+/// Writes a generated document, creating its folder if needed.
+fn write_document(output_path: &str, bytes: &[u8]) -> Result<String, String> {
+    let path = PathBuf::from(output_path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, bytes).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn generate_invoice_pdf(
+    invoice_id: String,
+    output_path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let invoice = sqlx::query(
+        "SELECT invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, payment_method, notes FROM invoices WHERE id = ?"
+    )
+    .bind(&invoice_id)
+    .fetch_optional(&*pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Invoice not found".to_string())?;
+    let client_id: String = invoice.get("client_id");
+
+    let client = document_client(&pool, &client_id).await?;
+    let (company, logo, currency) = document_letterhead(&pool).await?;
+
+    let items = sqlx::query(
+        r#"
+        SELECT si.description, si.coil_ref, si.coil_thickness, si.coil_width, si.top_coat_ral, si.back_coat_ral,
+               si.coil_weight, si.quantity, si.price_per_ton, si.total_amount, si.product_type
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        JOIN invoice_sales l ON l.sale_id = s.id
+        WHERE l.invoice_id = ? AND s.is_deleted = 0
+        ORDER BY s.date, s.created_at, si.created_at, si.rowid
+        "#
+    )
+    .bind(&invoice_id)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let transportation_fee: Money = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(s.transportation_fee), 0) FROM sales s JOIN invoice_sales l ON l.sale_id = s.id WHERE l.invoice_id = ? AND s.is_deleted = 0"
+    )
+    .bind(&invoice_id)
+    .fetch_one(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let tax_rates: Vec<f64> = sqlx::query_scalar(
+        "SELECT DISTINCT s.tax_rate FROM sales s JOIN invoice_sales l ON l.sale_id = s.id WHERE l.invoice_id = ? AND s.is_deleted = 0 AND s.tax_rate IS NOT NULL"
+    )
+    .bind(&invoice_id)
     .fetch_all(&*pool)
     .await
     .map_err(|e| e.to_string())?;
 
-    let payments = rows.into_iter().map(|row| Payment {
+    let document = pdf::InvoiceDocument {
+        number: invoice.get("invoice_number"),
+        date: invoice.get("date"),
+        due_date: invoice.get("due_date"),
+        payment_method: invoice.get("payment_method"),
+        notes: invoice.get("notes"),
+        currency,
+        logo,
+        company,
+        client,
+        lines: items.iter().map(invoice_line_from_row).collect(),
+        transportation_fee,
+        total_ht: invoice.get("total_amount_ht"),
+        total_ttc: invoice.get("total_amount_ttc"),
+        tax_rate: if tax_rates.len() == 1 { Some(tax_rates[0]) } else { None },
+    };
+    let bytes = pdf::render_invoice(&document)?;
+    write_document(&output_path, &bytes)
+}
+
+// --- Client statement ---
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatementEntry {
+    pub date: String,
+    /// invoice, sale, payment, credit_note, credit_payment, credit_adjustment,
+    /// bulk_credit, or one of the first three suffixed with `_deleted` for a
+    /// soft-deletion
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub document_id: String,
+    pub reference: Option<String>,
+    pub description: String,
+    pub debit: Money,
+    pub credit: Money,
+    pub balance: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientStatement {
+    pub client_id: String,
+    pub client_name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub opening_balance: Money,
+    pub entries: Vec<StatementEntry>,
+    pub total_debit: Money,
+    pub total_credit: Money,
+    pub closing_balance: Money,
+}
+
+/// `YYYY-MM-DD` part of a stored date or timestamp.
+fn statement_day(value: &str) -> String {
+    value.get(..10).unwrap_or(value).to_string()
+}
+
+/// Everything that moved the client's balance, oldest first. The balance is
+/// what the client owes: invoices and uninvoiced sales are debits, payments
+/// and credit granted are credits, and a soft-deletion reverses its document
+/// on the day it happened so past balances stay as they were.
+async fn client_statement_entries(pool: &SqlitePool, client_id: &str) -> Result<Vec<StatementEntry>, String> {
+    // (day, timestamp) keeps same-day entries in the order they were recorded
+    let mut entries: Vec<((String, String), StatementEntry)> = Vec::new();
+    let mut push = |date: &str, stamp: &str, kind: &str, id: &str, reference: Option<String>, description: String, debit: Money, credit: Money| {
+        entries.push(((statement_day(date), stamp.to_string()), StatementEntry {
+            date: statement_day(date),
+            kind: kind.to_string(),
+            document_id: id.to_string(),
+            reference,
+            description,
+            debit,
+            credit,
+            balance: Money::ZERO,
+        }));
+    };
+
+    let invoices = sqlx::query(
+        "SELECT id, invoice_number, date, total_amount_ttc, created_at, is_deleted, deleted_at FROM invoices WHERE client_id = ?"
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in &invoices {
+        let id: String = row.get("id");
+        let number: String = row.get("invoice_number");
+        let date: String = row.get("date");
+        let created_at: Option<String> = row.get("created_at");
+        let total: Money = row.get("total_amount_ttc");
+        push(&date, created_at.as_deref().unwrap_or(&date), "invoice", &id, Some(number.clone()), format!("Invoice {}", number), total, Money::ZERO);
+        if row.get::<Option<bool>, _>("is_deleted").unwrap_or(false) {
+            let deleted_at: String = row.get::<Option<String>, _>("deleted_at").unwrap_or_else(|| date.clone());
+            push(&deleted_at, &deleted_at, "invoice_deleted", &id, Some(number.clone()), format!("Invoice {} deleted", number), Money::ZERO, total);
+        }
+    }
+
+    // Credit notes lower what is owed; the part that became client credit
+    // is not listed again
+    let credit_notes = sqlx::query(
+        r#"
+        SELECT cn.id, cn.credit_note_number, cn.date, cn.total_amount_ttc, cn.created_at, i.invoice_number
+        FROM credit_notes cn
+        JOIN invoices i ON i.id = cn.invoice_id
+        WHERE cn.client_id = ?
+        "#
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in &credit_notes {
+        let id: String = row.get("id");
+        let number: String = row.get("credit_note_number");
+        let invoice_number: String = row.get("invoice_number");
+        let date: String = row.get("date");
+        let created_at: Option<String> = row.get("created_at");
+        let total: Money = row.get("total_amount_ttc");
+        let description = format!("Credit note {} on invoice {}", number, invoice_number);
+        push(&date, created_at.as_deref().unwrap_or(&date), "credit_note", &id, Some(number), description, Money::ZERO, total);
+    }
+
+    // Invoiced sales are carried by their invoice. A sale whose invoice was
+    // deleted is owed again from the day of that deletion.
+    let sales = sqlx::query(
+        r#"
+        SELECT s.id, s.date, s.total_amount_ttc, s.created_at, s.is_deleted, s.deleted_at,
+               (SELECT MAX(i.deleted_at) FROM invoice_sales l JOIN invoices i ON i.id = l.invoice_id
+                WHERE l.sale_id = s.id AND i.is_deleted = 1) AS uninvoiced_at
+        FROM sales s
+        LEFT JOIN invoices live ON live.id = s.invoice_id AND live.is_deleted = 0
+        WHERE s.client_id = ? AND live.id IS NULL
+        "#
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in &sales {
+        let id: String = row.get("id");
+        let sale_date: String = row.get("date");
+        let created_at: Option<String> = row.get("created_at");
+        let uninvoiced_at: Option<String> = row.get("uninvoiced_at");
+        let total: Money = row.get("total_amount_ttc");
+        let (date, stamp) = match uninvoiced_at {
+            Some(at) if statement_day(&at) > statement_day(&sale_date) => (at.clone(), at),
+            _ => (sale_date.clone(), created_at.unwrap_or_else(|| sale_date.clone())),
+        };
+        push(&date, &stamp, "sale", &id, None, format!("Sale of {}", statement_day(&sale_date)), total, Money::ZERO);
+        if row.get::<Option<bool>, _>("is_deleted").unwrap_or(false) {
+            let deleted_at: String = row.get::<Option<String>, _>("deleted_at").unwrap_or_else(|| date.clone());
+            push(&deleted_at, &deleted_at, "sale_deleted", &id, None, format!("Sale of {} deleted", statement_day(&sale_date)), Money::ZERO, total);
+        }
+    }
+
+    let payments = sqlx::query(
+        r#"
+        SELECT p.id, p.date, p.amount, p.method, p.check_number, p.created_at, p.is_deleted, p.deleted_at, i.invoice_number
+        FROM payments p
+        LEFT JOIN invoices i ON i.id = p.invoice_id
+        WHERE p.client_id = ?
+        "#
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in &payments {
+        let id: String = row.get("id");
+        let date: String = row.get("date");
+        let created_at: Option<String> = row.get("created_at");
+        let method: String = row.get("method");
+        let amount: Money = row.get("amount");
+        let check_number: Option<String> = row.get("check_number");
+        let invoice_number: Option<String> = row.get("invoice_number");
+        let reference = invoice_number.clone().or(check_number.clone());
+        let stamp = created_at.unwrap_or_else(|| date.clone());
+        let target = invoice_number.map(|n| format!(" on invoice {}", n)).unwrap_or_default();
+        let deleted = row.get::<Option<bool>, _>("is_deleted").unwrap_or(false);
+        if method == CREDIT_PAYMENT_METHOD {
+            // Spending credit moves no money: the credit was counted when it was granted
+            push(&date, &stamp, "credit_payment", &id, reference, format!("{} paid from client credit{}", amount, target), Money::ZERO, Money::ZERO);
+            continue;
+        }
+        let description = match check_number.filter(|n| !n.is_empty()) {
+            Some(number) => format!("Payment by {} n° {}{}", method, number, target),
+            None => format!("Payment by {}{}", method, target),
+        };
+        push(&date, &stamp, "payment", &id, reference.clone(), description, Money::ZERO, amount);
+        if deleted {
+            let deleted_at: String = row.get::<Option<String>, _>("deleted_at").unwrap_or_else(|| date.clone());
+            push(&deleted_at, &deleted_at, "payment_deleted", &id, reference, "Payment deleted".to_string(), amount, Money::ZERO);
+        }
+    }
+
+    // Overpayments and credit spent are already in the payment amounts; only
+    // credit that did not come from a payment row changes what is owed
+    let credits = sqlx::query(
+        r#"
+        SELECT ct.id, ct.amount, ct.type, ct.source_type, ct.notes, ct.created_at
+        FROM credit_transactions ct
+        WHERE ct.client_id = ?
+          AND (ct.source_type IN ('manual_adjustment', 'refund')
+               OR (ct.source_type = 'payment' AND ct.source_id IN (SELECT id FROM bulk_payments)))
+        "#
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in &credits {
+        let id: String = row.get("id");
+        let created_at: String = row.get("created_at");
+        let amount: Money = row.get("amount");
+        let kind: String = row.get("type");
+        let source_type: String = row.get("source_type");
+        let notes: Option<String> = row.get("notes");
+        let (entry_kind, label) = match source_type.as_str() {
+            "payment" => ("bulk_credit", "Bulk payment remainder credited"),
+            "refund" => ("credit_adjustment", "Credit refund"),
+            _ => ("credit_adjustment", "Credit adjustment"),
+        };
+        let description = match notes.filter(|n| !n.is_empty()) {
+            Some(notes) => format!("{}: {}", label, notes),
+            None => label.to_string(),
+        };
+        let (debit, credit) = if kind == "credit" { (Money::ZERO, amount) } else { (amount, Money::ZERO) };
+        push(&created_at, &created_at, entry_kind, &id, None, description, debit, credit);
+    }
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+async fn build_client_statement(
+    pool: &SqlitePool,
+    client_id: &str,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<ClientStatement, String> {
+    let client_name: String = sqlx::query_scalar("SELECT name FROM clients WHERE id = ?")
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Client not found".to_string())?;
+    let from = from.filter(|d| !d.is_empty()).map(|d| statement_day(&d));
+    let to = to.filter(|d| !d.is_empty()).map(|d| statement_day(&d));
+    if let (Some(from), Some(to)) = (&from, &to) {
+        if from > to {
+            return Err("The statement start date must be before its end date".to_string());
+        }
+    }
+
+    let mut opening_balance = Money::ZERO;
+    let mut balance = Money::ZERO;
+    let mut entries = Vec::new();
+    for mut entry in client_statement_entries(pool, client_id).await? {
+        if to.as_ref().is_some_and(|to| &entry.date > to) {
+            break;
+        }
+        balance += entry.debit - entry.credit;
+        if from.as_ref().is_some_and(|from| &entry.date < from) {
+            opening_balance = balance;
+            continue;
+        }
+        entry.balance = balance;
+        entries.push(entry);
+    }
+    Ok(ClientStatement {
+        client_id: client_id.to_string(),
+        client_name,
+        from,
+        to,
+        opening_balance,
+        total_debit: entries.iter().map(|e| e.debit).sum(),
+        total_credit: entries.iter().map(|e| e.credit).sum(),
+        closing_balance: balance,
+        entries,
+    })
+}
+
+#[tauri::command]
+pub async fn get_client_statement(
+    client_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<ClientStatement, String> {
+    build_client_statement(&pool, &client_id, from, to).await
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn statement_csv(statement: &ClientStatement) -> String {
+    let mut csv = String::from("date,type,reference,description,debit,credit,balance\n");
+    let mut row = |date: &str, kind: &str, reference: &str, description: &str, debit: String, credit: String, balance: Money| {
+        let fields = [date, kind, reference, description, &debit, &credit, &balance.to_string()].map(csv_field);
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    };
+    row(statement.from.as_deref().unwrap_or(""), "opening_balance", "", "Opening balance", String::new(), String::new(), statement.opening_balance);
+    for entry in &statement.entries {
+        row(
+            &entry.date,
+            &entry.kind,
+            entry.reference.as_deref().unwrap_or(""),
+            &entry.description,
+            entry.debit.to_string(),
+            entry.credit.to_string(),
+            entry.balance,
+        );
+    }
+    row(
+        statement.to.as_deref().unwrap_or(""),
+        "closing_balance",
+        "",
+        "Closing balance",
+        statement.total_debit.to_string(),
+        statement.total_credit.to_string(),
+        statement.closing_balance,
+    );
+    csv
+}
+
+fn statement_pdf_label(entry: &StatementEntry) -> String {
+    let reference = entry.reference.as_deref().unwrap_or("");
+    match entry.kind.as_str() {
+        "invoice" => format!("Facture {}", reference),
+        "invoice_deleted" => format!("Annulation facture {}", reference),
+        "sale" => "Vente".to_string(),
+        "sale_deleted" => "Annulation vente".to_string(),
+        "payment" => "Règlement".to_string(),
+        "payment_deleted" => "Annulation règlement".to_string(),
+        "credit_payment" => "Règlement par avoir".to_string(),
+        "bulk_credit" => "Reliquat de règlement groupé".to_string(),
+        _ if entry.credit.is_positive() => "Avoir accordé".to_string(),
+        _ => "Débit sur avoir".to_string(),
+    }
+}
+
+/// Exports the statement as `pdf` or `csv` to `output_path`.
+#[tauri::command]
+pub async fn export_client_statement(
+    client_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    format: String,
+    output_path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let statement = build_client_statement(&pool, &client_id, from, to).await?;
+    let bytes = match format.as_str() {
+        "csv" => statement_csv(&statement).into_bytes(),
+        "pdf" => {
+            let client = document_client(&pool, &client_id).await?;
+            let (company, logo, currency) = document_letterhead(&pool).await?;
+            let lines = statement.entries.iter().map(|entry| pdf::StatementLine {
+                date: entry.date.clone(),
+                label: statement_pdf_label(entry),
+                reference: entry.reference.clone().unwrap_or_default(),
+                debit: entry.debit,
+                credit: entry.credit,
+                balance: entry.balance,
+            }).collect();
+            pdf::render_statement(&pdf::StatementDocument {
+                currency,
+                logo,
+                company,
+                client,
+                from: statement.from.clone(),
+                to: statement.to.clone(),
+                opening_balance: statement.opening_balance,
+                lines,
+                closing_balance: statement.closing_balance,
+            })?
+        }
+        other => return Err(format!("Unsupported statement format: {}", other)),
+    };
+    write_document(&output_path, &bytes)
+}
+
+// --- Accounts receivable aging ---
+
+/// Default bucket edges in days past due: current, 1-30, 31-60, 61-90, 90+.
+pub const DEFAULT_AGING_EDGES: [i64; 3] = [30, 60, 90];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgingBucket {
+    pub label: String,
+    /// Inclusive bounds in days past due; `max_days` is open for the last bucket
+    pub min_days: Option<i64>,
+    pub max_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AgingInvoice {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub due_date: String,
+    pub days_past_due: i64,
+    pub total_amount_ttc: Money,
+    /// TTC credited by credit notes issued by `as_of`
+    pub credited_amount: Money,
+    pub outstanding: Money,
+    /// Index into `ArAgingReport::buckets`
+    pub bucket: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientAging {
+    pub client_id: String,
+    pub client_name: String,
+    /// Outstanding amount per bucket, in the order of `ArAgingReport::buckets`
+    pub amounts: Vec<Money>,
+    pub total: Money,
+    pub invoices: Vec<AgingInvoice>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArAgingReport {
+    pub as_of: String,
+    pub buckets: Vec<AgingBucket>,
+    pub clients: Vec<ClientAging>,
+    pub bucket_totals: Vec<Money>,
+    pub total: Money,
+}
+
+/// Turns `[30, 60, 90]` into current, 1-30, 31-60, 61-90 and 90+.
+fn aging_buckets(edges: &[i64]) -> Result<Vec<AgingBucket>, String> {
+    if edges.is_empty() || edges[0] < 1 || edges.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("Aging bucket edges must be positive and increasing".to_string());
+    }
+    let mut buckets = vec![AgingBucket { label: "current".to_string(), min_days: None, max_days: Some(0) }];
+    let mut start = 1;
+    for &edge in edges {
+        buckets.push(AgingBucket { label: format!("{}-{}", start, edge), min_days: Some(start), max_days: Some(edge) });
+        start = edge + 1;
+    }
+    buckets.push(AgingBucket { label: format!("{}+", start - 1), min_days: Some(start), max_days: None });
+    Ok(buckets)
+}
+
+async fn build_ar_aging(pool: &SqlitePool, as_of: Option<String>, bucket_edges: Option<Vec<i64>>) -> Result<ArAgingReport, String> {
+    let as_of = match as_of.filter(|d| !d.is_empty()) {
+        Some(date) => chrono::NaiveDate::parse_from_str(&statement_day(&date), "%Y-%m-%d").map_err(|e| e.to_string())?,
+        None => Utc::now().date_naive(),
+    };
+    let as_of_text = as_of.format("%Y-%m-%d").to_string();
+    let edges = bucket_edges.unwrap_or_else(|| DEFAULT_AGING_EDGES.to_vec());
+    let buckets = aging_buckets(&edges)?;
+
+    // Same paid amount as invoice_payment_totals, limited to payments made
+    // and credit notes issued by as_of
+    let rows = sqlx::query(
+        r#"
+        SELECT i.id, i.invoice_number, i.client_id, c.name AS client_name, i.due_date, i.total_amount_ttc AS total,
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM invoice_sales s
+            JOIN payments p ON p.sale_id = s.sale_id AND p.is_deleted = 0
+            WHERE s.invoice_id = i.id AND substr(p.date, 1, 10) <= ?1
+          ), 0) +
+          IFNULL((
+            SELECT SUM(p.amount)
+            FROM payments p
+            WHERE p.invoice_id = i.id AND p.is_deleted = 0 AND substr(p.date, 1, 10) <= ?1
+              AND NOT EXISTS (SELECT 1 FROM invoice_sales s WHERE s.invoice_id = i.id AND s.sale_id = p.sale_id)
+          ), 0) AS paid,
+          IFNULL((
+            SELECT SUM(cn.total_amount_ttc)
+            FROM credit_notes cn
+            WHERE cn.invoice_id = i.id AND substr(cn.date, 1, 10) <= ?1
+          ), 0) AS credited
+        FROM invoices i
+        JOIN clients c ON c.id = i.client_id
+        WHERE i.is_deleted = 0 AND substr(i.date, 1, 10) <= ?1
+        ORDER BY c.name, i.client_id, i.due_date, i.invoice_number
+        "#
+    )
+    .bind(&as_of_text)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut clients: Vec<ClientAging> = Vec::new();
+    for row in &rows {
+        let total: Money = row.get("total");
+        let paid: Money = row.get("paid");
+        let credited: Money = row.get("credited");
+        let outstanding = total - credited - paid;
+        if !outstanding.is_positive() {
+            continue;
+        }
+        let due_date: String = row.get("due_date");
+        let due = chrono::NaiveDate::parse_from_str(&statement_day(&due_date), "%Y-%m-%d").map_err(|e| e.to_string())?;
+        let days_past_due = (as_of - due).num_days();
+        let bucket = buckets
+            .iter()
+            .position(|b| b.max_days.is_none_or(|max| days_past_due <= max))
+            .unwrap_or(buckets.len() - 1);
+
+        let client_id: String = row.get("client_id");
+        if clients.last().is_none_or(|c| c.client_id != client_id) {
+            clients.push(ClientAging {
+                client_id: client_id.clone(),
+                client_name: row.get("client_name"),
+                amounts: vec![Money::ZERO; buckets.len()],
+                total: Money::ZERO,
+                invoices: Vec::new(),
+            });
+        }
+        let client = clients.last_mut().expect("pushed above");
+        client.amounts[bucket] += outstanding;
+        client.total += outstanding;
+        client.invoices.push(AgingInvoice {
+            invoice_id: row.get("id"),
+            invoice_number: row.get("invoice_number"),
+            due_date,
+            days_past_due,
+            total_amount_ttc: total,
+            credited_amount: credited,
+            outstanding,
+            bucket,
+        });
+    }
+
+    let bucket_totals: Vec<Money> = (0..buckets.len()).map(|i| clients.iter().map(|c| c.amounts[i]).sum()).collect();
+    Ok(ArAgingReport {
+        as_of: as_of_text,
+        total: bucket_totals.iter().copied().sum(),
+        buckets,
+        clients,
+        bucket_totals,
+    })
+}
+
+#[tauri::command]
+pub async fn get_ar_aging(
+    as_of: Option<String>,
+    bucket_edges: Option<Vec<i64>>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<ArAgingReport, String> {
+    build_ar_aging(&pool, as_of, bucket_edges).await
+}
+
+/// Exports the aging report as CSV: one row per client plus a totals row.
+#[tauri::command]
+pub async fn export_ar_aging(
+    as_of: Option<String>,
+    bucket_edges: Option<Vec<i64>>,
+    output_path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let report = build_ar_aging(&pool, as_of, bucket_edges).await?;
+    let mut header = vec!["client".to_string(), "invoices".to_string()];
+    header.extend(report.buckets.iter().map(|b| b.label.clone()));
+    header.push("total".to_string());
+    let mut csv = header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(",");
+    csv.push('\n');
+    let mut row = |name: &str, invoices: usize, amounts: &[Money], total: Money| {
+        let mut fields = vec![csv_field(name), invoices.to_string()];
+        fields.extend(amounts.iter().map(|a| a.to_string()));
+        fields.push(total.to_string());
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    };
+    for client in &report.clients {
+        row(&client.client_name, client.invoices.len(), &client.amounts, client.total);
+    }
+    let invoice_count = report.clients.iter().map(|c| c.invoices.len()).sum();
+    row("Total", invoice_count, &report.bucket_totals, report.total);
+    write_document(&output_path, csv.as_bytes())
+}
+
+// --- VAT report ---
+
+pub const VAT_REPORT_BASES: [&str; 2] = ["invoiced", "cash"];
+
+/// One document of the VAT report: an invoice or an uninvoiced sale on the
+/// invoiced basis, a payment on the cash basis, and credit notes on both
+/// with negative amounts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VatReportRow {
+    /// invoice, sale, payment or credit_note
+    pub kind: String,
+    pub id: String,
+    /// Invoice number, when the document has one
+    pub reference: Option<String>,
+    pub date: String,
+    pub client_id: String,
+    pub client_name: String,
+    pub client_nif: Option<String>,
+    /// HT taxed at a non-zero rate
+    pub taxable_amount: Money,
+    pub tax_amount: Money,
+    /// HT of the exempt and zero-rated lines
+    pub exempt_amount: Money,
+    pub stamp_duty: Money,
+    /// Non-zero rates only
+    pub vat_by_rate: Vec<VatLine>,
+}
+
+/// Data of the monthly G50 return for `period`, with the company's tax ids
+/// for the header.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VatReport {
+    /// YYYY-MM or YYYY-Qn
+    pub period: String,
+    /// invoiced or cash
+    pub basis: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub company_name: String,
+    pub nif: Option<String>,
+    pub nis: Option<String>,
+    pub ai: Option<String>,
+    pub rc: Option<String>,
+    pub vat_by_rate: Vec<VatLine>,
+    pub taxable_amount: Money,
+    pub tax_amount: Money,
+    pub exempt_amount: Money,
+    pub stamp_duty: Money,
+    pub rows: Vec<VatReportRow>,
+}
+
+/// First and last day of a `YYYY-MM` month or `YYYY-Qn` quarter.
+fn vat_period_range(period: &str) -> Result<(chrono::NaiveDate, chrono::NaiveDate), String> {
+    let invalid = || format!("Invalid period '{}'; expected YYYY-MM or YYYY-Qn", period);
+    let (year, rest) = period.trim().split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let (first_month, months) = match rest.strip_prefix('Q') {
+        Some(quarter) => match quarter.parse::<u32>() {
+            Ok(q @ 1..=4) => ((q - 1) * 3 + 1, 3),
+            _ => return Err(invalid()),
+        },
+        None => match rest.parse::<u32>() {
+            Ok(m @ 1..=12) => (m, 1),
+            _ => return Err(invalid()),
+        },
+    };
+    let start = chrono::NaiveDate::from_ymd_opt(year, first_month, 1).ok_or_else(invalid)?;
+    let end = start.checked_add_months(chrono::Months::new(months)).ok_or_else(invalid)? - chrono::Duration::days(1);
+    Ok((start, end))
+}
+
+/// Adds `lines` into `into`, rate by rate, keeping the rates in ascending order.
+fn merge_vat_lines(into: &mut Vec<VatLine>, lines: &[VatLine]) {
+    for line in lines {
+        match into.iter_mut().find(|g| g.tax_rate == line.tax_rate) {
+            Some(group) => {
+                group.taxable_amount += line.taxable_amount;
+                group.tax_amount += line.tax_amount;
+            }
+            None => into.push(line.clone()),
+        }
+    }
+    into.sort_by(|a, b| a.tax_rate.total_cmp(&b.tax_rate));
+}
+
+struct SaleVat {
+    lines: Vec<VatLine>,
+    stamp_duty: Money,
+    total_amount_ttc: Money,
+}
+
+/// VAT by rate of each sale in `sale_ids`, rounded once per rate like the
+/// sale's own tax_amount.
+async fn sales_vat(pool: &SqlitePool, sale_ids: &[String]) -> Result<std::collections::HashMap<String, SaleVat>, String> {
+    let mut vat = std::collections::HashMap::new();
+    if sale_ids.is_empty() {
+        return Ok(vat);
+    }
+    let default_tax_rate: Option<f64> = sqlx::query_scalar("SELECT tax_rate FROM settings LIMIT 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
+    let default_tax_rate = default_tax_rate.unwrap_or(0.0);
+    let placeholders = vec!["?"; sale_ids.len()].join(", ");
+    let query = format!(
+        r#"
+        SELECT si.sale_id,
+               CASE WHEN s.tax_exemption IS NOT NULL THEN 0.0 ELSE COALESCE(si.tax_rate, s.tax_rate, ?) END AS tax_rate,
+               si.total_amount AS amount
+        FROM sale_items si
+        JOIN sales s ON s.id = si.sale_id
+        WHERE si.sale_id IN ({ids})
+        UNION ALL
+        SELECT s.id,
+               CASE WHEN s.tax_exemption IS NOT NULL THEN 0.0 ELSE COALESCE(s.tax_rate, ?) END,
+               s.transportation_fee
+        FROM sales s
+        WHERE s.id IN ({ids}) AND IFNULL(s.transportation_fee, 0) <> 0
+        "#,
+        ids = placeholders,
+    );
+    let mut q = sqlx::query(&query).bind(default_tax_rate);
+    for id in sale_ids {
+        q = q.bind(id);
+    }
+    q = q.bind(default_tax_rate);
+    for id in sale_ids {
+        q = q.bind(id);
+    }
+    let mut lines: std::collections::HashMap<String, Vec<(f64, Money)>> = std::collections::HashMap::new();
+    for row in q.fetch_all(pool).await.map_err(|e| e.to_string())? {
+        lines.entry(row.get("sale_id")).or_default().push((row.get("tax_rate"), row.get("amount")));
+    }
+    let query = format!("SELECT id, stamp_duty, total_amount_ttc FROM sales WHERE id IN ({})", placeholders);
+    let mut q = sqlx::query(&query);
+    for id in sale_ids {
+        q = q.bind(id);
+    }
+    for row in q.fetch_all(pool).await.map_err(|e| e.to_string())? {
+        let id: String = row.get("id");
+        vat.insert(id.clone(), SaleVat {
+            lines: vat_by_rate(lines.remove(&id).unwrap_or_default()),
+            stamp_duty: row.get("stamp_duty"),
+            total_amount_ttc: row.get("total_amount_ttc"),
+        });
+    }
+    Ok(vat)
+}
+
+/// Builds a report row from the VAT of `sales`, scaled by `share` for
+/// payments that settle part of them.
+fn vat_report_row(kind: &str, row: &sqlx::sqlite::SqliteRow, sales: &[&SaleVat], share: f64) -> VatReportRow {
+    let mut lines = Vec::new();
+    let mut stamp_duty = Money::ZERO;
+    for sale in sales {
+        merge_vat_lines(&mut lines, &sale.lines);
+        stamp_duty += sale.stamp_duty;
+    }
+    if share != 1.0 {
+        for line in &mut lines {
+            line.taxable_amount = line.taxable_amount.mul_f64(share);
+            line.tax_amount = line.tax_amount.mul_f64(share);
+        }
+        stamp_duty = stamp_duty.mul_f64(share);
+    }
+    let (exempt, taxed): (Vec<VatLine>, Vec<VatLine>) = lines.into_iter().partition(|line| line.tax_rate == 0.0);
+    let date: String = row.get("date");
+    let client_name: Option<String> = row.get("client_name");
+    VatReportRow {
+        kind: kind.to_string(),
+        id: row.get("id"),
+        reference: row.get("reference"),
+        date: statement_day(&date),
+        client_id: row.get("client_id"),
+        client_name: client_name.unwrap_or_default(),
+        client_nif: row.get("client_nif"),
+        taxable_amount: taxed.iter().map(|l| l.taxable_amount).sum(),
+        tax_amount: taxed.iter().map(|l| l.tax_amount).sum(),
+        exempt_amount: exempt.iter().map(|l| l.taxable_amount).sum(),
+        stamp_duty,
+        vat_by_rate: taxed,
+    }
+}
+
+/// Credit notes dated from `from` to `to`, as negative rows. On the cash
+/// basis only the share given back as client credit counts: VAT on what was
+/// never collected was never declared.
+async fn credit_notes_vat_rows(pool: &SqlitePool, from: &str, to: &str, cash: bool) -> Result<Vec<VatReportRow>, String> {
+    let notes = sqlx::query(
+        r#"
+        SELECT cn.id, cn.credit_note_number AS reference, cn.date, cn.client_id, c.name AS client_name, c.nif AS client_nif,
+               cn.stamp_duty, cn.total_amount_ttc,
+               IFNULL((SELECT SUM(ct.amount) FROM credit_transactions ct
+                       WHERE ct.source_type = 'credit_note' AND ct.source_id = cn.id), 0) AS refunded
+        FROM credit_notes cn
+        LEFT JOIN clients c ON c.id = cn.client_id
+        WHERE substr(cn.date, 1, 10) BETWEEN ? AND ?
+        ORDER BY cn.date, cn.credit_note_number
+        "#
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut rows = Vec::new();
+    for note in &notes {
+        let lines = sqlx::query(
+            "SELECT tax_rate, SUM(total_amount) AS taxable_amount, SUM(tax_amount) AS tax_amount FROM credit_note_items WHERE credit_note_id = ? GROUP BY tax_rate ORDER BY tax_rate"
+        )
+        .bind(note.get::<String, _>("id"))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let total: Money = note.get("total_amount_ttc");
+        let share = if cash {
+            let refunded: Money = note.get("refunded");
+            if !refunded.is_positive() || !total.is_positive() {
+                continue;
+            }
+            refunded.centimes() as f64 / total.centimes() as f64
+        } else {
+            1.0
+        };
+        let vat = SaleVat {
+            lines: lines.iter()
+                .map(|line| VatLine {
+                    tax_rate: line.get("tax_rate"),
+                    taxable_amount: -line.get::<Money, _>("taxable_amount"),
+                    tax_amount: -line.get::<Money, _>("tax_amount"),
+                })
+                .collect(),
+            stamp_duty: -note.get::<Money, _>("stamp_duty"),
+            total_amount_ttc: -total,
+        };
+        rows.push(vat_report_row("credit_note", note, &[&vat], share));
+    }
+    Ok(rows)
+}
+
+async fn build_vat_report(pool: &SqlitePool, period: String, basis: String) -> Result<VatReport, String> {
+    if !VAT_REPORT_BASES.contains(&basis.as_str()) {
+        return Err(format!("Invalid basis '{}'; expected one of {}", basis, VAT_REPORT_BASES.join(", ")));
+    }
+    let (start, end) = vat_period_range(&period)?;
+    let from = start.format("%Y-%m-%d").to_string();
+    let to = end.format("%Y-%m-%d").to_string();
+
+    let mut rows = Vec::new();
+    if basis == "invoiced" {
+        // VAT is due when invoiced: the period's invoices, and the sales
+        // dated in the period that no live invoice covers
+        let invoices = sqlx::query(
+            r#"
+            SELECT i.id, i.invoice_number AS reference, i.date, i.client_id, c.name AS client_name, c.nif AS client_nif,
+                   (SELECT group_concat(l.sale_id) FROM invoice_sales l JOIN sales s ON s.id = l.sale_id
+                    WHERE l.invoice_id = i.id AND (s.is_deleted = 0 OR s.is_deleted IS NULL)) AS sale_ids
+            FROM invoices i
+            LEFT JOIN clients c ON c.id = i.client_id
+            WHERE (i.is_deleted = 0 OR i.is_deleted IS NULL) AND substr(i.date, 1, 10) BETWEEN ? AND ?
+            ORDER BY i.date, i.invoice_number
+            "#
+        )
+        .bind(&from)
+        .bind(&to)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let sales = sqlx::query(
+            r#"
+            SELECT s.id, NULL AS reference, s.date, s.client_id, c.name AS client_name, c.nif AS client_nif
+            FROM sales s
+            LEFT JOIN clients c ON c.id = s.client_id
+            WHERE (s.is_deleted = 0 OR s.is_deleted IS NULL) AND substr(s.date, 1, 10) BETWEEN ? AND ?
+              AND NOT EXISTS (
+                SELECT 1 FROM invoice_sales l JOIN invoices i ON i.id = l.invoice_id
+                WHERE l.sale_id = s.id AND (i.is_deleted = 0 OR i.is_deleted IS NULL)
+              )
+            ORDER BY s.date, s.id
+            "#
+        )
+        .bind(&from)
+        .bind(&to)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let invoice_sales: Vec<Vec<String>> = invoices.iter()
+            .map(|row| row.get::<Option<String>, _>("sale_ids").map(|ids| ids.split(',').map(str::to_string).collect()).unwrap_or_default())
+            .collect();
+        let mut sale_ids: Vec<String> = invoice_sales.iter().flatten().cloned().collect();
+        sale_ids.extend(sales.iter().map(|row| row.get::<String, _>("id")));
+        let vat = sales_vat(pool, &sale_ids).await?;
+        for (row, ids) in invoices.iter().zip(&invoice_sales) {
+            let documents: Vec<&SaleVat> = ids.iter().filter_map(|id| vat.get(id)).collect();
+            rows.push(vat_report_row("invoice", row, &documents, 1.0));
+        }
+        for row in &sales {
+            let documents: Vec<&SaleVat> = vat.get(&row.get::<String, _>("id")).into_iter().collect();
+            rows.push(vat_report_row("sale", row, &documents, 1.0));
+        }
+    } else {
+        // VAT is due when collected: each payment carries its share of the
+        // VAT of the sale, or of the invoice's sales, it settles
+        let payments = sqlx::query(
+            r#"
+            SELECT p.id, i.invoice_number AS reference, p.date, p.client_id, c.name AS client_name, c.nif AS client_nif, p.amount,
+                   CASE
+                     WHEN p.sale_id IS NOT NULL AND p.sale_id <> '' THEN p.sale_id
+                     ELSE (SELECT group_concat(l.sale_id) FROM invoice_sales l JOIN sales s ON s.id = l.sale_id
+                           WHERE l.invoice_id = p.invoice_id AND (s.is_deleted = 0 OR s.is_deleted IS NULL))
+                   END AS sale_ids
+            FROM payments p
+            LEFT JOIN clients c ON c.id = p.client_id
+            LEFT JOIN sales ps ON ps.id = p.sale_id
+            LEFT JOIN invoices i ON i.id = COALESCE(p.invoice_id, ps.invoice_id)
+            WHERE (p.is_deleted = 0 OR p.is_deleted IS NULL) AND substr(p.date, 1, 10) BETWEEN ? AND ?
+            ORDER BY p.date, p.id
+            "#
+        )
+        .bind(&from)
+        .bind(&to)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let payment_sales: Vec<Vec<String>> = payments.iter()
+            .map(|row| row.get::<Option<String>, _>("sale_ids").map(|ids| ids.split(',').map(str::to_string).collect()).unwrap_or_default())
+            .collect();
+        let sale_ids: Vec<String> = payment_sales.iter().flatten().cloned().collect();
+        let vat = sales_vat(pool, &sale_ids).await?;
+        for (row, ids) in payments.iter().zip(&payment_sales) {
+            let documents: Vec<&SaleVat> = ids.iter().filter_map(|id| vat.get(id)).collect();
+            let due: Money = documents.iter().map(|sale| sale.total_amount_ttc).sum();
+            // Payments on nothing with VAT (advances, credit) have nothing to declare
+            if !due.is_positive() {
+                continue;
+            }
+            let amount: Money = row.get("amount");
+            let share = (amount.centimes() as f64 / due.centimes() as f64).min(1.0);
+            rows.push(vat_report_row("payment", row, &documents, share));
+        }
+    }
+    rows.extend(credit_notes_vat_rows(pool, &from, &to, basis == "cash").await?);
+
+    let mut vat_by_rate = Vec::new();
+    for row in &rows {
+        merge_vat_lines(&mut vat_by_rate, &row.vat_by_rate);
+    }
+    let settings = sqlx::query("SELECT company_name, nif, nis, ai, rc FROM settings LIMIT 1")
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let setting = |column: &str| -> Option<String> { settings.as_ref().and_then(|row| row.get(column)) };
+    Ok(VatReport {
+        period,
+        basis,
+        start_date: from,
+        end_date: to,
+        company_name: setting("company_name").unwrap_or_default(),
+        nif: setting("nif"),
+        nis: setting("nis"),
+        ai: setting("ai"),
+        rc: setting("rc"),
+        taxable_amount: rows.iter().map(|r| r.taxable_amount).sum(),
+        tax_amount: rows.iter().map(|r| r.tax_amount).sum(),
+        exempt_amount: rows.iter().map(|r| r.exempt_amount).sum(),
+        stamp_duty: rows.iter().map(|r| r.stamp_duty).sum(),
+        vat_by_rate,
+        rows,
+    })
+}
+
+/// VAT report for the G50 return of `period` (YYYY-MM or YYYY-Qn). On the
+/// `invoiced` basis VAT is declared on the invoices and uninvoiced sales of
+/// the period; on the `cash` basis on the payments received, pro rata of
+/// what they settle. Deleted documents are left out.
+#[tauri::command]
+pub async fn get_vat_report(
+    period: String,
+    basis: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<VatReport, String> {
+    build_vat_report(&pool, period, basis).await
+}
+
+fn vat_rate_label(tax_rate: f64) -> String {
+    format!("{}%", (tax_rate * 10000.0).round() / 100.0)
+}
+
+/// Exports the VAT report as CSV: the company header, then one row per
+/// document with a HT and VAT column per rate, then a totals row.
+#[tauri::command]
+pub async fn export_vat_report(
+    period: String,
+    basis: String,
+    output_path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<String, String> {
+    let report = build_vat_report(&pool, period, basis).await?;
+    let mut csv = String::new();
+    for (label, value) in [
+        ("company", Some(report.company_name.clone())),
+        ("nif", report.nif.clone()),
+        ("nis", report.nis.clone()),
+        ("ai", report.ai.clone()),
+        ("rc", report.rc.clone()),
+        ("period", Some(report.period.clone())),
+        ("basis", Some(report.basis.clone())),
+        ("from", Some(report.start_date.clone())),
+        ("to", Some(report.end_date.clone())),
+    ] {
+        csv.push_str(&format!("{},{}\n", label, csv_field(&value.unwrap_or_default())));
+    }
+    csv.push('\n');
+
+    let rates: Vec<f64> = report.vat_by_rate.iter().map(|line| line.tax_rate).collect();
+    let mut header: Vec<String> = ["type", "reference", "date", "client", "client_nif", "taxable_amount", "tax_amount", "exempt_amount", "stamp_duty"]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+    for rate in &rates {
+        header.push(format!("ht {}", vat_rate_label(*rate)));
+        header.push(format!("tva {}", vat_rate_label(*rate)));
+    }
+    csv.push_str(&header.iter().map(|h| csv_field(h)).collect::<Vec<_>>().join(","));
+    csv.push('\n');
+    let mut line = |fields: Vec<String>, amounts: [Money; 4], vat: &[VatLine]| {
+        let mut fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        fields.extend(amounts.iter().map(|a| a.to_string()));
+        for rate in &rates {
+            let group = vat.iter().find(|g| g.tax_rate == *rate);
+            fields.push(group.map(|g| g.taxable_amount).unwrap_or_default().to_string());
+            fields.push(group.map(|g| g.tax_amount).unwrap_or_default().to_string());
+        }
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    };
+    for row in &report.rows {
+        line(
+            vec![row.kind.clone(), row.reference.clone().unwrap_or_default(), row.date.clone(), row.client_name.clone(), row.client_nif.clone().unwrap_or_default()],
+            [row.taxable_amount, row.tax_amount, row.exempt_amount, row.stamp_duty],
+            &row.vat_by_rate,
+        );
+    }
+    line(
+        vec!["Total".to_string(), String::new(), String::new(), String::new(), String::new()],
+        [report.taxable_amount, report.tax_amount, report.exempt_amount, report.stamp_duty],
+        &report.vat_by_rate,
+    );
+    write_document(&output_path, csv.as_bytes())
+}
+
+// --- Global search ---
+
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// A `global_search` hit. `kind` is client, sale_item, invoice or payment and
+/// `id` is the id of the matching row; sale items and payments also carry the
+/// sale they belong to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub sale_id: Option<String>,
+    pub client_id: Option<String>,
+    pub title: String,
+    /// Best matching text, with the matched terms in [brackets]
+    pub snippet: String,
+    pub date: Option<String>,
+    /// Higher is more relevant
+    pub score: f64,
+}
+
+/// Turns free text into an FTS5 query where every word must match as a
+/// prefix. Quoting the words keeps text such as "0.5" or "FAC-24/" literal.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|t| t.replace('"', ""))
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{}\"*", t))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Searches clients, sale items, invoices and payments, best matches first.
+/// Soft-deleted rows, and items of deleted sales, are left out.
+#[tauri::command]
+pub async fn global_search(
+    query: String,
+    limit: Option<u32>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<SearchHit>, String> {
+    let Some(fts) = fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let rows = sqlx::query(
+        r#"
+        SELECT 'client' AS kind, c.id, NULL AS sale_id, c.id AS client_id,
+               c.name AS title,
+               snippet(clients_fts, -1, '[', ']', '…', 10) AS snippet,
+               c.created_at AS date,
+               -bm25(clients_fts) AS score
+        FROM clients_fts
+        JOIN clients c ON c.id = clients_fts.id
+        WHERE clients_fts MATCH ?1 AND (c.is_deleted = 0 OR c.is_deleted IS NULL)
+        UNION ALL
+        SELECT 'sale_item', si.id, s.id, s.client_id,
+               si.description || COALESCE(' - ' || c.name, ''),
+               snippet(sale_items_fts, -1, '[', ']', '…', 10),
+               s.date,
+               -bm25(sale_items_fts)
+        FROM sale_items_fts
+        JOIN sale_items si ON si.id = sale_items_fts.id
+        JOIN sales s ON s.id = si.sale_id
+        LEFT JOIN clients c ON c.id = s.client_id
+        WHERE sale_items_fts MATCH ?1 AND (s.is_deleted = 0 OR s.is_deleted IS NULL)
+        UNION ALL
+        SELECT 'invoice', i.id, NULL, i.client_id,
+               i.invoice_number || COALESCE(' - ' || c.name, ''),
+               snippet(invoices_fts, -1, '[', ']', '…', 10),
+               i.date,
+               -bm25(invoices_fts)
+        FROM invoices_fts
+        JOIN invoices i ON i.id = invoices_fts.id
+        LEFT JOIN clients c ON c.id = i.client_id
+        WHERE invoices_fts MATCH ?1 AND (i.is_deleted = 0 OR i.is_deleted IS NULL)
+        UNION ALL
+        SELECT 'payment', p.id, p.sale_id, p.client_id,
+               p.method || COALESCE(' ' || p.check_number, '') || COALESCE(' - ' || c.name, ''),
+               snippet(payments_fts, -1, '[', ']', '…', 10),
+               p.date,
+               -bm25(payments_fts)
+        FROM payments_fts
+        JOIN payments p ON p.id = payments_fts.id
+        LEFT JOIN clients c ON c.id = p.client_id
+        WHERE payments_fts MATCH ?1 AND (p.is_deleted = 0 OR p.is_deleted IS NULL)
+        ORDER BY score DESC
+        LIMIT ?2
+        "#
+    )
+    .bind(&fts)
+    .bind(limit as i64)
+    .fetch_all(&*pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(|row| SearchHit {
+        kind: row.get("kind"),
         id: row.get("id"),
         sale_id: row.get("sale_id"),
-        invoice_id: row.get("invoice_id"),
         client_id: row.get("client_id"),
-        amount: row.get("amount"),
+        title: row.get("title"),
+        snippet: row.get("snippet"),
         date: row.get("date"),
-        method: row.get("method"),
+        score: row.get("score"),
+    }).collect())
+}
+
+// --- Coil stock ---
+
+/// Weights (in tons) closer than this are treated as equal.
+const COIL_WEIGHT_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Coil {
+    pub id: String,
+    pub coil_ref: String,
+    pub received_at: String,
+    pub supplier: Option<String>,
+    pub supplier_id: Option<String>,
+    /// The purchase order line the coil was received against
+    pub purchase_order_item_id: Option<String>,
+    pub thickness: Option<f64>,
+    pub width: Option<f64>,
+    pub top_coat_ral: Option<String>,
+    pub back_coat_ral: Option<String>,
+    /// Tons, packaging included
+    pub gross_weight: f64,
+    /// Tons of steel received
+    pub net_weight: f64,
+    /// Tons still in stock; partly cut coils keep what is left
+    pub remaining_weight: f64,
+    pub cost_per_ton: Money,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveCoilRequest {
+    pub coil_ref: String,
+    pub received_at: String,
+    pub supplier: Option<String>,
+    pub thickness: Option<f64>,
+    pub width: Option<f64>,
+    pub top_coat_ral: Option<String>,
+    pub back_coat_ral: Option<String>,
+    pub gross_weight: f64,
+    pub net_weight: f64,
+    pub cost_per_ton: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoilAdjustmentRequest {
+    pub coil_id: String,
+    /// Tons; positive adds stock, negative removes it
+    pub weight: f64,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoilMovement {
+    pub id: String,
+    pub coil_id: String,
+    /// receipt, sale, sale_release or adjustment
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Tons, negative when the movement takes weight out of stock
+    pub weight: f64,
+    pub sale_id: Option<String>,
+    pub sale_item_id: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedCoilsResult {
+    pub rows: Vec<Coil>,
+    pub total: i64,
+}
+
+const COIL_LIST: ListColumns = ListColumns {
+    date: "coil.received_at",
+    client_id: None,
+    supplier_id: Some("coil.supplier_id"),
+    amount: Some("coil.cost_per_ton"),
+    search: &[
+        "coil.coil_ref LIKE ?",
+        "coil.supplier LIKE ?",
+        "coil.top_coat_ral LIKE ?",
+        "coil.back_coat_ral LIKE ?",
+    ],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("received_at", "coil.received_at"),
+        ("coil_ref", "coil.coil_ref"),
+        ("thickness", "coil.thickness"),
+        ("width", "coil.width"),
+        ("remaining_weight", "coil.remaining_weight"),
+        ("cost_per_ton", "coil.cost_per_ton"),
+    ],
+    default_sort: ("received_at", "DESC"),
+    tie_breaker: "coil.id",
+};
+
+fn coil_from_row(row: &sqlx::sqlite::SqliteRow) -> Coil {
+    Coil {
+        id: row.get("id"),
+        coil_ref: row.get("coil_ref"),
+        received_at: row.get("received_at"),
+        supplier: row.get("supplier"),
+        supplier_id: row.get("supplier_id"),
+        purchase_order_item_id: row.get("purchase_order_item_id"),
+        thickness: row.get("thickness"),
+        width: row.get("width"),
+        top_coat_ral: row.get("top_coat_ral"),
+        back_coat_ral: row.get("back_coat_ral"),
+        gross_weight: row.get("gross_weight"),
+        net_weight: row.get("net_weight"),
+        remaining_weight: row.get("remaining_weight"),
+        cost_per_ton: row.get("cost_per_ton"),
         notes: row.get("notes"),
-        check_number: row.get("check_number"),
         created_at: row.get("created_at"),
         updated_at: row.get("updated_at"),
-        is_deleted: row.get("is_deleted"),
-        deleted_at: row.get("deleted_at"),
-    }).collect();
-    Ok(payments)
+    }
+}
+
+async fn fetch_coil(conn: &mut sqlx::SqliteConnection, coil_id: &str) -> Result<Coil, String> {
+    sqlx::query("SELECT * FROM coils WHERE id = ?")
+        .bind(coil_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|row| coil_from_row(&row))
+        .ok_or_else(|| "Coil not found".to_string())
+}
+
+/// The coil_movements triggers keep coils.remaining_weight in sync.
+async fn insert_coil_movement(
+    conn: &mut sqlx::SqliteConnection,
+    coil_id: &str,
+    kind: &str,
+    weight: f64,
+    sale_id: Option<&str>,
+    sale_item_id: Option<&str>,
+    notes: Option<&str>,
+) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO coil_movements (id, coil_id, movement_type, weight, sale_id, sale_item_id, notes, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(coil_id)
+    .bind(kind)
+    .bind(weight)
+    .bind(sale_id)
+    .bind(sale_item_id)
+    .bind(notes)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// Takes the weight of the sale's items out of the coils they are cut from.
+/// Items whose coil_ref is not in stock are not tracked and left alone.
+async fn consume_sale_stock(conn: &mut sqlx::SqliteConnection, sale_id: &str, allow_oversell: bool) -> Result<(), String> {
+    let items = sqlx::query(
+        r#"
+        SELECT si.id, si.coil_weight, coil.id AS coil_id, coil.coil_ref
+        FROM sale_items si
+        JOIN coils coil ON coil.coil_ref = TRIM(si.coil_ref)
+        WHERE si.sale_id = ? AND si.coil_weight > 0
+        ORDER BY si.rowid
+        "#
+    )
+    .bind(sale_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    for item in items {
+        let item_id: String = item.get("id");
+        let weight: f64 = item.get("coil_weight");
+        let coil_id: String = item.get("coil_id");
+        let coil_ref: String = item.get("coil_ref");
+        let remaining: f64 = sqlx::query_scalar("SELECT remaining_weight FROM coils WHERE id = ?")
+            .bind(&coil_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        let oversold = weight > remaining + COIL_WEIGHT_EPSILON;
+        if oversold && !allow_oversell {
+            return Err(format!(
+                "Coil {} has {:.3} t left, {:.3} t requested",
+                coil_ref, remaining.max(0.0), weight
+            ));
+        }
+        let notes = oversold.then_some("Oversold");
+        insert_coil_movement(conn, &coil_id, "sale", -weight, Some(sale_id), Some(&item_id), notes).await?;
+    }
+    Ok(())
+}
+
+/// Puts back whatever weight the sale currently holds from each coil.
+async fn release_sale_stock(conn: &mut sqlx::SqliteConnection, sale_id: &str) -> Result<(), String> {
+    let held = sqlx::query(
+        "SELECT coil_id, SUM(weight) AS weight FROM coil_movements WHERE sale_id = ? GROUP BY coil_id HAVING SUM(weight) < ?"
+    )
+    .bind(sale_id)
+    .bind(-COIL_WEIGHT_EPSILON)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    for row in held {
+        let coil_id: String = row.get("coil_id");
+        let weight: f64 = row.get("weight");
+        insert_coil_movement(conn, &coil_id, "sale_release", -weight, Some(sale_id), None, None).await?;
+    }
+    Ok(())
+}
+
+/// Validates and stores a received coil with its receipt movement. Shared by
+/// receive_coil and receive_purchase_order_item.
+async fn insert_coil(
+    conn: &mut sqlx::SqliteConnection,
+    coil: &ReceiveCoilRequest,
+    supplier_id: Option<&str>,
+    purchase_order_item_id: Option<&str>,
+) -> Result<Coil, String> {
+    let coil_ref = coil.coil_ref.trim();
+    if coil_ref.is_empty() {
+        return Err("Coil reference is required".to_string());
+    }
+    document_period(&coil.received_at)?;
+    if coil.net_weight <= 0.0 {
+        return Err("Net weight must be positive".to_string());
+    }
+    if coil.gross_weight + COIL_WEIGHT_EPSILON < coil.net_weight {
+        return Err("Gross weight cannot be less than net weight".to_string());
+    }
+    if coil.cost_per_ton.is_negative() {
+        return Err("Cost per ton cannot be negative".to_string());
+    }
+    let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM coils WHERE coil_ref = ?")
+        .bind(coil_ref)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if existing > 0 {
+        return Err(format!("Coil {} is already in stock", coil_ref));
+    }
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        r#"
+        INSERT INTO coils (id, coil_ref, received_at, supplier, supplier_id, purchase_order_item_id, thickness, width, top_coat_ral, back_coat_ral, gross_weight, net_weight, remaining_weight, cost_per_ton, notes, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&id)
+    .bind(coil_ref)
+    .bind(&coil.received_at)
+    .bind(&coil.supplier)
+    .bind(supplier_id)
+    .bind(purchase_order_item_id)
+    .bind(coil.thickness)
+    .bind(coil.width)
+    .bind(&coil.top_coat_ral)
+    .bind(&coil.back_coat_ral)
+    .bind(coil.gross_weight)
+    .bind(coil.net_weight)
+    .bind(coil.cost_per_ton)
+    .bind(&coil.notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_coil_movement(conn, &id, "receipt", coil.net_weight, None, None, None).await?;
+    let details = format!("Coil {} received, {:.3} t", coil_ref, coil.net_weight);
+    insert_audit_log(&mut *conn, "receive", "coil", &id, None, Some(&details)).await?;
+    fetch_coil(conn, &id).await
 }
 
 #[tauri::command]
-pub async fn delete_payment(
-    id: String,
+pub async fn receive_coil(
+    coil: ReceiveCoilRequest,
     pool: tauri::State<'_, SqlitePool>
-) -> Result<(), String> {
-    let now = chrono::Utc::now().to_rfc3339();
-    sqlx::query("UPDATE payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
-        .bind(&now)
-        .bind(&id)
-        .execute(&*pool)
+) -> Result<Coil, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let received = insert_coil(&mut tx, &coil, None, None).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(received)
+}
+
+/// Corrects a coil's remaining weight, e.g. after weighing it or writing off scrap.
+#[tauri::command]
+pub async fn adjust_coil_stock(
+    adjustment: CoilAdjustmentRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Coil, String> {
+    if adjustment.weight.abs() < COIL_WEIGHT_EPSILON {
+        return Err("Adjustment weight cannot be zero".to_string());
+    }
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    fetch_coil(&mut tx, &adjustment.coil_id).await?;
+    insert_coil_movement(&mut tx, &adjustment.coil_id, "adjustment", adjustment.weight, None, None, adjustment.notes.as_deref()).await?;
+    let coil = fetch_coil(&mut tx, &adjustment.coil_id).await?;
+    if coil.remaining_weight < -COIL_WEIGHT_EPSILON {
+        return Err("Adjustment would make the remaining weight negative".to_string());
+    }
+    let details = format!("{:+.3} t (remaining {:.3} t)", adjustment.weight, coil.remaining_weight);
+    insert_audit_log(&mut *tx, "stock_adjustment", "coil", &coil.id, None, Some(&details)).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(coil)
+}
+
+#[tauri::command]
+pub async fn get_coils(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    in_stock_only: Option<bool>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedCoilsResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE 1=1");
+    if in_stock_only.unwrap_or(false) {
+        where_clause.push_str(&format!(" AND coil.remaining_weight > {}", COIL_WEIGHT_EPSILON));
+    }
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &COIL_LIST, &mut where_clause, &mut params)?;
+    // Total count
+    let count_query = format!("SELECT COUNT(*) FROM coils coil {}", where_clause);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT coil.* FROM coils coil {}{} LIMIT ? OFFSET ?", where_clause, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
         .await
         .map_err(|e| e.to_string())?;
-    // Insert audit log entry for payment soft delete
-    insert_audit_log(&*pool, "soft_delete", "payment", &id, None, Some("Payment soft-deleted")).await?;
-    Ok(())
+    Ok(PaginatedCoilsResult { rows: rows.iter().map(coil_from_row).collect(), total })
 }
 
 #[tauri::command]
-pub async fn restore_payment(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
-    sqlx::query("UPDATE payments SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
-        .bind(&id)
-        .execute(&*pool)
+pub async fn get_coil_movements(
+    coil_id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<CoilMovement>, String> {
+    let rows = sqlx::query("SELECT * FROM coil_movements WHERE coil_id = ? ORDER BY created_at ASC, rowid ASC")
+        .bind(&coil_id)
+        .fetch_all(&*pool)
         .await
         .map_err(|e| e.to_string())?;
-    // Insert audit log entry for payment restore
-    insert_audit_log(&*pool, "restore", "payment", &id, None, Some("Payment restored")).await?;
-    Ok(())
+    Ok(rows.into_iter().map(|row| CoilMovement {
+        id: row.get("id"),
+        coil_id: row.get("coil_id"),
+        kind: row.get("movement_type"),
+        weight: row.get("weight"),
+        sale_id: row.get("sale_id"),
+        sale_item_id: row.get("sale_item_id"),
+        notes: row.get("notes"),
+        created_at: row.get("created_at"),
+    }).collect())
 }
 
 #[tauri::command]
@@ -1476,7 +5749,7 @@ pub async fn get_deleted_invoices(pool: tauri::State<'_, SqlitePool>) -> Result<
         r#"
         SELECT i.id, i.invoice_number, i.client_id, i.date, i.due_date, 
                i.total_amount_ht, i.total_amount_ttc, i.is_paid, i.paid_at, 
-               i.created_at, i.updated_at,
+               i.notes, i.created_at, i.updated_at,
                GROUP_CONCAT(s.id) as sales_ids
         FROM invoices i
         LEFT JOIN sales s ON s.invoice_id = i.id
@@ -1501,10 +5774,11 @@ pub async fn get_deleted_invoices(pool: tauri::State<'_, SqlitePool>) -> Result<
                 "client_id": row.get::<String, _>("client_id"),
                 "date": row.get::<String, _>("date"),
                 "due_date": row.get::<String, _>("due_date"),
-                "total_amount_ht": row.get::<f64, _>("total_amount_ht"),
-                "total_amount_ttc": row.get::<f64, _>("total_amount_ttc"),
+                "total_amount_ht": row.get::<Money, _>("total_amount_ht"),
+                "total_amount_ttc": row.get::<Money, _>("total_amount_ttc"),
                 "is_paid": row.get::<bool, _>("is_paid"),
                 "paid_at": row.get::<Option<String>, _>("paid_at"),
+                "notes": row.get::<Option<String>, _>("notes"),
                 "created_at": row.get::<String, _>("created_at"),
                 "updated_at": row.get::<Option<String>, _>("updated_at"),
                 // "deleted_at": row.get::<String, _>("deleted_at"),
@@ -1518,57 +5792,18 @@ pub async fn get_deleted_invoices(pool: tauri::State<'_, SqlitePool>) -> Result<
 
 #[tauri::command]
 pub async fn get_deleted_sales(pool: tauri::State<'_, SqlitePool>) -> Result<Vec<Sale>, String> {
-    let sales_rows = sqlx::query(
-        r#"SELECT * FROM sales WHERE is_deleted = 1 ORDER BY deleted_at DESC"#
-    )
+    let sales_rows = sqlx::query(&format!(
+        "SELECT s.* FROM {} s WHERE s.is_deleted = 1 ORDER BY s.deleted_at DESC",
+        sales_with_payment_progress()
+    ))
     .fetch_all(&*pool)
     .await
     .map_err(|e| e.to_string())?;
     let mut sales = Vec::new();
     for sale_row in sales_rows {
         let sale_id: String = sale_row.get("id");
-        let items_rows = sqlx::query(
-            r#"SELECT * FROM sale_items WHERE sale_id = ?"#
-        )
-        .bind(&sale_id)
-        .fetch_all(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-        let items = items_rows.into_iter().map(|row| SaleItem {
-            id: row.get("id"),
-            sale_id: row.get("sale_id"),
-            description: row.get("description"),
-            coil_ref: row.get("coil_ref"),
-            coil_thickness: row.get("coil_thickness"),
-            coil_width: row.get("coil_width"),
-            top_coat_ral: row.get("top_coat_ral"),
-            back_coat_ral: row.get("back_coat_ral"),
-            coil_weight: row.get("coil_weight"),
-            quantity: row.get("quantity"),
-            price_per_ton: row.get("price_per_ton"),
-            total_amount: row.get("total_amount"),
-            product_type: row.get("product_type"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
-        }).collect();
-        sales.push(Sale {
-            id: sale_row.get("id"),
-            client_id: sale_row.get("client_id"),
-            date: sale_row.get("date"),
-            total_amount: sale_row.get("total_amount"),
-            total_amount_ttc: sale_row.get("total_amount_ttc"),
-            is_invoiced: sale_row.get("is_invoiced"),
-            invoice_id: sale_row.get("invoice_id"),
-            notes: sale_row.get("notes"),
-            payment_method: sale_row.get("payment_method"),
-            transportation_fee: sale_row.get("transportation_fee"),
-            tax_rate: sale_row.get("tax_rate"),
-            created_at: sale_row.get("created_at"),
-            updated_at: sale_row.get("updated_at"),
-            is_paid: sale_row.get("is_paid"),
-            paid_at: sale_row.get("paid_at"),
-            items,
-        });
+        let items = fetch_sale_items(&pool, &sale_id).await?;
+        sales.push(sale_from_row(&sale_row, items));
     }
     Ok(sales)
 }
@@ -1591,6 +5826,7 @@ pub async fn get_deleted_payments(pool: tauri::State<'_, SqlitePool>) -> Result<
         method: row.get("method"),
         notes: row.get("notes"),
         check_number: row.get("check_number"),
+        instrument_id: row.get("instrument_id"),
         created_at: row.get("created_at"),
         updated_at: row.get("updated_at"),
         is_deleted: row.get("is_deleted"),
@@ -1619,6 +5855,15 @@ pub struct Settings {
     pub notifications: Option<bool>,
     pub dark_mode: Option<bool>,
     pub user_id: Option<String>,
+    pub invoice_number_format: Option<String>,
+    pub credit_note_number_format: Option<String>,
+    pub quote_number_format: Option<String>,
+    pub delivery_note_number_format: Option<String>,
+    /// Droit de timbre on cash sales: a share of the amount due, clamped
+    /// between the min and the max; a max of 0 means no cap
+    pub stamp_duty_rate: Option<f64>,
+    pub stamp_duty_min: Option<Money>,
+    pub stamp_duty_max: Option<Money>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
 }
@@ -1630,7 +5875,7 @@ pub async fn get_settings(pool: tauri::State<'_, SqlitePool>) -> Result<Settings
         SELECT
             id, company_name, company_address, company_phone, company_email, company_logo,
             tax_rate, currency, nif, nis, rc, ai, rib, language, theme, notifications, dark_mode,
-            user_id, created_at, updated_at
+            user_id, invoice_number_format, credit_note_number_format, quote_number_format, delivery_note_number_format, stamp_duty_rate, stamp_duty_min, stamp_duty_max, created_at, updated_at
         FROM settings
         LIMIT 1
         "#
@@ -1659,6 +5904,13 @@ pub async fn get_settings(pool: tauri::State<'_, SqlitePool>) -> Result<Settings
             notifications: row.get("notifications"),
             dark_mode: row.get("dark_mode"),
             user_id: row.get("user_id"),
+            invoice_number_format: row.get("invoice_number_format"),
+            credit_note_number_format: row.get("credit_note_number_format"),
+            quote_number_format: row.get("quote_number_format"),
+            delivery_note_number_format: row.get("delivery_note_number_format"),
+            stamp_duty_rate: row.get("stamp_duty_rate"),
+            stamp_duty_min: row.get("stamp_duty_min"),
+            stamp_duty_max: row.get("stamp_duty_max"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -1686,6 +5938,13 @@ pub struct UpdateSettingsRequest {
     pub notifications: Option<bool>,
     pub dark_mode: Option<bool>,
     pub user_id: Option<String>,
+    pub invoice_number_format: Option<String>,
+    pub credit_note_number_format: Option<String>,
+    pub quote_number_format: Option<String>,
+    pub delivery_note_number_format: Option<String>,
+    pub stamp_duty_rate: Option<f64>,
+    pub stamp_duty_min: Option<Money>,
+    pub stamp_duty_max: Option<Money>,
 }
 
 #[tauri::command]
@@ -1712,6 +5971,41 @@ pub async fn update_settings(
     if let Some(_) = updates.notifications { set_clauses.push("notifications = ?"); }
     if let Some(_) = updates.dark_mode { set_clauses.push("dark_mode = ?"); }
     if let Some(_) = updates.user_id { set_clauses.push("user_id = ?"); }
+    if let Some(ref format) = updates.invoice_number_format {
+        // Reject patterns that could not produce a unique number
+        format_document_number(format, 2000, 1, 1)?;
+        set_clauses.push("invoice_number_format = ?");
+    }
+    if let Some(ref format) = updates.credit_note_number_format {
+        format_document_number(format, 2000, 1, 1)?;
+        set_clauses.push("credit_note_number_format = ?");
+    }
+    if let Some(ref format) = updates.quote_number_format {
+        format_document_number(format, 2000, 1, 1)?;
+        set_clauses.push("quote_number_format = ?");
+    }
+    if let Some(ref format) = updates.delivery_note_number_format {
+        format_document_number(format, 2000, 1, 1)?;
+        set_clauses.push("delivery_note_number_format = ?");
+    }
+    if let Some(rate) = updates.stamp_duty_rate {
+        if !(0.0..1.0).contains(&rate) {
+            return Err("Stamp duty rate must be between 0 and 1".to_string());
+        }
+        set_clauses.push("stamp_duty_rate = ?");
+    }
+    if let Some(min) = updates.stamp_duty_min {
+        if min.is_negative() {
+            return Err("Stamp duty minimum cannot be negative".to_string());
+        }
+        set_clauses.push("stamp_duty_min = ?");
+    }
+    if let Some(max) = updates.stamp_duty_max {
+        if max.is_negative() {
+            return Err("Stamp duty maximum cannot be negative".to_string());
+        }
+        set_clauses.push("stamp_duty_max = ?");
+    }
 
     if set_clauses.is_empty() {
         return Err("No fields to update".to_string());
@@ -1738,6 +6032,13 @@ pub async fn update_settings(
     if let Some(v) = updates.notifications { q = q.bind(v); }
     if let Some(v) = updates.dark_mode { q = q.bind(v); }
     if let Some(ref v) = updates.user_id { q = q.bind(v); }
+    if let Some(ref v) = updates.invoice_number_format { q = q.bind(v); }
+    if let Some(ref v) = updates.credit_note_number_format { q = q.bind(v); }
+    if let Some(ref v) = updates.quote_number_format { q = q.bind(v); }
+    if let Some(ref v) = updates.delivery_note_number_format { q = q.bind(v); }
+    if let Some(v) = updates.stamp_duty_rate { q = q.bind(v); }
+    if let Some(v) = updates.stamp_duty_min { q = q.bind(v); }
+    if let Some(v) = updates.stamp_duty_max { q = q.bind(v); }
 
     q.execute(&*pool).await.map_err(|e| e.to_string())?;
     Ok(())
@@ -1815,7 +6116,7 @@ pub async fn get_audit_log(
     page_size: Option<u32>,
     pool: tauri::State<'_, SqlitePool>
 ) -> Result<PaginatedAuditLogResult, String> {
-    let page = page.unwrap_or(1);
+    let page = page.unwrap_or(1).max(1);
     let page_size = page_size.unwrap_or(50);
     let offset = (page - 1) * page_size;
     // Total count
@@ -1855,9 +6156,9 @@ pub struct SoldProductsFilter {
     /// Multi-select filter for width (mm)
     pub width: Option<Vec<f64>>,
     /// Unit price min filter
-    pub unit_price_min: Option<f64>,
+    pub unit_price_min: Option<Money>,
     /// Unit price max filter
-    pub unit_price_max: Option<f64>,
+    pub unit_price_max: Option<Money>,
     /// Payment status filter: 'all', 'paid', 'unpaid'
     pub payment_status: Option<String>,
 }
@@ -1870,8 +6171,8 @@ pub struct SoldProduct {
     pub width: f64,
     pub quantity: f64,
     pub weight: f64,
-    pub unit_price: f64,
-    pub total_price: f64,
+    pub unit_price: Money,
+    pub total_price: Money,
     pub invoice_number: String,
     pub sale_date: String,
     pub payment_status: String,
@@ -1880,13 +6181,17 @@ pub struct SoldProduct {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SoldProductsSummary {
     pub total_weight: f64,
-    pub total_revenue: f64, // item-level total (legacy)
-    pub official_total_revenue: f64, // sum of sales.total_amount_ttc
-    pub item_total_revenue: f64, // sum of sale_items.total_amount * 1.19
+    pub total_revenue: Money, // item-level total (legacy)
+    pub official_total_revenue: Money, // sum of sales.total_amount_ttc less stamp duty
+    pub item_total_revenue: Money, // sum of sale_items.total_amount + tax_amount
     pub total_quantity: f64,
     pub unique_products: i64,
     pub unique_clients: i64,
-    pub average_order_value: f64,
+    pub average_order_value: Money,
+    /// VAT on the matching items, by rate
+    pub vat_breakdown: Vec<VatLine>,
+    /// Stamp duty charged on the matching sales
+    pub total_stamp_duty: Money,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -1895,86 +6200,90 @@ pub struct SoldProductsAnalyticsResult {
     pub total: i64,
 }
 
-#[tauri::command]
-pub async fn get_sold_products_analytics(
-    filter: SoldProductsFilter,
-    page: Option<u32>,
-    page_size: Option<u32>,
-    pool: tauri::State<'_, SqlitePool>,
-) -> Result<SoldProductsAnalyticsResult, String> {
-    // println!("[get_sold_products_analytics] filter: {:?}", filter);
-    let page = page.unwrap_or(1);
-    let page_size = page_size.unwrap_or(5);
-    let offset = (page - 1) * page_size;
-    let mut query = String::from(r#"
-        SELECT
-            si.description as product_name,
-            c.name as client_name,
-            si.coil_thickness as thickness,
-            si.coil_width as width,
-            si.quantity,
-            si.coil_weight as weight,
-            si.price_per_ton as unit_price,
-            (si.total_amount * 1.19) as total_price,
-            i.invoice_number,
-            s.date as sale_date,
-            CASE WHEN i.is_paid = 1 THEN 'Paid' ELSE 'Unpaid' END as payment_status
-        FROM sale_items si
-        JOIN sales s ON si.sale_id = s.id
-        JOIN clients c ON s.client_id = c.id
-        LEFT JOIN invoices i ON s.invoice_id = i.id
-        WHERE 1=1
-    "#);
-    let mut params: Vec<(String, String)> = Vec::new();
+/// Appends the filter's conditions on `sale_items si` and
+/// `sales_with_payment_progress() s` to `where_clause` and `params`.
+fn apply_sold_products_filter(filter: &SoldProductsFilter, where_clause: &mut String, params: &mut Vec<String>) -> Result<(), String> {
     if let Some(ref start) = filter.start_date {
-        query.push_str(" AND s.date >= ?");
-        params.push(("start_date".to_string(), start.clone()));
+        where_clause.push_str(" AND s.date >= ?");
+        params.push(start.clone());
     }
     if let Some(ref end) = filter.end_date {
-        query.push_str(" AND s.date <= ?");
-        params.push(("end_date".to_string(), end.clone()));
+        where_clause.push_str(" AND s.date <= ?");
+        params.push(end.clone());
     }
     if let Some(ref pt) = filter.product_type {
-        query.push_str(" AND si.product_type = ?");
-        params.push(("product_type".to_string(), pt.clone()));
+        where_clause.push_str(" AND si.product_type = ?");
+        params.push(pt.clone());
     }
     if let Some(ref cid) = filter.client_id {
-        query.push_str(" AND s.client_id = ?");
-        params.push(("client_id".to_string(), cid.clone()));
+        where_clause.push_str(" AND s.client_id = ?");
+        params.push(cid.clone());
     }
     if let Some(ref thicknesses) = filter.thickness {
         if !thicknesses.is_empty() {
             let placeholders = vec!["?"; thicknesses.len()].join(", ");
-            query.push_str(&format!(" AND si.coil_thickness IN ({})", placeholders));
+            where_clause.push_str(&format!(" AND si.coil_thickness IN ({})", placeholders));
             for t in thicknesses {
-                params.push(("thickness".to_string(), t.to_string()));
+                params.push(t.to_string());
             }
         }
     }
     if let Some(ref widths) = filter.width {
         if !widths.is_empty() {
             let placeholders = vec!["?"; widths.len()].join(", ");
-            query.push_str(&format!(" AND si.coil_width IN ({})", placeholders));
+            where_clause.push_str(&format!(" AND si.coil_width IN ({})", placeholders));
             for w in widths {
-                params.push(("width".to_string(), w.to_string()));
+                params.push(w.to_string());
             }
         }
     }
     if let Some(min) = filter.unit_price_min {
-        query.push_str(" AND si.price_per_ton >= ?");
-        params.push(("unit_price_min".to_string(), min.to_string()));
+        where_clause.push_str(" AND si.price_per_ton >= ?");
+        params.push(min.centimes().to_string());
     }
     if let Some(max) = filter.unit_price_max {
-        query.push_str(" AND si.price_per_ton <= ?");
-        params.push(("unit_price_max".to_string(), max.to_string()));
+        where_clause.push_str(" AND si.price_per_ton <= ?");
+        params.push(max.centimes().to_string());
     }
-    if let Some(ref status) = filter.payment_status {
-        if status == "paid" {
-            query.push_str(" AND i.is_paid = 1");
-        } else if status == "unpaid" {
-            query.push_str(" AND (i.is_paid = 0 OR i.is_paid IS NULL)");
-        }
+    if let Some(status) = payment_status_filter(filter.payment_status.as_deref())? {
+        where_clause.push_str(" AND s.payment_status = ?");
+        params.push(status);
     }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_sold_products_analytics(
+    filter: SoldProductsFilter,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    pool: tauri::State<'_, SqlitePool>,
+) -> Result<SoldProductsAnalyticsResult, String> {
+    // println!("[get_sold_products_analytics] filter: {:?}", filter);
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut query = format!(r#"
+        SELECT
+            si.description as product_name,
+            c.name as client_name,
+            si.coil_thickness as thickness,
+            si.coil_width as width,
+            si.quantity,
+            si.coil_weight as weight,
+            si.price_per_ton as unit_price,
+            (si.total_amount + si.tax_amount) as total_price,
+            i.invoice_number,
+            s.date as sale_date,
+            s.payment_status
+        FROM sale_items si
+        JOIN {} s ON si.sale_id = s.id
+        JOIN clients c ON s.client_id = c.id
+        LEFT JOIN invoices i ON s.invoice_id = i.id
+        WHERE 1=1
+    "#, sales_with_payment_progress());
+    let mut params: Vec<String> = Vec::new();
+    apply_sold_products_filter(&filter, &mut query, &mut params)?;
     query.push_str(" ORDER BY s.date DESC, si.description ASC");
     // For pagination
     let paginated_query = format!("{} LIMIT ? OFFSET ?", query);
@@ -1982,7 +6291,7 @@ pub async fn get_sold_products_analytics(
     let count_query = format!("SELECT COUNT(*) as total FROM ({} ) as sub", query);
     // Fetch total count
     let mut count_q = sqlx::query(&count_query);
-    for (_k, v) in &params {
+    for v in &params {
         count_q = count_q.bind(v);
     }
     let total: i64 = match count_q.fetch_one(&*pool).await {
@@ -1994,7 +6303,7 @@ pub async fn get_sold_products_analytics(
     };
     // Fetch paginated rows
     let mut q = sqlx::query(&paginated_query);
-    for (_k, v) in &params {
+    for v in &params {
         q = q.bind(v);
     }
     q = q.bind(page_size as i64);
@@ -2013,8 +6322,8 @@ pub async fn get_sold_products_analytics(
         width: row.try_get("width").unwrap_or(0.0),
         quantity: row.get("quantity"),
         weight: row.try_get("weight").unwrap_or(0.0),
-        unit_price: row.try_get("unit_price").unwrap_or(0.0),
-        total_price: row.try_get("total_price").unwrap_or(0.0),
+        unit_price: row.try_get("unit_price").unwrap_or_default(),
+        total_price: row.try_get("total_price").unwrap_or_default(),
         invoice_number: row.try_get("invoice_number").unwrap_or_default(),
         sale_date: row.try_get("sale_date").unwrap_or_default(),
         payment_status: row.try_get("payment_status").unwrap_or_default(),
@@ -2027,85 +6336,61 @@ pub async fn get_sold_products_summary(
     filter: SoldProductsFilter,
     pool: tauri::State<'_, SqlitePool>,
 ) -> Result<SoldProductsSummary, String> {
-    // Build WHERE clause and params as before
     let mut where_clause = String::from("WHERE 1=1");
     let mut params: Vec<String> = Vec::new();
-    if let Some(ref start) = filter.start_date {
-        where_clause.push_str(" AND s.date >= ?");
-        params.push(start.clone());
-    }
-    if let Some(ref end) = filter.end_date {
-        where_clause.push_str(" AND s.date <= ?");
-        params.push(end.clone());
-    }
-    if let Some(ref pt) = filter.product_type {
-        where_clause.push_str(" AND si.product_type = ?");
-        params.push(pt.clone());
-    }
-    if let Some(ref cid) = filter.client_id {
-        where_clause.push_str(" AND s.client_id = ?");
-        params.push(cid.clone());
-    }
-    if let Some(ref thicknesses) = filter.thickness {
-        if !thicknesses.is_empty() {
-            let placeholders = vec!["?"; thicknesses.len()].join(", ");
-            where_clause.push_str(&format!(" AND si.coil_thickness IN ({})", placeholders));
-            for t in thicknesses {
-                params.push(t.to_string());
-            }
-        }
-    }
-    if let Some(ref widths) = filter.width {
-        if !widths.is_empty() {
-            let placeholders = vec!["?"; widths.len()].join(", ");
-            where_clause.push_str(&format!(" AND si.coil_width IN ({})", placeholders));
-            for w in widths {
-                params.push(w.to_string());
-            }
-        }
-    }
-    if let Some(min) = filter.unit_price_min {
-        where_clause.push_str(" AND si.price_per_ton >= ?");
-        params.push(min.to_string());
-    }
-    if let Some(max) = filter.unit_price_max {
-        where_clause.push_str(" AND si.price_per_ton <= ?");
-        params.push(max.to_string());
-    }
-    if let Some(ref status) = filter.payment_status {
-        if status == "paid" {
-            where_clause.push_str(" AND i.is_paid = 1");
-        } else if status == "unpaid" {
-            where_clause.push_str(" AND (i.is_paid = 0 OR i.is_paid IS NULL)");
-        }
-    }
+    apply_sold_products_filter(&filter, &mut where_clause, &mut params)?;
     // Item-level total (legacy)
     let item_query = format!(r#"
         SELECT
             SUM(si.coil_weight) as total_weight,
-            SUM(si.total_amount * 1.19) as item_total_revenue,
+            SUM(si.total_amount + si.tax_amount) as item_total_revenue,
             SUM(si.quantity) as total_quantity,
             COUNT(DISTINCT si.description) as unique_products,
             COUNT(DISTINCT s.client_id) as unique_clients,
             AVG(s.total_amount) as average_order_value
         FROM sale_items si
-        JOIN sales s ON si.sale_id = s.id
+        JOIN {} s ON si.sale_id = s.id
         JOIN clients c ON s.client_id = c.id
         LEFT JOIN invoices i ON s.invoice_id = i.id
         {}
-    "#, where_clause);
+    "#, sales_with_payment_progress(), where_clause);
     let mut item_q = sqlx::query(&item_query);
     for v in &params {
         item_q = item_q.bind(v);
     }
     let item_row = item_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
     let total_weight: f64 = item_row.try_get("total_weight").unwrap_or(0.0);
-    let item_total_revenue: f64 = item_row.try_get("item_total_revenue").unwrap_or(0.0);
+    let item_total_revenue: Money = item_row.try_get("item_total_revenue").unwrap_or_default();
     let total_quantity: f64 = item_row.try_get("total_quantity").unwrap_or(0.0);
     let unique_products: i64 = item_row.try_get("unique_products").unwrap_or(0);
     let unique_clients: i64 = item_row.try_get("unique_clients").unwrap_or(0);
-    let average_order_value: f64 = item_row.try_get("average_order_value").unwrap_or(0.0);
-    // Official total: sum sales.total_amount_ttc for matching sales
+    let average_order_value: Money = item_row.try_get("average_order_value").unwrap_or_default();
+    let vat_query = format!(r#"
+        SELECT
+            CASE WHEN s.tax_exemption IS NOT NULL THEN 0.0 ELSE COALESCE(si.tax_rate, s.tax_rate, 0.0) END as tax_rate,
+            SUM(si.total_amount) as taxable_amount,
+            SUM(si.tax_amount) as tax_amount
+        FROM sale_items si
+        JOIN {} s ON si.sale_id = s.id
+        JOIN clients c ON s.client_id = c.id
+        LEFT JOIN invoices i ON s.invoice_id = i.id
+        {}
+        GROUP BY 1
+        ORDER BY 1
+    "#, sales_with_payment_progress(), where_clause);
+    let mut vat_q = sqlx::query(&vat_query);
+    for v in &params {
+        vat_q = vat_q.bind(v);
+    }
+    let vat_breakdown = vat_q.fetch_all(&*pool).await.map_err(|e| e.to_string())?
+        .iter()
+        .map(|row| VatLine {
+            tax_rate: row.get("tax_rate"),
+            taxable_amount: row.get("taxable_amount"),
+            tax_amount: row.get("tax_amount"),
+        })
+        .collect();
+    // Official total: sum sales.total_amount_ttc for matching sales, without the stamp duty
     let mut sales_where = String::from("WHERE is_deleted = 0 OR is_deleted IS NULL");
     let mut sales_params: Vec<String> = Vec::new();
     if let Some(ref start) = filter.start_date {
@@ -2120,14 +6405,17 @@ pub async fn get_sold_products_summary(
         sales_where.push_str(" AND client_id = ?");
         sales_params.push(cid.clone());
     }
-    // If product/thickness/width filters are present, restrict to sales that have at least one matching item
+    // If product/thickness/width/payment status filters are present, restrict to sales that have at least one matching item
     let mut restrict_to_sales = false;
     if filter.product_type.is_some() || (filter.thickness.is_some() && !filter.thickness.as_ref().unwrap().is_empty()) || (filter.width.is_some() && !filter.width.as_ref().unwrap().is_empty()) {
         restrict_to_sales = true;
     }
-    let official_total_revenue = if restrict_to_sales {
+    if payment_status_filter(filter.payment_status.as_deref())?.is_some() {
+        restrict_to_sales = true;
+    }
+    let (official_total_revenue, total_stamp_duty): (Money, Money) = if restrict_to_sales {
         // Find sale_ids matching the item filters
-        let mut sale_ids_query = format!("SELECT DISTINCT s.id FROM sales s JOIN sale_items si ON si.sale_id = s.id {}", where_clause);
+        let mut sale_ids_query = format!("SELECT DISTINCT s.id FROM {} s JOIN sale_items si ON si.sale_id = s.id {}", sales_with_payment_progress(), where_clause);
         let mut sale_ids_q = sqlx::query(&sale_ids_query);
         for v in &params {
             sale_ids_q = sale_ids_q.bind(v);
@@ -2135,27 +6423,27 @@ pub async fn get_sold_products_summary(
         let sale_ids_rows = sale_ids_q.fetch_all(&*pool).await.map_err(|e| e.to_string())?;
         let sale_ids: Vec<String> = sale_ids_rows.into_iter().filter_map(|row| row.try_get::<String, _>("id").ok()).collect();
         if sale_ids.is_empty() {
-            0.0
+            (Money::ZERO, Money::ZERO)
         } else {
             // Build a query to sum total_amount_ttc for these sales
             let placeholders = sale_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-            let sum_query = format!("SELECT SUM(total_amount_ttc) as official_total_revenue FROM sales WHERE id IN ({})", placeholders);
+            let sum_query = format!("SELECT SUM(total_amount_ttc - stamp_duty) as official_total_revenue, SUM(stamp_duty) as total_stamp_duty FROM sales WHERE id IN ({})", placeholders);
             let mut sum_q = sqlx::query(&sum_query);
             for id in &sale_ids {
                 sum_q = sum_q.bind(id);
             }
             let sum_row = sum_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
-            sum_row.try_get("official_total_revenue").unwrap_or(0.0)
+            (sum_row.try_get("official_total_revenue").unwrap_or_default(), sum_row.try_get("total_stamp_duty").unwrap_or_default())
         }
     } else {
         // No product/thickness/width filter: sum all matching sales
-        let sum_query = format!("SELECT SUM(total_amount_ttc) as official_total_revenue FROM sales {}", sales_where);
+        let sum_query = format!("SELECT SUM(total_amount_ttc - stamp_duty) as official_total_revenue, SUM(stamp_duty) as total_stamp_duty FROM sales {}", sales_where);
         let mut sum_q = sqlx::query(&sum_query);
         for v in &sales_params {
             sum_q = sum_q.bind(v);
         }
         let sum_row = sum_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
-        sum_row.try_get("official_total_revenue").unwrap_or(0.0)
+        (sum_row.try_get("official_total_revenue").unwrap_or_default(), sum_row.try_get("total_stamp_duty").unwrap_or_default())
     };
     Ok(SoldProductsSummary {
         total_weight,
@@ -2166,6 +6454,478 @@ pub async fn get_sold_products_summary(
         unique_products,
         unique_clients,
         average_order_value,
+        vat_breakdown,
+        total_stamp_duty,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarginRow {
+    /// product_type, "thickness×width", client_id or YYYY-MM
+    pub key: String,
+    pub label: String,
+    /// HT revenue of every matching item
+    pub revenue: Money,
+    pub cost: Money,
+    /// Revenue less cost, over the items that have a cost
+    pub gross_margin: Money,
+    /// gross_margin as a percentage of the revenue of the items that have a
+    /// cost; None when none of them has one
+    pub margin_percent: Option<f64>,
+    /// Revenue of the items without a cost, left out of the margin
+    pub uncosted_revenue: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarginAnalytics {
+    pub totals: MarginRow,
+    pub by_product_type: Vec<MarginRow>,
+    pub by_dimensions: Vec<MarginRow>,
+    pub by_client: Vec<MarginRow>,
+    pub by_month: Vec<MarginRow>,
+}
+
+fn margin_row(row: &sqlx::sqlite::SqliteRow) -> MarginRow {
+    let revenue: Money = row.get("revenue");
+    let costed_revenue: Money = row.get("costed_revenue");
+    let cost: Money = row.get("cost");
+    let gross_margin = costed_revenue - cost;
+    let margin_percent = (!costed_revenue.is_zero())
+        .then(|| (gross_margin.centimes() as f64 * 10000.0 / costed_revenue.centimes() as f64).round() / 100.0);
+    MarginRow {
+        key: row.get("key"),
+        label: row.get("label"),
+        revenue,
+        cost,
+        gross_margin,
+        margin_percent,
+        uncosted_revenue: revenue - costed_revenue,
+    }
+}
+
+/// Revenue, cost and gross margin of the sold items matching `filter`, in
+/// total and grouped by product type, thickness×width, client and month.
+/// Amounts are HT; deleted sales are left out.
+#[tauri::command]
+pub async fn get_margin_analytics(
+    filter: SoldProductsFilter,
+    pool: tauri::State<'_, SqlitePool>,
+) -> Result<MarginAnalytics, String> {
+    let mut where_clause = String::from("WHERE (s.is_deleted = 0 OR s.is_deleted IS NULL)");
+    let mut params: Vec<String> = Vec::new();
+    apply_sold_products_filter(&filter, &mut where_clause, &mut params)?;
+    let groupings = [
+        ("'all'", "'all'", "1"),
+        ("si.product_type", "si.product_type", "revenue DESC"),
+        (
+            "printf('%g×%g', IFNULL(si.coil_thickness, 0), IFNULL(si.coil_width, 0))",
+            "printf('%g×%g', IFNULL(si.coil_thickness, 0), IFNULL(si.coil_width, 0))",
+            "revenue DESC",
+        ),
+        ("s.client_id", "MAX(c.name)", "revenue DESC"),
+        ("substr(s.date, 1, 7)", "substr(s.date, 1, 7)", "key ASC"),
+    ];
+    let mut groups = Vec::with_capacity(groupings.len());
+    for (key_sql, label_sql, order_by) in groupings {
+        let query = format!(
+            r#"
+            SELECT
+                {key} AS key,
+                {label} AS label,
+                IFNULL(SUM(si.total_amount), 0) AS revenue,
+                IFNULL(SUM(CASE WHEN si.cost_amount IS NOT NULL THEN si.total_amount END), 0) AS costed_revenue,
+                IFNULL(SUM(si.cost_amount), 0) AS cost
+            FROM sale_items si
+            JOIN {sales} s ON si.sale_id = s.id
+            JOIN clients c ON s.client_id = c.id
+            {where_clause}
+            GROUP BY 1
+            ORDER BY {order_by}, key ASC
+            "#,
+            key = key_sql,
+            label = label_sql,
+            sales = sales_with_payment_progress(),
+            where_clause = where_clause,
+            order_by = order_by,
+        );
+        let mut q = sqlx::query(&query);
+        for v in &params {
+            q = q.bind(v);
+        }
+        let rows = q.fetch_all(&*pool).await.map_err(|e| e.to_string())?;
+        groups.push(rows.iter().map(margin_row).collect::<Vec<_>>());
+    }
+    let mut groups = groups.into_iter();
+    let totals = groups.next().unwrap_or_default().into_iter().next().unwrap_or(MarginRow {
+        key: "all".to_string(),
+        label: "all".to_string(),
+        revenue: Money::ZERO,
+        cost: Money::ZERO,
+        gross_margin: Money::ZERO,
+        margin_percent: None,
+        uncosted_revenue: Money::ZERO,
+    });
+    Ok(MarginAnalytics {
+        totals,
+        by_product_type: groups.next().unwrap_or_default(),
+        by_dimensions: groups.next().unwrap_or_default(),
+        by_client: groups.next().unwrap_or_default(),
+        by_month: groups.next().unwrap_or_default(),
+    })
+}
+
+// --- Sales time series ---
+
+pub const TIMESERIES_GRANULARITIES: [&str; 4] = ["day", "week", "month", "quarter"];
+pub const TIMESERIES_METRICS: [&str; 6] = ["revenue_ht", "revenue_ttc", "tonnage", "quantity", "sale_count", "collected"];
+pub const TIMESERIES_BREAKDOWNS: [&str; 2] = ["product_type", "client"];
+const MAX_TIMESERIES_BUCKETS: usize = 5000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeseriesBucket {
+    /// "2024-06-03", "2024-W23", "2024-06" or "2024-Q2"
+    pub period: String,
+    pub start_date: String,
+    /// Last day of the bucket, inclusive
+    pub end_date: String,
+    pub revenue_ht: Money,
+    pub revenue_ttc: Money,
+    /// Sum of the items' coil_weight
+    pub tonnage: f64,
+    pub quantity: f64,
+    pub sale_count: i64,
+    /// Payments dated in the bucket
+    pub collected: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeseriesSeries {
+    /// product_type or client_id
+    pub key: String,
+    pub label: String,
+    /// Sum of the requested metric over every bucket
+    pub total: f64,
+    pub buckets: Vec<TimeseriesBucket>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesTimeseries {
+    pub granularity: String,
+    pub metric: String,
+    pub breakdown: Option<String>,
+    pub buckets: Vec<TimeseriesBucket>,
+    /// The same buckets one year earlier, index for index
+    pub previous_year: Option<Vec<TimeseriesBucket>>,
+    /// One entry per product type or client, largest metric total first
+    pub series: Vec<TimeseriesSeries>,
+}
+
+struct TimeseriesDay {
+    day: chrono::NaiveDate,
+    /// None for payments when the breakdown is by product type
+    key: Option<String>,
+    label: String,
+    totals: TimeseriesBucket,
+}
+
+struct TimeseriesRange {
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+    period: String,
+}
+
+fn timeseries_period(granularity: &str, day: chrono::NaiveDate) -> String {
+    match granularity {
+        "week" => day.format("%G-W%V").to_string(),
+        "month" => day.format("%Y-%m").to_string(),
+        "quarter" => format!("{}-Q{}", day.year(), day.month0() / 3 + 1),
+        _ => day.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Consecutive buckets covering `start..=end`, aligned on Mondays, the first
+/// of the month or the first of the quarter.
+fn timeseries_ranges(granularity: &str, start: chrono::NaiveDate, end: chrono::NaiveDate) -> Result<Vec<TimeseriesRange>, String> {
+    let first = match granularity {
+        "week" => start - chrono::Duration::days(start.weekday().num_days_from_monday() as i64),
+        "month" => start.with_day(1).ok_or("Invalid start date")?,
+        "quarter" => chrono::NaiveDate::from_ymd_opt(start.year(), start.month0() / 3 * 3 + 1, 1).ok_or("Invalid start date")?,
+        _ => start,
+    };
+    let mut ranges = Vec::new();
+    let mut bucket_start = first;
+    while bucket_start <= end {
+        if ranges.len() == MAX_TIMESERIES_BUCKETS {
+            return Err(format!("The period spans more than {} {} buckets; pick a coarser granularity", MAX_TIMESERIES_BUCKETS, granularity));
+        }
+        let next = match granularity {
+            "week" => bucket_start + chrono::Duration::days(7),
+            "month" => bucket_start.checked_add_months(chrono::Months::new(1)).ok_or("Date out of range")?,
+            "quarter" => bucket_start.checked_add_months(chrono::Months::new(3)).ok_or("Date out of range")?,
+            _ => bucket_start + chrono::Duration::days(1),
+        };
+        ranges.push(TimeseriesRange {
+            start: bucket_start,
+            end: next - chrono::Duration::days(1),
+            period: timeseries_period(granularity, bucket_start),
+        });
+        bucket_start = next;
+    }
+    Ok(ranges)
+}
+
+/// The same bucket a year earlier. Weeks move back 52 weeks so they still
+/// start on a Monday; 29 February falls back to the 28th.
+fn previous_year_range(granularity: &str, range: &TimeseriesRange) -> TimeseriesRange {
+    let shift = |day: chrono::NaiveDate| match granularity {
+        "week" => day - chrono::Duration::days(364),
+        _ => day.checked_sub_months(chrono::Months::new(12)).unwrap_or(day),
+    };
+    let start = shift(range.start);
+    let end = match granularity {
+        // Month ends move with their month: February 29 becomes the 28th
+        "month" | "quarter" => shift(range.end + chrono::Duration::days(1)) - chrono::Duration::days(1),
+        _ => shift(range.end),
+    };
+    TimeseriesRange { start, end, period: timeseries_period(granularity, start) }
+}
+
+fn timeseries_metric(bucket: &TimeseriesBucket, metric: &str) -> f64 {
+    match metric {
+        "revenue_ht" => bucket.revenue_ht.centimes() as f64 / 100.0,
+        "tonnage" => bucket.tonnage,
+        "quantity" => bucket.quantity,
+        "sale_count" => bucket.sale_count as f64,
+        "collected" => bucket.collected.centimes() as f64 / 100.0,
+        _ => bucket.revenue_ttc.centimes() as f64 / 100.0,
+    }
+}
+
+/// Adds up `days` into one bucket per range; days outside every range are
+/// ignored and buckets without sales stay at zero.
+fn fill_timeseries<'a>(ranges: &[TimeseriesRange], days: impl IntoIterator<Item = &'a TimeseriesDay>) -> Vec<TimeseriesBucket> {
+    let mut buckets: Vec<TimeseriesBucket> = ranges.iter().map(|r| TimeseriesBucket {
+        period: r.period.clone(),
+        start_date: r.start.format("%Y-%m-%d").to_string(),
+        end_date: r.end.format("%Y-%m-%d").to_string(),
+        ..Default::default()
+    }).collect();
+    for day in days {
+        let index = ranges.partition_point(|r| r.start <= day.day);
+        if index == 0 || ranges[index - 1].end < day.day {
+            continue;
+        }
+        let bucket = &mut buckets[index - 1];
+        bucket.revenue_ht += day.totals.revenue_ht;
+        bucket.revenue_ttc += day.totals.revenue_ttc;
+        bucket.tonnage += day.totals.tonnage;
+        bucket.quantity += day.totals.quantity;
+        bucket.sale_count += day.totals.sale_count;
+        bucket.collected += day.totals.collected;
+    }
+    buckets
+}
+
+/// Daily totals of the sales and payments matching `filter` between `from`
+/// and `to`, one row per day and breakdown key.
+async fn timeseries_days(
+    pool: &SqlitePool,
+    filter: &SoldProductsFilter,
+    breakdown: Option<&str>,
+    from: &str,
+    to: &str,
+) -> Result<Vec<TimeseriesDay>, String> {
+    let (key_sql, label_sql) = match breakdown {
+        Some("product_type") => ("IFNULL(si.product_type, '')", "IFNULL(si.product_type, '')"),
+        Some("client") => ("s.client_id", "MAX(c.name)"),
+        _ => ("''", "''"),
+    };
+    let mut where_clause = String::from("WHERE (s.is_deleted = 0 OR s.is_deleted IS NULL) AND substr(s.date, 1, 10) BETWEEN ? AND ?");
+    let mut params: Vec<String> = vec![from.to_string(), to.to_string()];
+    apply_sold_products_filter(filter, &mut where_clause, &mut params)?;
+    let query = format!(
+        r#"
+        SELECT
+            substr(s.date, 1, 10) AS day,
+            {key} AS key,
+            {label} AS label,
+            IFNULL(SUM(si.total_amount), 0) AS revenue_ht,
+            IFNULL(SUM(si.total_amount + si.tax_amount), 0) AS revenue_ttc,
+            IFNULL(SUM(si.coil_weight), 0.0) AS tonnage,
+            IFNULL(SUM(si.quantity), 0.0) AS quantity,
+            COUNT(DISTINCT s.id) AS sale_count
+        FROM sale_items si
+        JOIN {sales} s ON si.sale_id = s.id
+        JOIN clients c ON s.client_id = c.id
+        {where_clause}
+        GROUP BY 1, 2
+        "#,
+        key = key_sql,
+        label = label_sql,
+        sales = sales_with_payment_progress(),
+        where_clause = where_clause,
+    );
+    let mut q = sqlx::query(&query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q.fetch_all(pool).await.map_err(|e| e.to_string())?;
+    let mut days = Vec::with_capacity(rows.len());
+    for row in rows {
+        let day: String = row.get("day");
+        days.push(TimeseriesDay {
+            day: chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d").map_err(|e| e.to_string())?,
+            key: row.get("key"),
+            label: row.get("label"),
+            totals: TimeseriesBucket {
+                revenue_ht: row.get("revenue_ht"),
+                revenue_ttc: row.get("revenue_ttc"),
+                tonnage: row.get("tonnage"),
+                quantity: row.get("quantity"),
+                sale_count: row.get("sale_count"),
+                ..Default::default()
+            },
+        });
+    }
+
+    // Payments are not tied to products, so they only follow the client
+    // filter and are split by client alone
+    let (key_sql, label_sql) = match breakdown {
+        Some("client") => ("p.client_id", "MAX(c.name)"),
+        Some(_) => ("NULL", "''"),
+        _ => ("''", "''"),
+    };
+    let mut payment_where = String::from("WHERE p.is_deleted = 0 AND substr(p.date, 1, 10) BETWEEN ? AND ?");
+    let mut payment_params: Vec<String> = vec![from.to_string(), to.to_string()];
+    if let Some(ref cid) = filter.client_id {
+        payment_where.push_str(" AND p.client_id = ?");
+        payment_params.push(cid.clone());
+    }
+    let query = format!(
+        r#"
+        SELECT substr(p.date, 1, 10) AS day, {key} AS key, {label} AS label, IFNULL(SUM(p.amount), 0) AS collected
+        FROM payments p
+        LEFT JOIN clients c ON c.id = p.client_id
+        {where_clause}
+        GROUP BY 1, 2
+        "#,
+        key = key_sql,
+        label = label_sql,
+        where_clause = payment_where,
+    );
+    let mut q = sqlx::query(&query);
+    for v in &payment_params {
+        q = q.bind(v);
+    }
+    let rows = q.fetch_all(pool).await.map_err(|e| e.to_string())?;
+    for row in rows {
+        let day: String = row.get("day");
+        let label: Option<String> = row.get("label");
+        days.push(TimeseriesDay {
+            day: chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d").map_err(|e| e.to_string())?,
+            key: row.get("key"),
+            label: label.unwrap_or_default(),
+            totals: TimeseriesBucket { collected: row.get("collected"), ..Default::default() },
+        });
+    }
+    Ok(days)
+}
+
+/// Sales matching `filter` bucketed by day, ISO week, month or quarter:
+/// item revenue HT and TTC (with each line's VAT, transport fees left out),
+/// tonnage, quantity, number of sales, and the payments collected in
+/// each bucket. Buckets without activity are filled in between the filter's
+/// dates, or between the first and last matching day when they are not set.
+/// `breakdown` adds one series per product type or client, ranked by
+/// `metric`; `compare_previous_year` adds the same buckets a year earlier.
+/// Deleted sales and payments are left out.
+#[tauri::command]
+pub async fn get_sales_timeseries(
+    filter: SoldProductsFilter,
+    granularity: String,
+    metric: Option<String>,
+    breakdown: Option<String>,
+    compare_previous_year: Option<bool>,
+    pool: tauri::State<'_, SqlitePool>,
+) -> Result<SalesTimeseries, String> {
+    if !TIMESERIES_GRANULARITIES.contains(&granularity.as_str()) {
+        return Err(format!("Invalid granularity '{}'; expected one of {}", granularity, TIMESERIES_GRANULARITIES.join(", ")));
+    }
+    let metric = metric.filter(|m| !m.is_empty()).unwrap_or_else(|| "revenue_ttc".to_string());
+    if !TIMESERIES_METRICS.contains(&metric.as_str()) {
+        return Err(format!("Invalid metric '{}'; expected one of {}", metric, TIMESERIES_METRICS.join(", ")));
+    }
+    let breakdown = breakdown.filter(|b| !b.is_empty());
+    if let Some(ref b) = breakdown {
+        if !TIMESERIES_BREAKDOWNS.contains(&b.as_str()) {
+            return Err(format!("Invalid breakdown '{}'; expected one of {}", b, TIMESERIES_BREAKDOWNS.join(", ")));
+        }
+    }
+    let parse_day = |value: &Option<String>| -> Result<Option<chrono::NaiveDate>, String> {
+        match value.as_deref().filter(|d| !d.is_empty()) {
+            Some(date) => chrono::NaiveDate::parse_from_str(&statement_day(date), "%Y-%m-%d").map(Some).map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    };
+    let start = parse_day(&filter.start_date)?;
+    let end = parse_day(&filter.end_date)?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            return Err("The start date is after the end date".to_string());
+        }
+    }
+    // Dates are matched on the calendar day here, so the end date is inclusive
+    let filter = SoldProductsFilter { start_date: None, end_date: None, ..filter };
+    let from = start.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "0000-01-01".to_string());
+    let to = end.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "9999-12-31".to_string());
+    let days = timeseries_days(&pool, &filter, breakdown.as_deref(), &from, &to).await?;
+
+    let first_day = start.or_else(|| days.iter().map(|d| d.day).min());
+    let last_day = end.or_else(|| days.iter().map(|d| d.day).max());
+    let ranges = match (first_day, last_day) {
+        (Some(first), Some(last)) => timeseries_ranges(&granularity, first, last)?,
+        _ => Vec::new(),
+    };
+    let buckets = fill_timeseries(&ranges, &days);
+
+    let mut series = Vec::new();
+    if breakdown.is_some() {
+        let mut keys: Vec<(String, String)> = Vec::new();
+        for day in &days {
+            let Some(ref day_key) = day.key else { continue };
+            match keys.iter_mut().find(|(key, _)| key == day_key) {
+                Some((_, label)) if label.is_empty() => *label = day.label.clone(),
+                Some(_) => {}
+                None => keys.push((day_key.clone(), day.label.clone())),
+            }
+        }
+        for (key, label) in keys {
+            let buckets = fill_timeseries(&ranges, days.iter().filter(|d| d.key.as_deref() == Some(key.as_str())));
+            let total = buckets.iter().map(|b| timeseries_metric(b, &metric)).sum();
+            series.push(TimeseriesSeries { key, label, total, buckets });
+        }
+        series.sort_by(|a: &TimeseriesSeries, b: &TimeseriesSeries| b.total.total_cmp(&a.total).then_with(|| a.key.cmp(&b.key)));
+    }
+
+    let previous_year = match (compare_previous_year.unwrap_or(false), ranges.first(), ranges.last()) {
+        (true, Some(first), Some(last)) => {
+            let previous_ranges: Vec<TimeseriesRange> = ranges.iter().map(|r| previous_year_range(&granularity, r)).collect();
+            let from = previous_year_range(&granularity, first).start.format("%Y-%m-%d").to_string();
+            let to = previous_year_range(&granularity, last).end.format("%Y-%m-%d").to_string();
+            let previous_days = timeseries_days(&pool, &filter, None, &from, &to).await?;
+            Some(fill_timeseries(&previous_ranges, &previous_days))
+        }
+        (true, _, _) => Some(Vec::new()),
+        _ => None,
+    };
+
+    Ok(SalesTimeseries {
+        granularity,
+        metric,
+        breakdown,
+        buckets,
+        previous_year,
+        series,
     })
 }
 
@@ -2205,10 +6965,10 @@ pub struct ClientSummary {
     pub phone: Option<String>,
     pub address: Option<String>,
     pub rib: Option<String>,
-    pub credit: Option<f64>,
+    pub credit: Option<Money>,
     pub created_at: String,
     pub updated_at: Option<String>,
-    pub total_sales_volume: f64,
+    pub total_sales_volume: Money,
     pub last_sale_date: Option<String>,
 }
 
@@ -2217,9 +6977,11 @@ pub struct SaleSummary {
     pub id: String,
     pub client_id: String,
     pub date: String,
-    pub total_amount: f64,
+    pub total_amount: Money,
     pub payment_method: Option<String>,
-    pub payment_status: Option<String>,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+    pub payment_status: String,
     pub is_invoiced: bool,
     pub created_at: String,
     pub updated_at: Option<String>,
@@ -2232,8 +6994,10 @@ pub struct InvoiceSummary {
     pub client_id: String,
     pub date: String,
     pub due_date: String,
-    pub total_amount: f64,
-    pub status: String,
+    pub total_amount: Money,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+    pub payment_status: String,
     pub created_at: String,
     pub updated_at: Option<String>,
     pub client_name: String,
@@ -2241,13 +7005,15 @@ pub struct InvoiceSummary {
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct DashboardStats {
-    pub total_revenue: f64,
+    pub total_revenue: Money,
     pub sales_count: i64,
-    pub monthly_revenue: f64,
+    pub monthly_revenue: Money,
     pub monthly_sales_count: i64,
     pub new_clients: i64,
     pub overdue_invoices: i64,
     pub unpaid_invoices: i64,
+    /// Still owed to suppliers on their invoices
+    pub total_payable: Money,
 }
 
 // --- Summary Commands ---
@@ -2267,7 +7033,7 @@ pub async fn get_clients_summary(pool: tauri::State<'_, SqlitePool>) -> Result<V
             c.credit_balance as credit,
             c.created_at,
             c.updated_at,
-            COALESCE(SUM(s.total_amount), 0.0) AS total_sales_volume,
+            COALESCE(SUM(s.total_amount), 0) AS total_sales_volume,
             MAX(s.date) AS last_sale_date
         FROM
             clients c
@@ -2289,7 +7055,7 @@ pub async fn get_clients_summary(pool: tauri::State<'_, SqlitePool>) -> Result<V
 
 #[tauri::command]
 pub async fn get_sales_summary(pool: tauri::State<'_, SqlitePool>, limit: i64, offset: i64) -> Result<Vec<SaleSummary>, String> {
-    let rows = sqlx::query_as::<_, SaleSummary>(
+    let rows = sqlx::query_as::<_, SaleSummary>(&format!(
         r#"
         SELECT
             s.id,
@@ -2297,13 +7063,15 @@ pub async fn get_sales_summary(pool: tauri::State<'_, SqlitePool>, limit: i64, o
             s.date,
             s.total_amount,
             s.payment_method,
+            s.amount_paid,
+            s.balance_due,
             s.payment_status,
             s.is_invoiced,
             s.created_at,
             s.updated_at,
             c.name as client_name
         FROM
-            sales s
+            {} s
         JOIN
             clients c ON s.client_id = c.id
         WHERE
@@ -2311,8 +7079,9 @@ pub async fn get_sales_summary(pool: tauri::State<'_, SqlitePool>, limit: i64, o
         ORDER BY
             s.date DESC
         LIMIT ? OFFSET ?
-        "#
-    )
+        "#,
+        sales_with_payment_progress()
+    ))
     .bind(limit)
     .bind(offset)
     .fetch_all(&*pool)
@@ -2323,7 +7092,7 @@ pub async fn get_sales_summary(pool: tauri::State<'_, SqlitePool>, limit: i64, o
 
 #[tauri::command]
 pub async fn get_invoices_summary(pool: tauri::State<'_, SqlitePool>, limit: i64, offset: i64) -> Result<Vec<InvoiceSummary>, String> {
-    let rows = sqlx::query_as::<_, InvoiceSummary>(
+    let rows = sqlx::query_as::<_, InvoiceSummary>(&format!(
         r#"
         SELECT
             i.id,
@@ -2331,12 +7100,14 @@ pub async fn get_invoices_summary(pool: tauri::State<'_, SqlitePool>, limit: i64
             i.date,
             i.due_date,
             i.total_amount_ttc as total_amount,
-            i.status,
+            i.amount_paid,
+            i.balance_due,
+            i.payment_status,
             i.created_at,
             i.updated_at,
             c.name as client_name
         FROM
-            invoices i
+            {} i
         JOIN
             clients c ON i.client_id = c.id
         WHERE
@@ -2344,8 +7115,9 @@ pub async fn get_invoices_summary(pool: tauri::State<'_, SqlitePool>, limit: i64
         ORDER BY
             i.date DESC
         LIMIT ? OFFSET ?
-        "#
-    )
+        "#,
+        invoices_with_payment_progress()
+    ))
     .bind(limit)
     .bind(offset)
     .fetch_all(&*pool)
@@ -2361,19 +7133,19 @@ pub async fn get_invoices_summary(pool: tauri::State<'_, SqlitePool>, limit: i64
 pub async fn get_dashboard_stats(pool: tauri::State<'_, SqlitePool>) -> Result<DashboardStats, String> {
     // This query now uses `is_deleted` which matches your schema.
     // It also uses `query_as!` for safe, direct mapping to the struct.
-    let stats = sqlx::query_as::<_, DashboardStats>(
+    let query = format!(
         r#"
         WITH
           AllTimeSales AS (
             SELECT
-              COALESCE(SUM(total_amount_ttc), 0.0) AS total_revenue,
+              COALESCE(SUM(total_amount_ttc - stamp_duty), 0) AS total_revenue,
               COUNT(id) AS sales_count
             FROM sales
             WHERE is_deleted = 0 OR is_deleted IS NULL
           ),
           MonthlySales AS (
             SELECT
-              COALESCE(SUM(total_amount_ttc), 0.0) AS monthly_revenue,
+              COALESCE(SUM(total_amount_ttc - stamp_duty), 0) AS monthly_revenue,
               COUNT(id) AS monthly_sales_count
             FROM sales
             WHERE (strftime('%Y-%m', date) = strftime('%Y-%m', 'now', 'localtime'))
@@ -2391,6 +7163,11 @@ pub async fn get_dashboard_stats(pool: tauri::State<'_, SqlitePool>) -> Result<D
               COUNT(CASE WHEN is_paid = 0 AND due_date >= date('now', 'localtime') THEN 1 END) AS unpaid_invoices
             FROM invoices
             WHERE is_deleted = 0 OR is_deleted IS NULL
+          ),
+          Payables AS (
+            SELECT COALESCE(SUM(balance_due), 0) AS total_payable
+            FROM {supplier_invoices} sinv
+            WHERE is_deleted = 0
           )
         SELECT
           AllTimeSales.total_revenue,
@@ -2399,10 +7176,13 @@ pub async fn get_dashboard_stats(pool: tauri::State<'_, SqlitePool>) -> Result<D
           MonthlySales.monthly_sales_count,
           NewClients.new_clients,
           InvoiceStats.overdue_invoices,
-          InvoiceStats.unpaid_invoices
-        FROM AllTimeSales, MonthlySales, NewClients, InvoiceStats
-        "#
-    )
+          InvoiceStats.unpaid_invoices,
+          Payables.total_payable
+        FROM AllTimeSales, MonthlySales, NewClients, InvoiceStats, Payables
+        "#,
+        supplier_invoices = purchasing::supplier_invoices_with_payment_progress(),
+    );
+    let stats = sqlx::query_as::<_, DashboardStats>(&query)
     .fetch_one(&*pool)
     .await
     .map_err(|e| {
@@ -0,0 +1,755 @@
+use super::Money;
+use base64::Engine;
+use printpdf::image_crate::{self, DynamicImage};
+use printpdf::path::PaintMode;
+use printpdf::{
+    Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point, Rect, Rgb,
+};
+use std::io::{BufWriter, Cursor};
+
+// Embedded so rendering never depends on the fonts installed on the machine
+const REGULAR_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans.ttf");
+const BOLD_FONT: &[u8] = include_bytes!("../../fonts/DejaVuSans-Bold.ttf");
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const ROW_HEIGHT: f32 = 6.0;
+/// Room kept under the last row of a page for the footer
+const BOTTOM_LIMIT: f32 = 25.0;
+/// Room the totals, amount in words and payment terms need on the last page
+const SUMMARY_HEIGHT: f32 = 75.0;
+
+/// Seller or buyer block of a printed document.
+#[derive(Debug, Default)]
+pub struct DocumentParty {
+    pub name: String,
+    pub company: Option<String>,
+    pub address: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub nif: Option<String>,
+    pub nis: Option<String>,
+    pub rc: Option<String>,
+    pub ai: Option<String>,
+    pub rib: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct InvoiceLine {
+    pub description: String,
+    /// Dimensions, coil reference, colours... depending on the product type
+    pub details: String,
+    /// Quantity with its unit, e.g. "1,250 T"
+    pub quantity: String,
+    pub unit_price: Money,
+    pub total: Money,
+}
+
+#[derive(Debug)]
+pub struct InvoiceDocument {
+    pub number: String,
+    pub date: String,
+    pub due_date: String,
+    pub payment_method: Option<String>,
+    pub notes: Option<String>,
+    pub currency: String,
+    /// Data URL or file path, as stored in settings.company_logo
+    pub logo: Option<String>,
+    pub company: DocumentParty,
+    pub client: DocumentParty,
+    pub lines: Vec<InvoiceLine>,
+    pub transportation_fee: Money,
+    pub total_ht: Money,
+    pub total_ttc: Money,
+    /// Shown next to the TVA line when every sale uses the same rate
+    pub tax_rate: Option<f64>,
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    regular_face: ttf_parser::Face<'static>,
+    bold_face: ttf_parser::Face<'static>,
+}
+
+impl Fonts {
+    /// Width of `text` in millimetres at `size` points.
+    fn width(&self, text: &str, size: f32, bold: bool) -> f32 {
+        let face = if bold { &self.bold_face } else { &self.regular_face };
+        let units: u32 = text
+            .chars()
+            .map(|c| {
+                face.glyph_index(c)
+                    .and_then(|id| face.glyph_hor_advance(id))
+                    .unwrap_or(face.units_per_em() / 2) as u32
+            })
+            .sum();
+        units as f32 / face.units_per_em() as f32 * size * 25.4 / 72.0
+    }
+
+    fn font(&self, bold: bool) -> &IndirectFontRef {
+        if bold { &self.bold } else { &self.regular }
+    }
+}
+
+struct Canvas<'a> {
+    doc: &'a PdfDocumentReference,
+    fonts: &'a Fonts,
+    pages: Vec<PdfLayerReference>,
+}
+
+impl Canvas<'_> {
+    fn layer(&self) -> &PdfLayerReference {
+        self.pages.last().expect("the document always has a first page")
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.pages.push(self.doc.get_page(page).get_layer(layer));
+    }
+
+    fn text(&self, text: &str, size: f32, x: f32, y: f32, bold: bool) {
+        self.layer().use_text(text, size, Mm(x), Mm(y), self.fonts.font(bold));
+    }
+
+    fn text_right(&self, text: &str, size: f32, right: f32, y: f32, bold: bool) {
+        let x = right - self.fonts.width(text, size, bold);
+        self.text(text, size, x, y, bold);
+    }
+
+    /// Shortens `text` with an ellipsis so it fits in `max_width` millimetres.
+    fn fit(&self, text: &str, size: f32, max_width: f32) -> String {
+        if self.fonts.width(text, size, false) <= max_width {
+            return text.to_string();
+        }
+        let mut fitted: String = text.to_string();
+        while !fitted.is_empty() && self.fonts.width(&format!("{}…", fitted), size, false) > max_width {
+            fitted.pop();
+        }
+        format!("{}…", fitted.trim_end())
+    }
+
+    /// Splits `text` into lines no wider than `max_width` millimetres.
+    fn wrap(&self, text: &str, size: f32, max_width: f32, bold: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut current = String::new();
+        for word in text.split_whitespace() {
+            let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+            if !current.is_empty() && self.fonts.width(&candidate, size, bold) > max_width {
+                lines.push(std::mem::replace(&mut current, word.to_string()));
+            } else {
+                current = candidate;
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+        lines
+    }
+
+    fn hline(&self, x1: f32, x2: f32, y: f32) {
+        self.layer().add_line(Line {
+            points: vec![(Point::new(Mm(x1), Mm(y)), false), (Point::new(Mm(x2), Mm(y)), false)],
+            is_closed: false,
+        });
+    }
+
+    fn fill_rect(&self, x1: f32, y1: f32, x2: f32, y2: f32, grey: f32) {
+        let layer = self.layer();
+        layer.set_fill_color(Color::Rgb(Rgb::new(grey, grey, grey, None)));
+        layer.add_rect(Rect::new(Mm(x1), Mm(y1), Mm(x2), Mm(y2)).with_mode(PaintMode::Fill));
+        layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    }
+}
+
+/// Formats an amount the French way: `1 234 567,89`.
+pub fn format_amount(amount: Money) -> String {
+    let centimes = amount.centimes().unsigned_abs();
+    let units = (centimes / 100).to_string();
+    let mut grouped = String::new();
+    for (i, digit) in units.chars().enumerate() {
+        if i > 0 && (units.len() - i).is_multiple_of(3) {
+            grouped.push('\u{a0}');
+        }
+        grouped.push(digit);
+    }
+    let sign = if amount.is_negative() { "-" } else { "" };
+    format!("{}{},{:02}", sign, grouped, centimes % 100)
+}
+
+/// Formats a plain quantity with a decimal comma, dropping useless zeros.
+pub fn format_quantity(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    let trimmed = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &formatted
+    };
+    trimmed.replace('.', ",")
+}
+
+/// `2024-06-10` or an RFC 3339 timestamp as `10/06/2024`.
+fn format_date(date: &str) -> String {
+    let day = date.get(..10).unwrap_or(date);
+    match chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+        Ok(parsed) => parsed.format("%d/%m/%Y").to_string(),
+        Err(_) => date.to_string(),
+    }
+}
+
+fn payment_method_label(method: &str) -> &str {
+    match method {
+        "cash" => "Espèces",
+        "bank_transfer" => "Virement bancaire",
+        "check" => "Chèque",
+        "traite" => "Traite",
+        "term" => "À terme",
+        "deferred" => "Différé",
+        "credit" => "Avoir client",
+        other => other,
+    }
+}
+
+const UNITS: [&str; 17] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+    "dix", "onze", "douze", "treize", "quatorze", "quinze", "seize",
+];
+const TENS: [&str; 7] = ["", "dix", "vingt", "trente", "quarante", "cinquante", "soixante"];
+
+fn below_hundred(n: u64) -> String {
+    match n {
+        0..=16 => UNITS[n as usize].to_string(),
+        17..=19 => format!("dix-{}", UNITS[(n - 10) as usize]),
+        20..=69 => {
+            let (ten, unit) = (n / 10, n % 10);
+            match unit {
+                0 => TENS[ten as usize].to_string(),
+                1 => format!("{} et un", TENS[ten as usize]),
+                _ => format!("{}-{}", TENS[ten as usize], UNITS[unit as usize]),
+            }
+        }
+        70..=79 => {
+            if n == 71 {
+                "soixante et onze".to_string()
+            } else {
+                format!("soixante-{}", below_hundred(n - 60))
+            }
+        }
+        80 => "quatre-vingts".to_string(),
+        _ => format!("quatre-vingt-{}", below_hundred(n - 80)),
+    }
+}
+
+/// `plural` is false in front of "mille", which freezes "vingts" and "cents".
+fn below_thousand(n: u64, plural: bool) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let mut words = match hundreds {
+        0 => String::new(),
+        1 => "cent".to_string(),
+        _ if rest == 0 && plural => format!("{} cents", UNITS[hundreds as usize]),
+        _ => format!("{} cent", UNITS[hundreds as usize]),
+    };
+    if rest > 0 {
+        if !words.is_empty() {
+            words.push(' ');
+        }
+        let tail = below_hundred(rest);
+        words.push_str(if rest == 80 && !plural { "quatre-vingt" } else { &tail });
+    }
+    words
+}
+
+fn integer_in_words(n: u64) -> String {
+    if n == 0 {
+        return UNITS[0].to_string();
+    }
+    let scales = [(1_000_000_000, "milliard"), (1_000_000, "million")];
+    let mut parts = Vec::new();
+    let mut rest = n;
+    for (scale, name) in scales {
+        let count = rest / scale;
+        if count > 0 {
+            let plural = if count > 1 { "s" } else { "" };
+            parts.push(format!("{} {}{}", integer_in_words(count), name, plural));
+            rest %= scale;
+        }
+    }
+    let thousands = rest / 1000;
+    if thousands == 1 {
+        parts.push("mille".to_string());
+    } else if thousands > 1 {
+        parts.push(format!("{} mille", below_thousand(thousands, false)));
+    }
+    if !rest.is_multiple_of(1000) {
+        parts.push(below_thousand(rest % 1000, true));
+    }
+    parts.join(" ")
+}
+
+/// Writes an amount out in French, e.g. "mille deux cent trente-quatre dinars
+/// algériens et cinquante centimes".
+pub fn amount_in_words(amount: Money, currency: &str) -> String {
+    let centimes = amount.centimes().unsigned_abs();
+    let (units, cents) = (centimes / 100, centimes % 100);
+    let currency_name = match currency {
+        "DZD" | "" => "dinars algériens",
+        "EUR" => "euros",
+        other => other,
+    };
+    // "un million de dinars", "deux milliards d'euros"
+    let linker = if units >= 1_000_000 && units.is_multiple_of(1_000_000) {
+        if currency_name.starts_with(['a', 'e', 'i', 'o', 'u']) { "d'" } else { "de " }
+    } else {
+        ""
+    };
+    let mut words = format!("{} {}{}", integer_in_words(units), linker, currency_name);
+    if units == 1 && currency_name.ends_with('s') {
+        // "un dinar algérien"
+        words = words.replace("dinars algériens", "dinar algérien").replace("euros", "euro");
+    }
+    if cents > 0 {
+        words.push_str(&format!(" et {} centime{}", integer_in_words(cents), if cents > 1 { "s" } else { "" }));
+    }
+    if amount.is_negative() {
+        words = format!("moins {}", words);
+    }
+    words
+}
+
+/// Decodes the logo stored in settings: a `data:image/...;base64,` URL or a file path.
+fn load_logo(logo: &str) -> Option<DynamicImage> {
+    let bytes = if let Some(data) = logo.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,")?;
+        base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?
+    } else {
+        std::fs::read(logo).ok()?
+    };
+    image_crate::load_from_memory(&bytes).ok()
+}
+
+fn party_lines(party: &DocumentParty) -> Vec<String> {
+    let mut lines = Vec::new();
+    let optional = [
+        (None, &party.company),
+        (None, &party.address),
+        (Some("Tél"), &party.phone),
+        (Some("Email"), &party.email),
+        (Some("RC"), &party.rc),
+        (Some("NIF"), &party.nif),
+        (Some("NIS"), &party.nis),
+        (Some("AI"), &party.ai),
+        (Some("RIB"), &party.rib),
+    ];
+    for (label, value) in optional {
+        let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        match label {
+            Some(label) => lines.push(format!("{} : {}", label, value)),
+            None => lines.push(value.to_string()),
+        }
+    }
+    lines
+}
+
+// Column right edges / left starts of the line items table
+const COL_DESCRIPTION: f32 = MARGIN + 2.0;
+const COL_DETAILS: f32 = 72.0;
+const COL_QUANTITY_RIGHT: f32 = 137.0;
+const COL_PRICE_RIGHT: f32 = 166.0;
+const COL_TOTAL_RIGHT: f32 = PAGE_WIDTH - MARGIN - 2.0;
+
+fn table_header(canvas: &Canvas, y: f32) -> f32 {
+    canvas.fill_rect(MARGIN, y - 2.0, PAGE_WIDTH - MARGIN, y + 4.5, 0.9);
+    canvas.text("Désignation", 9.0, COL_DESCRIPTION, y, true);
+    canvas.text("Détails", 9.0, COL_DETAILS, y, true);
+    canvas.text_right("Quantité", 9.0, COL_QUANTITY_RIGHT, y, true);
+    canvas.text_right("P.U. HT", 9.0, COL_PRICE_RIGHT, y, true);
+    canvas.text_right("Montant HT", 9.0, COL_TOTAL_RIGHT, y, true);
+    y - ROW_HEIGHT - 1.0
+}
+
+fn load_fonts(doc: &PdfDocumentReference) -> Result<Fonts, String> {
+    Ok(Fonts {
+        regular: doc.add_external_font(Cursor::new(REGULAR_FONT)).map_err(|e| e.to_string())?,
+        bold: doc.add_external_font(Cursor::new(BOLD_FONT)).map_err(|e| e.to_string())?,
+        regular_face: ttf_parser::Face::parse(REGULAR_FONT, 0).map_err(|e| e.to_string())?,
+        bold_face: ttf_parser::Face::parse(BOLD_FONT, 0).map_err(|e| e.to_string())?,
+    })
+}
+
+/// Draws the company block and logo at the top of the first page, returning
+/// the height where the document title can start.
+fn company_header(canvas: &Canvas, company: &DocumentParty, logo: Option<&str>) -> f32 {
+    let right = PAGE_WIDTH - MARGIN;
+    let mut y = PAGE_HEIGHT - MARGIN - 4.0;
+    canvas.text(&company.name, 13.0, MARGIN, y, true);
+    for line in party_lines(company) {
+        y -= 4.5;
+        canvas.text(&canvas.fit(&line, 8.5, 110.0), 8.5, MARGIN, y, false);
+    }
+    if let Some(logo) = logo.filter(|l| !l.trim().is_empty()) {
+        match load_logo(logo) {
+            Some(image) => {
+                // Fit the logo in a 45 x 25 mm box
+                let (width_px, height_px) = (image.width().max(1) as f32, image.height().max(1) as f32);
+                let scale = (45.0 / width_px).min(25.0 / height_px);
+                let dpi = 25.4 / scale;
+                let image = DynamicImage::ImageRgb8(image.to_rgb8());
+                Image::from_dynamic_image(&image).add_to_layer(canvas.layer().clone(), ImageTransform {
+                    translate_x: Some(Mm(right - width_px * scale)),
+                    translate_y: Some(Mm(PAGE_HEIGHT - MARGIN - height_px * scale)),
+                    dpi: Some(dpi),
+                    ..Default::default()
+                });
+            }
+            None => log::warn!("[company_header] Could not decode the company logo, rendering without it"),
+        }
+    }
+    let y = y.min(PAGE_HEIGHT - MARGIN - 30.0) - 12.0;
+    canvas.hline(MARGIN, right, y + 6.0);
+    y
+}
+
+/// Draws the client block on the right, returning its lowest baseline.
+fn client_block(canvas: &Canvas, client: &DocumentParty, y: f32) -> f32 {
+    let right = PAGE_WIDTH - MARGIN;
+    let client_x = 115.0;
+    let mut client_y = y - 2.0;
+    canvas.text("Client", 9.0, client_x, client_y, true);
+    client_y -= 5.0;
+    canvas.text(&canvas.fit(&client.name, 10.0, right - client_x), 10.0, client_x, client_y, true);
+    for line in party_lines(client) {
+        client_y -= 4.5;
+        canvas.text(&canvas.fit(&line, 8.5, right - client_x), 8.5, client_x, client_y, false);
+    }
+    client_y
+}
+
+/// Adds the company contact line and "Page i / n" at the bottom of every page.
+fn page_footers(canvas: &Canvas, company: &DocumentParty) {
+    let right = PAGE_WIDTH - MARGIN;
+    let page_count = canvas.pages.len();
+    let contact: Vec<&str> = [Some(company.name.as_str()), company.phone.as_deref(), company.email.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.trim().is_empty())
+        .collect();
+    let contact = contact.join(" · ");
+    for (i, layer) in canvas.pages.iter().enumerate() {
+        layer.add_line(Line {
+            points: vec![(Point::new(Mm(MARGIN), Mm(16.0)), false), (Point::new(Mm(right), Mm(16.0)), false)],
+            is_closed: false,
+        });
+        layer.use_text(&contact, 7.5, Mm(MARGIN), Mm(11.0), &canvas.fonts.regular);
+        let page_label = format!("Page {} / {}", i + 1, page_count);
+        let x = right - canvas.fonts.width(&page_label, 7.5, false);
+        layer.use_text(&page_label, 7.5, Mm(x), Mm(11.0), &canvas.fonts.regular);
+    }
+}
+
+fn save(doc: PdfDocumentReference) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    doc.save(&mut BufWriter::new(&mut bytes)).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Renders an invoice to PDF bytes.
+pub fn render_invoice(invoice: &InvoiceDocument) -> Result<Vec<u8>, String> {
+    let title = format!("Facture {}", invoice.number);
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let fonts = load_fonts(&doc)?;
+    let mut canvas = Canvas {
+        doc: &doc,
+        fonts: &fonts,
+        pages: vec![doc.get_page(page).get_layer(layer)],
+    };
+    let right = PAGE_WIDTH - MARGIN;
+
+    // Invoice title and dates
+    let mut y = company_header(&canvas, &invoice.company, invoice.logo.as_deref());
+    canvas.text(&format!("FACTURE N° {}", invoice.number), 15.0, MARGIN, y - 2.0, true);
+    canvas.text(&format!("Date : {}", format_date(&invoice.date)), 9.0, MARGIN, y - 9.0, false);
+    canvas.text(&format!("Échéance : {}", format_date(&invoice.due_date)), 9.0, MARGIN, y - 14.0, false);
+    let client_y = client_block(&canvas, &invoice.client, y);
+    y = client_y.min(y - 14.0) - 12.0;
+
+    // Line items
+    y = table_header(&canvas, y);
+    for line in &invoice.lines {
+        if y < BOTTOM_LIMIT {
+            canvas.new_page();
+            y = table_header(&canvas, PAGE_HEIGHT - MARGIN - 5.0);
+        }
+        canvas.text(&canvas.fit(&line.description, 8.5, COL_DETAILS - COL_DESCRIPTION - 2.0), 8.5, COL_DESCRIPTION, y, false);
+        canvas.text(&canvas.fit(&line.details, 8.0, COL_QUANTITY_RIGHT - 22.0 - COL_DETAILS), 8.0, COL_DETAILS, y, false);
+        canvas.text_right(&line.quantity, 8.5, COL_QUANTITY_RIGHT, y, false);
+        canvas.text_right(&format_amount(line.unit_price), 8.5, COL_PRICE_RIGHT, y, false);
+        canvas.text_right(&format_amount(line.total), 8.5, COL_TOTAL_RIGHT, y, false);
+        canvas.hline(MARGIN, right, y - 2.0);
+        y -= ROW_HEIGHT;
+    }
+    if y - SUMMARY_HEIGHT < BOTTOM_LIMIT {
+        canvas.new_page();
+        y = PAGE_HEIGHT - MARGIN - 5.0;
+    }
+
+    // Totals
+    y -= 4.0;
+    let label_x = 120.0;
+    let tva = invoice.total_ttc - invoice.total_ht;
+    let tva_label = match invoice.tax_rate {
+        Some(rate) => format!("TVA ({} %)", format_quantity(rate * 100.0, 2)),
+        None => "TVA".to_string(),
+    };
+    let mut totals = Vec::new();
+    if invoice.transportation_fee.is_positive() {
+        totals.push(("Transport".to_string(), invoice.transportation_fee, false));
+    }
+    totals.push(("Total HT".to_string(), invoice.total_ht, false));
+    totals.push((tva_label, tva, false));
+    totals.push((format!("Total TTC ({})", invoice.currency), invoice.total_ttc, true));
+    for (label, amount, bold) in totals {
+        if bold {
+            canvas.fill_rect(label_x - 2.0, y - 2.0, right, y + 4.5, 0.9);
+        }
+        canvas.text(&label, 9.5, label_x, y, bold);
+        canvas.text_right(&format_amount(amount), 9.5, COL_TOTAL_RIGHT, y, bold);
+        y -= ROW_HEIGHT;
+    }
+
+    // Amount in words
+    y -= 6.0;
+    let words = amount_in_words(invoice.total_ttc, &invoice.currency);
+    let sentence = format!("Arrêtée la présente facture à la somme de : {}.", words);
+    for line in canvas.wrap(&sentence, 9.0, right - MARGIN, false) {
+        canvas.text(&line, 9.0, MARGIN, y, false);
+        y -= 4.5;
+    }
+
+    // Payment terms
+    y -= 4.0;
+    canvas.text("Conditions de paiement", 9.5, MARGIN, y, true);
+    y -= 5.0;
+    if let Some(method) = invoice.payment_method.as_deref().filter(|m| !m.is_empty()) {
+        canvas.text(&format!("Mode de paiement : {}", payment_method_label(method)), 9.0, MARGIN, y, false);
+        y -= 4.5;
+    }
+    canvas.text(&format!("Payable au plus tard le {}", format_date(&invoice.due_date)), 9.0, MARGIN, y, false);
+    y -= 4.5;
+    if let Some(rib) = invoice.company.rib.as_deref().filter(|r| !r.trim().is_empty()) {
+        canvas.text(&format!("Par virement sur le RIB : {}", rib.trim()), 9.0, MARGIN, y, false);
+        y -= 4.5;
+    }
+    if let Some(notes) = invoice.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        y -= 2.0;
+        for line in canvas.wrap(notes, 8.5, right - MARGIN, false).into_iter().take(3) {
+            canvas.text(&line, 8.5, MARGIN, y, false);
+            y -= 4.0;
+        }
+    }
+
+    page_footers(&canvas, &invoice.company);
+    save(doc)
+}
+
+#[derive(Debug)]
+pub struct StatementLine {
+    pub date: String,
+    pub label: String,
+    pub reference: String,
+    pub debit: Money,
+    pub credit: Money,
+    pub balance: Money,
+}
+
+#[derive(Debug)]
+pub struct StatementDocument {
+    pub currency: String,
+    pub logo: Option<String>,
+    pub company: DocumentParty,
+    pub client: DocumentParty,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub opening_balance: Money,
+    pub lines: Vec<StatementLine>,
+    pub closing_balance: Money,
+}
+
+const COL_STATEMENT_LABEL: f32 = MARGIN + 22.0;
+const COL_STATEMENT_REFERENCE: f32 = 92.0;
+const COL_STATEMENT_DEBIT_RIGHT: f32 = 143.0;
+const COL_STATEMENT_CREDIT_RIGHT: f32 = 169.0;
+
+fn statement_header(canvas: &Canvas, y: f32) -> f32 {
+    canvas.fill_rect(MARGIN, y - 2.0, PAGE_WIDTH - MARGIN, y + 4.5, 0.9);
+    canvas.text("Date", 9.0, COL_DESCRIPTION, y, true);
+    canvas.text("Libellé", 9.0, COL_STATEMENT_LABEL, y, true);
+    canvas.text("Référence", 9.0, COL_STATEMENT_REFERENCE, y, true);
+    canvas.text_right("Débit", 9.0, COL_STATEMENT_DEBIT_RIGHT, y, true);
+    canvas.text_right("Crédit", 9.0, COL_STATEMENT_CREDIT_RIGHT, y, true);
+    canvas.text_right("Solde", 9.0, COL_TOTAL_RIGHT, y, true);
+    y - ROW_HEIGHT - 1.0
+}
+
+fn amount_or_blank(amount: Money) -> String {
+    if amount.is_zero() { String::new() } else { format_amount(amount) }
+}
+
+/// Renders a client account statement to PDF bytes.
+pub fn render_statement(statement: &StatementDocument) -> Result<Vec<u8>, String> {
+    let title = format!("Relevé de compte {}", statement.client.name);
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let fonts = load_fonts(&doc)?;
+    let mut canvas = Canvas {
+        doc: &doc,
+        fonts: &fonts,
+        pages: vec![doc.get_page(page).get_layer(layer)],
+    };
+    let right = PAGE_WIDTH - MARGIN;
+
+    let mut y = company_header(&canvas, &statement.company, statement.logo.as_deref());
+    canvas.text("RELEVÉ DE COMPTE", 15.0, MARGIN, y - 2.0, true);
+    let period = match (statement.from.as_deref(), statement.to.as_deref()) {
+        (Some(from), Some(to)) => format!("Du {} au {}", format_date(from), format_date(to)),
+        (Some(from), None) => format!("Depuis le {}", format_date(from)),
+        (None, Some(to)) => format!("Jusqu'au {}", format_date(to)),
+        (None, None) => "Historique complet".to_string(),
+    };
+    canvas.text(&period, 9.0, MARGIN, y - 9.0, false);
+    canvas.text(&format!("Devise : {}", statement.currency), 9.0, MARGIN, y - 14.0, false);
+    let client_y = client_block(&canvas, &statement.client, y);
+    y = client_y.min(y - 14.0) - 12.0;
+
+    y = statement_header(&canvas, y);
+    canvas.text("Solde d'ouverture", 8.5, COL_STATEMENT_LABEL, y, true);
+    canvas.text_right(&format_amount(statement.opening_balance), 8.5, COL_TOTAL_RIGHT, y, true);
+    canvas.hline(MARGIN, right, y - 2.0);
+    y -= ROW_HEIGHT;
+    for line in &statement.lines {
+        if y < BOTTOM_LIMIT {
+            canvas.new_page();
+            y = statement_header(&canvas, PAGE_HEIGHT - MARGIN - 5.0);
+        }
+        canvas.text(&format_date(&line.date), 8.0, COL_DESCRIPTION, y, false);
+        canvas.text(&canvas.fit(&line.label, 8.0, COL_STATEMENT_REFERENCE - COL_STATEMENT_LABEL - 2.0), 8.0, COL_STATEMENT_LABEL, y, false);
+        canvas.text(&canvas.fit(&line.reference, 8.0, COL_STATEMENT_DEBIT_RIGHT - 24.0 - COL_STATEMENT_REFERENCE), 8.0, COL_STATEMENT_REFERENCE, y, false);
+        canvas.text_right(&amount_or_blank(line.debit), 8.0, COL_STATEMENT_DEBIT_RIGHT, y, false);
+        canvas.text_right(&amount_or_blank(line.credit), 8.0, COL_STATEMENT_CREDIT_RIGHT, y, false);
+        canvas.text_right(&format_amount(line.balance), 8.0, COL_TOTAL_RIGHT, y, false);
+        canvas.hline(MARGIN, right, y - 2.0);
+        y -= ROW_HEIGHT;
+    }
+    if y < BOTTOM_LIMIT + ROW_HEIGHT {
+        canvas.new_page();
+        y = PAGE_HEIGHT - MARGIN - 5.0;
+    }
+    y -= 2.0;
+    canvas.fill_rect(110.0, y - 2.0, right, y + 4.5, 0.9);
+    canvas.text("Solde de clôture", 9.5, 112.0, y, true);
+    canvas.text_right(&format_amount(statement.closing_balance), 9.5, COL_TOTAL_RIGHT, y, true);
+
+    page_footers(&canvas, &statement.company);
+    save(doc)
+}
+
+#[derive(Debug)]
+pub struct DeliveryLine {
+    pub description: String,
+    pub details: String,
+    /// What the sale is for, with its unit, e.g. "12,500 T"
+    pub ordered: String,
+    /// What this delivery note carries
+    pub delivered: String,
+    /// What is still to deliver once this delivery note is in
+    pub remaining: String,
+}
+
+#[derive(Debug)]
+pub struct DeliveryNoteDocument {
+    pub number: String,
+    pub date: String,
+    pub truck: Option<String>,
+    pub driver: Option<String>,
+    pub plate: Option<String>,
+    pub notes: Option<String>,
+    pub logo: Option<String>,
+    pub company: DocumentParty,
+    pub client: DocumentParty,
+    pub lines: Vec<DeliveryLine>,
+}
+
+const COL_DELIVERY_ORDERED_RIGHT: f32 = 137.0;
+const COL_DELIVERY_DELIVERED_RIGHT: f32 = 164.0;
+
+fn delivery_header(canvas: &Canvas, y: f32) -> f32 {
+    canvas.fill_rect(MARGIN, y - 2.0, PAGE_WIDTH - MARGIN, y + 4.5, 0.9);
+    canvas.text("Désignation", 9.0, COL_DESCRIPTION, y, true);
+    canvas.text("Détails", 9.0, COL_DETAILS, y, true);
+    canvas.text_right("Commandé", 9.0, COL_DELIVERY_ORDERED_RIGHT, y, true);
+    canvas.text_right("Livré", 9.0, COL_DELIVERY_DELIVERED_RIGHT, y, true);
+    canvas.text_right("Reste", 9.0, COL_TOTAL_RIGHT, y, true);
+    y - ROW_HEIGHT - 1.0
+}
+
+/// Renders a delivery note (bon de livraison) to PDF bytes. It carries no
+/// prices: the invoice does.
+pub fn render_delivery_note(note: &DeliveryNoteDocument) -> Result<Vec<u8>, String> {
+    let title = format!("Bon de livraison {}", note.number);
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let fonts = load_fonts(&doc)?;
+    let mut canvas = Canvas {
+        doc: &doc,
+        fonts: &fonts,
+        pages: vec![doc.get_page(page).get_layer(layer)],
+    };
+    let right = PAGE_WIDTH - MARGIN;
+
+    let mut y = company_header(&canvas, &note.company, note.logo.as_deref());
+    canvas.text(&format!("BON DE LIVRAISON N° {}", note.number), 15.0, MARGIN, y - 2.0, true);
+    canvas.text(&format!("Date : {}", format_date(&note.date)), 9.0, MARGIN, y - 9.0, false);
+    let mut transport_y = y - 9.0;
+    for (label, value) in [("Camion", &note.truck), ("Chauffeur", &note.driver), ("Matricule", &note.plate)] {
+        if let Some(value) = value.as_deref().filter(|v| !v.trim().is_empty()) {
+            transport_y -= 5.0;
+            canvas.text(&canvas.fit(&format!("{} : {}", label, value.trim()), 9.0, 95.0), 9.0, MARGIN, transport_y, false);
+        }
+    }
+    let client_y = client_block(&canvas, &note.client, y);
+    y = client_y.min(transport_y) - 12.0;
+
+    y = delivery_header(&canvas, y);
+    for line in &note.lines {
+        if y < BOTTOM_LIMIT {
+            canvas.new_page();
+            y = delivery_header(&canvas, PAGE_HEIGHT - MARGIN - 5.0);
+        }
+        canvas.text(&canvas.fit(&line.description, 8.5, COL_DETAILS - COL_DESCRIPTION - 2.0), 8.5, COL_DESCRIPTION, y, false);
+        canvas.text(&canvas.fit(&line.details, 8.0, COL_DELIVERY_ORDERED_RIGHT - 22.0 - COL_DETAILS), 8.0, COL_DETAILS, y, false);
+        canvas.text_right(&line.ordered, 8.5, COL_DELIVERY_ORDERED_RIGHT, y, false);
+        canvas.text_right(&line.delivered, 8.5, COL_DELIVERY_DELIVERED_RIGHT, y, true);
+        canvas.text_right(&line.remaining, 8.5, COL_TOTAL_RIGHT, y, false);
+        canvas.hline(MARGIN, right, y - 2.0);
+        y -= ROW_HEIGHT;
+    }
+    // Notes and the two signature boxes stay together
+    if y - 45.0 < BOTTOM_LIMIT {
+        canvas.new_page();
+        y = PAGE_HEIGHT - MARGIN - 5.0;
+    }
+    if let Some(notes) = note.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        y -= 2.0;
+        for line in canvas.wrap(notes, 8.5, right - MARGIN, false).into_iter().take(3) {
+            canvas.text(&line, 8.5, MARGIN, y, false);
+            y -= 4.0;
+        }
+    }
+    y -= 8.0;
+    canvas.text("Visa de l'expéditeur", 9.5, MARGIN, y, true);
+    canvas.text("Reçu par le client (nom, date et signature)", 9.5, 110.0, y, true);
+
+    page_footers(&canvas, &note.company);
+    save(doc)
+}
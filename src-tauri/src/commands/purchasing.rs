@@ -0,0 +1,1386 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+use chrono::Utc;
+
+use super::{
+    apply_list_filter, compute_sale_totals, document_period, insert_audit_log, insert_coil, with_payment_progress, Coil, ListColumns,
+    ListFilter, Money, ReceiveCoilRequest,
+};
+
+/// What we still owe the supplier aliased `sup`: its non-deleted invoices less
+/// the non-deleted payments made to it. Negative when we paid in advance.
+const SUPPLIER_BALANCE_SQL: &str = r#"(
+          IFNULL((SELECT SUM(sinv.total_amount_ttc) FROM supplier_invoices sinv WHERE sinv.supplier_id = sup.id AND sinv.is_deleted = 0), 0) -
+          IFNULL((SELECT SUM(sp.amount) FROM supplier_payments sp WHERE sp.supplier_id = sup.id AND sp.is_deleted = 0), 0))"#;
+
+/// Non-deleted payments made on the supplier invoice aliased `sinv`.
+const SUPPLIER_INVOICE_AMOUNT_PAID_SQL: &str = "IFNULL((SELECT SUM(sp.amount) FROM supplier_payments sp WHERE sp.supplier_invoice_id = sinv.id AND sp.is_deleted = 0), 0)";
+
+/// open, partial or received, from how many of the order's lines have a coil.
+const PURCHASE_ORDER_RECEIPT_STATUS_SQL: &str = r#"(
+          SELECT CASE
+            WHEN COUNT(poi.coil_id) = 0 THEN 'open'
+            WHEN COUNT(poi.coil_id) < COUNT(*) THEN 'partial'
+            ELSE 'received'
+          END
+          FROM purchase_order_items poi WHERE poi.purchase_order_id = po.id)"#;
+
+fn suppliers_with_balance() -> String {
+    format!("(SELECT sup.*, {} AS balance_due FROM suppliers sup)", SUPPLIER_BALANCE_SQL)
+}
+
+fn purchase_orders_with_receipt_status() -> String {
+    format!("(SELECT po.*, {} AS receipt_status FROM purchase_orders po)", PURCHASE_ORDER_RECEIPT_STATUS_SQL)
+}
+
+/// `supplier_invoices` with their payment progress columns.
+pub(super) fn supplier_invoices_with_payment_progress() -> String {
+    with_payment_progress("supplier_invoices", "sinv", SUPPLIER_INVOICE_AMOUNT_PAID_SQL, "sinv.total_amount_ttc")
+}
+
+/// Used with `suppliers_with_balance() sup`.
+const SUPPLIER_LIST: ListColumns = ListColumns {
+    date: "sup.created_at",
+    client_id: None,
+    supplier_id: Some("sup.id"),
+    amount: Some("sup.balance_due"),
+    search: &["sup.name LIKE ?", "sup.company LIKE ?", "sup.nif LIKE ?"],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("name", "sup.name"),
+        ("company", "sup.company"),
+        ("created_at", "sup.created_at"),
+        ("balance_due", "sup.balance_due"),
+    ],
+    default_sort: ("name", "ASC"),
+    tie_breaker: "sup.id",
+};
+
+/// Used with `purchase_orders_with_receipt_status() po` and `suppliers sup`.
+const PURCHASE_ORDER_LIST: ListColumns = ListColumns {
+    date: "po.date",
+    client_id: None,
+    supplier_id: Some("po.supplier_id"),
+    amount: Some("po.total_amount_ttc"),
+    search: &[
+        "sup.name LIKE ?",
+        "sup.company LIKE ?",
+        "po.reference LIKE ?",
+        "EXISTS (SELECT 1 FROM purchase_order_items poi WHERE poi.purchase_order_id = po.id AND poi.coil_ref LIKE ?)",
+    ],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("date", "po.date"),
+        ("reference", "po.reference"),
+        ("supplier_name", "sup.name"),
+        ("total_amount_ttc", "po.total_amount_ttc"),
+        ("receipt_status", "po.receipt_status"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "po.id",
+};
+
+/// Used with `supplier_invoices_with_payment_progress() sinv` and `suppliers sup`.
+const SUPPLIER_INVOICE_LIST: ListColumns = ListColumns {
+    date: "sinv.date",
+    client_id: None,
+    supplier_id: Some("sinv.supplier_id"),
+    amount: Some("sinv.total_amount_ttc"),
+    search: &["sup.name LIKE ?", "sup.company LIKE ?", "sinv.invoice_number LIKE ?"],
+    payment_status: Some("sinv.payment_status"),
+    is_invoiced: None,
+    sorts: &[
+        ("date", "sinv.date"),
+        ("due_date", "sinv.due_date"),
+        ("invoice_number", "sinv.invoice_number"),
+        ("supplier_name", "sup.name"),
+        ("total_amount_ttc", "sinv.total_amount_ttc"),
+        ("balance_due", "sinv.balance_due"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "sinv.id",
+};
+
+/// Used with `supplier_payments sp`, `suppliers sup` and `supplier_invoices sinv`.
+const SUPPLIER_PAYMENT_LIST: ListColumns = ListColumns {
+    date: "sp.date",
+    client_id: None,
+    supplier_id: Some("sp.supplier_id"),
+    amount: Some("sp.amount"),
+    search: &[
+        "sup.name LIKE ?",
+        "sup.company LIKE ?",
+        "sinv.invoice_number LIKE ?",
+        "sp.check_number LIKE ?",
+    ],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("date", "sp.date"),
+        ("amount", "sp.amount"),
+        ("supplier_name", "sup.name"),
+        ("method", "sp.method"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "sp.id",
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Supplier {
+    pub id: String,
+    pub name: String,
+    pub company: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+    pub nif: Option<String>,
+    pub nis: Option<String>,
+    pub rc: Option<String>,
+    pub ai: Option<String>,
+    pub rib: Option<String>,
+    /// What we still owe: invoiced less paid, negative when paid in advance
+    pub balance_due: Money,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSupplierRequest {
+    pub name: String,
+    pub company: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+    pub nif: Option<String>,
+    pub nis: Option<String>,
+    pub rc: Option<String>,
+    pub ai: Option<String>,
+    pub rib: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSupplierRequest {
+    pub name: Option<String>,
+    pub company: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+    pub nif: Option<String>,
+    pub nis: Option<String>,
+    pub rc: Option<String>,
+    pub ai: Option<String>,
+    pub rib: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedSuppliersResult {
+    pub rows: Vec<Supplier>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderItem {
+    pub id: String,
+    pub purchase_order_id: String,
+    pub description: String,
+    pub coil_ref: Option<String>,
+    pub thickness: Option<f64>,
+    pub width: Option<f64>,
+    pub top_coat_ral: Option<String>,
+    pub back_coat_ral: Option<String>,
+    /// Tons ordered
+    pub weight: f64,
+    pub price_per_ton: Money,
+    pub total_amount: Money,
+    /// The coil received for this line, if any
+    pub coil_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    pub id: String,
+    pub supplier_id: String,
+    pub reference: Option<String>,
+    pub date: String,
+    pub total_amount: Money,
+    pub total_amount_ttc: Money,
+    pub tax_rate: f64,
+    pub notes: Option<String>,
+    /// open, partial or received
+    pub receipt_status: String,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub is_deleted: Option<bool>,
+    pub deleted_at: Option<String>,
+    pub items: Vec<PurchaseOrderItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePurchaseOrderItemRequest {
+    pub description: String,
+    pub coil_ref: Option<String>,
+    pub thickness: Option<f64>,
+    pub width: Option<f64>,
+    pub top_coat_ral: Option<String>,
+    pub back_coat_ral: Option<String>,
+    pub weight: f64,
+    pub price_per_ton: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePurchaseOrderRequest {
+    pub supplier_id: String,
+    pub reference: Option<String>,
+    pub date: String,
+    pub tax_rate: f64,
+    pub notes: Option<String>,
+    pub items: Vec<CreatePurchaseOrderItemRequest>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedPurchaseOrdersResult {
+    pub rows: Vec<PurchaseOrder>,
+    pub total: i64,
+}
+
+/// A coil arriving for a purchase order line. The line gives the coil its
+/// supplier, dimensions, colours and cost per ton.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceivePurchaseOrderItemRequest {
+    /// Defaults to the line's coil_ref
+    pub coil_ref: Option<String>,
+    pub received_at: String,
+    pub gross_weight: f64,
+    pub net_weight: f64,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierInvoice {
+    pub id: String,
+    pub supplier_id: String,
+    pub purchase_order_id: Option<String>,
+    /// The number printed on the supplier's invoice
+    pub invoice_number: String,
+    pub date: String,
+    pub due_date: Option<String>,
+    pub total_amount_ht: Money,
+    pub total_amount_ttc: Money,
+    pub notes: Option<String>,
+    pub amount_paid: Money,
+    pub balance_due: Money,
+    /// unpaid, partial, paid or overpaid
+    pub payment_status: String,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub is_deleted: Option<bool>,
+    pub deleted_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSupplierInvoiceRequest {
+    pub supplier_id: String,
+    pub purchase_order_id: Option<String>,
+    pub invoice_number: String,
+    pub date: String,
+    pub due_date: Option<String>,
+    pub total_amount_ht: Money,
+    pub total_amount_ttc: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedSupplierInvoicesResult {
+    pub rows: Vec<SupplierInvoice>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierPayment {
+    pub id: String,
+    pub supplier_id: String,
+    pub supplier_invoice_id: Option<String>,
+    pub amount: Money,
+    pub date: String,
+    pub method: String,
+    pub check_number: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub is_deleted: Option<bool>,
+    pub deleted_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSupplierPaymentRequest {
+    pub supplier_id: String,
+    pub supplier_invoice_id: Option<String>,
+    pub amount: Money,
+    pub date: String,
+    pub method: String,
+    pub check_number: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedSupplierPaymentsResult {
+    pub rows: Vec<SupplierPayment>,
+    pub total: i64,
+}
+
+fn supplier_from_row(row: &sqlx::sqlite::SqliteRow) -> Supplier {
+    Supplier {
+        id: row.get("id"),
+        name: row.get("name"),
+        company: row.get("company"),
+        email: row.get("email"),
+        phone: row.get("phone"),
+        address: row.get("address"),
+        notes: row.get("notes"),
+        nif: row.get("nif"),
+        nis: row.get("nis"),
+        rc: row.get("rc"),
+        ai: row.get("ai"),
+        rib: row.get("rib"),
+        balance_due: row.get("balance_due"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn purchase_order_item_from_row(row: &sqlx::sqlite::SqliteRow) -> PurchaseOrderItem {
+    PurchaseOrderItem {
+        id: row.get("id"),
+        purchase_order_id: row.get("purchase_order_id"),
+        description: row.get("description"),
+        coil_ref: row.get("coil_ref"),
+        thickness: row.get("thickness"),
+        width: row.get("width"),
+        top_coat_ral: row.get("top_coat_ral"),
+        back_coat_ral: row.get("back_coat_ral"),
+        weight: row.get("weight"),
+        price_per_ton: row.get("price_per_ton"),
+        total_amount: row.get("total_amount"),
+        coil_id: row.get("coil_id"),
+    }
+}
+
+fn purchase_order_from_row(row: &sqlx::sqlite::SqliteRow, items: Vec<PurchaseOrderItem>) -> PurchaseOrder {
+    PurchaseOrder {
+        id: row.get("id"),
+        supplier_id: row.get("supplier_id"),
+        reference: row.get("reference"),
+        date: row.get("date"),
+        total_amount: row.get("total_amount"),
+        total_amount_ttc: row.get("total_amount_ttc"),
+        tax_rate: row.get("tax_rate"),
+        notes: row.get("notes"),
+        receipt_status: row.get("receipt_status"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+        items,
+    }
+}
+
+fn supplier_invoice_from_row(row: &sqlx::sqlite::SqliteRow) -> SupplierInvoice {
+    SupplierInvoice {
+        id: row.get("id"),
+        supplier_id: row.get("supplier_id"),
+        purchase_order_id: row.get("purchase_order_id"),
+        invoice_number: row.get("invoice_number"),
+        date: row.get("date"),
+        due_date: row.get("due_date"),
+        total_amount_ht: row.get("total_amount_ht"),
+        total_amount_ttc: row.get("total_amount_ttc"),
+        notes: row.get("notes"),
+        amount_paid: row.get("amount_paid"),
+        balance_due: row.get("balance_due"),
+        payment_status: row.get("payment_status"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+fn supplier_payment_from_row(row: &sqlx::sqlite::SqliteRow) -> SupplierPayment {
+    SupplierPayment {
+        id: row.get("id"),
+        supplier_id: row.get("supplier_id"),
+        supplier_invoice_id: row.get("supplier_invoice_id"),
+        amount: row.get("amount"),
+        date: row.get("date"),
+        method: row.get("method"),
+        check_number: row.get("check_number"),
+        notes: row.get("notes"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Fails unless the supplier exists and is not deleted.
+async fn ensure_active_supplier(conn: &mut sqlx::SqliteConnection, supplier_id: &str) -> Result<(), String> {
+    let active: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM suppliers WHERE id = ? AND is_deleted = 0")
+        .bind(supplier_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if active == 0 {
+        return Err("Supplier not found".to_string());
+    }
+    Ok(())
+}
+
+async fn fetch_supplier(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Option<Supplier>, String> {
+    let query = format!("SELECT * FROM {} sup WHERE sup.id = ?", suppliers_with_balance());
+    let row = sqlx::query(&query)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| supplier_from_row(&row)))
+}
+
+async fn fetch_purchase_order_items(conn: &mut sqlx::SqliteConnection, purchase_order_id: &str) -> Result<Vec<PurchaseOrderItem>, String> {
+    let rows = sqlx::query("SELECT * FROM purchase_order_items WHERE purchase_order_id = ? ORDER BY rowid")
+        .bind(purchase_order_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(purchase_order_item_from_row).collect())
+}
+
+async fn fetch_purchase_order(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Option<PurchaseOrder>, String> {
+    let query = format!("SELECT * FROM {} po WHERE po.id = ?", purchase_orders_with_receipt_status());
+    let row = sqlx::query(&query)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(row) => {
+            let items = fetch_purchase_order_items(conn, id).await?;
+            Ok(Some(purchase_order_from_row(&row, items)))
+        }
+        None => Ok(None),
+    }
+}
+
+async fn fetch_supplier_invoice(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Option<SupplierInvoice>, String> {
+    let query = format!("SELECT * FROM {} sinv WHERE sinv.id = ?", supplier_invoices_with_payment_progress());
+    let row = sqlx::query(&query)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| supplier_invoice_from_row(&row)))
+}
+
+async fn fetch_supplier_payment(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Option<SupplierPayment>, String> {
+    let row = sqlx::query("SELECT * FROM supplier_payments WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|row| supplier_payment_from_row(&row)))
+}
+
+#[tauri::command]
+pub async fn get_suppliers(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedSuppliersResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE sup.is_deleted = 0");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &SUPPLIER_LIST, &mut where_clause, &mut params)?;
+    let from = format!("FROM {} sup {}", suppliers_with_balance(), where_clause);
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT sup.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(PaginatedSuppliersResult { rows: rows.iter().map(supplier_from_row).collect(), total })
+}
+
+#[tauri::command]
+pub async fn get_supplier_by_id(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Option<Supplier>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_supplier(&mut conn, &id).await
+}
+
+#[tauri::command]
+pub async fn create_supplier(
+    supplier: CreateSupplierRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Supplier, String> {
+    if supplier.name.trim().is_empty() {
+        return Err("Supplier name is required".to_string());
+    }
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query(
+        r#"
+        INSERT INTO suppliers (
+            id, name, company, email, phone, address, notes, nif, nis, rc, ai, rib,
+            created_at, updated_at, is_deleted
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+        "#
+    )
+    .bind(&id)
+    .bind(supplier.name.trim())
+    .bind(&supplier.company)
+    .bind(&supplier.email)
+    .bind(&supplier.phone)
+    .bind(&supplier.address)
+    .bind(&supplier.notes)
+    .bind(&supplier.nif)
+    .bind(&supplier.nis)
+    .bind(&supplier.rc)
+    .bind(&supplier.ai)
+    .bind(&supplier.rib)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "create", "supplier", &id, None, Some("Supplier created")).await?;
+    let created = fetch_supplier(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve created supplier".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_supplier(
+    id: String,
+    supplier: UpdateSupplierRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Supplier, String> {
+    let now = Utc::now().to_rfc3339();
+    let mut query = String::from("UPDATE suppliers SET updated_at = ?");
+    let mut bind_values: Vec<String> = Vec::new();
+
+    if let Some(ref name) = supplier.name {
+        if name.trim().is_empty() {
+            return Err("Supplier name is required".to_string());
+        }
+        query.push_str(", name = ?");
+        bind_values.push(name.trim().to_string());
+    }
+    if let Some(ref company) = supplier.company { query.push_str(", company = ?"); bind_values.push(company.clone()); }
+    if let Some(ref email) = supplier.email { query.push_str(", email = ?"); bind_values.push(email.clone()); }
+    if let Some(ref phone) = supplier.phone { query.push_str(", phone = ?"); bind_values.push(phone.clone()); }
+    if let Some(ref address) = supplier.address { query.push_str(", address = ?"); bind_values.push(address.clone()); }
+    if let Some(ref notes) = supplier.notes { query.push_str(", notes = ?"); bind_values.push(notes.clone()); }
+    if let Some(ref nif) = supplier.nif { query.push_str(", nif = ?"); bind_values.push(nif.clone()); }
+    if let Some(ref nis) = supplier.nis { query.push_str(", nis = ?"); bind_values.push(nis.clone()); }
+    if let Some(ref rc) = supplier.rc { query.push_str(", rc = ?"); bind_values.push(rc.clone()); }
+    if let Some(ref ai) = supplier.ai { query.push_str(", ai = ?"); bind_values.push(ai.clone()); }
+    if let Some(ref rib) = supplier.rib { query.push_str(", rib = ?"); bind_values.push(rib.clone()); }
+
+    query.push_str(" WHERE id = ? AND is_deleted = 0");
+
+    if bind_values.is_empty() {
+        return Err("No fields to update".to_string());
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut q = sqlx::query(&query);
+    q = q.bind(&now);
+    for value in &bind_values {
+        q = q.bind(value);
+    }
+    let updated = q.bind(&id).execute(&mut *tx).await.map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err("Supplier not found".to_string());
+    }
+    insert_audit_log(&mut *tx, "update", "supplier", &id, None, Some("Supplier updated")).await?;
+    let supplier = fetch_supplier(&mut tx, &id).await?
+        .ok_or_else(|| "Supplier not found after update".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(supplier)
+}
+
+#[tauri::command]
+pub async fn delete_supplier(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("UPDATE suppliers SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for supplier soft delete
+    insert_audit_log(&mut *tx, "soft_delete", "supplier", &id, None, Some("Supplier soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_supplier(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE suppliers SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for supplier restore
+    insert_audit_log(&mut *tx, "restore", "supplier", &id, None, Some("Supplier restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn validate_purchase_order(order: &CreatePurchaseOrderRequest) -> Result<(), String> {
+    document_period(&order.date)?;
+    if !(0.0..1.0).contains(&order.tax_rate) {
+        return Err("Tax rate must be between 0 and 1".to_string());
+    }
+    if order.items.is_empty() {
+        return Err("A purchase order needs at least one line".to_string());
+    }
+    for item in &order.items {
+        if item.description.trim().is_empty() {
+            return Err("Line description is required".to_string());
+        }
+        if item.weight <= 0.0 {
+            return Err("Line weight must be positive".to_string());
+        }
+        if item.price_per_ton.is_negative() {
+            return Err("Price per ton cannot be negative".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the order's lines and its totals, which are always derived from them.
+async fn insert_purchase_order_items(
+    conn: &mut sqlx::SqliteConnection,
+    purchase_order_id: &str,
+    order: &CreatePurchaseOrderRequest,
+) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339();
+    let mut line_totals = Vec::with_capacity(order.items.len());
+    for item in &order.items {
+        let total_amount = item.price_per_ton.mul_f64(item.weight);
+        line_totals.push(total_amount);
+        sqlx::query(
+            r#"INSERT INTO purchase_order_items (
+                id, purchase_order_id, description, coil_ref, thickness, width, top_coat_ral, back_coat_ral, weight, price_per_ton, total_amount, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(purchase_order_id)
+        .bind(&item.description)
+        .bind(item.coil_ref.as_deref().map(str::trim))
+        .bind(item.thickness)
+        .bind(item.width)
+        .bind(&item.top_coat_ral)
+        .bind(&item.back_coat_ral)
+        .bind(item.weight)
+        .bind(item.price_per_ton)
+        .bind(total_amount)
+        .bind(&now)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    let totals = compute_sale_totals(line_totals.into_iter().map(|total| (order.tax_rate, total)), None);
+    sqlx::query("UPDATE purchase_orders SET total_amount = ?, total_amount_ttc = ? WHERE id = ?")
+        .bind(totals.total_amount)
+        .bind(totals.total_amount_ttc)
+        .bind(purchase_order_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Orders whose coils have started arriving can no longer be changed or deleted.
+async fn ensure_nothing_received(conn: &mut sqlx::SqliteConnection, purchase_order_id: &str, action: &str) -> Result<(), String> {
+    let received: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM purchase_order_items WHERE purchase_order_id = ? AND coil_id IS NOT NULL")
+        .bind(purchase_order_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    if received > 0 {
+        return Err(format!("Cannot {} purchase order: coils have already been received against it", action));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_purchase_orders(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedPurchaseOrdersResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE po.is_deleted = 0");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &PURCHASE_ORDER_LIST, &mut where_clause, &mut params)?;
+    let from = format!(
+        "FROM {} po LEFT JOIN suppliers sup ON sup.id = po.supplier_id {}",
+        purchase_orders_with_receipt_status(), where_clause
+    );
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let total: i64 = count_q.fetch_one(&mut *conn).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT po.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut orders = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: String = row.get("id");
+        let items = fetch_purchase_order_items(&mut conn, &id).await?;
+        orders.push(purchase_order_from_row(&row, items));
+    }
+    Ok(PaginatedPurchaseOrdersResult { rows: orders, total })
+}
+
+#[tauri::command]
+pub async fn get_purchase_order_by_id(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Option<PurchaseOrder>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_purchase_order(&mut conn, &id).await
+}
+
+#[tauri::command]
+pub async fn create_purchase_order(
+    order: CreatePurchaseOrderRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PurchaseOrder, String> {
+    validate_purchase_order(&order)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    ensure_active_supplier(&mut tx, &order.supplier_id).await?;
+    sqlx::query(
+        r#"INSERT INTO purchase_orders (
+            id, supplier_id, reference, date, tax_rate, notes, created_at, updated_at, is_deleted
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0)"#
+    )
+    .bind(&id)
+    .bind(&order.supplier_id)
+    .bind(&order.reference)
+    .bind(&order.date)
+    .bind(order.tax_rate)
+    .bind(&order.notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_purchase_order_items(&mut tx, &id, &order).await?;
+    insert_audit_log(&mut *tx, "create", "purchase_order", &id, None, Some("Purchase order created")).await?;
+    let created = fetch_purchase_order(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve created purchase order".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_purchase_order(
+    id: String,
+    order: CreatePurchaseOrderRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PurchaseOrder, String> {
+    validate_purchase_order(&order)?;
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    ensure_active_supplier(&mut tx, &order.supplier_id).await?;
+    ensure_nothing_received(&mut tx, &id, "update").await?;
+    let updated = sqlx::query(
+        "UPDATE purchase_orders SET supplier_id = ?, reference = ?, date = ?, tax_rate = ?, notes = ?, updated_at = ? WHERE id = ? AND is_deleted = 0"
+    )
+    .bind(&order.supplier_id)
+    .bind(&order.reference)
+    .bind(&order.date)
+    .bind(order.tax_rate)
+    .bind(&order.notes)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err("Purchase order not found".to_string());
+    }
+    sqlx::query("DELETE FROM purchase_order_items WHERE purchase_order_id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_purchase_order_items(&mut tx, &id, &order).await?;
+    insert_audit_log(&mut *tx, "update", "purchase_order", &id, None, Some("Purchase order updated")).await?;
+    let updated = fetch_purchase_order(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve updated purchase order".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn delete_purchase_order(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    ensure_nothing_received(&mut tx, &id, "delete").await?;
+    sqlx::query("UPDATE purchase_orders SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for purchase order soft delete
+    insert_audit_log(&mut *tx, "soft_delete", "purchase_order", &id, None, Some("Purchase order soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_purchase_order(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE purchase_orders SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for purchase order restore
+    insert_audit_log(&mut *tx, "restore", "purchase_order", &id, None, Some("Purchase order restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Books the coil delivered for a purchase order line into stock, at the
+/// line's price per ton.
+#[tauri::command]
+pub async fn receive_purchase_order_item(
+    item_id: String,
+    receipt: ReceivePurchaseOrderItemRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Coil, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let row = sqlx::query(
+        r#"
+        SELECT poi.*, po.supplier_id, po.is_deleted AS order_deleted, sup.name AS supplier_name
+        FROM purchase_order_items poi
+        JOIN purchase_orders po ON po.id = poi.purchase_order_id
+        JOIN suppliers sup ON sup.id = po.supplier_id
+        WHERE poi.id = ?
+        "#
+    )
+    .bind(&item_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Purchase order line not found".to_string())?;
+    let order_deleted: bool = row.get("order_deleted");
+    if order_deleted {
+        return Err("Cannot receive coils for a deleted purchase order".to_string());
+    }
+    let item = purchase_order_item_from_row(&row);
+    if item.coil_id.is_some() {
+        return Err("This purchase order line has already been received".to_string());
+    }
+    let supplier_id: String = row.get("supplier_id");
+    let coil = ReceiveCoilRequest {
+        coil_ref: receipt.coil_ref.or(item.coil_ref).unwrap_or_default(),
+        received_at: receipt.received_at,
+        supplier: Some(row.get("supplier_name")),
+        thickness: item.thickness,
+        width: item.width,
+        top_coat_ral: item.top_coat_ral,
+        back_coat_ral: item.back_coat_ral,
+        gross_weight: receipt.gross_weight,
+        net_weight: receipt.net_weight,
+        cost_per_ton: item.price_per_ton,
+        notes: receipt.notes,
+    };
+    let received = insert_coil(&mut tx, &coil, Some(&supplier_id), Some(&item_id)).await?;
+    sqlx::query("UPDATE purchase_order_items SET coil_id = ? WHERE id = ?")
+        .bind(&received.id)
+        .bind(&item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = format!("Coil {} received for line {}", received.coil_ref, item_id);
+    insert_audit_log(&mut *tx, "receive", "purchase_order", &item.purchase_order_id, None, Some(&details)).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(received)
+}
+
+/// Checks the invoice's amounts and links, and that the supplier's number is
+/// not already used by another of its live invoices.
+async fn validate_supplier_invoice(
+    conn: &mut sqlx::SqliteConnection,
+    invoice: &CreateSupplierInvoiceRequest,
+    id: Option<&str>,
+) -> Result<(), String> {
+    if invoice.invoice_number.trim().is_empty() {
+        return Err("Invoice number is required".to_string());
+    }
+    document_period(&invoice.date)?;
+    if let Some(ref due_date) = invoice.due_date {
+        document_period(due_date)?;
+    }
+    if !invoice.total_amount_ht.is_positive() {
+        return Err("Invoice amount must be positive".to_string());
+    }
+    if invoice.total_amount_ttc < invoice.total_amount_ht {
+        return Err("Total TTC cannot be less than total HT".to_string());
+    }
+    ensure_active_supplier(conn, &invoice.supplier_id).await?;
+    if let Some(ref order_id) = invoice.purchase_order_id {
+        let order_supplier: Option<String> = sqlx::query_scalar("SELECT supplier_id FROM purchase_orders WHERE id = ? AND is_deleted = 0")
+            .bind(order_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        match order_supplier {
+            None => return Err("Purchase order not found".to_string()),
+            Some(supplier_id) if supplier_id != invoice.supplier_id => {
+                return Err("The purchase order belongs to another supplier".to_string());
+            }
+            Some(_) => {}
+        }
+    }
+    let duplicates: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM supplier_invoices WHERE supplier_id = ? AND invoice_number = ? AND is_deleted = 0 AND id != ?"
+    )
+    .bind(&invoice.supplier_id)
+    .bind(invoice.invoice_number.trim())
+    .bind(id.unwrap_or(""))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if duplicates > 0 {
+        return Err(format!("Invoice {} is already recorded for this supplier", invoice.invoice_number.trim()));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_supplier_invoices(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedSupplierInvoicesResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE sinv.is_deleted = 0");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &SUPPLIER_INVOICE_LIST, &mut where_clause, &mut params)?;
+    let from = format!(
+        "FROM {} sinv LEFT JOIN suppliers sup ON sup.id = sinv.supplier_id {}",
+        supplier_invoices_with_payment_progress(), where_clause
+    );
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT sinv.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(PaginatedSupplierInvoicesResult { rows: rows.iter().map(supplier_invoice_from_row).collect(), total })
+}
+
+#[tauri::command]
+pub async fn create_supplier_invoice(
+    invoice: CreateSupplierInvoiceRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<SupplierInvoice, String> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    validate_supplier_invoice(&mut tx, &invoice, None).await?;
+    sqlx::query(
+        r#"INSERT INTO supplier_invoices (
+            id, supplier_id, purchase_order_id, invoice_number, date, due_date, total_amount_ht, total_amount_ttc, notes, created_at, updated_at, is_deleted
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)"#
+    )
+    .bind(&id)
+    .bind(&invoice.supplier_id)
+    .bind(&invoice.purchase_order_id)
+    .bind(invoice.invoice_number.trim())
+    .bind(&invoice.date)
+    .bind(&invoice.due_date)
+    .bind(invoice.total_amount_ht)
+    .bind(invoice.total_amount_ttc)
+    .bind(&invoice.notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "create", "supplier_invoice", &id, None, Some("Supplier invoice created")).await?;
+    let created = fetch_supplier_invoice(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve created supplier invoice".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_supplier_invoice(
+    id: String,
+    invoice: CreateSupplierInvoiceRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<SupplierInvoice, String> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    validate_supplier_invoice(&mut tx, &invoice, Some(&id)).await?;
+    // Payments already made on the invoice would end up with the wrong supplier
+    let other_supplier_payments: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM supplier_payments WHERE supplier_invoice_id = ? AND supplier_id != ? AND is_deleted = 0"
+    )
+    .bind(&id)
+    .bind(&invoice.supplier_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if other_supplier_payments > 0 {
+        return Err("Cannot change the supplier of an invoice that has payments".to_string());
+    }
+    let updated = sqlx::query(
+        r#"UPDATE supplier_invoices
+        SET supplier_id = ?, purchase_order_id = ?, invoice_number = ?, date = ?, due_date = ?, total_amount_ht = ?, total_amount_ttc = ?, notes = ?, updated_at = ?
+        WHERE id = ? AND is_deleted = 0"#
+    )
+    .bind(&invoice.supplier_id)
+    .bind(&invoice.purchase_order_id)
+    .bind(invoice.invoice_number.trim())
+    .bind(&invoice.date)
+    .bind(&invoice.due_date)
+    .bind(invoice.total_amount_ht)
+    .bind(invoice.total_amount_ttc)
+    .bind(&invoice.notes)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err("Supplier invoice not found".to_string());
+    }
+    insert_audit_log(&mut *tx, "update", "supplier_invoice", &id, None, Some("Supplier invoice updated")).await?;
+    let updated = fetch_supplier_invoice(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve updated supplier invoice".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// Soft-deletes the invoice together with the payments made on it.
+#[tauri::command]
+pub async fn delete_supplier_invoice(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("UPDATE supplier_invoices SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE supplier_payments SET is_deleted = 1, deleted_at = ? WHERE supplier_invoice_id = ? AND is_deleted = 0")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for supplier invoice soft delete
+    insert_audit_log(&mut *tx, "soft_delete", "supplier_invoice", &id, None, Some("Supplier invoice soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Restores the invoice and the payments deleted along with it.
+#[tauri::command]
+pub async fn restore_supplier_invoice(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let deleted_at: Option<String> = sqlx::query_scalar("SELECT deleted_at FROM supplier_invoices WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten();
+    sqlx::query("UPDATE supplier_invoices SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(deleted_at) = deleted_at {
+        sqlx::query("UPDATE supplier_payments SET is_deleted = 0, deleted_at = NULL WHERE supplier_invoice_id = ? AND deleted_at = ?")
+            .bind(&id)
+            .bind(&deleted_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    // Insert audit log entry for supplier invoice restore
+    insert_audit_log(&mut *tx, "restore", "supplier_invoice", &id, None, Some("Supplier invoice restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Same rules as validate_payment, plus the invoice paid must be one of the
+/// supplier's live invoices.
+async fn validate_supplier_payment(
+    conn: &mut sqlx::SqliteConnection,
+    payment: &mut CreateSupplierPaymentRequest,
+) -> Result<(), String> {
+    if !payment.amount.is_positive() {
+        return Err("Payment amount must be positive".to_string());
+    }
+    if payment.method.trim().is_empty() {
+        return Err("Payment method is required".to_string());
+    }
+    document_period(&payment.date)?;
+    if payment.method != "check" {
+        payment.check_number = None;
+    }
+    ensure_active_supplier(conn, &payment.supplier_id).await?;
+    if let Some(ref invoice_id) = payment.supplier_invoice_id {
+        let invoice_supplier: Option<String> = sqlx::query_scalar("SELECT supplier_id FROM supplier_invoices WHERE id = ? AND is_deleted = 0")
+            .bind(invoice_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        match invoice_supplier {
+            None => return Err("Supplier invoice not found".to_string()),
+            Some(supplier_id) if supplier_id != payment.supplier_id => {
+                return Err("The invoice belongs to another supplier".to_string());
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_supplier_payments(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedSupplierPaymentsResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE sp.is_deleted = 0");
+    let mut params: Vec<String> = Vec::new();
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &SUPPLIER_PAYMENT_LIST, &mut where_clause, &mut params)?;
+    let from = format!(
+        "FROM supplier_payments sp LEFT JOIN suppliers sup ON sup.id = sp.supplier_id LEFT JOIN supplier_invoices sinv ON sinv.id = sp.supplier_invoice_id {}",
+        where_clause
+    );
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let total: i64 = count_q.fetch_one(&*pool).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT sp.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&*pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(PaginatedSupplierPaymentsResult { rows: rows.iter().map(supplier_payment_from_row).collect(), total })
+}
+
+#[tauri::command]
+pub async fn create_supplier_payment(
+    mut payment: CreateSupplierPaymentRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<SupplierPayment, String> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    validate_supplier_payment(&mut tx, &mut payment).await?;
+    sqlx::query(
+        r#"
+        INSERT INTO supplier_payments (id, supplier_id, supplier_invoice_id, amount, date, method, check_number, notes, created_at, updated_at, is_deleted)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0)
+        "#,
+    )
+    .bind(&id)
+    .bind(&payment.supplier_id)
+    .bind(&payment.supplier_invoice_id)
+    .bind(payment.amount)
+    .bind(&payment.date)
+    .bind(&payment.method)
+    .bind(&payment.check_number)
+    .bind(&payment.notes)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "create", "supplier_payment", &id, None, Some("Supplier payment created")).await?;
+    let created = fetch_supplier_payment(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve created supplier payment".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub async fn update_supplier_payment(
+    id: String,
+    mut payment: CreateSupplierPaymentRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<SupplierPayment, String> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    validate_supplier_payment(&mut tx, &mut payment).await?;
+    // Deleted payments must be restored before they can be edited
+    let updated = sqlx::query(
+        r#"
+        UPDATE supplier_payments
+        SET supplier_id = ?, supplier_invoice_id = ?, amount = ?, date = ?, method = ?, check_number = ?, notes = ?, updated_at = ?
+        WHERE id = ? AND is_deleted = 0
+        "#,
+    )
+    .bind(&payment.supplier_id)
+    .bind(&payment.supplier_invoice_id)
+    .bind(payment.amount)
+    .bind(&payment.date)
+    .bind(&payment.method)
+    .bind(&payment.check_number)
+    .bind(&payment.notes)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err("Supplier payment not found".to_string());
+    }
+    insert_audit_log(&mut *tx, "update", "supplier_payment", &id, None, Some("Supplier payment updated")).await?;
+    let updated = fetch_supplier_payment(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve updated supplier payment".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn delete_supplier_payment(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    sqlx::query("UPDATE supplier_payments SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for supplier payment soft delete
+    insert_audit_log(&mut *tx, "soft_delete", "supplier_payment", &id, None, Some("Supplier payment soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_supplier_payment(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    // A payment on a deleted invoice comes back with the invoice
+    let invoice_deleted: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM supplier_payments sp JOIN supplier_invoices sinv ON sinv.id = sp.supplier_invoice_id WHERE sp.id = ? AND sinv.is_deleted = 1"
+    )
+    .bind(&id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if invoice_deleted > 0 {
+        return Err("Restore the supplier invoice first".to_string());
+    }
+    sqlx::query("UPDATE supplier_payments SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Insert audit log entry for supplier payment restore
+    insert_audit_log(&mut *tx, "restore", "supplier_payment", &id, None, Some("Supplier payment restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,534 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use super::{
+    apply_list_filter, calculate_total_amount, charge_lines, compute_sale_totals, document_period, get_sale_by_id, insert_audit_log,
+    insert_sale, item_tax_rate, next_document_number, stamp_duty_rule, validate_charges, validate_sale_item, CreateSaleItemRequest,
+    CreateSaleRequest, ListColumns, ListFilter, Money, Sale, QUOTE_NUMBERING,
+};
+
+pub const QUOTE_STATUSES: [&str; 4] = ["draft", "sent", "accepted", "expired"];
+
+/// Draft and sent quotes aliased `q` read as expired once valid_until has passed.
+const QUOTE_STATUS_SQL: &str = "CASE WHEN q.status IN ('draft', 'sent') AND substr(q.valid_until, 1, 10) < date('now') THEN 'expired' ELSE q.status END";
+
+fn quotes_with_status() -> String {
+    format!("(SELECT q.*, {} AS current_status FROM quotes q)", QUOTE_STATUS_SQL)
+}
+
+/// Used with `quotes_with_status() q` and `clients c`.
+const QUOTE_LIST: ListColumns = ListColumns {
+    date: "q.date",
+    client_id: Some("q.client_id"),
+    supplier_id: None,
+    amount: Some("q.total_amount_ttc"),
+    search: &["c.name LIKE ?", "c.company LIKE ?", "q.quote_number LIKE ?"],
+    payment_status: None,
+    is_invoiced: None,
+    sorts: &[
+        ("date", "q.date"),
+        ("valid_until", "q.valid_until"),
+        ("quote_number", "q.quote_number"),
+        ("client_name", "c.name"),
+        ("total_amount_ttc", "q.total_amount_ttc"),
+        ("status", "q.current_status"),
+    ],
+    default_sort: ("date", "DESC"),
+    tie_breaker: "q.id",
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteItem {
+    pub id: String,
+    pub quote_id: String,
+    pub description: String,
+    pub coil_ref: Option<String>,
+    pub coil_thickness: Option<f64>,
+    pub coil_width: Option<f64>,
+    pub top_coat_ral: Option<String>,
+    pub back_coat_ral: Option<String>,
+    pub coil_weight: Option<f64>,
+    pub quantity: f64,
+    pub price_per_ton: Money,
+    pub total_amount: Money,
+    pub unit_cost: Option<Money>,
+    /// VAT rate set on this line; None when it follows the quote's rate
+    pub tax_rate: Option<f64>,
+    pub tax_amount: Money,
+    pub product_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: String,
+    pub quote_number: String,
+    pub client_id: String,
+    pub date: String,
+    pub valid_until: String,
+    /// draft, sent, accepted or expired
+    pub status: String,
+    pub notes: Option<String>,
+    pub payment_method: Option<String>,
+    pub transportation_fee: Option<Money>,
+    pub tax_rate: f64,
+    pub tax_exemption: Option<String>,
+    pub total_amount: Money,
+    pub tax_amount: Money,
+    pub stamp_duty: Money,
+    pub total_amount_ttc: Money,
+    /// The sale the quote was converted to
+    pub sale_id: Option<String>,
+    pub converted_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+    pub is_deleted: Option<bool>,
+    pub deleted_at: Option<String>,
+    pub items: Vec<QuoteItem>,
+}
+
+/// Items are priced and taxed like the items of a sale.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateQuoteRequest {
+    pub client_id: String,
+    pub date: String,
+    pub valid_until: String,
+    pub notes: Option<String>,
+    pub payment_method: Option<String>,
+    pub transportation_fee: Option<Money>,
+    pub tax_rate: f64,
+    #[serde(default)]
+    pub tax_exemption: Option<String>,
+    pub items: Vec<CreateSaleItemRequest>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PaginatedQuotesResult {
+    pub rows: Vec<Quote>,
+    pub total: i64,
+}
+
+fn quote_item_from_row(row: &sqlx::sqlite::SqliteRow) -> QuoteItem {
+    QuoteItem {
+        id: row.get("id"),
+        quote_id: row.get("quote_id"),
+        description: row.get("description"),
+        coil_ref: row.get("coil_ref"),
+        coil_thickness: row.get("coil_thickness"),
+        coil_width: row.get("coil_width"),
+        top_coat_ral: row.get("top_coat_ral"),
+        back_coat_ral: row.get("back_coat_ral"),
+        coil_weight: row.get("coil_weight"),
+        quantity: row.get("quantity"),
+        price_per_ton: row.get("price_per_ton"),
+        total_amount: row.get("total_amount"),
+        unit_cost: row.get("unit_cost"),
+        tax_rate: row.get("tax_rate"),
+        tax_amount: row.get("tax_amount"),
+        product_type: row.get("product_type"),
+    }
+}
+
+/// Expects a row of `quotes_with_status()`.
+fn quote_from_row(row: &sqlx::sqlite::SqliteRow, items: Vec<QuoteItem>) -> Quote {
+    Quote {
+        id: row.get("id"),
+        quote_number: row.get("quote_number"),
+        client_id: row.get("client_id"),
+        date: row.get("date"),
+        valid_until: row.get("valid_until"),
+        status: row.get("current_status"),
+        notes: row.get("notes"),
+        payment_method: row.get("payment_method"),
+        transportation_fee: row.get("transportation_fee"),
+        tax_rate: row.get("tax_rate"),
+        tax_exemption: row.get("tax_exemption"),
+        total_amount: row.get("total_amount"),
+        tax_amount: row.get("tax_amount"),
+        stamp_duty: row.get("stamp_duty"),
+        total_amount_ttc: row.get("total_amount_ttc"),
+        sale_id: row.get("sale_id"),
+        converted_at: row.get("converted_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        is_deleted: row.get("is_deleted"),
+        deleted_at: row.get("deleted_at"),
+        items,
+    }
+}
+
+/// The sale line a quote line turns into.
+fn quote_item_sale_request(item: &QuoteItem) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: item.description.clone(),
+        coil_ref: item.coil_ref.clone(),
+        coil_thickness: item.coil_thickness,
+        coil_width: item.coil_width,
+        top_coat_ral: item.top_coat_ral.clone(),
+        back_coat_ral: item.back_coat_ral.clone(),
+        coil_weight: item.coil_weight,
+        quantity: item.quantity,
+        price_per_ton: item.price_per_ton,
+        total_amount: item.total_amount,
+        product_type: item.product_type.clone(),
+        unit_cost: item.unit_cost,
+        tax_rate: item.tax_rate,
+    }
+}
+
+async fn fetch_quote_items(conn: &mut sqlx::SqliteConnection, quote_id: &str) -> Result<Vec<QuoteItem>, String> {
+    let rows = sqlx::query("SELECT * FROM quote_items WHERE quote_id = ? ORDER BY rowid")
+        .bind(quote_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows.iter().map(quote_item_from_row).collect())
+}
+
+async fn fetch_quote(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Option<Quote>, String> {
+    let query = format!("SELECT * FROM {} q WHERE q.id = ?", quotes_with_status());
+    let row = sqlx::query(&query)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    match row {
+        Some(row) => {
+            let items = fetch_quote_items(conn, id).await?;
+            Ok(Some(quote_from_row(&row, items)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// The quote, unless it is deleted or has already become a sale.
+async fn fetch_open_quote(conn: &mut sqlx::SqliteConnection, id: &str) -> Result<Quote, String> {
+    let quote = fetch_quote(conn, id).await?
+        .filter(|quote| quote.is_deleted != Some(true))
+        .ok_or_else(|| "Quote not found".to_string())?;
+    if quote.sale_id.is_some() {
+        return Err(format!("Quote {} has been converted to a sale and can no longer be changed", quote.quote_number));
+    }
+    Ok(quote)
+}
+
+fn validate_quote(quote: &mut CreateQuoteRequest) -> Result<(), String> {
+    document_period(&quote.date)?;
+    document_period(&quote.valid_until)?;
+    if quote.valid_until.get(..10) < quote.date.get(..10) {
+        return Err("A quote cannot expire before its date".to_string());
+    }
+    validate_charges(quote.tax_rate, &quote.items, quote.transportation_fee)?;
+    if quote.items.is_empty() {
+        return Err("A quote needs at least one line".to_string());
+    }
+    for item in &quote.items {
+        validate_sale_item(item)?;
+    }
+    quote.tax_exemption = quote.tax_exemption.as_deref().map(str::trim).filter(|r| !r.is_empty()).map(str::to_string);
+    Ok(())
+}
+
+/// Writes the quote's lines and its totals, which are always derived from
+/// them the way a sale's are.
+async fn insert_quote_items(conn: &mut sqlx::SqliteConnection, quote_id: &str, quote: &CreateQuoteRequest) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339();
+    let exempt = quote.tax_exemption.is_some();
+    for item in &quote.items {
+        let total_amount = calculate_total_amount(item);
+        let tax_amount = total_amount.mul_f64(item_tax_rate(item.tax_rate, quote.tax_rate, exempt));
+        sqlx::query(
+            r#"INSERT INTO quote_items (
+                id, quote_id, description, coil_ref, coil_thickness, coil_width, top_coat_ral, back_coat_ral, coil_weight, quantity, price_per_ton, total_amount, unit_cost, tax_rate, tax_amount, product_type, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(quote_id)
+        .bind(&item.description)
+        .bind(&item.coil_ref)
+        .bind(item.coil_thickness)
+        .bind(item.coil_width)
+        .bind(&item.top_coat_ral)
+        .bind(&item.back_coat_ral)
+        .bind(item.coil_weight)
+        .bind(item.quantity)
+        .bind(item.price_per_ton)
+        .bind(total_amount)
+        .bind(item.unit_cost)
+        .bind(item.tax_rate)
+        .bind(tax_amount)
+        .bind(&item.product_type)
+        .bind(&now)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    let stamp_duty = stamp_duty_rule(&mut *conn).await?;
+    let lines = charge_lines(&quote.items, quote.tax_rate, exempt, quote.transportation_fee);
+    let totals = compute_sale_totals(lines, Some((&stamp_duty, quote.payment_method.as_deref())));
+    sqlx::query("UPDATE quotes SET total_amount = ?, tax_amount = ?, stamp_duty = ?, total_amount_ttc = ? WHERE id = ?")
+        .bind(totals.total_amount)
+        .bind(totals.tax_amount)
+        .bind(totals.stamp_duty)
+        .bind(totals.total_amount_ttc)
+        .bind(quote_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_quotes(
+    page: Option<u32>,
+    page_size: Option<u32>,
+    filter: Option<ListFilter>,
+    status: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<PaginatedQuotesResult, String> {
+    let page = page.unwrap_or(1).max(1);
+    let page_size = page_size.unwrap_or(5);
+    let offset = (page - 1) * page_size;
+    let mut where_clause = String::from("WHERE q.is_deleted = 0");
+    let mut params: Vec<String> = Vec::new();
+    if let Some(status) = status.filter(|s| s != "all") {
+        if !QUOTE_STATUSES.contains(&status.as_str()) {
+            return Err(format!("Unknown quote status '{}'", status));
+        }
+        where_clause.push_str(" AND q.current_status = ?");
+        params.push(status);
+    }
+    let order_by = apply_list_filter(&filter.unwrap_or_default(), &QUOTE_LIST, &mut where_clause, &mut params)?;
+    let from = format!("FROM {} q LEFT JOIN clients c ON c.id = q.client_id {}", quotes_with_status(), where_clause);
+    // Total count
+    let count_query = format!("SELECT COUNT(*) {}", from);
+    let mut count_q = sqlx::query_scalar(&count_query);
+    for v in &params {
+        count_q = count_q.bind(v);
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let total: i64 = count_q.fetch_one(&mut *conn).await.map_err(|e| e.to_string())?;
+    // Paginated rows
+    let rows_query = format!("SELECT q.* {}{} LIMIT ? OFFSET ?", from, order_by);
+    let mut q = sqlx::query(&rows_query);
+    for v in &params {
+        q = q.bind(v);
+    }
+    let rows = q
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut quotes = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: String = row.get("id");
+        let items = fetch_quote_items(&mut conn, &id).await?;
+        quotes.push(quote_from_row(&row, items));
+    }
+    Ok(PaginatedQuotesResult { rows: quotes, total })
+}
+
+#[tauri::command]
+pub async fn get_quote_by_id(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Option<Quote>, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_quote(&mut conn, &id).await
+}
+
+#[tauri::command]
+pub async fn create_quote(
+    mut quote: CreateQuoteRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Quote, String> {
+    validate_quote(&mut quote)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let quote_number = next_document_number(&mut tx, &QUOTE_NUMBERING, &quote.date).await?;
+    sqlx::query(
+        r#"INSERT INTO quotes (
+            id, quote_number, client_id, date, valid_until, status, notes, payment_method, transportation_fee, tax_rate, tax_exemption, created_at, updated_at, is_deleted
+        ) VALUES (?, ?, ?, ?, ?, 'draft', ?, ?, ?, ?, ?, ?, ?, 0)"#
+    )
+    .bind(&id)
+    .bind(&quote_number)
+    .bind(&quote.client_id)
+    .bind(&quote.date)
+    .bind(&quote.valid_until)
+    .bind(&quote.notes)
+    .bind(&quote.payment_method)
+    .bind(quote.transportation_fee)
+    .bind(quote.tax_rate)
+    .bind(&quote.tax_exemption)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    insert_quote_items(&mut tx, &id, &quote).await?;
+    insert_audit_log(&mut *tx, "create", "quote", &id, None, Some(&format!("Quote {} created", quote_number))).await?;
+    let created = fetch_quote(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve created quote".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+/// Replaces the quote's header and lines; its number and status are kept.
+#[tauri::command]
+pub async fn update_quote(
+    id: String,
+    mut quote: CreateQuoteRequest,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Quote, String> {
+    validate_quote(&mut quote)?;
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    fetch_open_quote(&mut tx, &id).await?;
+    sqlx::query(
+        "UPDATE quotes SET client_id = ?, date = ?, valid_until = ?, notes = ?, payment_method = ?, transportation_fee = ?, tax_rate = ?, tax_exemption = ?, updated_at = ? WHERE id = ?"
+    )
+    .bind(&quote.client_id)
+    .bind(&quote.date)
+    .bind(&quote.valid_until)
+    .bind(&quote.notes)
+    .bind(&quote.payment_method)
+    .bind(quote.transportation_fee)
+    .bind(quote.tax_rate)
+    .bind(&quote.tax_exemption)
+    .bind(&now)
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM quote_items WHERE quote_id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_quote_items(&mut tx, &id, &quote).await?;
+    insert_audit_log(&mut *tx, "update", "quote", &id, None, Some("Quote updated")).await?;
+    let updated = fetch_quote(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve updated quote".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// Moves a quote between draft, sent, accepted and expired. Only an expired
+/// status can be set once valid_until has passed.
+#[tauri::command]
+pub async fn set_quote_status(
+    id: String,
+    status: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Quote, String> {
+    if !QUOTE_STATUSES.contains(&status.as_str()) {
+        return Err(format!("Unknown quote status '{}'; expected draft, sent, accepted or expired", status));
+    }
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let quote = fetch_open_quote(&mut tx, &id).await?;
+    let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+    if status != "expired" && quote.valid_until.get(..10).unwrap_or_default() < today.as_str() {
+        return Err(format!("Quote {} expired on {}; extend its validity first", quote.quote_number, quote.valid_until));
+    }
+    sqlx::query("UPDATE quotes SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(&status)
+        .bind(Utc::now().to_rfc3339())
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let details = format!("status {} -> {}", quote.status, status);
+    insert_audit_log(&mut *tx, "update_status", "quote", &id, None, Some(&details)).await?;
+    let updated = fetch_quote(&mut tx, &id).await?
+        .ok_or_else(|| "Failed to retrieve updated quote".to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// Creates the sale and its items from the quote in one step and marks the
+/// quote accepted. The sale keeps the quote's prices and links back to it.
+#[tauri::command]
+pub async fn convert_quote_to_sale(
+    id: String,
+    date: Option<DateTime<Utc>>,
+    allow_oversell: Option<bool>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Sale, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let quote = fetch_open_quote(&mut tx, &id).await?;
+    if quote.status == "expired" {
+        return Err(format!("Quote {} has expired", quote.quote_number));
+    }
+    let mut sale = CreateSaleRequest {
+        client_id: quote.client_id.clone(),
+        date: date.unwrap_or_else(Utc::now),
+        total_amount: quote.total_amount,
+        total_amount_ttc: quote.total_amount_ttc,
+        is_invoiced: false,
+        invoice_id: None,
+        notes: quote.notes.clone(),
+        payment_method: quote.payment_method.clone(),
+        transportation_fee: quote.transportation_fee,
+        tax_rate: quote.tax_rate,
+        tax_exemption: quote.tax_exemption.clone(),
+        is_paid: None,
+        paid_at: None,
+        items: quote.items.iter().map(quote_item_sale_request).collect(),
+        allow_oversell: allow_oversell.unwrap_or(false),
+    };
+    let sale_id = insert_sale(&mut tx, &mut sale, "convert_quote_to_sale").await?;
+    sqlx::query("UPDATE sales SET quote_id = ? WHERE id = ?")
+        .bind(&id)
+        .bind(&sale_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339();
+    sqlx::query("UPDATE quotes SET status = 'accepted', sale_id = ?, converted_at = ?, updated_at = ? WHERE id = ?")
+        .bind(&sale_id)
+        .bind(&now)
+        .bind(&now)
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "create", "sale", &sale_id, None, Some(&format!("Sale created from quote {}", quote.quote_number))).await?;
+    insert_audit_log(&mut *tx, "convert", "quote", &id, None, Some(&format!("Converted to sale {}", sale_id))).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    get_sale_by_id(sale_id, pool).await.and_then(|opt| opt.ok_or_else(|| "Failed to retrieve created sale".to_string()))
+}
+
+#[tauri::command]
+pub async fn delete_quote(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    fetch_open_quote(&mut tx, &id).await?;
+    sqlx::query("UPDATE quotes SET is_deleted = 1, deleted_at = ? WHERE id = ?")
+        .bind(Utc::now().to_rfc3339())
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "soft_delete", "quote", &id, None, Some("Quote soft-deleted")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn restore_quote(id: String, pool: tauri::State<'_, SqlitePool>) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE quotes SET is_deleted = 0, deleted_at = NULL WHERE id = ?")
+        .bind(&id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    insert_audit_log(&mut *tx, "restore", "quote", &id, None, Some("Quote restored")).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,483 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use uuid::Uuid;
+use chrono::Utc;
+use std::fs;
+use std::path::Path;
+
+use super::{bank_statement, insert_audit_log, Money};
+
+/// Methods whose money goes through the bank account.
+pub const BANK_PAYMENT_METHODS: [&str; 3] = ["bank_transfer", "check", "traite"];
+/// Days a bank line may be booked before or after the day the money was expected.
+pub const RECONCILE_WINDOW_DAYS: i64 = 7;
+
+/// The bank transaction aliased `t` is matched to a payment or bulk payment that still stands.
+const BANK_TRANSACTION_MATCHED_SQL: &str = r#"(
+    EXISTS (SELECT 1 FROM payments p WHERE p.id = t.payment_id AND (p.is_deleted = 0 OR p.is_deleted IS NULL))
+    OR EXISTS (SELECT 1 FROM bulk_payments b WHERE b.id = t.bulk_payment_id AND (b.is_deleted = 0 OR b.is_deleted IS NULL))
+)"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankTransaction {
+    pub id: String,
+    pub source_file: String,
+    /// 'csv', 'ofx' or 'camt053'
+    pub format: String,
+    pub date: String,
+    /// Credits are positive, debits negative
+    pub amount: Money,
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub payment_id: Option<String>,
+    pub bulk_payment_id: Option<String>,
+    /// 'auto' or 'manual'; None while unmatched
+    pub match_method: Option<String>,
+    pub matched_at: Option<String>,
+    pub imported_at: String,
+}
+
+/// A payment or bulk payment expected on the bank account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReconciliationPayment {
+    /// Exactly one of payment_id / bulk_payment_id
+    pub payment_id: Option<String>,
+    pub bulk_payment_id: Option<String>,
+    pub client_id: String,
+    pub client_name: Option<String>,
+    pub date: String,
+    /// When the bank should credit it: the deposit or maturity of a cheque or traite
+    pub expected_date: String,
+    pub amount: Money,
+    pub method: String,
+    pub check_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankStatementImport {
+    pub source_file: String,
+    pub format: String,
+    pub imported: i64,
+    /// Lines already imported from an earlier statement
+    pub duplicates: i64,
+    pub auto_matched: i64,
+    pub transactions: Vec<BankTransaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BankReconciliation {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub matched_count: i64,
+    pub unmatched_transactions: Vec<BankTransaction>,
+    pub unmatched_payments: Vec<ReconciliationPayment>,
+    pub unmatched_transactions_total: Money,
+    pub unmatched_payments_total: Money,
+}
+
+fn bank_transaction_from_row(row: &sqlx::sqlite::SqliteRow) -> BankTransaction {
+    BankTransaction {
+        id: row.get("id"),
+        source_file: row.get("source_file"),
+        format: row.get("format"),
+        date: row.get("date"),
+        amount: row.get("amount"),
+        description: row.get("description"),
+        reference: row.get("reference"),
+        payment_id: row.get("payment_id"),
+        bulk_payment_id: row.get("bulk_payment_id"),
+        match_method: row.get("match_method"),
+        matched_at: row.get("matched_at"),
+        imported_at: row.get("imported_at"),
+    }
+}
+
+async fn fetch_bank_transactions(
+    conn: &mut sqlx::SqliteConnection,
+    where_clause: &str,
+    params: &[String],
+) -> Result<Vec<BankTransaction>, String> {
+    let sql = format!("SELECT t.* FROM bank_transactions t {} ORDER BY t.date ASC, t.rowid ASC", where_clause);
+    let mut query = sqlx::query(&sql);
+    for param in params {
+        query = query.bind(param);
+    }
+    Ok(query
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(bank_transaction_from_row)
+        .collect())
+}
+
+/// Live bank payments and bulk payments no bank transaction accounts for yet,
+/// the money expected soonest first.
+async fn unreconciled_payments(
+    conn: &mut sqlx::SqliteConnection,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<ReconciliationPayment>, String> {
+    let methods = BANK_PAYMENT_METHODS.map(|m| format!("'{}'", m)).join(", ");
+    let sql = format!(
+        r#"
+        SELECT * FROM (
+            SELECT p.id AS payment_id, NULL AS bulk_payment_id, p.client_id, c.name AS client_name,
+                   date(p.date) AS date, date(COALESCE(pi.deposited_at, pi.maturity_date, p.date)) AS expected_date,
+                   p.amount, p.method, p.check_number
+            FROM payments p
+            LEFT JOIN clients c ON c.id = p.client_id
+            LEFT JOIN payment_instruments pi ON pi.id = p.instrument_id
+            WHERE (p.is_deleted = 0 OR p.is_deleted IS NULL) AND p.bulk_payment_id IS NULL AND p.method IN ({methods})
+              AND NOT EXISTS (SELECT 1 FROM bank_transactions t WHERE t.payment_id = p.id AND {matched})
+            UNION ALL
+            SELECT NULL AS payment_id, b.id AS bulk_payment_id, b.client_id, c.name AS client_name,
+                   date(b.date) AS date, date(COALESCE(pi.deposited_at, pi.maturity_date, b.date)) AS expected_date,
+                   b.total_amount AS amount, b.method, b.check_number
+            FROM bulk_payments b
+            LEFT JOIN clients c ON c.id = b.client_id
+            LEFT JOIN payment_instruments pi ON pi.id = b.instrument_id
+            WHERE (b.is_deleted = 0 OR b.is_deleted IS NULL) AND b.method IN ({methods})
+              AND NOT EXISTS (SELECT 1 FROM bank_transactions t WHERE t.bulk_payment_id = b.id AND {matched})
+        )
+        WHERE (? IS NULL OR expected_date >= date(?)) AND (? IS NULL OR expected_date <= date(?))
+        ORDER BY expected_date ASC, date ASC
+        "#,
+        methods = methods,
+        matched = BANK_TRANSACTION_MATCHED_SQL
+    );
+    let rows = sqlx::query(&sql)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(rows
+        .iter()
+        .map(|row| ReconciliationPayment {
+            payment_id: row.get("payment_id"),
+            bulk_payment_id: row.get("bulk_payment_id"),
+            client_id: row.get("client_id"),
+            client_name: row.get("client_name"),
+            date: row.get("date"),
+            expected_date: row.get("expected_date"),
+            amount: row.get("amount"),
+            method: row.get("method"),
+            check_number: row.get("check_number"),
+        })
+        .collect())
+}
+
+/// Cheque numbers compare without spaces, punctuation or leading zeros.
+fn normalize_check_number(value: &str) -> String {
+    let alnum: String = value.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase();
+    alnum.trim_start_matches('0').to_string()
+}
+
+/// The bank line names the cheque: in its reference, or as a word of its description.
+fn transaction_mentions_check(transaction: &BankTransaction, check_number: &str) -> bool {
+    let number = normalize_check_number(check_number);
+    if number.len() < 3 {
+        return false;
+    }
+    let in_reference = transaction.reference.as_deref().is_some_and(|r| normalize_check_number(r) == number);
+    let in_description = transaction.description.as_deref().is_some_and(|d| {
+        d.split(|c: char| !c.is_ascii_alphanumeric()).any(|word| normalize_check_number(word) == number)
+    });
+    in_reference || in_description
+}
+
+async fn link_bank_transaction(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+    payment_id: Option<&str>,
+    bulk_payment_id: Option<&str>,
+    method: &str,
+) -> Result<(), String> {
+    sqlx::query("UPDATE bank_transactions SET payment_id = ?, bulk_payment_id = ?, match_method = ?, matched_at = ? WHERE id = ?")
+        .bind(payment_id)
+        .bind(bulk_payment_id)
+        .bind(method)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Matches each unmatched credit to the one payment of the same amount
+/// expected within RECONCILE_WINDOW_DAYS. When several qualify, the one whose
+/// cheque number the bank line carries wins; otherwise the line is left for
+/// a manual match. Returns how many lines were matched.
+async fn auto_match(conn: &mut sqlx::SqliteConnection) -> Result<i64, String> {
+    let transactions = fetch_bank_transactions(conn, &format!("WHERE t.amount > 0 AND NOT {}", BANK_TRANSACTION_MATCHED_SQL), &[]).await?;
+    let mut candidates = unreconciled_payments(conn, None, None).await?;
+    let mut matched = 0;
+    for transaction in &transactions {
+        let Ok(booked) = chrono::NaiveDate::parse_from_str(&transaction.date, "%Y-%m-%d") else {
+            continue;
+        };
+        let in_window: Vec<usize> = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.amount == transaction.amount)
+            .filter(|(_, c)| {
+                chrono::NaiveDate::parse_from_str(&c.expected_date, "%Y-%m-%d")
+                    .is_ok_and(|expected| (booked - expected).num_days().abs() <= RECONCILE_WINDOW_DAYS)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let by_number: Vec<usize> = in_window
+            .iter()
+            .copied()
+            .filter(|&i| candidates[i].check_number.as_deref().is_some_and(|n| transaction_mentions_check(transaction, n)))
+            .collect();
+        let chosen = match (by_number.as_slice(), in_window.as_slice()) {
+            ([only], _) => *only,
+            ([], [only]) => *only,
+            _ => continue,
+        };
+        let candidate = candidates.remove(chosen);
+        link_bank_transaction(conn, &transaction.id, candidate.payment_id.as_deref(), candidate.bulk_payment_id.as_deref(), "auto").await?;
+        matched += 1;
+    }
+    Ok(matched)
+}
+
+/// Reads a CSV, OFX or CAMT.053 statement into bank_transactions, skipping
+/// lines imported before, then matches what it can.
+#[tauri::command]
+pub async fn import_bank_statement(
+    path: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<BankStatementImport, String> {
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let statement = bank_statement::parse(&bank_statement::decode(&bytes))?;
+    let source_file = Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut new_ids = Vec::new();
+    let mut duplicates = 0;
+    // Identical lines within one file (two cheques of the same amount on the
+    // same day) are told apart by their rank
+    let mut seen: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for line in &statement.lines {
+        let fingerprint = match line.bank_id {
+            Some(ref bank_id) => format!("{}:{}", statement.format, bank_id),
+            None => {
+                let key = format!(
+                    "{}|{}|{}|{}",
+                    line.date,
+                    line.amount.centimes(),
+                    line.reference.as_deref().unwrap_or(""),
+                    line.description.as_deref().unwrap_or("")
+                );
+                let rank = seen.entry(key.clone()).or_insert(0);
+                *rank += 1;
+                format!("{}|{}", key, rank)
+            }
+        };
+        let id = Uuid::new_v4().to_string();
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO bank_transactions (id, fingerprint, source_file, format, date, amount, description, reference)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(fingerprint) DO NOTHING
+            "#,
+        )
+        .bind(&id)
+        .bind(&fingerprint)
+        .bind(&source_file)
+        .bind(statement.format)
+        .bind(&line.date)
+        .bind(line.amount)
+        .bind(&line.description)
+        .bind(&line.reference)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+        if inserted == 0 {
+            duplicates += 1;
+        } else {
+            new_ids.push(id);
+        }
+    }
+    let auto_matched = auto_match(&mut tx).await?;
+
+    let details = format!(
+        "{} line(s) imported from {} ({} already imported), {} matched",
+        new_ids.len(),
+        source_file,
+        duplicates,
+        auto_matched
+    );
+    insert_audit_log(&mut *tx, "import", "bank_statement", &source_file, None, Some(&details)).await?;
+    let mut transactions = Vec::with_capacity(new_ids.len());
+    for id in &new_ids {
+        transactions.extend(fetch_bank_transactions(&mut tx, "WHERE t.id = ?", std::slice::from_ref(id)).await?);
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(BankStatementImport {
+        source_file,
+        format: statement.format.to_string(),
+        imported: new_ids.len() as i64,
+        duplicates,
+        auto_matched,
+        transactions,
+    })
+}
+
+#[tauri::command]
+pub async fn auto_match_bank_transactions(pool: tauri::State<'_, SqlitePool>) -> Result<i64, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let matched = auto_match(&mut tx).await?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(matched)
+}
+
+/// Bank lines booked between `from` and `to`; `status` is 'matched' or 'unmatched'.
+#[tauri::command]
+pub async fn get_bank_transactions(
+    status: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<Vec<BankTransaction>, String> {
+    let mut where_clause = String::from("WHERE 1 = 1");
+    let mut params = Vec::new();
+    match status.as_deref() {
+        None => {}
+        Some("matched") => where_clause.push_str(&format!(" AND {}", BANK_TRANSACTION_MATCHED_SQL)),
+        Some("unmatched") => where_clause.push_str(&format!(" AND NOT {}", BANK_TRANSACTION_MATCHED_SQL)),
+        Some(other) => return Err(format!("Unknown reconciliation status '{}'", other)),
+    }
+    if let Some(from) = from {
+        where_clause.push_str(" AND t.date >= date(?)");
+        params.push(from);
+    }
+    if let Some(to) = to {
+        where_clause.push_str(" AND t.date <= date(?)");
+        params.push(to);
+    }
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    fetch_bank_transactions(&mut conn, &where_clause, &params).await
+}
+
+/// Reconciles a bank credit by hand with a payment or a bulk payment, e.g.
+/// when bank fees make the amounts differ.
+#[tauri::command]
+pub async fn match_bank_transaction(
+    id: String,
+    payment_id: Option<String>,
+    bulk_payment_id: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<BankTransaction, String> {
+    if payment_id.is_some() == bulk_payment_id.is_some() {
+        return Err("Match the bank transaction with exactly one payment or bulk payment".to_string());
+    }
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let transaction = fetch_bank_transactions(&mut tx, "WHERE t.id = ?", std::slice::from_ref(&id))
+        .await?
+        .pop()
+        .ok_or_else(|| "Bank transaction not found".to_string())?;
+    if !transaction.amount.is_positive() {
+        return Err("Only bank credits can be matched with client payments".to_string());
+    }
+    let already: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM bank_transactions t WHERE t.id = ? AND {}", BANK_TRANSACTION_MATCHED_SQL))
+        .bind(&id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if already > 0 {
+        return Err("This bank transaction is already matched; unmatch it first".to_string());
+    }
+
+    let open = unreconciled_payments(&mut tx, None, None).await?;
+    let target = open
+        .iter()
+        .find(|p| p.payment_id == payment_id && p.bulk_payment_id == bulk_payment_id)
+        .ok_or_else(|| "The payment is not waiting for a bank transaction (already matched, deleted, not a bank payment or part of a bulk payment)".to_string())?;
+    link_bank_transaction(&mut tx, &id, payment_id.as_deref(), bulk_payment_id.as_deref(), "manual").await?;
+
+    let details = format!(
+        "Matched {} of {} with {} of {}",
+        transaction.date,
+        transaction.amount,
+        payment_id.as_deref().or(bulk_payment_id.as_deref()).unwrap_or_default(),
+        target.amount
+    );
+    insert_audit_log(&mut *tx, "match", "bank_transaction", &id, None, Some(&details)).await?;
+    let matched = fetch_bank_transactions(&mut tx, "WHERE t.id = ?", std::slice::from_ref(&id)).await?.pop();
+    tx.commit().await.map_err(|e| e.to_string())?;
+    matched.ok_or_else(|| "Bank transaction not found".to_string())
+}
+
+#[tauri::command]
+pub async fn unmatch_bank_transaction(
+    id: String,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<BankTransaction, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let updated = sqlx::query(
+        "UPDATE bank_transactions SET payment_id = NULL, bulk_payment_id = NULL, match_method = NULL, matched_at = NULL WHERE id = ?"
+    )
+    .bind(&id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err("Bank transaction not found".to_string());
+    }
+    insert_audit_log(&mut *tx, "unmatch", "bank_transaction", &id, None, Some("Bank transaction unmatched")).await?;
+    let unmatched = fetch_bank_transactions(&mut tx, "WHERE t.id = ?", std::slice::from_ref(&id)).await?.pop();
+    tx.commit().await.map_err(|e| e.to_string())?;
+    unmatched.ok_or_else(|| "Bank transaction not found".to_string())
+}
+
+/// What is left to reconcile on both sides: bank lines without a payment,
+/// and bank payments the bank has not shown yet.
+#[tauri::command]
+pub async fn get_bank_reconciliation(
+    from: Option<String>,
+    to: Option<String>,
+    pool: tauri::State<'_, SqlitePool>
+) -> Result<BankReconciliation, String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    let period = "(? IS NULL OR t.date >= date(?)) AND (? IS NULL OR t.date <= date(?))";
+    let matched_count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM bank_transactions t WHERE {} AND {}", period, BANK_TRANSACTION_MATCHED_SQL))
+        .bind(&from)
+        .bind(&from)
+        .bind(&to)
+        .bind(&to)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut where_clause = format!("WHERE NOT {}", BANK_TRANSACTION_MATCHED_SQL);
+    let mut params = Vec::new();
+    if let Some(ref from) = from {
+        where_clause.push_str(" AND t.date >= date(?)");
+        params.push(from.clone());
+    }
+    if let Some(ref to) = to {
+        where_clause.push_str(" AND t.date <= date(?)");
+        params.push(to.clone());
+    }
+    let unmatched_transactions = fetch_bank_transactions(&mut conn, &where_clause, &params).await?;
+    let unmatched_payments = unreconciled_payments(&mut conn, from.as_deref(), to.as_deref()).await?;
+    Ok(BankReconciliation {
+        matched_count,
+        unmatched_transactions_total: unmatched_transactions.iter().map(|t| t.amount).sum(),
+        unmatched_payments_total: unmatched_payments.iter().map(|p| p.amount).sum(),
+        unmatched_transactions,
+        unmatched_payments,
+        from,
+        to,
+    })
+}
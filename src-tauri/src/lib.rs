@@ -23,7 +23,7 @@
 
 // It's also good practice to import the AppHandle and Manager traits in lib.rs
 use tauri::{AppHandle, Manager};
-mod commands;
+pub mod commands;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
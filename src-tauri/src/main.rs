@@ -160,10 +160,33 @@ async fn main() {
             commands::update_sale,
             commands::mark_sale_invoiced,
             commands::unmark_sale_invoiced,
+            commands::recalculate_all_sale_totals,
             // Invoice commands
             commands::get_invoices,
             commands::create_invoice,
+            commands::update_invoice,
+            commands::preview_next_invoice_number,
+            commands::generate_invoice_pdf,
             commands::delete_invoice,
+            // Credit note commands
+            commands::create_credit_note,
+            commands::get_credit_notes,
+            commands::get_credit_note,
+            // Quote commands
+            commands::get_quotes,
+            commands::get_quote_by_id,
+            commands::create_quote,
+            commands::update_quote,
+            commands::set_quote_status,
+            commands::convert_quote_to_sale,
+            commands::delete_quote,
+            commands::restore_quote,
+            // Delivery note commands
+            commands::create_delivery_note,
+            commands::get_delivery_notes,
+            commands::get_delivery_note,
+            commands::delete_delivery_note,
+            commands::generate_delivery_note_pdf,
             // Product commands
             commands::get_corrugated_sheet_items,
             commands::create_corrugated_sheet_item,
@@ -171,6 +194,7 @@ async fn main() {
             commands::create_steel_slitting_strip_item,
             // Payment commands
             commands::create_payment,
+            commands::update_payment,
             commands::get_payments,
             // Soft-delete and restore commands
             commands::delete_payment,
@@ -180,6 +204,66 @@ async fn main() {
             commands::get_deleted_invoices,
             commands::get_deleted_sales,
             commands::get_deleted_payments,
+            // Client credit commands
+            commands::create_credit_adjustment,
+            commands::get_client_credit_ledger,
+            commands::reconcile_credit_balances,
+            // Client statement commands
+            commands::get_client_statement,
+            commands::export_client_statement,
+            // Reports
+            commands::get_ar_aging,
+            commands::export_ar_aging,
+            commands::get_vat_report,
+            commands::export_vat_report,
+            // Search
+            commands::global_search,
+            // Coil stock
+            commands::receive_coil,
+            commands::adjust_coil_stock,
+            commands::get_coils,
+            commands::get_coil_movements,
+            // Purchasing
+            commands::get_suppliers,
+            commands::get_supplier_by_id,
+            commands::create_supplier,
+            commands::update_supplier,
+            commands::delete_supplier,
+            commands::restore_supplier,
+            commands::get_purchase_orders,
+            commands::get_purchase_order_by_id,
+            commands::create_purchase_order,
+            commands::update_purchase_order,
+            commands::delete_purchase_order,
+            commands::restore_purchase_order,
+            commands::receive_purchase_order_item,
+            commands::get_supplier_invoices,
+            commands::create_supplier_invoice,
+            commands::update_supplier_invoice,
+            commands::delete_supplier_invoice,
+            commands::restore_supplier_invoice,
+            commands::get_supplier_payments,
+            commands::create_supplier_payment,
+            commands::update_supplier_payment,
+            commands::delete_supplier_payment,
+            commands::restore_supplier_payment,
+            // Cheque and traite commands
+            commands::get_payment_instruments,
+            commands::get_cheques_due,
+            commands::deposit_payment_instruments,
+            commands::clear_payment_instrument,
+            commands::bounce_payment_instrument,
+            // Bulk payment commands
+            commands::create_bulk_payment,
+            commands::get_bulk_payments,
+            commands::delete_bulk_payment,
+            // Bank reconciliation commands
+            commands::import_bank_statement,
+            commands::auto_match_bank_transactions,
+            commands::get_bank_transactions,
+            commands::match_bank_transaction,
+            commands::unmatch_bank_transaction,
+            commands::get_bank_reconciliation,
             // Settings commands
             commands::get_settings,
             commands::update_settings,
@@ -194,6 +278,8 @@ async fn main() {
             // Analytics commands
             commands::get_sold_products_analytics,
             commands::get_sold_products_summary,
+            commands::get_margin_analytics,
+            commands::get_sales_timeseries,
             commands::get_unique_thickness_width,
             // --- Summary commands ---
             commands::get_clients_summary,
@@ -0,0 +1,92 @@
+mod common;
+
+use app_lib::commands::{self, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Alpha"), ("cli2", "Beta")]).await?;
+    // One sale per invoice; inv5 is deleted and must not count
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('s1', 'cli1', '2024-06-01', 10000, 10000, 1, 'inv1', 0), ('s2', 'cli1', '2024-05-01', 20000, 20000, 1, 'inv2', 0), ('s3', 'cli2', '2024-02-01', 30000, 30000, 1, 'inv3', 0), ('s4', 'cli2', '2024-04-01', 40000, 40000, 1, 'inv4', 0), ('s5', 'cli2', '2024-04-01', 50000, 50000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-06-01', '2024-07-01', 10000, 10000, 0, 0), ('inv2', 'FAC-24/00002', 'cli1', '2024-05-01', '2024-06-01', 20000, 20000, 0, 0), ('inv3', 'FAC-24/00003', 'cli2', '2024-02-01', '2024-03-01', 30000, 30000, 0, 0), ('inv4', 'FAC-24/00004', 'cli2', '2024-04-01', '2024-05-01', 40000, 40000, 0, 0), ('inv5', 'FAC-24/00005', 'cli2', '2024-04-01', '2024-05-01', 50000, 50000, 0, 1)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('l1', 'inv1', 's1'), ('l2', 'inv2', 's2'), ('l3', 'inv3', 's3'), ('l4', 'inv4', 's4'), ('l5', 'inv5', 's5')")
+        .execute(&pool)
+        .await?;
+    // inv2 half paid, inv4 fully paid, inv3 paid only after the report date, one deleted payment
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('p1', 's2', 'cli1', 5000, '2024-06-10', 'cash', 0), ('p2', 's4', 'cli2', 40000, '2024-05-01', 'cash', 0), ('p3', 's3', 'cli2', 30000, '2024-07-15', 'cash', 0), ('p4', 's1', 'cli1', 10000, '2024-06-20', 'cash', 1)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+#[tokio::test]
+async fn test_ar_aging_buckets() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let report = commands::get_ar_aging(Some("2024-07-01".to_string()), None, app.state()).await?;
+    let labels: Vec<&str> = report.buckets.iter().map(|b| b.label.as_str()).collect();
+    assert_eq!(labels, vec!["current", "1-30", "31-60", "61-90", "90+"]);
+    assert_eq!(report.clients.len(), 2);
+
+    let alpha = &report.clients[0];
+    assert_eq!(alpha.client_name, "Alpha");
+    assert_eq!(alpha.amounts[0], Money::from_units(100.0), "Due today is current");
+    assert_eq!(alpha.amounts[1], Money::from_units(150.0), "30 days late, 50 already paid");
+    assert_eq!(alpha.total, Money::from_units(250.0));
+
+    let beta = &report.clients[1];
+    assert_eq!(beta.invoices.len(), 1, "Paid and deleted invoices are left out");
+    assert_eq!(beta.invoices[0].days_past_due, 122);
+    assert_eq!(beta.amounts[4], Money::from_units(300.0), "The July payment is after the report date");
+
+    assert_eq!(report.bucket_totals, vec![
+        Money::from_units(100.0),
+        Money::from_units(150.0),
+        Money::ZERO,
+        Money::ZERO,
+        Money::from_units(300.0),
+    ]);
+    assert_eq!(report.total, Money::from_units(550.0));
+
+    // Paid by the later date, inv3 drops out
+    let later = commands::get_ar_aging(Some("2024-07-31".to_string()), None, app.state()).await?;
+    assert_eq!(later.total, Money::from_units(250.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ar_aging_custom_edges_and_export() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let report = commands::get_ar_aging(Some("2024-07-01".to_string()), Some(vec![15, 120]), app.state()).await?;
+    let labels: Vec<&str> = report.buckets.iter().map(|b| b.label.as_str()).collect();
+    assert_eq!(labels, vec!["current", "1-15", "16-120", "120+"]);
+    assert_eq!(report.bucket_totals[2], Money::from_units(150.0));
+    assert_eq!(report.bucket_totals[3], Money::from_units(300.0));
+
+    for edges in [vec![], vec![0, 30], vec![60, 30]] {
+        assert!(commands::get_ar_aging(None, Some(edges), app.state()).await.is_err());
+    }
+
+    let path = std::env::temp_dir().join(format!("ar_aging_{}.csv", uuid::Uuid::new_v4()));
+    commands::export_ar_aging(Some("2024-07-01".to_string()), None, path.to_string_lossy().to_string(), app.state()).await?;
+    let csv = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines, vec![
+        "client,invoices,current,1-30,31-60,61-90,90+,total",
+        "Alpha,2,100.00,150.00,0.00,0.00,0.00,250.00",
+        "Beta,1,0.00,0.00,0.00,0.00,300.00,300.00",
+        "Total,3,100.00,150.00,0.00,0.00,300.00,550.00",
+    ]);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
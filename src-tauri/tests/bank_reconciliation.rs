@@ -0,0 +1,200 @@
+mod common;
+
+use app_lib::commands::{self, CreateBulkPaymentRequest, CreatePaymentRequest, Money};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Alpha")]).await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-05-01', 500000, 500000, 0, 0), ('sale2', 'cli1', '2024-05-02', 500000, 500000, 0, 0)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn payment(amount: f64, date: &str, method: &str, check_number: Option<&str>) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        method: method.to_string(),
+        check_number: check_number.map(|n| n.to_string()),
+        ..common::payment("sale1", amount, date)
+    }
+}
+
+fn statement_file(name: &str, content: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bank-statement-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+const CSV_STATEMENT: &str = "\
+Date opération;Libellé;Référence;Débit;Crédit
+05/06/2024;VIR RECU ALPHA SARL;;;1 190,00
+12/06/2024;REMISE CHQ 12345 ALPHA;;;500,00
+12/06/2024;REMISE CHEQUE;0012346;;500,00
+14/06/2024;FRAIS TENUE DE COMPTE;;250,00;
+20/06/2024;VIR RECU;;;300,00
+;Solde au 30/06/2024;;;2 240,00
+";
+
+#[tokio::test]
+async fn test_csv_import_auto_and_manual_matching() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let transfer = commands::create_payment(payment(1190.0, "2024-06-03", "bank_transfer", None), app.state()).await?;
+    let first = commands::create_payment(payment(500.0, "2024-06-01", "check", Some("0012345")), app.state()).await?;
+    let second = commands::create_payment(payment(500.0, "2024-06-01", "check", Some("0012346")), app.state()).await?;
+    commands::deposit_payment_instruments(
+        vec![first.instrument_id.clone().unwrap(), second.instrument_id.clone().unwrap()],
+        "REM-01".to_string(),
+        "2024-06-10".to_string(),
+        app.state(),
+    ).await?;
+    commands::create_payment(payment(200.0, "2024-06-04", "cash", None), app.state()).await?;
+    let late = commands::create_payment(payment(300.0, "2024-05-01", "bank_transfer", None), app.state()).await?;
+
+    // Latin-1, as the bank portal exports it
+    let latin1: Vec<u8> = CSV_STATEMENT.chars().map(|c| c as u8).collect();
+    let path = statement_file("releve-juin.csv", &latin1);
+    let imported = commands::import_bank_statement(path.to_string_lossy().to_string(), app.state()).await?;
+    assert_eq!((imported.format.as_str(), imported.imported, imported.duplicates), ("csv", 5, 0));
+    assert_eq!(imported.transactions[3].amount, Money::from_units(-250.0), "Debits are negative");
+    assert_eq!(imported.transactions[0].date, "2024-06-05");
+    assert_eq!(imported.auto_matched, 3, "Same amount in the window, told apart by cheque number");
+    let linked = |i: usize| imported.transactions[i].payment_id.clone();
+    assert_eq!((linked(0), linked(1), linked(2)), (Some(transfer.id.clone()), Some(first.id.clone()), Some(second.id.clone())));
+    assert_eq!(imported.transactions[1].match_method.as_deref(), Some("auto"));
+
+    // Importing the same statement again adds nothing
+    let again = commands::import_bank_statement(path.to_string_lossy().to_string(), app.state()).await?;
+    assert_eq!((again.imported, again.duplicates), (0, 5));
+
+    // The 300 transfer was booked too long after it was recorded
+    let report = commands::get_bank_reconciliation(None, None, app.state()).await?;
+    assert_eq!(report.matched_count, 3);
+    assert_eq!(report.unmatched_transactions.iter().map(|t| t.amount).collect::<Vec<_>>(), [Money::from_units(-250.0), Money::from_units(300.0)]);
+    assert_eq!(report.unmatched_payments.len(), 1, "Cash never reaches the bank");
+    assert_eq!(report.unmatched_payments[0].payment_id.as_deref(), Some(late.id.as_str()));
+    assert_eq!(report.unmatched_transactions_total, Money::from_units(50.0));
+
+    let fees = report.unmatched_transactions[0].id.clone();
+    let credit = report.unmatched_transactions[1].id.clone();
+    assert!(commands::match_bank_transaction(fees, Some(late.id.clone()), None, app.state()).await.is_err(), "Debits are not client payments");
+    assert!(commands::match_bank_transaction(credit.clone(), Some(transfer.id.clone()), None, app.state()).await.is_err(), "Already reconciled");
+    let manual = commands::match_bank_transaction(credit.clone(), Some(late.id.clone()), None, app.state()).await?;
+    assert_eq!(manual.match_method.as_deref(), Some("manual"));
+    assert!(commands::get_bank_reconciliation(None, None, app.state()).await?.unmatched_payments.is_empty());
+
+    commands::unmatch_bank_transaction(credit.clone(), app.state()).await?;
+    let unmatched = commands::get_bank_transactions(Some("unmatched".to_string()), Some("2024-06-15".to_string()), None, app.state()).await?;
+    assert_eq!(unmatched.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), [credit.as_str()]);
+
+    // A deleted payment no longer counts as reconciled
+    commands::delete_payment(transfer.id.clone(), app.state()).await?;
+    let report = commands::get_bank_reconciliation(Some("2024-06-01".to_string()), Some("2024-06-30".to_string()), app.state()).await?;
+    assert_eq!((report.matched_count, report.unmatched_transactions.len()), (2, 3));
+    std::fs::remove_dir_all(path.parent().unwrap())?;
+    Ok(())
+}
+
+const OFX_STATEMENT: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20240705120000[+1:CET]
+<TRNAMT>2000.00
+<FITID>OFX-0001
+<CHECKNUM>0077
+<NAME>REMISE CHEQUE
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20240706
+<TRNAMT>-35.50
+<FITID>OFX-0002
+<NAME>COMMISSION
+<MEMO>Frais remise
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+const CAMT_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Ntry>
+        <Amt Ccy="DZD">150.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2024-07-08</Dt></BookgDt>
+        <AcctSvcrRef>CAMT-1</AcctSvcrRef>
+        <NtryDtls><TxDtls><RmtInf><Ustrd>VIR ALPHA &amp; FILS</Ustrd></RmtInf></TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="DZD">80.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><DtTm>2024-07-09T10:00:00</DtTm></BookgDt>
+        <AcctSvcrRef>CAMT-2</AcctSvcrRef>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+#[tokio::test]
+async fn test_ofx_and_camt_statements() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // One cheque settling both sales, and two identical transfers
+    let bulk = commands::create_bulk_payment(CreateBulkPaymentRequest {
+        client_id: "cli1".to_string(),
+        total_amount: Money::from_units(2000.0),
+        date: "2024-07-02".to_string(),
+        method: "check".to_string(),
+        notes: None,
+        check_number: Some("77".to_string()),
+        bank: None,
+        maturity_date: None,
+        strategy: "oldest_due_first".to_string(),
+        allocations: None,
+    }, app.state()).await?;
+    commands::create_payment(payment(150.0, "2024-07-05", "bank_transfer", None), app.state()).await?;
+    commands::create_payment(payment(150.0, "2024-07-05", "bank_transfer", None), app.state()).await?;
+
+    let ofx = statement_file("juillet.ofx", OFX_STATEMENT.as_bytes());
+    let imported = commands::import_bank_statement(ofx.to_string_lossy().to_string(), app.state()).await?;
+    assert_eq!((imported.format.as_str(), imported.imported, imported.auto_matched), ("ofx", 2, 1));
+    assert_eq!(imported.transactions[0].bulk_payment_id.as_deref(), Some(bulk.id.as_str()), "The cheque settles the bulk payment");
+    assert_eq!((imported.transactions[1].date.as_str(), imported.transactions[1].amount), ("2024-07-06", Money::from_units(-35.5)));
+    assert_eq!(imported.transactions[1].description.as_deref(), Some("COMMISSION Frais remise"));
+
+    let camt = statement_file("camt053.xml", CAMT_STATEMENT.as_bytes());
+    let imported = commands::import_bank_statement(camt.to_string_lossy().to_string(), app.state()).await?;
+    assert_eq!((imported.format.as_str(), imported.imported), ("camt053", 2));
+    assert_eq!(imported.transactions[0].description.as_deref(), Some("VIR ALPHA & FILS"));
+    assert_eq!((imported.transactions[1].date.as_str(), imported.transactions[1].amount), ("2024-07-09", Money::from_units(-80.0)));
+    assert_eq!(imported.auto_matched, 0, "Two transfers qualify: left for a manual match");
+
+    let report = commands::get_bank_reconciliation(None, None, app.state()).await?;
+    assert_eq!((report.unmatched_transactions.len(), report.unmatched_payments.len()), (3, 2));
+
+    let garbage = statement_file("notes.txt", b"hello\nworld\n");
+    assert!(commands::import_bank_statement(garbage.to_string_lossy().to_string(), app.state()).await.is_err());
+    for path in [ofx, camt, garbage] {
+        std::fs::remove_dir_all(path.parent().unwrap())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use app_lib::commands::{self, BulkPaymentAllocation, CreateBulkPaymentRequest, CreatePaymentRequest, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    // inv1 (due first) covers sale1 + sale2, inv2 covers sale3, sale4 is not invoiced
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-05-01', 10000, 10000, 1, 'inv1', 0), ('sale2', 'cli1', '2024-05-02', 5000, 5000, 1, 'inv1', 0), ('sale3', 'cli1', '2024-05-03', 8000, 8000, 1, 'inv2', 0), ('sale4', 'cli1', '2024-07-01', 3000, 3000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-05-02', '2024-06-01', 15000, 15000, 0, 0), ('inv2', 'FAC-24/00002', 'cli1', '2024-05-03', '2024-06-15', 8000, 8000, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1'), ('is2', 'inv1', 'sale2'), ('is3', 'inv2', 'sale3')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+async fn is_paid(pool: &SqlitePool, table: &str, id: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT is_paid FROM {} WHERE id = ?", table))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn bulk_request(total: f64, strategy: &str, allocations: Option<Vec<BulkPaymentAllocation>>) -> CreateBulkPaymentRequest {
+    CreateBulkPaymentRequest {
+        client_id: "cli1".to_string(),
+        total_amount: Money::from_units(total),
+        date: "2024-07-10".to_string(),
+        method: "check".to_string(),
+        notes: None,
+        check_number: Some("CHK-100".to_string()),
+        bank: None,
+        maturity_date: None,
+        strategy: strategy.to_string(),
+        allocations,
+    }
+}
+
+#[tokio::test]
+async fn test_oldest_due_first_allocation_and_remainder() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 150 + 80 + 30 are due; 300 leaves 40 for client credit
+    let bulk = commands::create_bulk_payment(bulk_request(300.0, "oldest_due_first", None), app.state()).await?;
+    assert_eq!(bulk.payments.len(), 4, "inv1 is spread over its two sales");
+    assert!(bulk.payments.iter().all(|p| p.check_number.as_deref() == Some("CHK-100")));
+    assert_eq!(bulk.credited_amount, Money::from_units(40.0));
+    for invoice in ["inv1", "inv2"] {
+        assert_eq!(is_paid(&pool, "invoices", invoice).await, 1);
+    }
+    assert_eq!(is_paid(&pool, "sales", "sale4").await, 1);
+    let credit: Money = sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = 'cli1'").fetch_one(&pool).await?;
+    assert_eq!(credit, Money::from_units(40.0));
+
+    let listed = commands::get_bulk_payments(Some("cli1".to_string()), app.state()).await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].payments.len(), 4);
+
+    commands::delete_bulk_payment(bulk.id.clone(), app.state()).await?;
+    let live: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM payments WHERE bulk_payment_id = ? AND is_deleted = 0")
+        .bind(&bulk.id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(live, 0, "Child payments are deleted with the bulk payment");
+    assert_eq!(is_paid(&pool, "invoices", "inv1").await, 0);
+    let credit: Money = sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = 'cli1'").fetch_one(&pool).await?;
+    assert_eq!(credit, Money::ZERO, "The remainder credit is withdrawn");
+    assert!(commands::get_bulk_payments(None, app.state()).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_partial_oldest_due_first_allocation() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let bulk = commands::create_bulk_payment(bulk_request(170.0, "oldest_due_first", None), app.state()).await?;
+    assert_eq!(bulk.credited_amount, Money::ZERO);
+    assert_eq!(is_paid(&pool, "invoices", "inv1").await, 1);
+    assert_eq!(is_paid(&pool, "invoices", "inv2").await, 0, "Only 20 of 80 reached inv2");
+    let on_inv2: Money = sqlx::query_scalar("SELECT SUM(amount) FROM payments WHERE invoice_id = 'inv2'").fetch_one(&pool).await?;
+    assert_eq!(on_inv2, Money::from_units(20.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_explicit_allocation_is_validated() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let to_invoice = |id: &str, amount: f64| BulkPaymentAllocation {
+        invoice_id: Some(id.to_string()),
+        sale_id: None,
+        amount: Money::from_units(amount),
+    };
+    let too_much = commands::create_bulk_payment(bulk_request(200.0, "explicit", Some(vec![to_invoice("inv2", 90.0)])), app.state()).await;
+    assert!(too_much.is_err(), "An allocation cannot exceed what is due");
+    let over_total = commands::create_bulk_payment(bulk_request(100.0, "explicit", Some(vec![to_invoice("inv1", 60.0), to_invoice("inv2", 50.0)])), app.state()).await;
+    assert!(over_total.is_err(), "Allocations cannot exceed the payment");
+    let unknown = commands::create_bulk_payment(bulk_request(100.0, "newest_first", None), app.state()).await;
+    assert!(unknown.is_err());
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bulk_payments").fetch_one(&pool).await?;
+    assert_eq!(count, 0, "Rejected bulk payments leave nothing behind");
+
+    let bulk = commands::create_bulk_payment(bulk_request(100.0, "explicit", Some(vec![to_invoice("inv2", 80.0)])), app.state()).await?;
+    assert_eq!(bulk.payments.len(), 1);
+    assert_eq!(bulk.payments[0].sale_id.as_deref(), Some("sale3"));
+    assert_eq!(bulk.credited_amount, Money::from_units(20.0));
+    assert_eq!(is_paid(&pool, "invoices", "inv2").await, 1);
+    assert_eq!(is_paid(&pool, "invoices", "inv1").await, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deleting_bulk_payment_recounts_invoice_with_direct_payment() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // inv1 is 150: 100 paid on sale1 against the invoice, 50 by the bulk payment
+    commands::create_payment(CreatePaymentRequest {
+        sale_id: Some("sale1".to_string()),
+        invoice_id: Some("inv1".to_string()),
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(100.0),
+        date: "2024-07-01".to_string(),
+        method: "cash".to_string(),
+        notes: None,
+        check_number: None,
+        bank: None,
+        maturity_date: None,
+    }, app.state()).await?;
+    let allocation = BulkPaymentAllocation { invoice_id: Some("inv1".to_string()), sale_id: None, amount: Money::from_units(50.0) };
+    let bulk = commands::create_bulk_payment(bulk_request(50.0, "explicit", Some(vec![allocation])), app.state()).await?;
+    assert_eq!(is_paid(&pool, "invoices", "inv1").await, 1);
+
+    commands::delete_bulk_payment(bulk.id.clone(), app.state()).await?;
+    assert_eq!(is_paid(&pool, "invoices", "inv1").await, 0, "The payment on sale1 counts once");
+    assert_eq!(is_paid(&pool, "sales", "sale2").await, 0);
+    Ok(())
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use app_lib::commands::{self, CreateCreditNoteRequest, CreatePaymentRequest, CreditAdjustmentRequest, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Test Client"), ("cli2", "Other Client")]).await?;
+    // sale1 is billed on its own, sale2 through inv1; cli2's sale must never show up
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-05-01', 10000, 11900, 0, NULL, 0), ('sale2', 'cli1', '2024-05-08', 5000, 5950, 1, 'inv1', 0), ('sale3', 'cli2', '2024-05-08', 5000, 5950, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-05-10', '2024-06-10', 5000, 5950, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale2')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+/// A cheque on sale1
+fn payment_request(amount: f64, date: &str) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        method: "check".to_string(),
+        check_number: Some("CHK-7".to_string()),
+        ..common::payment("sale1", amount, date)
+    }
+}
+
+fn period(from: &str, to: &str) -> (Option<String>, Option<String>) {
+    (Some(from.to_string()), Some(to.to_string()))
+}
+
+#[tokio::test]
+async fn test_statement_running_and_opening_balances() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    commands::create_payment(payment_request(100.0, "2024-05-15"), app.state()).await?;
+    let refunded = commands::create_payment(payment_request(19.0, "2024-05-20"), app.state()).await?;
+
+    let statement = commands::get_client_statement("cli1".to_string(), None, None, app.state()).await?;
+    let running: Vec<(String, Money)> = statement.entries.iter().map(|e| (e.kind.clone(), e.balance)).collect();
+    assert_eq!(running, vec![
+        ("sale".to_string(), Money::from_units(119.0)),
+        ("invoice".to_string(), Money::from_units(178.5)),
+        ("payment".to_string(), Money::from_units(78.5)),
+        ("payment".to_string(), Money::from_units(59.5)),
+    ]);
+    assert_eq!(statement.opening_balance, Money::ZERO);
+    assert_eq!(statement.closing_balance, Money::from_units(59.5));
+    assert_eq!(statement.entries[1].reference.as_deref(), Some("FAC-24/00001"));
+
+    let (from, to) = period("2024-05-12", "2024-05-31");
+    let statement = commands::get_client_statement("cli1".to_string(), from.clone(), to.clone(), app.state()).await?;
+    assert_eq!(statement.opening_balance, Money::from_units(178.5));
+    assert_eq!(statement.entries.len(), 2);
+    assert_eq!(statement.total_credit, Money::from_units(119.0));
+    assert_eq!(statement.closing_balance, Money::from_units(59.5));
+
+    // Deleting a payment and crediting the invoice today leaves May's history untouched
+    commands::delete_payment(refunded.id.clone(), app.state()).await?;
+    commands::create_credit_note(CreateCreditNoteRequest {
+        invoice_id: "inv1".to_string(),
+        date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        reason: None,
+        lines: vec![],
+        amount: None,
+    }, app.state()).await?;
+    let may = commands::get_client_statement("cli1".to_string(), from, to, app.state()).await?;
+    assert_eq!(may.opening_balance, Money::from_units(178.5));
+    assert_eq!(may.closing_balance, Money::from_units(59.5));
+
+    let today = commands::get_client_statement("cli1".to_string(), None, None, app.state()).await?;
+    let kinds: Vec<&str> = today.entries.iter().map(|e| e.kind.as_str()).collect();
+    for kind in ["payment_deleted", "credit_note"] {
+        assert!(kinds.contains(&kind), "{} should be listed", kind);
+    }
+    assert_eq!(kinds.iter().filter(|k| **k == "sale").count(), 1, "sale2 stays billed through its credited invoice");
+    // 119 + 59.50 - 100 - 59.50
+    assert_eq!(today.closing_balance, Money::from_units(19.0));
+
+    assert!(commands::get_client_statement("cli1".to_string(), Some("2024-06-01".to_string()), Some("2024-05-01".to_string()), app.state()).await.is_err());
+    assert!(commands::get_client_statement("nope".to_string(), None, None, app.state()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_statement_credit_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 150 against 119 leaves 31 of credit, part of which pays the invoice
+    commands::create_payment(payment_request(150.0, "2024-05-15"), app.state()).await?;
+    let mut from_credit = payment_request(20.0, "2024-05-16");
+    from_credit.sale_id = Some("sale2".to_string());
+    from_credit.invoice_id = Some("inv1".to_string());
+    from_credit.method = "credit".to_string();
+    commands::create_payment(from_credit, app.state()).await?;
+    commands::create_credit_adjustment(CreditAdjustmentRequest {
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(10.0),
+        notes: Some("Goodwill".to_string()),
+    }, app.state()).await?;
+
+    let statement = commands::get_client_statement("cli1".to_string(), None, None, app.state()).await?;
+    let credit_payment = statement.entries.iter().find(|e| e.kind == "credit_payment").expect("credit payment listed");
+    assert_eq!((credit_payment.debit, credit_payment.credit), (Money::ZERO, Money::ZERO), "Spending credit moves no money");
+    let adjustment = statement.entries.iter().find(|e| e.kind == "credit_adjustment").expect("adjustment listed");
+    assert_eq!(adjustment.credit, Money::from_units(10.0));
+    // 119 + 59.50 - 150 - 10
+    assert_eq!(statement.closing_balance, Money::from_units(18.5));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_client_statement() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    commands::create_payment(payment_request(100.0, "2024-05-15"), app.state()).await?;
+
+    let dir = std::env::temp_dir().join(format!("client_statement_{}", uuid::Uuid::new_v4()));
+    let csv_path = dir.join("statement.csv").to_string_lossy().to_string();
+    commands::export_client_statement("cli1".to_string(), None, None, "csv".to_string(), csv_path.clone(), app.state()).await?;
+    let csv = std::fs::read_to_string(&csv_path)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "date,type,reference,description,debit,credit,balance");
+    assert!(lines[1].starts_with(",opening_balance,"));
+    assert!(lines.iter().any(|l| l.starts_with("2024-05-15,payment,CHK-7,")));
+    assert!(lines.last().unwrap().ends_with(",178.50,100.00,78.50"));
+
+    let pdf_path = dir.join("statement.pdf").to_string_lossy().to_string();
+    commands::export_client_statement("cli1".to_string(), None, None, "pdf".to_string(), pdf_path.clone(), app.state()).await?;
+    assert!(std::fs::read(&pdf_path)?.starts_with(b"%PDF"));
+
+    let xls_path = dir.join("statement.xls").to_string_lossy().to_string();
+    assert!(commands::export_client_statement("cli1".to_string(), None, None, "xls".to_string(), xls_path, app.state()).await.is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use app_lib::commands::{self, CoilAdjustmentRequest, CreateSaleItemRequest, CreateSaleRequest, ListFilter, Money, ReceiveCoilRequest};
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Test Client")]).await?;
+    Ok(pool)
+}
+
+fn coil(coil_ref: &str, received_at: &str, net_weight: f64) -> ReceiveCoilRequest {
+    ReceiveCoilRequest {
+        coil_ref: coil_ref.to_string(),
+        received_at: received_at.to_string(),
+        supplier: Some("ArcelorMittal Annaba".to_string()),
+        thickness: Some(0.5),
+        width: Some(1250.0),
+        top_coat_ral: Some("9002".to_string()),
+        back_coat_ral: None,
+        gross_weight: net_weight + 0.05,
+        net_weight,
+        cost_per_ton: Money::from_units(150000.0),
+        notes: None,
+    }
+}
+
+fn item(coil_ref: &str, weight: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        coil_ref: Some(coil_ref.to_string()),
+        ..common::coil_item(weight, 200000.0)
+    }
+}
+
+fn sale_request(items: Vec<CreateSaleItemRequest>, allow_oversell: bool) -> CreateSaleRequest {
+    CreateSaleRequest {
+        date: Utc::now(),
+        allow_oversell,
+        ..common::sale(items)
+    }
+}
+
+async fn remaining(pool: &SqlitePool, coil_ref: &str) -> Result<f64, sqlx::Error> {
+    sqlx::query_scalar("SELECT remaining_weight FROM coils WHERE coil_ref = ?")
+        .bind(coil_ref)
+        .fetch_one(pool)
+        .await
+}
+
+fn assert_weight(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "expected {} t, got {} t", expected, actual);
+}
+
+#[tokio::test]
+async fn test_receive_coil_validates_and_records_receipt() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let received = commands::receive_coil(coil(" BOB-100 ", "2024-07-01", 5.0), app.state()).await?;
+    assert_eq!(received.coil_ref, "BOB-100");
+    assert_weight(received.remaining_weight, 5.0);
+    let movements = commands::get_coil_movements(received.id.clone(), app.state()).await?;
+    assert_eq!(movements.len(), 1);
+    assert_eq!(movements[0].kind, "receipt");
+
+    assert!(commands::receive_coil(coil("BOB-100", "2024-07-02", 3.0), app.state()).await.is_err(), "Coil refs are unique");
+    assert!(commands::receive_coil(coil("BOB-101", "July", 3.0), app.state()).await.is_err());
+    assert!(commands::receive_coil(coil("BOB-101", "2024-07-02", 0.0), app.state()).await.is_err());
+    let mut light = coil("BOB-101", "2024-07-02", 3.0);
+    light.gross_weight = 2.0;
+    assert!(commands::receive_coil(light, app.state()).await.is_err(), "Gross weight includes the net weight");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sales_consume_and_release_stock() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    commands::receive_coil(coil("BOB-200", "2024-07-01", 5.0), app.state()).await?;
+
+    // Items without a coil in stock are not tracked
+    let sale = commands::create_sale(sale_request(vec![item("BOB-200", 2.0), item("UNKNOWN", 9.0)], false), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-200").await?, 3.0);
+
+    let err = commands::create_sale(sale_request(vec![item("BOB-200", 4.0)], false), app.state()).await.unwrap_err();
+    assert!(err.contains("BOB-200"), "{}", err);
+    assert_weight(remaining(&pool, "BOB-200").await?, 3.0);
+
+    // Updating the sale gives the old weight back before taking the new one
+    commands::update_sale(sale.id.clone(), sale_request(vec![item("BOB-200", 4.5)], false), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-200").await?, 0.5);
+
+    commands::delete_sale(sale.id.clone(), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-200").await?, 5.0);
+    commands::restore_sale(sale.id.clone(), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-200").await?, 0.5);
+    // Restoring a live sale again changes nothing
+    commands::restore_sale(sale.id.clone(), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-200").await?, 0.5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oversell_and_restore_checks() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    let received = commands::receive_coil(coil("BOB-300", "2024-07-01", 2.0), app.state()).await?;
+
+    let first = commands::create_sale(sale_request(vec![item("BOB-300", 1.5)], false), app.state()).await?;
+    commands::delete_sale(first.id.clone(), app.state()).await?;
+    let second = commands::create_sale(sale_request(vec![item("BOB-300", 2.5)], true), app.state()).await?;
+    assert_weight(remaining(&pool, "BOB-300").await?, -0.5);
+    let movements = commands::get_coil_movements(received.id.clone(), app.state()).await?;
+    let oversold = movements.iter().find(|m| m.sale_id.as_deref() == Some(second.id.as_str())).unwrap();
+    assert_eq!(oversold.notes.as_deref(), Some("Oversold"));
+
+    // The weight the deleted sale held has been sold since
+    assert!(commands::restore_sale(first.id.clone(), app.state()).await.is_err());
+    let is_deleted: bool = sqlx::query_scalar("SELECT is_deleted FROM sales WHERE id = ?")
+        .bind(&first.id)
+        .fetch_one(&pool)
+        .await?;
+    assert!(is_deleted, "A failed restore leaves the sale deleted");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_adjustments_and_coil_list() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    let a = commands::receive_coil(coil("BOB-401", "2024-07-01", 4.0), app.state()).await?;
+    commands::receive_coil(coil("BOB-402", "2024-07-05", 6.0), app.state()).await?;
+
+    let adjusted = commands::adjust_coil_stock(CoilAdjustmentRequest {
+        coil_id: a.id.clone(),
+        weight: -4.0,
+        notes: Some("Scrap".to_string()),
+    }, app.state()).await?;
+    assert_weight(adjusted.remaining_weight, 0.0);
+    assert!(commands::adjust_coil_stock(CoilAdjustmentRequest {
+        coil_id: a.id.clone(),
+        weight: -0.1,
+        notes: None,
+    }, app.state()).await.is_err());
+    assert!(commands::adjust_coil_stock(CoilAdjustmentRequest {
+        coil_id: a.id.clone(),
+        weight: 0.0,
+        notes: None,
+    }, app.state()).await.is_err());
+    assert_weight(remaining(&pool, "BOB-401").await?, 0.0);
+
+    let all = commands::get_coils(None, Some(10), None, None, app.state()).await?;
+    let refs: Vec<&str> = all.rows.iter().map(|c| c.coil_ref.as_str()).collect();
+    assert_eq!(refs, vec!["BOB-402", "BOB-401"], "Latest receipts come first");
+    let in_stock = commands::get_coils(None, Some(10), None, Some(true), app.state()).await?;
+    assert_eq!(in_stock.total, 1);
+    assert_eq!(in_stock.rows[0].coil_ref, "BOB-402");
+
+    let found = commands::get_coils(None, Some(10), Some(ListFilter {
+        search: Some("401".to_string()),
+        ..Default::default()
+    }), None, app.state()).await?;
+    assert_eq!(found.total, 1);
+    let by_date = commands::get_coils(None, Some(10), Some(ListFilter {
+        start_date: Some("2024-07-02".to_string()),
+        ..Default::default()
+    }), None, app.state()).await?;
+    assert_eq!(by_date.rows[0].coil_ref, "BOB-402");
+    assert!(commands::get_coils(None, None, Some(ListFilter {
+        sort_by: Some("payment_status".to_string()),
+        ..Default::default()
+    }), None, app.state()).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Fixtures shared by the integration tests. Each test binary only uses some
+//! of them.
+#![allow(dead_code)]
+
+use app_lib::commands::{CreateInvoiceRequest, CreatePaymentRequest, CreateSaleItemRequest, CreateSaleRequest, Money, SoldProductsFilter};
+use chrono::{TimeZone, Utc};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+
+/// An in-memory database with every migration applied. It lives in its one
+/// connection: a second connection would open another, empty database.
+pub async fn pool() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    Ok(pool)
+}
+
+/// Clients by (id, name).
+pub async fn insert_clients(pool: &SqlitePool, clients: &[(&str, &str)]) -> Result<(), sqlx::Error> {
+    for (id, name) in clients {
+        sqlx::query("INSERT INTO clients (id, name) VALUES (?, ?)")
+            .bind(id)
+            .bind(name)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// The company settings row, at 19% VAT.
+pub async fn insert_settings(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO settings (company_name, company_address, company_phone, tax_rate) VALUES ('Test Company', 'Address', '000', 0.19)")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// `pool()` with client cli1 "Alpha" and the company settings.
+pub async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = pool().await?;
+    insert_clients(&pool, &[("cli1", "Alpha")]).await?;
+    insert_settings(&pool).await?;
+    Ok(pool)
+}
+
+/// The integer `sql` selects: a count, a flag, an amount in centimes.
+pub async fn scalar(pool: &SqlitePool, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+}
+
+pub async fn credit_balance(pool: &SqlitePool, client_id: &str) -> Money {
+    sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = ?")
+        .bind(client_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// `weight` tons of coil at `price_per_ton`, taxed at the sale's rate.
+pub fn coil_item(weight: f64, price_per_ton: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: "Coil".to_string(),
+        coil_ref: None,
+        coil_thickness: Some(0.5),
+        coil_width: Some(1250.0),
+        top_coat_ral: None,
+        back_coat_ral: None,
+        coil_weight: Some(weight),
+        quantity: 1.0,
+        price_per_ton: Money::from_units(price_per_ton),
+        total_amount: Money::ZERO,
+        product_type: "coil".to_string(),
+        unit_cost: None,
+        tax_rate: None,
+    }
+}
+
+/// A sale of `items` to cli1 on 10 June 2024 at 19% VAT. The server works
+/// out the totals.
+pub fn sale(items: Vec<CreateSaleItemRequest>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        client_id: "cli1".to_string(),
+        date: Utc.with_ymd_and_hms(2024, 6, 10, 9, 0, 0).unwrap(),
+        total_amount: Money::ZERO,
+        total_amount_ttc: Money::ZERO,
+        is_invoiced: false,
+        invoice_id: None,
+        notes: None,
+        payment_method: None,
+        transportation_fee: None,
+        tax_rate: 0.19,
+        tax_exemption: None,
+        is_paid: None,
+        paid_at: None,
+        items,
+        allow_oversell: false,
+    }
+}
+
+/// An invoice of `sales_ids` for cli1, dated 10 June 2024 and due a month
+/// later, without totals.
+pub fn invoice(sales_ids: Vec<String>) -> CreateInvoiceRequest {
+    CreateInvoiceRequest {
+        invoice_number: None,
+        client_id: "cli1".to_string(),
+        date: "2024-06-10".to_string(),
+        due_date: "2024-07-10".to_string(),
+        total_amount_ht: Money::ZERO,
+        total_amount_ttc: Money::ZERO,
+        is_paid: false,
+        paid_at: None,
+        notes: None,
+        sales_ids,
+    }
+}
+
+/// cli1 paying `amount` in cash on `sale_id`.
+pub fn payment(sale_id: &str, amount: f64, date: &str) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        sale_id: Some(sale_id.to_string()),
+        invoice_id: None,
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(amount),
+        date: date.to_string(),
+        method: "cash".to_string(),
+        notes: None,
+        check_number: None,
+        bank: None,
+        maturity_date: None,
+    }
+}
+
+/// Sold products analytics over everything.
+pub fn no_filter() -> SoldProductsFilter {
+    SoldProductsFilter {
+        start_date: None,
+        end_date: None,
+        product_type: None,
+        client_id: None,
+        thickness: None,
+        width: None,
+        unit_price_min: None,
+        unit_price_max: None,
+        payment_status: None,
+    }
+}
@@ -0,0 +1,150 @@
+use app_lib::commands::{self, CreatePaymentRequest, CreditAdjustmentRequest, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 10000, 11900, 0, 0), ('sale2', 'cli1', '2024-06-11', 5000, 5950, 0, 0)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+async fn balance(pool: &SqlitePool) -> Money {
+    sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = 'cli1'").fetch_one(pool).await.unwrap()
+}
+
+fn payment_request(sale_id: &str, amount: f64, method: &str) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        sale_id: Some(sale_id.to_string()),
+        invoice_id: None,
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(amount),
+        date: "2024-06-12".to_string(),
+        method: method.to_string(),
+        notes: None,
+        check_number: None,
+        bank: None,
+        maturity_date: None,
+    }
+}
+
+#[tokio::test]
+async fn test_overpayment_becomes_credit_and_pays_another_sale() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 150.00 against a 119.00 sale leaves 31.00 of credit
+    let cash = commands::create_payment(payment_request("sale1", 150.0, "cash"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(31.0));
+
+    let too_much = commands::create_payment(payment_request("sale2", 40.0, "credit"), app.state()).await;
+    assert!(too_much.is_err(), "A credit payment cannot exceed the credit balance");
+    let over_due = commands::create_payment(payment_request("sale2", 60.0, "credit"), app.state()).await;
+    assert!(over_due.is_err(), "A credit payment cannot exceed the amount due");
+
+    commands::create_payment(payment_request("sale2", 30.0, "credit"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(1.0));
+
+    // The overpayment was spent, so the payment that produced it cannot be removed
+    assert!(commands::delete_payment(cash.id.clone(), app.state()).await.is_err());
+    assert_eq!(balance(&pool).await, Money::from_units(1.0), "The failed delete should roll back");
+
+    let ledger = commands::get_client_credit_ledger("cli1".to_string(), app.state()).await?;
+    let running: Vec<(String, Money)> = ledger.iter().map(|t| (t.source_type.clone(), t.balance)).collect();
+    assert_eq!(running, vec![
+        ("payment".to_string(), Money::from_units(31.0)),
+        ("credit_use".to_string(), Money::from_units(1.0)),
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_payment_edits_keep_the_ledger_in_sync() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let payment = commands::create_payment(payment_request("sale1", 120.0, "cash"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(1.0));
+    commands::update_payment(payment.id.clone(), payment_request("sale1", 119.0, "cash"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::ZERO, "An exact payment leaves no credit");
+    commands::update_payment(payment.id.clone(), payment_request("sale1", 125.0, "cash"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(6.0));
+
+    commands::delete_payment(payment.id.clone(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::ZERO);
+    commands::restore_payment(payment.id.clone(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(6.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sibling_payments_share_one_overpayment() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 100.00 + 50.00 against 119.00: only the second payment overpays
+    let first = commands::create_payment(payment_request("sale1", 100.0, "cash"), app.state()).await?;
+    commands::create_payment(payment_request("sale1", 50.0, "cash"), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(31.0));
+
+    // Without the first payment the second one no longer covers the sale
+    commands::delete_payment(first.id.clone(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::ZERO, "The sibling's overpayment is taken back");
+    commands::restore_payment(first.id.clone(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(31.0));
+
+    // Deleting the sale deletes its payments and their credit
+    commands::delete_sale("sale1".to_string(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::ZERO);
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM credit_transactions WHERE client_id = 'cli1'").fetch_one(&pool).await?;
+    assert_eq!(rows, 0);
+    commands::restore_sale("sale1".to_string(), app.state()).await?;
+    assert_eq!(balance(&pool).await, Money::from_units(31.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_manual_adjustments_and_reconciliation() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let adjustment = |amount: f64| CreditAdjustmentRequest {
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(amount),
+        notes: Some("Goodwill".to_string()),
+    };
+    let entry = commands::create_credit_adjustment(adjustment(50.0), app.state()).await?;
+    assert_eq!(entry.kind, "credit");
+    assert_eq!(entry.balance, Money::from_units(50.0));
+    let entry = commands::create_credit_adjustment(adjustment(-20.0), app.state()).await?;
+    assert_eq!(entry.kind, "debit");
+    assert_eq!(entry.amount, Money::from_units(20.0));
+    assert_eq!(balance(&pool).await, Money::from_units(30.0));
+    assert!(commands::create_credit_adjustment(adjustment(-40.0), app.state()).await.is_err(), "Balance cannot go negative");
+    assert!(commands::create_credit_adjustment(adjustment(0.0), app.state()).await.is_err());
+    let audits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE action = 'credit_adjustment'").fetch_one(&pool).await?;
+    assert_eq!(audits, 2);
+
+    // A balance edited behind the ledger's back is reset to the ledger total
+    sqlx::query("UPDATE clients SET credit_balance = 99999 WHERE id = 'cli1'").execute(&pool).await?;
+    let corrections = commands::reconcile_credit_balances(app.state()).await?;
+    assert_eq!(corrections.len(), 1);
+    assert_eq!(corrections[0].old_balance, Money::from_centimes(99999));
+    assert_eq!(corrections[0].ledger_balance, Money::from_units(30.0));
+    assert_eq!(balance(&pool).await, Money::from_units(30.0));
+    assert!(commands::reconcile_credit_balances(app.state()).await?.is_empty());
+    Ok(())
+}
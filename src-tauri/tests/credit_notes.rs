@@ -0,0 +1,163 @@
+mod common;
+
+use app_lib::commands::{self, CreateCreditNoteRequest, CreateDeliveryNoteRequest, CreateInvoiceRequest, CreatePaymentRequest, CreateSaleItemRequest, CreditNoteLineRequest, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+fn coil_item(price_per_ton: f64, tax_rate: Option<f64>) -> CreateSaleItemRequest {
+    CreateSaleItemRequest { tax_rate, ..common::coil_item(1.0, price_per_ton) }
+}
+
+/// Everything left on the sale, in one trip
+fn delivery_note(sale: &commands::Sale) -> CreateDeliveryNoteRequest {
+    CreateDeliveryNoteRequest {
+        sale_id: sale.id.clone(),
+        date: "2024-06-10".to_string(),
+        truck: None,
+        driver: None,
+        plate: None,
+        notes: None,
+        lines: vec![],
+    }
+}
+
+fn invoice(sale: &commands::Sale) -> CreateInvoiceRequest {
+    CreateInvoiceRequest {
+        total_amount_ht: sale.total_amount,
+        total_amount_ttc: sale.total_amount_ttc,
+        ..common::invoice(vec![sale.id.clone()])
+    }
+}
+
+fn payment(sale: &commands::Sale, invoice_id: &str, amount: f64, date: &str) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        invoice_id: Some(invoice_id.to_string()),
+        method: "transfer".to_string(),
+        ..common::payment(&sale.id, amount, date)
+    }
+}
+
+fn credit_note(invoice_id: &str, date: &str, lines: Vec<CreditNoteLineRequest>, amount: Option<f64>) -> CreateCreditNoteRequest {
+    CreateCreditNoteRequest {
+        invoice_id: invoice_id.to_string(),
+        date: date.to_string(),
+        reason: Some("Returned goods".to_string()),
+        lines,
+        amount: amount.map(Money::from_units),
+    }
+}
+
+async fn invoice_progress(pool: &SqlitePool, invoice_id: &str) -> (Money, String, bool) {
+    let page = {
+        let app = tauri::test::mock_app();
+        app.manage(pool.clone());
+        commands::get_invoices(Some(1), Some(50), None, app.state()).await.unwrap()
+    };
+    let row = page.rows.into_iter().find(|row| row["id"] == invoice_id).unwrap();
+    (
+        Money::from_units(row["balance_due"].as_f64().unwrap()),
+        row["payment_status"].as_str().unwrap().to_string(),
+        row["is_paid"].as_bool().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_partial_and_full_credit_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 100 000 at 19% and 50 000 at 9%: 173 500 TTC
+    let sold = commands::create_sale(common::sale(vec![coil_item(100000.0, None), coil_item(50000.0, Some(0.09))]), app.state()).await?;
+    commands::create_delivery_note(delivery_note(&sold), app.state()).await?;
+    let inv = commands::create_invoice(invoice(&sold), app.state()).await?;
+    let reduced_item = sold.items[1].id.clone();
+
+    let line = commands::create_credit_note(credit_note(&inv.id, "2024-06-15", vec![CreditNoteLineRequest {
+        sale_item_id: reduced_item.clone(),
+        amount: Some(Money::from_units(10000.0)),
+    }], None), app.state()).await?;
+    assert_eq!(line.credit_note_number, "AV-24/00001");
+    assert_eq!(line.invoice_number, inv.invoice_number);
+    assert_eq!((line.total_amount_ht, line.tax_amount, line.total_amount_ttc), (Money::from_units(10000.0), Money::from_units(900.0), Money::from_units(10900.0)));
+    assert_eq!(invoice_progress(&pool, &inv.id).await, (Money::from_units(162600.0), "unpaid".to_string(), false));
+
+    // Spread over both rates, to the centime
+    let spread = commands::create_credit_note(credit_note(&inv.id, "2024-06-20", vec![], Some(11900.0)), app.state()).await?;
+    assert_eq!(spread.credit_note_number, "AV-24/00002");
+    assert_eq!(spread.total_amount_ttc, Money::from_units(11900.0));
+    assert_eq!(spread.items.len(), 2);
+    assert_eq!(spread.items.iter().map(|i| i.total_amount).sum::<Money>(), spread.total_amount_ht);
+    assert_eq!(spread.items.iter().map(|i| i.tax_amount).sum::<Money>(), spread.tax_amount);
+
+    let too_much = credit_note(&inv.id, "2024-06-20", vec![CreditNoteLineRequest { sale_item_id: reduced_item.clone(), amount: Some(Money::from_units(50000.0)) }], None);
+    assert!(commands::create_credit_note(too_much, app.state()).await.is_err(), "The item was partly credited already");
+    assert!(commands::create_credit_note(credit_note(&inv.id, "2024-06-20", vec![], Some(500000.0)), app.state()).await.is_err());
+    let both = credit_note(&inv.id, "2024-06-20", vec![CreditNoteLineRequest { sale_item_id: reduced_item, amount: None }], Some(100.0));
+    assert!(commands::create_credit_note(both, app.state()).await.is_err());
+
+    // Credit the rest once 100 000 was paid: what was paid becomes client credit
+    let paid = commands::create_payment(payment(&sold, &inv.id, 100000.0, "2024-06-25"), app.state()).await?;
+    let rest = commands::create_credit_note(credit_note(&inv.id, "2024-06-30", vec![], None), app.state()).await?;
+    assert_eq!(rest.total_amount_ttc, Money::from_units(173500.0 - 10900.0 - 11900.0));
+    assert_eq!(invoice_progress(&pool, &inv.id).await, (Money::ZERO, "overpaid".to_string(), true));
+    assert_eq!(common::credit_balance(&pool, "cli1").await, Money::from_units(100000.0));
+    let items_credited: i64 = sqlx::query_scalar("SELECT SUM(total_amount + tax_amount) FROM credit_note_items").fetch_one(&pool).await?;
+    assert_eq!(items_credited, 17350000, "Lines add up to the invoice");
+    assert!(commands::create_credit_note(credit_note(&inv.id, "2024-07-01", vec![], None), app.state()).await.is_err(), "Nothing left to credit");
+
+    let refused = commands::delete_invoice(inv.id.clone(), app.state()).await.unwrap_err();
+    assert!(refused.contains("credit note"), "{}", refused);
+    assert!(commands::update_invoice(inv.id.clone(), invoice(&sold), app.state()).await.is_err());
+    let refused = commands::update_sale(sold.id.clone(), common::sale(vec![coil_item(100000.0, None)]), app.state()).await.unwrap_err();
+    assert!(refused.contains("credit notes (AV-24/00001, AV-24/00002, AV-24/00003)"), "{}", refused);
+
+    // The client was refunded only because they had paid
+    commands::delete_payment(paid.id, app.state()).await?;
+    assert_eq!(common::credit_balance(&pool, "cli1").await, Money::ZERO);
+
+    let notes = commands::get_credit_notes(Some(inv.id.clone()), None, app.state()).await?;
+    let numbers: Vec<&str> = notes.iter().map(|n| n.credit_note_number.as_str()).collect();
+    assert_eq!(numbers, vec!["AV-24/00001", "AV-24/00002", "AV-24/00003"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_credit_notes_in_statement_aging_and_vat_report() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sold = commands::create_sale(common::sale(vec![coil_item(100000.0, None)]), app.state()).await?;
+    commands::create_delivery_note(delivery_note(&sold), app.state()).await?;
+    let inv = commands::create_invoice(invoice(&sold), app.state()).await?;
+    let half = commands::create_credit_note(credit_note(&inv.id, "2024-07-05", vec![], Some(59500.0)), app.state()).await?;
+    assert_eq!((half.total_amount_ht, half.tax_amount), (Money::from_units(50000.0), Money::from_units(9500.0)));
+
+    let june = commands::get_ar_aging(Some("2024-06-30".to_string()), None, app.state()).await?;
+    assert_eq!(june.total, Money::from_units(119000.0), "Not credited yet at the end of June");
+    let july = commands::get_ar_aging(Some("2024-07-31".to_string()), None, app.state()).await?;
+    assert_eq!(july.total, Money::from_units(59500.0));
+    assert_eq!(july.clients[0].invoices[0].credited_amount, Money::from_units(59500.0));
+
+    let statement = commands::get_client_statement("cli1".to_string(), None, None, app.state()).await?;
+    let entry = statement.entries.iter().find(|e| e.kind == "credit_note").expect("credit note listed");
+    assert_eq!((entry.reference.as_deref(), entry.credit), (Some("AV-24/00001"), Money::from_units(59500.0)));
+    assert_eq!(statement.closing_balance, Money::from_units(59500.0));
+
+    let vat = commands::get_vat_report("2024-07".to_string(), "invoiced".to_string(), app.state()).await?;
+    assert_eq!(vat.rows.len(), 1);
+    assert_eq!(vat.rows[0].kind, "credit_note");
+    assert_eq!((vat.taxable_amount, vat.tax_amount), (Money::from_units(-50000.0), Money::from_units(-9500.0)));
+    let cash = commands::get_vat_report("2024-07".to_string(), "cash".to_string(), app.state()).await?;
+    assert!(cash.rows.is_empty(), "Nothing collected, nothing to give back");
+
+    // Paid in full afterwards: the credited half is refunded, and its VAT with it
+    commands::create_payment(payment(&sold, &inv.id, 119000.0, "2024-07-20"), app.state()).await?;
+    assert_eq!(common::credit_balance(&pool, "cli1").await, Money::from_units(59500.0));
+    let cash = commands::get_vat_report("2024-07".to_string(), "cash".to_string(), app.state()).await?;
+    let kinds: Vec<&str> = cash.rows.iter().map(|r| r.kind.as_str()).collect();
+    assert_eq!(kinds, vec!["payment", "credit_note"]);
+    assert_eq!(cash.tax_amount, Money::from_units(9500.0));
+    Ok(())
+}
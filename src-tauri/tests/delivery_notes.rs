@@ -0,0 +1,139 @@
+mod common;
+
+use app_lib::commands::{self, CreateDeliveryNoteRequest, CreateInvoiceRequest, CreateSaleItemRequest, CreateSaleRequest, DeliveryNoteLineRequest, Money};
+use chrono::{TimeZone, Utc};
+use tauri::Manager;
+
+fn item(description: &str, product_type: &str, weight: Option<f64>, quantity: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: description.to_string(),
+        coil_width: Some(1000.0),
+        top_coat_ral: Some("9002".to_string()),
+        coil_weight: weight,
+        quantity,
+        product_type: product_type.to_string(),
+        ..common::coil_item(0.0, 100.0)
+    }
+}
+
+/// A 10 t coil and 100 corrugated sheets
+fn sale() -> CreateSaleRequest {
+    CreateSaleRequest {
+        date: Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap(),
+        ..common::sale(vec![item("Coil", "coil", Some(10.0), 1.0), item("Sheets", "corrugated_sheet", None, 100.0)])
+    }
+}
+
+fn delivery_note(sale_id: &str, date: &str, lines: Vec<DeliveryNoteLineRequest>) -> CreateDeliveryNoteRequest {
+    CreateDeliveryNoteRequest {
+        sale_id: sale_id.to_string(),
+        date: date.to_string(),
+        truck: Some("Hino 500".to_string()),
+        driver: Some("K. Amrani".to_string()),
+        plate: Some(" 01234-116-16 ".to_string()),
+        notes: None,
+        lines,
+    }
+}
+
+fn line(sale_item_id: &str, weight: Option<f64>, quantity: Option<f64>) -> DeliveryNoteLineRequest {
+    DeliveryNoteLineRequest { sale_item_id: sale_item_id.to_string(), weight, quantity }
+}
+
+fn invoice(sale_id: &str) -> CreateInvoiceRequest {
+    CreateInvoiceRequest {
+        date: "2024-06-20".to_string(),
+        due_date: "2024-07-20".to_string(),
+        total_amount_ht: Money::from_units(100.0),
+        total_amount_ttc: Money::from_units(119.0),
+        ..common::invoice(vec![sale_id.to_string()])
+    }
+}
+
+#[tokio::test]
+async fn test_partial_deliveries_gate_invoicing() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sold = commands::create_sale(sale(), app.state()).await?;
+    let (coil, sheets) = (sold.items[0].id.clone(), sold.items[1].id.clone());
+    assert_eq!(sold.delivery_status, "undelivered");
+    assert!(commands::create_invoice(invoice(&sold.id), app.state()).await.is_err(), "Undelivered sales cannot be invoiced");
+
+    // First trip: 4 t of the coil and 30 sheets weighing 1.2 t
+    let first = commands::create_delivery_note(delivery_note(&sold.id, "2024-06-05", vec![
+        line(&coil, Some(4.0), None),
+        line(&sheets, Some(1.2), Some(30.0)),
+    ]), app.state()).await?;
+    assert_eq!(first.delivery_note_number, "BL-24/00001");
+    assert_eq!(first.plate.as_deref(), Some("01234-116-16"));
+    assert_eq!((first.items[1].weight, first.items[1].quantity), (Some(1.2), Some(30.0)));
+    assert_eq!(commands::get_sale_by_id(sold.id.clone(), app.state()).await?.unwrap().delivery_status, "partial");
+    assert!(commands::create_invoice(invoice(&sold.id), app.state()).await.is_err(), "Partly delivered sales cannot be invoiced");
+
+    // Nothing beyond the sale, nothing twice, nothing from another sale
+    assert!(commands::create_delivery_note(delivery_note(&sold.id, "2024-06-06", vec![line(&coil, Some(6.5), None)]), app.state()).await.is_err());
+    assert!(commands::create_delivery_note(delivery_note(&sold.id, "2024-06-06", vec![line(&sheets, None, Some(0.0))]), app.state()).await.is_err());
+    assert!(commands::create_delivery_note(delivery_note(&sold.id, "2024-06-06", vec![line(&coil, Some(1.0), None), line(&coil, Some(1.0), None)]), app.state()).await.is_err());
+    assert!(commands::create_delivery_note(delivery_note(&sold.id, "2024-06-06", vec![line("other", Some(1.0), None)]), app.state()).await.is_err());
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM delivery_notes").await, 1, "Rejected delivery notes are not written");
+
+    // Second trip takes whatever is left
+    let second = commands::create_delivery_note(delivery_note(&sold.id, "2024-06-07", vec![]), app.state()).await?;
+    assert_eq!(second.delivery_note_number, "BL-24/00002");
+    assert_eq!((second.items[0].weight, second.items[1].quantity), (Some(6.0), Some(70.0)));
+    let delivered = commands::get_sale_by_id(sold.id.clone(), app.state()).await?.unwrap();
+    assert_eq!(delivered.delivery_status, "delivered");
+    assert_eq!((delivered.items[0].delivered, delivered.items[1].delivered), (10.0, 100.0));
+    assert!(commands::create_delivery_note(delivery_note(&sold.id, "2024-06-08", vec![]), app.state()).await.is_err(), "Nothing is left to deliver");
+
+    // The delivered items are fixed, and so are the deliveries once invoiced
+    assert!(commands::update_sale(sold.id.clone(), sale(), app.state()).await.is_err());
+    assert!(commands::delete_sale(sold.id.clone(), app.state()).await.is_err());
+    commands::create_invoice(invoice(&sold.id), app.state()).await?;
+    assert!(commands::delete_delivery_note(first.id.clone(), app.state()).await.is_err());
+
+    let notes = commands::get_delivery_notes(Some(sold.id.clone()), None, app.state()).await?;
+    assert_eq!(notes.iter().map(|n| n.delivery_note_number.as_str()).collect::<Vec<_>>(), ["BL-24/00001", "BL-24/00002"]);
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM audit_log WHERE entity_type = 'delivery_note' AND action = 'create'").await, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deleted_delivery_notes_and_printing() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // Sales recorded before delivery notes count as delivered
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc) VALUES ('old', 'cli1', '2024-01-10T09:00:00Z', 1000, 1190)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, quantity, price_per_ton, total_amount, product_type) VALUES ('old-item', 'old', 'Coil', 1, 1000, 1000, 'coil')")
+        .execute(&pool)
+        .await?;
+    assert_eq!(commands::get_sale_by_id("old".to_string(), app.state()).await?.unwrap().delivery_status, "delivered");
+
+    let sold = commands::create_sale(sale(), app.state()).await?;
+    let note = commands::create_delivery_note(delivery_note(&sold.id, "2024-06-05", vec![line(&sold.items[0].id, Some(2.5), Some(1.0))]), app.state()).await?;
+    let dir = std::env::temp_dir().join(format!("delivery-note-{}", note.id));
+    let path = commands::generate_delivery_note_pdf(note.id.clone(), dir.join("bl.pdf").to_string_lossy().to_string(), app.state()).await?;
+    let bytes = std::fs::read(&path)?;
+    assert!(bytes.starts_with(b"%PDF"));
+    std::fs::remove_dir_all(&dir)?;
+
+    // A cancelled trip gives its weight back to the sale
+    commands::delete_delivery_note(note.id.clone(), app.state()).await?;
+    assert!(commands::get_delivery_note(note.id.clone(), app.state()).await?.is_none());
+    let reopened = commands::get_sale_by_id(sold.id.clone(), app.state()).await?.unwrap();
+    assert_eq!((reopened.delivery_status.as_str(), reopened.items[0].delivered), ("undelivered", 0.0));
+    assert!(commands::generate_delivery_note_pdf(note.id.clone(), dir.join("bl.pdf").to_string_lossy().to_string(), app.state()).await.is_err());
+
+    // With no live delivery note, the sale can change again
+    commands::update_sale(sold.id.clone(), sale(), app.state()).await?;
+    let replaced = commands::create_delivery_note(delivery_note(&sold.id, "2024-06-09", vec![]), app.state()).await?;
+    assert_eq!(replaced.delivery_note_number, "BL-24/00002", "Numbers of deleted notes are not reused");
+    assert_eq!(commands::get_sale_by_id(sold.id.clone(), app.state()).await?.unwrap().delivery_status, "delivered");
+    Ok(())
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use app_lib::commands::{self, UpdateClientRequest};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    sqlx::query("INSERT INTO clients (id, name, company, nif, rc) VALUES ('cli1', 'Karim Haddad', 'Toitures de l''Est', '000111222333', 'RC-16/0042')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('s1', 'cli1', '2024-04-10T09:00:00Z', 100000, 119000, 1, 'inv1', 0), ('s2', 'cli1', '2024-04-12T09:00:00Z', 50000, 59500, 0, NULL, 1)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, coil_ref, top_coat_ral, back_coat_ral, coil_thickness, quantity, price_per_ton, total_amount, product_type) VALUES ('it1', 's1', 'Bobine prélaquée 0.5mm', 'BOB-7781', '9002', '7016', 0.5, 1, 100000, 100000, 'coil'), ('it2', 's2', 'Bobine prélaquée 0.4mm', 'BOB-7782', '9002', NULL, 0.4, 1, 50000, 50000, 'coil')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, notes, is_deleted) VALUES ('inv1', 'FAC-24/00077', 'cli1', '2024-04-10', '2024-05-10', 100000, 119000, 'Livraison chantier Sétif', 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, check_number, notes, is_deleted) VALUES ('p1', 's1', 'cli1', 119000, '2024-04-20', 'check', 'CHQ-445566', 'Chèque BNA', 0)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn kinds_and_ids(hits: &[commands::SearchHit]) -> Vec<(&str, &str)> {
+    hits.iter().map(|h| (h.kind.as_str(), h.id.as_str())).collect()
+}
+
+#[tokio::test]
+async fn test_global_search_finds_every_entity_type() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // Items of the deleted sale s2 are left out
+    let hits = commands::global_search("9002 bobine".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("sale_item", "it1")]);
+    assert_eq!(hits[0].sale_id.as_deref(), Some("s1"));
+    assert_eq!(hits[0].client_id.as_deref(), Some("cli1"));
+    assert!(hits[0].snippet.contains('['), "The snippet highlights the match: {}", hits[0].snippet);
+
+    let hits = commands::global_search("toitures".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("client", "cli1")]);
+    let hits = commands::global_search("RC-16/0042".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("client", "cli1")]);
+
+    // Prefixes and accents without diacritics both match
+    let hits = commands::global_search("FAC-24/0007".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("invoice", "inv1")]);
+    let hits = commands::global_search("setif".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("invoice", "inv1")]);
+
+    let hits = commands::global_search("CHQ-445566".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("payment", "p1")]);
+
+    // Text that is not valid FTS syntax is searched literally
+    assert!(commands::global_search("\"0.5mm (".to_string(), None, app.state()).await?.iter().any(|h| h.id == "it1"));
+    assert!(commands::global_search("   ".to_string(), None, app.state()).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_index_follows_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // Rows that existed before the index was built are searchable too
+    let hits = commands::global_search("benali".to_string(), Some(5), app.state()).await?;
+    assert!(hits.iter().any(|h| h.kind == "client" && h.title == "Ahmed Benali"));
+
+    commands::update_client("cli1".to_string(), UpdateClientRequest {
+        name: None,
+        company: Some("Charpentes du Sud".to_string()),
+        email: None,
+        phone: None,
+        address: None,
+        notes: None,
+        nif: None,
+        nis: None,
+        rc: None,
+        ai: None,
+        rib: None,
+    }, app.state()).await?;
+    assert!(commands::global_search("toitures".to_string(), None, app.state()).await?.is_empty());
+    let hits = commands::global_search("charpentes".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("client", "cli1")]);
+
+    commands::delete_payment("p1".to_string(), app.state()).await?;
+    assert!(commands::global_search("CHQ-445566".to_string(), None, app.state()).await?.is_empty());
+    commands::restore_payment("p1".to_string(), app.state()).await?;
+    assert_eq!(commands::global_search("CHQ-445566".to_string(), None, app.state()).await?.len(), 1);
+
+    sqlx::query("DELETE FROM sale_items WHERE id = 'it1'").execute(&pool).await?;
+    assert!(commands::global_search("BOB-7781".to_string(), None, app.state()).await?.is_empty());
+
+    // Each row stays indexed exactly once, under the rowid its side table records
+    for table in ["clients", "sale_items", "invoices", "payments"] {
+        let (rows, indexed, mapped): (i64, i64, i64) = sqlx::query_as(&format!(
+            "SELECT (SELECT COUNT(*) FROM {0}), (SELECT COUNT(*) FROM {0}_fts), (SELECT COUNT(*) FROM {0}_fts_rowids r JOIN {0}_fts f ON f.rowid = r.fts_rowid AND f.id = r.id)",
+            table
+        ))
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!((indexed, mapped), (rows, rows), "{}", table);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_global_search_ranks_and_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // "haddad" is half the client's name; in the note it is one word among many
+    sqlx::query("UPDATE payments SET notes = 'Remis par le chauffeur de M. Haddad au dépôt principal de la zone industrielle' WHERE id = 'p1'")
+        .execute(&pool)
+        .await?;
+    let hits = commands::global_search("haddad".to_string(), None, app.state()).await?;
+    assert_eq!(kinds_and_ids(&hits), vec![("client", "cli1"), ("payment", "p1")]);
+    assert!(hits[0].score > hits[1].score);
+
+    let hits = commands::global_search("haddad".to_string(), Some(1), app.state()).await?;
+    assert_eq!(hits.len(), 1);
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use app_lib::commands::{self, CreateInvoiceRequest, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO settings (company_name, company_address, company_phone) VALUES ('Test Company', 'Address', '000')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn invoice_request(date: &str, sales_ids: Vec<String>) -> CreateInvoiceRequest {
+    CreateInvoiceRequest {
+        invoice_number: None,
+        client_id: "cli1".to_string(),
+        date: date.to_string(),
+        due_date: date.to_string(),
+        total_amount_ht: Money::ZERO,
+        total_amount_ttc: Money::ZERO,
+        is_paid: false,
+        paid_at: None,
+        notes: None,
+        sales_ids,
+    }
+}
+
+#[tokio::test]
+async fn test_invoice_numbers_are_sequential_per_year() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let preview = commands::preview_next_invoice_number(Some("2024-03-01".to_string()), app.state()).await?;
+    assert_eq!(preview, "FAC-24/00001");
+    // Previewing does not consume the number
+    let preview_again = commands::preview_next_invoice_number(Some("2024-03-01".to_string()), app.state()).await?;
+    assert_eq!(preview_again, "FAC-24/00001");
+
+    let first = commands::create_invoice(invoice_request("2024-03-01", vec![]), app.state()).await?;
+    let second = commands::create_invoice(invoice_request("2024-11-30T10:00:00.000Z", vec![]), app.state()).await?;
+    assert_eq!(first.invoice_number, "FAC-24/00001");
+    assert_eq!(second.invoice_number, "FAC-24/00002");
+
+    // A new year starts again from 1
+    let next_year = commands::create_invoice(invoice_request("2025-01-02", vec![]), app.state()).await?;
+    assert_eq!(next_year.invoice_number, "FAC-25/00001");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_invoice_does_not_consume_a_number() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let failed = commands::create_invoice(invoice_request("2024-03-01", vec!["missing-sale".to_string()]), app.state()).await;
+    assert!(failed.is_err(), "Linking a missing sale should fail");
+    let created = commands::create_invoice(invoice_request("2024-03-01", vec![]), app.state()).await?;
+    assert_eq!(created.invoice_number, "FAC-24/00001", "The rolled back number should be reused");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invoice_number_format_comes_from_settings() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    sqlx::query("UPDATE settings SET invoice_number_format = '{YY}{MM}/{SEQ:03}'")
+        .execute(&pool)
+        .await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let created = commands::create_invoice(invoice_request("2024-07-15", vec![]), app.state()).await?;
+    assert_eq!(created.invoice_number, "2407/001");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invoice_numbers_skip_existing_and_stay_fixed() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    // Issued before the sequence existed
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('old1', 'FAC-24/00001', 'cli1', '2024-01-10', '2024-02-10', 0, 0, 0, 0)")
+        .execute(&pool)
+        .await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let preview = commands::preview_next_invoice_number(Some("2024-03-01".to_string()), app.state()).await?;
+    assert_eq!(preview, "FAC-24/00002", "The preview skips existing numbers too");
+    let created = commands::create_invoice(invoice_request("2024-03-01", vec![]), app.state()).await?;
+    assert_eq!(created.invoice_number, "FAC-24/00002", "Existing numbers should be skipped");
+
+    let mut stale = invoice_request("2024-03-01", vec![]);
+    stale.invoice_number = Some("FAC-24/00002".to_string());
+    assert!(commands::create_invoice(stale, app.state()).await.is_err(), "A stale previewed number should be rejected");
+    assert!(commands::create_invoice(invoice_request("not a date", vec![]), app.state()).await.is_err(), "An unparsable date should be rejected");
+
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-03-01', 10000, 11900, 0, 0)")
+        .execute(&pool)
+        .await?;
+    let mut renumber = invoice_request("2024-03-01", vec!["sale1".to_string()]);
+    renumber.invoice_number = Some("FAC-24/00099".to_string());
+    assert!(commands::update_invoice(created.id.clone(), renumber, app.state()).await.is_err(), "Assigned numbers should not change");
+    let mut same_number = invoice_request("2024-03-01", vec!["sale1".to_string()]);
+    same_number.invoice_number = Some(created.invoice_number.clone());
+    let updated = commands::update_invoice(created.id.clone(), same_number, app.state()).await?;
+    assert_eq!(updated.invoice_number, "FAC-24/00002");
+
+    let refused = commands::delete_invoice(created.id.clone(), app.state()).await.unwrap_err();
+    assert!(refused.contains("credit note"), "Issued invoices are cancelled by credit note: {}", refused);
+
+    let empty = commands::create_invoice(invoice_request("2024-03-02", vec![]), app.state()).await?;
+    commands::delete_invoice(empty.id.clone(), app.state()).await?;
+    let kept: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM invoices WHERE id = ? AND is_deleted = 1")
+        .bind(&empty.id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(kept, 1, "Numbered invoices should only be soft-deleted");
+    Ok(())
+}
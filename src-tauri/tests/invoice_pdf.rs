@@ -0,0 +1,95 @@
+use app_lib::commands::{self, amount_in_words, invoice_line_quantity, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+// 2 x 2 PNG, as the settings page stores an uploaded logo
+const LOGO: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAIAAAD91JpzAAAAEElEQVR4nGNQcGgAIgYIBQAXDgOBobd1AQAAAABJRU5ErkJggg==";
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO settings (id, company_name, company_address, company_phone, company_logo, currency, nif, rib) VALUES ('set1', 'Acier Plus SARL', 'Zone industrielle, Rouiba', '023 00 00 00', ?, 'DZD', '000123456789', '00799999000123456789')")
+        .bind(LOGO)
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO clients (id, name, company, address, nif) VALUES ('cli1', 'Karim B.', 'Toitures de l''Est', 'Sétif', '000987654321')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, transportation_fee, tax_rate, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 1500000, 1785000, 1, 'inv1', 0, 0.19, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, coil_thickness, coil_width, top_coat_ral, coil_weight, quantity, price_per_ton, total_amount, product_type) VALUES ('item1', 'sale1', 'Bobine prélaquée', 0.5, 1250, '9010', 2.5, 1, 400000, 1000000, 'coil'), ('item2', 'sale1', 'Tôle ondulée TN40', 0.4, 6, NULL, NULL, 10, 8333, 500000, 'corrugated_sheet')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, payment_method, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-06-10', '2024-07-10', 1500000, 1785000, 'bank_transfer', 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+#[tokio::test]
+async fn test_generate_invoice_pdf_writes_a_pdf() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let dir = std::env::temp_dir().join(format!("invoice_pdf_{}", uuid::Uuid::new_v4()));
+    let output = dir.join("factures").join("FAC-24-00001.pdf");
+    let written = commands::generate_invoice_pdf("inv1".to_string(), output.to_string_lossy().to_string(), app.state()).await?;
+    let bytes = std::fs::read(&written)?;
+    assert!(bytes.starts_with(b"%PDF"), "Output should be a PDF document");
+    assert!(bytes.len() > 10_000, "Fonts are embedded in the document");
+
+    // Enough lines to spill over a second page still renders
+    for i in 0..60 {
+        sqlx::query("INSERT INTO sale_items (sale_id, description, quantity, price_per_ton, total_amount, product_type) VALUES ('sale1', ?, 1, 100, 100, 'other')")
+            .bind(format!("Accessoire {}", i))
+            .execute(&pool)
+            .await?;
+    }
+    commands::generate_invoice_pdf("inv1".to_string(), output.to_string_lossy().to_string(), app.state()).await?;
+    assert!(std::fs::read(&output)?.starts_with(b"%PDF"));
+
+    let missing = commands::generate_invoice_pdf("nope".to_string(), output.to_string_lossy().to_string(), app.state()).await;
+    assert!(missing.is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_amount_in_words() {
+    let words = |units: f64| amount_in_words(Money::from_units(units), "DZD");
+    assert_eq!(words(1.0), "un dinar algérien");
+    assert_eq!(words(21.0), "vingt et un dinars algériens");
+    assert_eq!(words(71.0), "soixante et onze dinars algériens");
+    assert_eq!(words(80.0), "quatre-vingts dinars algériens");
+    assert_eq!(words(81.0), "quatre-vingt-un dinars algériens");
+    assert_eq!(words(97.0), "quatre-vingt-dix-sept dinars algériens");
+    assert_eq!(words(200.0), "deux cents dinars algériens");
+    assert_eq!(words(201.0), "deux cent un dinars algériens");
+    assert_eq!(words(1000.0), "mille dinars algériens");
+    assert_eq!(words(80000.0), "quatre-vingt mille dinars algériens");
+    assert_eq!(words(200000.0), "deux cent mille dinars algériens");
+    assert_eq!(words(1_000_000.0), "un million de dinars algériens");
+    assert_eq!(
+        words(17850.5),
+        "dix-sept mille huit cent cinquante dinars algériens et cinquante centimes"
+    );
+    assert_eq!(words(2_300_000.01), "deux millions trois cent mille dinars algériens et un centime");
+}
+
+#[test]
+fn test_invoice_line_quantity_is_what_the_line_is_priced_by() {
+    // Four strips weighing 2.5 T in all are charged 2.5 × price_per_ton
+    assert_eq!(invoice_line_quantity("steel_slitting", 4.0, Some(250.0), Some(2.5)), "2,5 T");
+    assert_eq!(invoice_line_quantity("coil", 1.0, Some(1250.0), Some(2.5)), "2,5 T");
+    assert_eq!(invoice_line_quantity("corrugated_sheet", 10.0, Some(6.0), None), "60 m");
+    assert_eq!(invoice_line_quantity("other", 3.0, None, None), "3");
+}
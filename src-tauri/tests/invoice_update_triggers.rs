@@ -0,0 +1,138 @@
+use app_lib::commands::{self, CreateInvoiceRequest, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+use tauri::Manager;
+
+async fn invoice_status(pool: &SqlitePool, id: &str) -> Result<(i64, Option<String>), Box<dyn std::error::Error>> {
+    let row = sqlx::query("SELECT is_paid, paid_at FROM invoices WHERE id = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    Ok((row.get("is_paid"), row.get("paid_at")))
+}
+
+#[tokio::test]
+async fn test_invoice_relinking_recalculates_payment_status() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, paid_at, is_deleted) VALUES ('inv1', 'INV-001', 'cli1', '2024-06-10', '2024-06-20', 10000, 12000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 10000, 12000, 1, 'inv1', 0), ('sale2', 'cli1', '2024-06-11', 5000, 6000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', 'inv1', 'cli1', 12000, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+    assert_eq!(invoice_status(&pool, "inv1").await?.0, 1, "Single fully paid sale: invoice should be paid");
+
+    // Adding an unpaid sale raises the total and leaves the invoice partially paid
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is2', 'inv1', 'sale2')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("UPDATE invoices SET total_amount_ht = 15000, total_amount_ttc = 18000 WHERE id = 'inv1'")
+        .execute(&pool)
+        .await?;
+    assert_eq!(invoice_status(&pool, "inv1").await?, (0, None), "Added unpaid sale: invoice should be unpaid");
+
+    // Removing it again brings the total back down to what was paid
+    sqlx::query("DELETE FROM invoice_sales WHERE invoice_id = 'inv1' AND sale_id = 'sale2'")
+        .execute(&pool)
+        .await?;
+    sqlx::query("UPDATE invoices SET total_amount_ht = 10000, total_amount_ttc = 12000 WHERE id = 'inv1'")
+        .execute(&pool)
+        .await?;
+    let (is_paid, paid_at) = invoice_status(&pool, "inv1").await?;
+    assert_eq!(is_paid, 1, "Removed unpaid sale: invoice should be paid");
+    assert!(paid_at.is_some(), "Removed unpaid sale: paid_at should be set");
+
+    Ok(())
+}
+
+fn invoice_request(client_id: &str, sales_ids: &[&str]) -> CreateInvoiceRequest {
+    CreateInvoiceRequest {
+        invoice_number: None,
+        client_id: client_id.to_string(),
+        date: "2024-06-10".to_string(),
+        due_date: "2024-07-10".to_string(),
+        total_amount_ht: Money::ZERO,
+        total_amount_ttc: Money::ZERO,
+        is_paid: false,
+        paid_at: None,
+        notes: None,
+        sales_ids: sales_ids.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+async fn sale_link(pool: &SqlitePool, sale_id: &str) -> Result<(i64, Option<String>), Box<dyn std::error::Error>> {
+    let row = sqlx::query("SELECT is_invoiced, invoice_id FROM sales WHERE id = ?")
+        .bind(sale_id)
+        .fetch_one(pool)
+        .await?;
+    Ok((row.get("is_invoiced"), row.get("invoice_id")))
+}
+
+#[tokio::test]
+async fn test_update_invoice_relinks_sales_and_payments() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Client One'), ('cli2', 'Client Two')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 10000, 11900, 0, NULL, 0), ('sale2', 'cli1', '2024-06-11', 20000, 23800, 0, NULL, 0), ('sale3', 'cli2', '2024-06-11', 5000, 5950, 0, NULL, 0), ('sale4', 'cli1', '2024-06-12', 1000, 1190, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('pay2', 'sale2', 'cli1', 23800, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let invoice = commands::create_invoice(invoice_request("cli1", &["sale1"]), app.state()).await?;
+    commands::create_invoice(invoice_request("cli1", &["sale4"]), app.state()).await?;
+
+    // Swap sale1 for sale2; the duplicate id is linked once
+    let updated = commands::update_invoice(invoice.id.clone(), invoice_request("cli1", &["sale2", "sale2"]), app.state()).await?;
+    assert_eq!(updated.total_amount_ht, Money::from_units(200.0));
+    assert_eq!(updated.total_amount_ttc, Money::from_units(238.0));
+    assert!(updated.is_paid, "sale2 is fully paid, so the invoice is too");
+    let links: Vec<String> = sqlx::query_scalar("SELECT sale_id FROM invoice_sales WHERE invoice_id = ?")
+        .bind(&invoice.id)
+        .fetch_all(&pool)
+        .await?;
+    assert_eq!(links, vec!["sale2".to_string()]);
+    assert_eq!(sale_link(&pool, "sale1").await?, (0, None), "Removed sale should be unlinked");
+    assert_eq!(sale_link(&pool, "sale2").await?, (1, Some(invoice.id.clone())), "Added sale should be linked");
+    let payment_invoice: Option<String> = sqlx::query_scalar("SELECT invoice_id FROM payments WHERE id = 'pay2'")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(payment_invoice, Some(invoice.id.clone()), "Payments of the added sale should follow it");
+
+    let other_client = commands::update_invoice(invoice.id.clone(), invoice_request("cli1", &["sale2", "sale3"]), app.state()).await;
+    assert!(other_client.unwrap_err().contains("another client"));
+    let invoiced_elsewhere = commands::update_invoice(invoice.id.clone(), invoice_request("cli1", &["sale2", "sale4"]), app.state()).await;
+    assert!(invoiced_elsewhere.unwrap_err().contains("already invoiced"));
+
+    // A payment recorded against the invoice but not one of its sales blocks a client change
+    sqlx::query("INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', ?, 'cli1', 1000, '2024-06-12', 'cash', 0)")
+        .bind(&invoice.id)
+        .execute(&pool)
+        .await?;
+    let client_change = commands::update_invoice(invoice.id.clone(), invoice_request("cli2", &["sale3"]), app.state()).await;
+    assert!(client_change.unwrap_err().contains("direct payments"));
+    assert_eq!(sale_link(&pool, "sale2").await?, (1, Some(invoice.id.clone())), "Rejected update should change nothing");
+    Ok(())
+}
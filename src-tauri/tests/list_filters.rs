@@ -0,0 +1,155 @@
+mod common;
+
+use app_lib::commands::{self, ListFilter, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    // Hide the mock clients seeded by the migrations
+    sqlx::query("UPDATE clients SET is_deleted = 1").execute(&pool).await?;
+    // Gamma is soft-deleted and must never be listed
+    sqlx::query("INSERT INTO clients (id, name, company, is_deleted) VALUES ('cli1', 'Alpha', 'Toitures Alpha', 0), ('cli2', 'Beta', 'Bâtiment Beta', 0), ('cli3', 'Gamma', NULL, 1)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('s1', 'cli1', '2024-05-01T09:00:00Z', 10000, 11900, 1, 'inv1', 0), ('s2', 'cli1', '2024-05-15T09:00:00Z', 5000, 5950, 0, NULL, 0), ('s3', 'cli2', '2024-06-01T09:00:00Z', 20000, 23800, 1, 'inv2', 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, coil_ref, quantity, price_per_ton, total_amount, product_type) VALUES ('it1', 's1', 'Coil', 'BOB-1001', 1, 10000, 10000, 'coil'), ('it2', 's2', 'Coil', 'BOB-2002', 1, 5000, 5000, 'coil'), ('it3', 's3', 'Coil', 'BOB-3003', 1, 20000, 20000, 'coil')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-05-02', '2024-06-02', 10000, 11900, 0, 0), ('inv2', 'FAC-24/00002', 'cli2', '2024-06-02', '2024-07-02', 20000, 23800, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('l1', 'inv1', 's1'), ('l2', 'inv2', 's3')")
+        .execute(&pool)
+        .await?;
+    // p1 is made on the sale only, but counts towards inv1
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, check_number, is_deleted) VALUES ('p1', 's1', 'cli1', 5000, '2024-05-10', 'check', 'CHK-111', 0), ('p2', 's3', 'cli2', 23800, '2024-06-05', 'bank_transfer', NULL, 0), ('p3', 's2', 'cli1', 1000, '2024-05-20', 'cash', NULL, 0), ('p4', 's2', 'cli1', 4950, '2024-05-21', 'cash', NULL, 1)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn search(text: &str) -> Option<ListFilter> {
+    Some(ListFilter { search: Some(text.to_string()), ..Default::default() })
+}
+
+#[tokio::test]
+async fn test_clients_filter_and_sort() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let all = commands::get_clients(None, Some(10), None, app.state()).await?;
+    assert_eq!(all.total, 2, "Soft-deleted clients are left out");
+    let names: Vec<&str> = all.rows.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["Alpha", "Beta"]);
+
+    let found = commands::get_clients(None, Some(10), search("bâtiment"), app.state()).await?;
+    assert_eq!(found.total, 1);
+    assert_eq!(found.rows[0].id, "cli2");
+    assert_eq!(commands::get_clients(None, Some(10), search("gamma"), app.state()).await?.total, 0);
+
+    let sorted = commands::get_clients(None, Some(1), Some(ListFilter {
+        sort_by: Some("name".to_string()),
+        sort_direction: Some("desc".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(sorted.total, 2, "The total counts every match, not just the page");
+    assert_eq!(sorted.rows[0].name, "Beta");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sales_and_invoices_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let by_coil = commands::get_sales(None, Some(10), search("bob-2002"), app.state()).await?;
+    assert_eq!(by_coil.total, 1);
+    assert_eq!(by_coil.rows[0].id, "s2");
+    let by_invoice = commands::get_sales(None, Some(10), search("FAC-24/00002"), app.state()).await?;
+    assert_eq!(by_invoice.rows[0].id, "s3");
+
+    let filtered = commands::get_sales(None, Some(10), Some(ListFilter {
+        client_id: Some("cli1".to_string()),
+        start_date: Some("2024-05-01".to_string()),
+        end_date: Some("2024-05-31".to_string()),
+        amount_min: Some(Money::from_units(100.0)),
+        is_invoiced: Some(true),
+        ..Default::default()
+    }), app.state()).await?;
+    let ids: Vec<&str> = filtered.rows.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["s1"], "The end date includes sales made during that day");
+
+    let cheapest_first = commands::get_sales(None, Some(10), Some(ListFilter {
+        sort_by: Some("total_amount_ttc".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    let ids: Vec<&str> = cheapest_first.rows.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["s2", "s1", "s3"]);
+
+    let invoices = commands::get_invoices(None, Some(10), search("toitures"), app.state()).await?;
+    assert_eq!(invoices.total, 1);
+    assert_eq!(invoices.rows[0]["invoice_number"], "FAC-24/00001");
+    let invoices = commands::get_invoices(None, Some(10), search("BOB-3003"), app.state()).await?;
+    assert_eq!(invoices.rows[0]["id"], "inv2");
+    let invoices = commands::get_invoices(None, Some(10), Some(ListFilter {
+        amount_max: Some(Money::from_units(200.0)),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(invoices.total, 1);
+    assert_eq!(invoices.rows[0]["id"], "inv1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_payments_are_paginated_and_filtered() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let page = commands::get_payments(Some(1), Some(2), None, app.state()).await?;
+    assert_eq!(page.total, 3, "Deleted payments are left out");
+    let ids: Vec<&str> = page.rows.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, vec!["p2", "p3"]);
+    let next = commands::get_payments(Some(2), Some(2), None, app.state()).await?;
+    assert_eq!(next.rows[0].id, "p1");
+    // Page 0 is read as the first page
+    let first = commands::get_payments(Some(0), Some(2), None, app.state()).await?;
+    assert_eq!(first.rows.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(), ids);
+
+    let by_check = commands::get_payments(None, Some(10), search("CHK-111"), app.state()).await?;
+    assert_eq!(by_check.rows[0].id, "p1");
+    let by_invoice = commands::get_payments(None, Some(10), search("FAC-24/00001"), app.state()).await?;
+    assert_eq!(by_invoice.total, 1, "Sale payments match their sale's invoice number");
+
+    let largest = commands::get_payments(None, Some(10), Some(ListFilter {
+        client_id: Some("cli1".to_string()),
+        sort_by: Some("amount".to_string()),
+        sort_direction: Some("DESC".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    let ids: Vec<&str> = largest.rows.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, vec!["p1", "p3"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invalid_filters_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let bad_sort = Some(ListFilter { sort_by: Some("id; DROP TABLE sales".to_string()), ..Default::default() });
+    assert!(commands::get_sales(None, None, bad_sort, app.state()).await.is_err());
+    let bad_direction = Some(ListFilter { sort_direction: Some("sideways".to_string()), ..Default::default() });
+    assert!(commands::get_payments(None, None, bad_direction, app.state()).await.is_err());
+    let bad_date = Some(ListFilter { start_date: Some("last week".to_string()), ..Default::default() });
+    assert!(commands::get_invoices(None, None, bad_date, app.state()).await.is_err());
+    let bad_sort_for_list = Some(ListFilter { sort_by: Some("balance_due".to_string()), ..Default::default() });
+    assert!(commands::get_clients(None, None, bad_sort_for_list, app.state()).await.is_err());
+    Ok(())
+}
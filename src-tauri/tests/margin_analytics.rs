@@ -0,0 +1,139 @@
+mod common;
+
+use app_lib::commands::{self, CreateSaleItemRequest, CreateSaleRequest, Money, ReceiveCoilRequest};
+use chrono::{TimeZone, Utc};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Alpha"), ("cli2", "Beta")]).await?;
+    Ok(pool)
+}
+
+fn coil_item(coil_ref: &str, thickness: f64, weight: f64, price_per_ton: f64, unit_cost: Option<f64>) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        coil_ref: Some(coil_ref.to_string()),
+        coil_thickness: Some(thickness),
+        unit_cost: unit_cost.map(Money::from_units),
+        ..common::coil_item(weight, price_per_ton)
+    }
+}
+
+fn sheet_item(length: f64, quantity: f64, price_per_meter: f64, unit_cost: Option<f64>) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: "Tôle ondulée".to_string(),
+        coil_ref: None,
+        coil_thickness: Some(0.4),
+        coil_width: Some(length),
+        top_coat_ral: None,
+        back_coat_ral: None,
+        coil_weight: None,
+        quantity,
+        price_per_ton: Money::from_units(price_per_meter),
+        total_amount: Money::ZERO,
+        product_type: "corrugated_sheet".to_string(),
+        unit_cost: unit_cost.map(Money::from_units),
+        tax_rate: None,
+    }
+}
+
+fn sale(client_id: &str, month: u32, items: Vec<CreateSaleItemRequest>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        client_id: client_id.to_string(),
+        date: Utc.with_ymd_and_hms(2024, month, 10, 9, 0, 0).unwrap(),
+        ..common::sale(items)
+    }
+}
+
+#[tokio::test]
+async fn test_item_costs_are_entered_or_taken_from_stock() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    commands::receive_coil(ReceiveCoilRequest {
+        coil_ref: "BOB-700".to_string(),
+        received_at: "2024-05-01".to_string(),
+        supplier: None,
+        thickness: Some(0.5),
+        width: Some(1250.0),
+        top_coat_ral: None,
+        back_coat_ral: None,
+        gross_weight: 5.0,
+        net_weight: 5.0,
+        cost_per_ton: Money::from_units(150000.0),
+        notes: None,
+    }, app.state()).await?;
+
+    let created = commands::create_sale(sale("cli1", 6, vec![
+        coil_item("BOB-700", 0.5, 2.0, 200000.0, None),
+        coil_item("BOB-700", 0.5, 1.0, 200000.0, Some(160000.0)),
+        sheet_item(6.0, 10.0, 1000.0, Some(700.0)),
+        coil_item("NOT-IN-STOCK", 0.5, 1.0, 200000.0, None),
+    ]), app.state()).await?;
+    let costs: Vec<(Option<Money>, Option<Money>)> = created.items.iter().map(|i| (i.unit_cost, i.cost_amount)).collect();
+    assert_eq!(costs, vec![
+        (Some(Money::from_units(150000.0)), Some(Money::from_units(300000.0))),
+        (Some(Money::from_units(160000.0)), Some(Money::from_units(160000.0))),
+        (Some(Money::from_units(700.0)), Some(Money::from_units(42000.0))),
+        (None, None),
+    ]);
+
+    let mut negative = sale("cli1", 6, vec![coil_item("X", 0.5, 1.0, 1.0, Some(-1.0))]);
+    negative.items[0].coil_ref = None;
+    assert!(commands::create_sale(negative, app.state()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_margin_analytics_groups() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // Alpha, June: 400 000 revenue for 300 000 cost on coils, 60 000 for 42 000 on sheets
+    commands::create_sale(sale("cli1", 6, vec![
+        coil_item("A", 0.5, 2.0, 200000.0, Some(150000.0)),
+        sheet_item(6.0, 10.0, 1000.0, Some(700.0)),
+    ]), app.state()).await?;
+    // Beta, July: a costed 0.4 mm coil and one without a cost
+    commands::create_sale(sale("cli2", 7, vec![
+        coil_item("B", 0.4, 1.0, 100000.0, Some(80000.0)),
+        coil_item("C", 0.4, 1.0, 50000.0, None),
+    ]), app.state()).await?;
+    // Deleted sales are left out
+    let deleted = commands::create_sale(sale("cli2", 7, vec![coil_item("D", 0.4, 9.0, 100000.0, Some(1.0))]), app.state()).await?;
+    commands::delete_sale(deleted.id, app.state()).await?;
+
+    let margins = commands::get_margin_analytics(common::no_filter(), app.state()).await?;
+    assert_eq!(margins.totals.revenue, Money::from_units(610000.0));
+    assert_eq!(margins.totals.cost, Money::from_units(422000.0));
+    assert_eq!(margins.totals.gross_margin, Money::from_units(138000.0));
+    assert_eq!(margins.totals.uncosted_revenue, Money::from_units(50000.0));
+    assert_eq!(margins.totals.margin_percent, Some(24.64));
+
+    let by_type: Vec<(&str, Money)> = margins.by_product_type.iter().map(|r| (r.key.as_str(), r.gross_margin)).collect();
+    assert_eq!(by_type, vec![("coil", Money::from_units(120000.0)), ("corrugated_sheet", Money::from_units(18000.0))]);
+    let dimensions: Vec<&str> = margins.by_dimensions.iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(dimensions, vec!["0.5×1250", "0.4×1250", "0.4×6"]);
+    let clients: Vec<(&str, &str)> = margins.by_client.iter().map(|r| (r.key.as_str(), r.label.as_str())).collect();
+    assert_eq!(clients, vec![("cli1", "Alpha"), ("cli2", "Beta")]);
+    let beta = &margins.by_client[1];
+    assert_eq!(beta.margin_percent, Some(20.0), "The uncosted coil does not count towards the margin");
+    let months: Vec<&str> = margins.by_month.iter().map(|r| r.key.as_str()).collect();
+    assert_eq!(months, vec!["2024-06", "2024-07"]);
+
+    let mut only_beta = common::no_filter();
+    only_beta.client_id = Some("cli2".to_string());
+    let margins = commands::get_margin_analytics(only_beta, app.state()).await?;
+    assert_eq!(margins.totals.revenue, Money::from_units(150000.0));
+    assert_eq!(margins.by_month.len(), 1);
+
+    let mut nothing = common::no_filter();
+    nothing.product_type = Some("steel_slitting".to_string());
+    let margins = commands::get_margin_analytics(nothing, app.state()).await?;
+    assert_eq!(margins.totals.revenue, Money::ZERO);
+    assert_eq!(margins.totals.margin_percent, None);
+    assert!(margins.by_client.is_empty());
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use app_lib::commands::Money;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::Row;
+use std::borrow::Cow;
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[test]
+fn test_money_arithmetic_and_serde() {
+    let price = Money::from_units(333.333);
+    assert_eq!(price.centimes(), 33333);
+    assert_eq!(price.to_string(), "333.33");
+    assert_eq!(Money::from_units(-0.5).to_string(), "-0.50");
+    assert_eq!(price.mul_f64(0.19), Money::from_centimes(6333));
+    assert_eq!(price + price - Money::from_centimes(1), Money::from_centimes(66665));
+    assert_eq!([Money::from_units(0.1), Money::from_units(0.2)].iter().sum::<Money>(), Money::from_units(0.3));
+
+    assert_eq!(serde_json::to_string(&Money::from_centimes(11990)).unwrap(), "119.9");
+    assert_eq!(serde_json::from_str::<Money>("119.99").unwrap(), Money::from_centimes(11999));
+}
+
+#[tokio::test]
+async fn test_centimes_migration_preserves_legacy_amounts() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    // Bring the schema up to the last version that stored REAL amounts
+    let legacy = Migrator {
+        migrations: Cow::Owned(MIGRATOR.iter().filter(|m| m.version < 20240720).cloned().collect()),
+        ignore_missing: false,
+        locking: true,
+    };
+    legacy.run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name, credit_balance) VALUES ('cli1', 'Test Client', 12.5)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, transportation_fee, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 100.1, 119.119, NULL, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', 'cli1', 119.12, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+
+    MIGRATOR.run(&pool).await?;
+    let sale = sqlx::query("SELECT total_amount, total_amount_ttc, transportation_fee, typeof(total_amount) AS kind, is_paid FROM sales WHERE id = 'sale1'")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(sale.get::<String, _>("kind"), "integer");
+    assert_eq!(sale.get::<i64, _>("total_amount"), 10010);
+    assert_eq!(sale.get::<i64, _>("total_amount_ttc"), 11912, "Amounts are rounded to the centime");
+    assert_eq!(sale.get::<Option<i64>, _>("transportation_fee"), None, "Missing fees should stay NULL");
+    assert_eq!(sale.get::<i64, _>("is_paid"), 1);
+    let amount: Money = sqlx::query_scalar("SELECT amount FROM payments WHERE id = 'pay1'").fetch_one(&pool).await?;
+    assert_eq!(amount, Money::from_units(119.12));
+    let credit: Money = sqlx::query_scalar("SELECT credit_balance FROM clients WHERE id = 'cli1'").fetch_one(&pool).await?;
+    assert_eq!(credit, Money::from_centimes(1250));
+    let opening: Money = sqlx::query_scalar("SELECT amount FROM credit_transactions WHERE client_id = 'cli1' AND notes = 'Opening balance'").fetch_one(&pool).await?;
+    assert_eq!(opening, credit, "Legacy balances are carried into the ledger");
+
+    // The payment status triggers survive the rebuild
+    sqlx::query("UPDATE payments SET amount = 5000 WHERE id = 'pay1'").execute(&pool).await?;
+    let is_paid: i64 = sqlx::query_scalar("SELECT is_paid FROM sales WHERE id = 'sale1'").fetch_one(&pool).await?;
+    assert_eq!(is_paid, 0);
+    Ok(())
+}
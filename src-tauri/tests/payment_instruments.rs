@@ -0,0 +1,130 @@
+mod common;
+
+use app_lib::commands::{self, CreateBulkPaymentRequest, CreatePaymentRequest, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Alpha")]).await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-05-01', 10000, 10000, 0, 0), ('sale2', 'cli1', '2024-05-02', 5000, 5000, 0, 0)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn payment(sale_id: &str, amount: f64, method: &str, maturity_date: Option<&str>) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        method: method.to_string(),
+        check_number: Some("0012345".to_string()),
+        bank: Some(" BNA ".to_string()),
+        maturity_date: maturity_date.map(|d| d.to_string()),
+        ..common::payment(sale_id, amount, "2024-06-03")
+    }
+}
+
+#[tokio::test]
+async fn test_cheque_lifecycle_and_bounce() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    assert!(commands::create_payment(payment("sale1", 100.0, "traite", None), app.state()).await.is_err(), "A traite needs a maturity date");
+    let cash = commands::create_payment(payment("sale2", 10.0, "cash", Some("2024-07-01")), app.state()).await?;
+    assert!(cash.instrument_id.is_none() && cash.check_number.is_none());
+
+    let traite = commands::create_payment(payment("sale1", 100.0, "traite", Some("2024-08-31")), app.state()).await?;
+    let cheque = commands::create_payment(payment("sale2", 40.0, "check", None), app.state()).await?;
+    assert_eq!(traite.check_number.as_deref(), Some("0012345"), "Traites keep their number");
+    assert_eq!(common::scalar(&pool, "SELECT is_paid FROM sales WHERE id = 'sale1'").await, 1);
+
+    // The portfolio lists what matures in the window, soonest first
+    let due = commands::get_cheques_due(Some("2024-06-01".to_string()), Some("2024-09-30".to_string()), app.state()).await?;
+    assert_eq!(due.iter().map(|i| i.maturity_date.as_str()).collect::<Vec<_>>(), ["2024-06-03", "2024-08-31"]);
+    assert_eq!((due[1].bank.as_deref(), due[1].client_name.as_deref()), (Some("BNA"), Some("Alpha")));
+    let traite_id = traite.instrument_id.clone().unwrap();
+    let cheque_id = cheque.instrument_id.clone().unwrap();
+
+    // Once at the bank, the payment is fixed
+    assert!(commands::clear_payment_instrument(cheque_id.clone(), "2024-06-10".to_string(), app.state()).await.is_err(), "Only deposited cheques clear");
+    let deposited = commands::deposit_payment_instruments(vec![cheque_id.clone(), traite_id.clone()], "REM-001".to_string(), "2024-06-05".to_string(), app.state()).await?;
+    assert!(deposited.iter().all(|i| i.status == "deposited" && i.deposit_batch.as_deref() == Some("REM-001")));
+    assert!(commands::update_payment(cheque.id.clone(), payment("sale2", 45.0, "check", None), app.state()).await.is_err());
+
+    let cleared = commands::clear_payment_instrument(cheque_id.clone(), "2024-06-10".to_string(), app.state()).await?;
+    assert_eq!(cleared.status, "cleared");
+    assert!(commands::bounce_payment_instrument(cheque_id, "2024-06-11".to_string(), None, app.state()).await.is_err());
+
+    // The unpaid traite takes its payment back and the sale is due again
+    let bounced = commands::bounce_payment_instrument(traite_id.clone(), "2024-09-02".to_string(), Some("Provision insuffisante".to_string()), app.state()).await?;
+    assert_eq!((bounced.status.as_str(), bounced.bounce_reason.as_deref()), ("bounced", Some("Provision insuffisante")));
+    assert_eq!(bounced.payments[0].is_deleted, Some(true));
+    assert_eq!(common::scalar(&pool, "SELECT is_paid FROM sales WHERE id = 'sale1'").await, 0);
+    assert!(commands::restore_payment(traite.id.clone(), app.state()).await.is_err(), "Bounced payments stay reversed");
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM audit_log WHERE action = 'bounce' AND entity_type = 'payment_instrument'").await, 1);
+
+    let due = commands::get_cheques_due(None, None, app.state()).await?;
+    assert!(due.is_empty(), "Cleared and bounced instruments leave the portfolio");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_received_instruments_follow_their_payment() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let cheque = commands::create_payment(payment("sale1", 100.0, "check", None), app.state()).await?;
+    let instrument_id = cheque.instrument_id.clone().unwrap();
+    let traite = commands::update_payment(cheque.id.clone(), payment("sale1", 60.0, "traite", Some("2024-09-15")), app.state()).await?;
+    assert_eq!(traite.instrument_id.as_deref(), Some(instrument_id.as_str()));
+    let listed = commands::get_payment_instruments(Some("cli1".to_string()), Some("received".to_string()), app.state()).await?;
+    assert_eq!((listed[0].kind.as_str(), listed[0].amount, listed[0].maturity_date.as_str()), ("traite", Money::from_units(60.0), "2024-09-15"));
+
+    // Paid in cash after all: the traite was never in hand
+    let cash = commands::update_payment(cheque.id.clone(), payment("sale1", 100.0, "cash", None), app.state()).await?;
+    assert!(cash.instrument_id.is_none());
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM payment_instruments").await, 0);
+
+    // A deleted payment's cheque is no longer expected
+    let cheque = commands::create_payment(payment("sale2", 50.0, "check", None), app.state()).await?;
+    commands::delete_payment(cheque.id.clone(), app.state()).await?;
+    assert!(commands::get_cheques_due(None, None, app.state()).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bounced_bulk_traite_reverses_the_bulk_payment() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 100 + 50 are due; 200 leaves 50 for client credit
+    let bulk = commands::create_bulk_payment(CreateBulkPaymentRequest {
+        client_id: "cli1".to_string(),
+        total_amount: Money::from_units(200.0),
+        date: "2024-06-03".to_string(),
+        method: "traite".to_string(),
+        notes: None,
+        check_number: Some("LC-77".to_string()),
+        bank: Some("CPA".to_string()),
+        maturity_date: Some("2024-07-31".to_string()),
+        strategy: "oldest_due_first".to_string(),
+        allocations: None,
+    }, app.state()).await?;
+    let instrument_id = bulk.instrument_id.clone().unwrap();
+    assert!(bulk.payments.iter().all(|p| p.instrument_id.as_deref() == Some(instrument_id.as_str())));
+    assert!(commands::update_payment(bulk.payments[0].id.clone(), payment("sale1", 100.0, "cash", None), app.state()).await.is_err());
+
+    let due = commands::get_cheques_due(Some("2024-07-01".to_string()), Some("2024-07-31".to_string()), app.state()).await?;
+    assert_eq!((due.len(), due[0].amount, due[0].bulk_payment_id.as_deref()), (1, Money::from_units(200.0), Some(bulk.id.as_str())));
+
+    commands::deposit_payment_instruments(vec![instrument_id.clone()], "REM-002".to_string(), "2024-07-31".to_string(), app.state()).await?;
+    commands::bounce_payment_instrument(instrument_id, "2024-08-02".to_string(), None, app.state()).await?;
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM payments WHERE is_deleted = 0").await, 0);
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM bulk_payments WHERE is_deleted = 1").await, 1);
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM sales WHERE is_paid = 1").await, 0);
+    let credit = common::credit_balance(&pool, "cli1").await;
+    assert_eq!(credit, Money::ZERO, "The remainder credited by the traite is taken back");
+    Ok(())
+}
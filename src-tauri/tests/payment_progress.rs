@@ -0,0 +1,124 @@
+mod common;
+
+use app_lib::commands::{self, ListFilter, Money, SoldProductsFilter};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Test Client")]).await?;
+    // inv1 = s1 + s2, inv2 = s3, s4 is not invoiced
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('s1', 'cli1', '2024-06-04T09:00:00Z', 10000, 11900, 1, 'inv1', 0), ('s2', 'cli1', '2024-06-03T09:00:00Z', 5000, 5950, 1, 'inv1', 0), ('s3', 'cli1', '2024-06-02T09:00:00Z', 10000, 10000, 1, 'inv2', 0), ('s4', 'cli1', '2024-06-01T09:00:00Z', 5000, 5000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, quantity, price_per_ton, total_amount, product_type) VALUES ('it1', 's1', 'Coil', 1, 10000, 10000, 'coil'), ('it2', 's2', 'Coil', 1, 5000, 5000, 'coil'), ('it3', 's3', 'Coil', 1, 10000, 10000, 'coil'), ('it4', 's4', 'Coil', 1, 5000, 5000, 'coil')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'FAC-24/00001', 'cli1', '2024-06-04', '2024-07-04', 15000, 17850, 0, 0), ('inv2', 'FAC-24/00002', 'cli1', '2024-06-02', '2024-07-02', 10000, 10000, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('l1', 'inv1', 's1'), ('l2', 'inv1', 's2'), ('l3', 'inv2', 's3')")
+        .execute(&pool)
+        .await?;
+    // s1 partly paid, s3 paid exactly, s4 overpaid; the deleted payment on s2 does not count
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('p1', 's1', 'cli1', 5000, '2024-06-10', 'cash', 0), ('p2', 's3', 'cli1', 10000, '2024-06-10', 'cash', 0), ('p3', 's4', 'cli1', 6000, '2024-06-10', 'cash', 0), ('p4', 's2', 'cli1', 5950, '2024-06-10', 'cash', 1)")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn status(payment_status: &str) -> Option<ListFilter> {
+    Some(ListFilter { payment_status: Some(payment_status.to_string()), ..Default::default() })
+}
+
+fn sold_filter(status: Option<&str>) -> SoldProductsFilter {
+    SoldProductsFilter {
+        start_date: None,
+        end_date: None,
+        product_type: None,
+        client_id: None,
+        thickness: None,
+        width: None,
+        unit_price_min: None,
+        unit_price_max: None,
+        payment_status: status.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn test_sales_expose_payment_progress() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sales = commands::get_sales(None, Some(10), None, app.state()).await?;
+    let progress: Vec<(&str, Money, Money, &str)> = sales.rows.iter()
+        .map(|s| (s.id.as_str(), s.amount_paid, s.balance_due, s.payment_status.as_str()))
+        .collect();
+    assert_eq!(progress, vec![
+        ("s1", Money::from_units(50.0), Money::from_units(69.0), "partial"),
+        ("s2", Money::ZERO, Money::from_units(59.5), "unpaid"),
+        ("s3", Money::from_units(100.0), Money::ZERO, "paid"),
+        ("s4", Money::from_units(60.0), Money::ZERO, "overpaid"),
+    ]);
+
+    let partial = commands::get_sales(None, Some(10), status("partial"), app.state()).await?;
+    assert_eq!(partial.total, 1);
+    assert_eq!(partial.rows[0].id, "s1");
+    assert_eq!(partial.rows[0].items.len(), 1);
+    let all = commands::get_sales(None, Some(10), status("all"), app.state()).await?;
+    assert_eq!(all.total, 4);
+    assert!(commands::get_sales(None, None, status("late"), app.state()).await.is_err());
+
+    let sale = commands::get_sale_by_id("s4".to_string(), app.state()).await?.expect("s4 exists");
+    assert_eq!(sale.payment_status, "overpaid");
+
+    let summaries = commands::get_sales_summary(app.state(), 10, 0).await?;
+    assert_eq!(summaries.len(), 4);
+    assert_eq!(summaries[0].payment_status, "partial");
+    assert_eq!(summaries[0].balance_due, Money::from_units(69.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_invoices_expose_payment_progress() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let invoices = commands::get_invoices(None, Some(10), None, app.state()).await?;
+    assert_eq!(invoices.total, 2);
+    assert_eq!(invoices.rows[0]["payment_status"], "partial");
+    assert_eq!(invoices.rows[0]["amount_paid"], 50.0);
+    assert_eq!(invoices.rows[0]["balance_due"], 128.5);
+    assert_eq!(invoices.rows[1]["payment_status"], "paid");
+
+    let paid = commands::get_invoices(None, Some(10), status("paid"), app.state()).await?;
+    assert_eq!(paid.total, 1);
+    assert_eq!(paid.rows[0]["id"], "inv2");
+    let unpaid = commands::get_invoices(None, Some(10), status("unpaid"), app.state()).await?;
+    assert_eq!(unpaid.total, 0, "A partly paid invoice is not unpaid");
+
+    let summaries = commands::get_invoices_summary(app.state(), 10, 0).await?;
+    let statuses: Vec<&str> = summaries.iter().map(|i| i.payment_status.as_str()).collect();
+    assert_eq!(statuses, vec!["partial", "paid"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sold_products_filter_on_payment_status() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let overpaid = commands::get_sold_products_analytics(sold_filter(Some("overpaid")), None, Some(10), app.state()).await?;
+    assert_eq!(overpaid.total, 1);
+    assert_eq!(overpaid.rows[0].payment_status, "overpaid");
+    let all = commands::get_sold_products_analytics(sold_filter(None), None, Some(10), app.state()).await?;
+    assert_eq!(all.total, 4);
+
+    let summary = commands::get_sold_products_summary(sold_filter(Some("partial")), app.state()).await?;
+    assert_eq!(summary.official_total_revenue, Money::from_units(119.0));
+    assert!(commands::get_sold_products_summary(sold_filter(Some("late")), app.state()).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use app_lib::commands::{self, CreatePaymentRequest, Money};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    // Single connection so every query sees the same in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, paid_at, is_deleted) VALUES ('inv1', 'INV-001', 'cli1', '2024-06-10', '2024-06-20', 10000, 12000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 10000, 12000, 1, 'inv1', 0), ('sale2', 'cli1', '2024-06-11', 10000, 10000, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+async fn status(pool: &SqlitePool, table: &str, id: &str) -> Result<(i64, Option<String>), Box<dyn std::error::Error>> {
+    let row = sqlx::query(&format!("SELECT is_paid, paid_at FROM {} WHERE id = ?", table))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    Ok((row.get("is_paid"), row.get("paid_at")))
+}
+
+#[tokio::test]
+async fn test_payment_update_recalculates_sale_and_invoice() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+
+    // A sale payment that was also linked to the invoice by create_invoice must only count once
+    sqlx::query("INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', 'inv1', 'cli1', 6000, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+    assert_eq!(status(&pool, "sales", "sale1").await?.0, 0, "Partial payment: sale should be unpaid");
+    assert_eq!(status(&pool, "invoices", "inv1").await?.0, 0, "Partial payment: invoice should be unpaid");
+
+    // Raising the amount pays both the sale and the invoice
+    sqlx::query("UPDATE payments SET amount = 12000 WHERE id = 'pay1'")
+        .execute(&pool)
+        .await?;
+    let (sale_paid, sale_paid_at) = status(&pool, "sales", "sale1").await?;
+    let (invoice_paid, invoice_paid_at) = status(&pool, "invoices", "inv1").await?;
+    assert_eq!(sale_paid, 1, "Raised amount: sale should be paid");
+    assert!(sale_paid_at.is_some(), "Raised amount: sale paid_at should be set");
+    assert_eq!(invoice_paid, 1, "Raised amount: invoice should be paid");
+    assert!(invoice_paid_at.is_some(), "Raised amount: invoice paid_at should be set");
+
+    // Lowering the amount flips both back
+    sqlx::query("UPDATE payments SET amount = 10000 WHERE id = 'pay1'")
+        .execute(&pool)
+        .await?;
+    assert_eq!(status(&pool, "sales", "sale1").await?, (0, None), "Lowered amount: sale should be unpaid");
+    assert_eq!(status(&pool, "invoices", "inv1").await?, (0, None), "Lowered amount: invoice should be unpaid");
+
+    // Moving the payment to another sale pays the new sale and leaves the old one unpaid
+    sqlx::query("UPDATE payments SET sale_id = 'sale2', invoice_id = NULL WHERE id = 'pay1'")
+        .execute(&pool)
+        .await?;
+    assert_eq!(status(&pool, "sales", "sale2").await?.0, 1, "Re-linked: new sale should be paid");
+    assert_eq!(status(&pool, "sales", "sale1").await?, (0, None), "Re-linked: old sale should be unpaid");
+    assert_eq!(status(&pool, "invoices", "inv1").await?, (0, None), "Re-linked: old invoice should be unpaid");
+
+    // Moving it back with the full amount pays the original sale and invoice again
+    sqlx::query("UPDATE payments SET sale_id = 'sale1', invoice_id = 'inv1', amount = 12000 WHERE id = 'pay1'")
+        .execute(&pool)
+        .await?;
+    assert_eq!(status(&pool, "sales", "sale2").await?, (0, None), "Moved back: sale2 should be unpaid");
+    assert_eq!(status(&pool, "sales", "sale1").await?.0, 1, "Moved back: sale1 should be paid");
+    assert_eq!(status(&pool, "invoices", "inv1").await?.0, 1, "Moved back: invoice should be paid");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_payment_update_rejects_invalid_references() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', 'cli1', 6000, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+
+    let missing_sale = sqlx::query("UPDATE payments SET sale_id = 'nope' WHERE id = 'pay1'")
+        .execute(&pool)
+        .await;
+    assert!(missing_sale.is_err(), "Re-linking to a missing sale should fail");
+
+    sqlx::query("UPDATE invoices SET is_deleted = 1 WHERE id = 'inv1'")
+        .execute(&pool)
+        .await?;
+    let deleted_invoice = sqlx::query("UPDATE payments SET invoice_id = 'inv1' WHERE id = 'pay1'")
+        .execute(&pool)
+        .await;
+    assert!(deleted_invoice.is_err(), "Re-linking to a deleted invoice should fail");
+
+    Ok(())
+}
+
+fn payment_request(amount: f64, method: &str, check_number: Option<&str>) -> CreatePaymentRequest {
+    CreatePaymentRequest {
+        sale_id: Some("sale2".to_string()),
+        invoice_id: None,
+        client_id: "cli1".to_string(),
+        amount: Money::from_units(amount),
+        date: "2024-06-12".to_string(),
+        method: method.to_string(),
+        notes: None,
+        check_number: check_number.map(|n| n.to_string()),
+        bank: None,
+        maturity_date: None,
+    }
+}
+
+#[tokio::test]
+async fn test_create_payment_validates_amount_and_method() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    assert!(commands::create_payment(payment_request(0.0, "cash", None), app.state()).await.is_err(), "Zero amount should be rejected");
+    assert!(commands::create_payment(payment_request(-5.0, "cash", None), app.state()).await.is_err(), "Negative amount should be rejected");
+    assert!(commands::create_payment(payment_request(50.0, " ", None), app.state()).await.is_err(), "Empty method should be rejected");
+
+    let payment = commands::create_payment(payment_request(50.0, "cash", Some("CHK-1")), app.state()).await?;
+    assert!(payment.check_number.is_none(), "Only cheques keep a check number");
+    let payment = commands::create_payment(payment_request(50.0, "check", Some("CHK-2")), app.state()).await?;
+    assert_eq!(payment.check_number.as_deref(), Some("CHK-2"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_payment_command() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let payment = commands::create_payment(payment_request(40.0, "check", Some("CHK-1")), app.state()).await?;
+    let updated = commands::update_payment(payment.id.clone(), payment_request(100.0, "bank_transfer", Some("CHK-1")), app.state()).await?;
+    assert_eq!(updated.amount, Money::from_units(100.0));
+    assert_eq!(updated.method, "bank_transfer");
+    assert!(updated.check_number.is_none(), "Switching away from cheque should drop the check number");
+    assert_eq!(status(&pool, "sales", "sale2").await?.0, 1, "Raised amount: sale should be paid");
+    let audits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE action = 'update' AND entity_type = 'payment' AND entity_id = ?")
+        .bind(&payment.id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(audits, 1, "The update should be audited");
+
+    let invalid = commands::update_payment(payment.id.clone(), payment_request(0.0, "cash", None), app.state()).await;
+    assert!(invalid.is_err(), "Zero amount should be rejected");
+
+    commands::delete_payment(payment.id.clone(), app.state()).await?;
+    let deleted = commands::update_payment(payment.id.clone(), payment_request(10.0, "cash", None), app.state()).await;
+    assert_eq!(deleted.unwrap_err(), "Payment not found");
+    let missing = commands::update_payment("nope".to_string(), payment_request(10.0, "cash", None), app.state()).await;
+    assert_eq!(missing.unwrap_err(), "Payment not found");
+    Ok(())
+}
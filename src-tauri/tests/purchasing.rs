@@ -0,0 +1,224 @@
+mod common;
+
+use app_lib::commands::{
+    self, CreatePurchaseOrderItemRequest, CreatePurchaseOrderRequest, CreateSupplierInvoiceRequest,
+    CreateSupplierPaymentRequest, CreateSupplierRequest, ListFilter, Money, ReceivePurchaseOrderItemRequest,
+};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    sqlx::query("INSERT INTO suppliers (id, name, company) VALUES ('sup1', 'ArcelorMittal', 'AMA Annaba'), ('sup2', 'Tosyali', 'Tosyali Algérie')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn line(coil_ref: &str, weight: f64, price_per_ton: f64) -> CreatePurchaseOrderItemRequest {
+    CreatePurchaseOrderItemRequest {
+        description: "Bobine prélaquée".to_string(),
+        coil_ref: Some(coil_ref.to_string()),
+        thickness: Some(0.5),
+        width: Some(1250.0),
+        top_coat_ral: Some("9002".to_string()),
+        back_coat_ral: None,
+        weight,
+        price_per_ton: Money::from_units(price_per_ton),
+    }
+}
+
+fn order(supplier_id: &str, items: Vec<CreatePurchaseOrderItemRequest>) -> CreatePurchaseOrderRequest {
+    CreatePurchaseOrderRequest {
+        supplier_id: supplier_id.to_string(),
+        reference: Some("BC-24/001".to_string()),
+        date: "2024-07-01".to_string(),
+        tax_rate: 0.19,
+        notes: None,
+        items,
+    }
+}
+
+fn receipt(net_weight: f64) -> ReceivePurchaseOrderItemRequest {
+    ReceivePurchaseOrderItemRequest {
+        coil_ref: None,
+        received_at: "2024-07-10".to_string(),
+        gross_weight: net_weight + 0.05,
+        net_weight,
+        notes: None,
+    }
+}
+
+fn supplier_invoice(supplier_id: &str, number: &str, ht: f64) -> CreateSupplierInvoiceRequest {
+    CreateSupplierInvoiceRequest {
+        supplier_id: supplier_id.to_string(),
+        purchase_order_id: None,
+        invoice_number: number.to_string(),
+        date: "2024-07-12".to_string(),
+        due_date: Some("2024-08-12".to_string()),
+        total_amount_ht: Money::from_units(ht),
+        total_amount_ttc: Money::from_units(ht * 1.19),
+        notes: None,
+    }
+}
+
+fn supplier_payment(supplier_id: &str, invoice_id: Option<&str>, amount: f64) -> CreateSupplierPaymentRequest {
+    CreateSupplierPaymentRequest {
+        supplier_id: supplier_id.to_string(),
+        supplier_invoice_id: invoice_id.map(str::to_string),
+        amount: Money::from_units(amount),
+        date: "2024-07-20".to_string(),
+        method: "bank_transfer".to_string(),
+        check_number: Some("ignored".to_string()),
+        notes: None,
+    }
+}
+
+#[tokio::test]
+async fn test_suppliers_crud_with_soft_delete_and_audit() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let created = commands::create_supplier(CreateSupplierRequest {
+        name: "Sider El Hadjar".to_string(),
+        company: None,
+        email: None,
+        phone: None,
+        address: None,
+        notes: None,
+        nif: Some("000416000000000".to_string()),
+        nis: None,
+        rc: None,
+        ai: None,
+        rib: None,
+    }, app.state()).await?;
+    assert_eq!(created.balance_due, Money::ZERO);
+
+    let listed = commands::get_suppliers(None, Some(10), Some(ListFilter {
+        search: Some("000416".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(listed.total, 1);
+    assert_eq!(listed.rows[0].id, created.id);
+
+    commands::delete_supplier(created.id.clone(), app.state()).await?;
+    assert_eq!(commands::get_suppliers(None, Some(10), None, app.state()).await?.total, 2);
+    commands::restore_supplier(created.id.clone(), app.state()).await?;
+    assert_eq!(commands::get_suppliers(None, Some(10), None, app.state()).await?.total, 3);
+
+    let actions: Vec<String> = sqlx::query_scalar("SELECT action FROM audit_log WHERE entity_type = 'supplier' AND entity_id = ? ORDER BY id")
+        .bind(&created.id)
+        .fetch_all(&pool)
+        .await?;
+    assert_eq!(actions, vec!["create", "soft_delete", "restore"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_received_lines_feed_coil_stock() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let created = commands::create_purchase_order(order("sup1", vec![line("BOB-501", 5.0, 150000.0), line("BOB-502", 4.0, 160000.0)]), app.state()).await?;
+    assert_eq!(created.total_amount, Money::from_units(1390000.0), "Totals come from the lines");
+    assert_eq!(created.total_amount_ttc, Money::from_units(1654100.0));
+    assert_eq!(created.receipt_status, "open");
+
+    let coil = commands::receive_purchase_order_item(created.items[0].id.clone(), receipt(4.98), app.state()).await?;
+    assert_eq!(coil.coil_ref, "BOB-501");
+    assert_eq!(coil.supplier_id.as_deref(), Some("sup1"));
+    assert_eq!(coil.supplier.as_deref(), Some("ArcelorMittal"));
+    assert_eq!(coil.cost_per_ton, Money::from_units(150000.0));
+    assert!((coil.remaining_weight - 4.98).abs() < 1e-9);
+
+    let partial = commands::get_purchase_order_by_id(created.id.clone(), app.state()).await?.unwrap();
+    assert_eq!(partial.receipt_status, "partial");
+    assert_eq!(partial.items[0].coil_id.as_deref(), Some(coil.id.as_str()));
+    assert!(commands::receive_purchase_order_item(created.items[0].id.clone(), receipt(4.98), app.state()).await.is_err(), "A line is received once");
+
+    // Once coils have arrived the order is frozen
+    assert!(commands::update_purchase_order(created.id.clone(), order("sup1", vec![line("BOB-501", 1.0, 1.0)]), app.state()).await.is_err());
+    assert!(commands::delete_purchase_order(created.id.clone(), app.state()).await.is_err());
+
+    let mut other = receipt(4.0);
+    other.coil_ref = Some("BOB-502-A".to_string());
+    commands::receive_purchase_order_item(created.items[1].id.clone(), other, app.state()).await?;
+    let received = commands::get_purchase_orders(None, Some(10), Some(ListFilter {
+        supplier_id: Some("sup1".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(received.rows[0].receipt_status, "received");
+    let coils = commands::get_coils(None, Some(10), Some(ListFilter {
+        supplier_id: Some("sup1".to_string()),
+        ..Default::default()
+    }), None, app.state()).await?;
+    assert_eq!(coils.total, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_purchase_orders_validate_and_soft_delete() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    assert!(commands::create_purchase_order(order("sup1", vec![]), app.state()).await.is_err());
+    assert!(commands::create_purchase_order(order("sup1", vec![line("BOB-1", 0.0, 100.0)]), app.state()).await.is_err());
+    assert!(commands::create_purchase_order(order("nobody", vec![line("BOB-1", 1.0, 100.0)]), app.state()).await.is_err());
+
+    let created = commands::create_purchase_order(order("sup1", vec![line("BOB-601", 2.0, 100000.0)]), app.state()).await?;
+    let updated = commands::update_purchase_order(created.id.clone(), order("sup2", vec![line("BOB-602", 3.0, 100000.0)]), app.state()).await?;
+    assert_eq!(updated.supplier_id, "sup2");
+    assert_eq!(updated.items.len(), 1);
+    assert_eq!(updated.total_amount, Money::from_units(300000.0));
+
+    commands::delete_purchase_order(created.id.clone(), app.state()).await?;
+    assert_eq!(commands::get_purchase_orders(None, Some(10), None, app.state()).await?.total, 0);
+    assert!(commands::receive_purchase_order_item(updated.items[0].id.clone(), receipt(3.0), app.state()).await.is_err());
+    commands::restore_purchase_order(created.id.clone(), app.state()).await?;
+    assert_eq!(commands::get_purchase_orders(None, Some(10), None, app.state()).await?.total, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_supplier_invoices_and_payments_track_what_we_owe() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let invoice = commands::create_supplier_invoice(supplier_invoice("sup1", "F-2024-881", 100000.0), app.state()).await?;
+    assert_eq!(invoice.payment_status, "unpaid");
+    assert!(commands::create_supplier_invoice(supplier_invoice("sup1", "F-2024-881", 5000.0), app.state()).await.is_err(), "Supplier numbers are unique per supplier");
+    commands::create_supplier_invoice(supplier_invoice("sup2", "F-2024-881", 5000.0), app.state()).await?;
+
+    let payment = commands::create_supplier_payment(supplier_payment("sup1", Some(&invoice.id), 50000.0), app.state()).await?;
+    assert_eq!(payment.check_number, None, "Only cheques keep a check number");
+    assert!(commands::create_supplier_payment(supplier_payment("sup2", Some(&invoice.id), 1.0), app.state()).await.is_err());
+
+    let partial = commands::get_supplier_invoices(None, Some(10), Some(ListFilter {
+        payment_status: Some("partial".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(partial.total, 1);
+    assert_eq!(partial.rows[0].balance_due, Money::from_units(69000.0));
+    let supplier = commands::get_supplier_by_id("sup1".to_string(), app.state()).await?.unwrap();
+    assert_eq!(supplier.balance_due, Money::from_units(69000.0));
+
+    let stats = commands::get_dashboard_stats(app.state()).await?;
+    assert_eq!(stats.total_payable, Money::from_units(69000.0 + 5950.0));
+
+    // Deleting the invoice takes its payments with it, and restoring brings them back
+    commands::delete_supplier_invoice(invoice.id.clone(), app.state()).await?;
+    assert_eq!(commands::get_supplier_payments(None, Some(10), None, app.state()).await?.total, 0);
+    assert!(commands::restore_supplier_payment(payment.id.clone(), app.state()).await.is_err());
+    commands::restore_supplier_invoice(invoice.id.clone(), app.state()).await?;
+    let payments = commands::get_supplier_payments(None, Some(10), Some(ListFilter {
+        search: Some("F-2024-881".to_string()),
+        ..Default::default()
+    }), app.state()).await?;
+    assert_eq!(payments.total, 1);
+    assert_eq!(payments.rows[0].id, payment.id);
+    Ok(())
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use app_lib::commands::{self, CreateQuoteRequest, CreateSaleItemRequest, Money};
+use chrono::{TimeZone, Utc};
+use tauri::Manager;
+
+fn coil_item(price_per_ton: f64, weight: f64, tax_rate: Option<f64>) -> CreateSaleItemRequest {
+    CreateSaleItemRequest { tax_rate, ..common::coil_item(weight, price_per_ton) }
+}
+
+fn quote_request(date: &str, valid_until: &str, items: Vec<CreateSaleItemRequest>) -> CreateQuoteRequest {
+    CreateQuoteRequest {
+        client_id: "cli1".to_string(),
+        date: date.to_string(),
+        valid_until: valid_until.to_string(),
+        notes: Some("Proforma".to_string()),
+        payment_method: Some("cash".to_string()),
+        transportation_fee: Some(Money::from_units(1000.0)),
+        tax_rate: 0.19,
+        tax_exemption: None,
+        items,
+    }
+}
+
+#[tokio::test]
+async fn test_quote_is_priced_like_a_sale_and_converted_once() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let items = vec![coil_item(100000.0, 2.0, None), coil_item(50000.0, 1.0, Some(0.09))];
+    let quote = commands::create_quote(quote_request("2024-06-01", "2099-12-31", items), app.state()).await?;
+    assert_eq!(quote.quote_number, "PRO-24/00001");
+    assert_eq!(quote.status, "draft");
+    // 200 000 + 1 000 at 19%, 50 000 at 9%, then 1% stamp duty on the cash TTC
+    assert_eq!(quote.total_amount, Money::from_units(251000.0));
+    assert_eq!(quote.tax_amount, Money::from_units(38190.0 + 4500.0));
+    assert_eq!(quote.stamp_duty, Money::from_units(2936.9));
+    assert_eq!(quote.total_amount_ttc, Money::from_units(296626.9));
+    assert_eq!(quote.items[0].total_amount, Money::from_units(200000.0));
+
+    let sent = commands::set_quote_status(quote.id.clone(), "sent".to_string(), app.state()).await?;
+    assert_eq!(sent.status, "sent");
+    assert!(commands::set_quote_status(quote.id.clone(), "won".to_string(), app.state()).await.is_err());
+    let mut changed = quote_request("2024-06-02", "2099-12-31", vec![coil_item(100000.0, 2.0, None), coil_item(50000.0, 1.0, Some(0.09))]);
+    changed.notes = Some("Revised".to_string());
+    let updated = commands::update_quote(quote.id.clone(), changed, app.state()).await?;
+    assert_eq!((updated.quote_number.as_str(), updated.status.as_str()), ("PRO-24/00001", "sent"), "Number and status are kept");
+
+    let sale_date = Utc.with_ymd_and_hms(2024, 6, 5, 10, 0, 0).unwrap();
+    let sale = commands::convert_quote_to_sale(quote.id.clone(), Some(sale_date), None, app.state()).await?;
+    assert_eq!(sale.quote_id.as_deref(), Some(quote.id.as_str()));
+    assert_eq!(sale.date, sale_date);
+    assert_eq!(sale.items.len(), 2);
+    assert_eq!((sale.total_amount, sale.total_amount_ttc), (quote.total_amount, quote.total_amount_ttc));
+    assert_eq!(sale.notes.as_deref(), Some("Revised"));
+
+    let converted = commands::get_quote_by_id(quote.id.clone(), app.state()).await?.expect("quote exists");
+    assert_eq!(converted.status, "accepted");
+    assert_eq!(converted.sale_id.as_deref(), Some(sale.id.as_str()));
+    assert!(converted.converted_at.is_some());
+    assert!(commands::convert_quote_to_sale(quote.id.clone(), None, None, app.state()).await.is_err(), "A quote becomes one sale only");
+    assert!(commands::update_quote(quote.id.clone(), quote_request("2024-06-01", "2099-12-31", vec![coil_item(1.0, 1.0, None)]), app.state()).await.is_err());
+    assert!(commands::delete_quote(quote.id.clone(), app.state()).await.is_err());
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM audit_log WHERE entity_type = 'quote' AND action = 'convert'").await, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_and_invalid_quotes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    assert!(commands::create_quote(quote_request("2024-06-01", "2024-05-31", vec![coil_item(100.0, 1.0, None)]), app.state()).await.is_err(), "Cannot expire before its date");
+    assert!(commands::create_quote(quote_request("2024-06-01", "2024-06-30", vec![]), app.state()).await.is_err(), "Needs a line");
+    let mut no_width = coil_item(100.0, 1.0, None);
+    no_width.coil_width = None;
+    assert!(commands::create_quote(quote_request("2024-06-01", "2024-06-30", vec![no_width]), app.state()).await.is_err(), "Lines are validated like sale items");
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM document_sequences WHERE document_type = 'quote'").await, 0, "Rejected quotes take no number");
+
+    let stale = commands::create_quote(quote_request("2024-06-01", "2024-06-30", vec![coil_item(100.0, 1.0, None)]), app.state()).await?;
+    let live = commands::create_quote(quote_request("2024-06-01", "2099-12-31", vec![coil_item(100.0, 1.0, None)]), app.state()).await?;
+    assert_eq!(stale.status, "expired", "A draft past its validity reads as expired");
+    assert_eq!(live.quote_number, "PRO-24/00002");
+
+    let expired = commands::get_quotes(Some(1), Some(10), None, Some("expired".to_string()), app.state()).await?;
+    assert_eq!(expired.rows.iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), vec![stale.id.as_str()]);
+    assert!(commands::get_quotes(None, None, None, Some("lost".to_string()), app.state()).await.is_err());
+    assert!(commands::convert_quote_to_sale(stale.id.clone(), None, None, app.state()).await.is_err());
+    assert!(commands::set_quote_status(stale.id.clone(), "sent".to_string(), app.state()).await.is_err(), "Extend the validity first");
+    let refused = commands::set_quote_status(live.id.clone(), "expired".to_string(), app.state()).await?;
+    assert_eq!(refused.status, "expired");
+    assert!(commands::convert_quote_to_sale(live.id.clone(), None, None, app.state()).await.is_err());
+
+    commands::delete_quote(stale.id.clone(), app.state()).await?;
+    let all = commands::get_quotes(Some(1), Some(10), None, None, app.state()).await?;
+    assert_eq!(all.total, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_convert_quote_rolls_back_when_audit_log_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let quote = commands::create_quote(quote_request("2024-06-01", "2099-12-31", vec![coil_item(100.0, 1.0, None)]), app.state()).await?;
+    sqlx::query("CREATE TRIGGER fail_audit BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'injected failure'); END;")
+        .execute(&pool)
+        .await?;
+
+    assert!(commands::convert_quote_to_sale(quote.id.clone(), None, None, app.state()).await.is_err());
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM sales").await, 0, "No sale should be written");
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM sale_items").await, 0, "No sale item should be written");
+    assert_eq!(common::scalar(&pool, "SELECT COUNT(*) FROM quotes WHERE sale_id IS NULL AND status = 'draft'").await, 1, "Quote should stay open");
+    Ok(())
+}
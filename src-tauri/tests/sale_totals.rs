@@ -0,0 +1,186 @@
+use app_lib::commands::{self, CreateSaleItemRequest, CreateSaleRequest, Money};
+use chrono::Utc;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+fn coil_item(weight: f64, price_per_ton: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: "Coil".to_string(),
+        coil_ref: None,
+        coil_thickness: Some(0.5),
+        coil_width: Some(1000.0),
+        top_coat_ral: None,
+        back_coat_ral: None,
+        coil_weight: Some(weight),
+        quantity: 1.0,
+        price_per_ton: Money::from_units(price_per_ton),
+        total_amount: Money::ZERO,
+        product_type: "coil".to_string(),
+        unit_cost: None,
+        tax_rate: None,
+    }
+}
+
+fn sale_request(items: Vec<CreateSaleItemRequest>, transportation_fee: Option<f64>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        client_id: "cli1".to_string(),
+        date: Utc::now(),
+        // Deliberately wrong: the backend must ignore these
+        total_amount: Money::from_units(1.0),
+        total_amount_ttc: Money::from_units(1.0),
+        is_invoiced: false,
+        invoice_id: None,
+        notes: None,
+        payment_method: None,
+        transportation_fee: transportation_fee.map(Money::from_units),
+        tax_rate: 0.19,
+        tax_exemption: None,
+        is_paid: None,
+        paid_at: None,
+        items,
+        allow_oversell: false,
+    }
+}
+
+#[tokio::test]
+async fn test_create_and_update_sale_recompute_totals() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item(1.0, 100.0), coil_item(2.0, 50.0)], Some(10.0)), app.state()).await?;
+    assert_eq!(sale.total_amount, Money::from_units(210.0), "HT should be items plus transportation fee");
+    assert_eq!(sale.total_amount_ttc, Money::from_units(249.9), "TTC should apply the tax rate to HT");
+
+    // Item totals are rounded to centimes before being summed
+    let sale = commands::update_sale(sale.id.clone(), sale_request(vec![coil_item(1.0, 333.333)], None), app.state()).await?;
+    assert_eq!(sale.items[0].total_amount, Money::from_units(333.33));
+    assert_eq!(sale.total_amount, Money::from_units(333.33));
+    assert_eq!(sale.total_amount_ttc, Money::from_units(396.66));
+
+    let mut invalid = sale_request(vec![coil_item(1.0, 100.0)], None);
+    invalid.tax_rate = 19.0;
+    assert!(commands::create_sale(invalid, app.state()).await.is_err(), "Tax rate given as a percentage should be rejected");
+    let mut invalid = sale_request(vec![coil_item(1.0, 100.0)], Some(-5.0));
+    invalid.tax_rate = 0.19;
+    assert!(commands::create_sale(invalid, app.state()).await.is_err(), "Negative transportation fee should be rejected");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_sale_refreshes_invoice_totals() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item(1.0, 100.0)], None), app.state()).await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'INV-001', 'cli1', '2024-06-10', '2024-07-10', 10000, 11900, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', ?)")
+        .bind(&sale.id)
+        .execute(&pool)
+        .await?;
+
+    commands::update_sale(sale.id.clone(), sale_request(vec![coil_item(2.0, 100.0)], None), app.state()).await?;
+    let row = sqlx::query("SELECT total_amount_ht, total_amount_ttc FROM invoices WHERE id = 'inv1'")
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(row.get::<i64, _>("total_amount_ht"), 20000);
+    assert_eq!(row.get::<i64, _>("total_amount_ttc"), 23800);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recalculate_all_sale_totals_fixes_drifted_rows() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // sale1 has a stale item total and ignores its transportation fee, sale2 is consistent
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, tax_amount, transportation_fee, tax_rate, is_invoiced, invoice_id, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 10000, 12000, 2000, 2000, 0.19, 1, 'inv1', 0), ('sale2', 'cli1', '2024-06-11', 10000, 11900, 1900, NULL, 0.19, 0, NULL, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, coil_thickness, coil_width, coil_weight, quantity, price_per_ton, total_amount, tax_amount, product_type) VALUES ('item1', 'sale1', 'Coil', 0.5, 1000, 1, 1, 10000, 9900, 1881, 'coil'), ('item2', 'sale2', 'Coil', 0.5, 1000, 1, 1, 10000, 10000, 1900, 'coil')")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES ('inv1', 'INV-001', 'cli1', '2024-06-10', '2024-07-10', 10000, 12000, 0, 0)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1')")
+        .execute(&pool)
+        .await?;
+    // Fully paid against the stale total
+    sqlx::query("INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', 'sale1', 'inv1', 'cli1', 12000, '2024-06-12', 'cash', 0)")
+        .execute(&pool)
+        .await?;
+
+    let report = commands::recalculate_all_sale_totals(Some(true), app.state()).await?;
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.corrections.len(), 1, "Only the drifted sale should be reported");
+    let correction = &report.corrections[0];
+    assert_eq!(correction.sale_id, "sale1");
+    assert_eq!(correction.corrected_items, 1);
+    assert_eq!(correction.new_total_amount, Money::from_units(120.0));
+    assert_eq!(correction.new_total_amount_ttc, Money::from_units(142.8));
+    let stored: i64 = sqlx::query_scalar("SELECT total_amount_ttc FROM sales WHERE id = 'sale1'").fetch_one(&pool).await?;
+    assert_eq!(stored, 12000, "Dry run should not write anything");
+
+    let report = commands::recalculate_all_sale_totals(None, app.state()).await?;
+    assert_eq!(report.corrections.len(), 1);
+    let sale = sqlx::query("SELECT total_amount, total_amount_ttc, is_paid FROM sales WHERE id = 'sale1'").fetch_one(&pool).await?;
+    assert_eq!(sale.get::<i64, _>("total_amount"), 12000);
+    assert_eq!(sale.get::<i64, _>("total_amount_ttc"), 14280);
+    assert_eq!(sale.get::<i64, _>("is_paid"), 0, "Sale is no longer fully paid");
+    let item_total: i64 = sqlx::query_scalar("SELECT total_amount FROM sale_items WHERE id = 'item1'").fetch_one(&pool).await?;
+    assert_eq!(item_total, 10000);
+    let invoice = sqlx::query("SELECT total_amount_ttc, is_paid FROM invoices WHERE id = 'inv1'").fetch_one(&pool).await?;
+    assert_eq!(invoice.get::<i64, _>("total_amount_ttc"), 14280);
+    assert_eq!(invoice.get::<i64, _>("is_paid"), 0, "Invoice is no longer fully paid");
+    let audits: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_log WHERE action = 'recalculate'").fetch_one(&pool).await?;
+    assert_eq!(audits, 1);
+
+    let report = commands::recalculate_all_sale_totals(None, app.state()).await?;
+    assert!(report.corrections.is_empty(), "A second run should find nothing to fix");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recalculate_all_sale_totals_skips_deleted_and_uses_settings_rate() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    sqlx::query("INSERT INTO settings (company_name, company_address, company_phone, tax_rate) VALUES ('Test Company', 'Address', '000', 0.09)")
+        .execute(&pool)
+        .await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // Both sales drifted, but sale2 is soft-deleted
+    sqlx::query("INSERT INTO sales (id, client_id, date, total_amount, total_amount_ttc, tax_rate, is_invoiced, is_deleted) VALUES ('sale1', 'cli1', '2024-06-10', 100, 100, NULL, 0, 0), ('sale2', 'cli1', '2024-06-11', 100, 100, 0.19, 0, 1)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO sale_items (id, sale_id, description, coil_thickness, coil_width, coil_weight, quantity, price_per_ton, total_amount, product_type) VALUES ('item1', 'sale1', 'Coil', 0.5, 1000, 1, 1, 10000, 10000, 'coil'), ('item2', 'sale2', 'Coil', 0.5, 1000, 1, 1, 10000, 10000, 'coil')")
+        .execute(&pool)
+        .await?;
+
+    let report = commands::recalculate_all_sale_totals(None, app.state()).await?;
+    assert_eq!(report.checked, 1, "Deleted sales should not be checked");
+    assert_eq!(report.corrections.len(), 1);
+    assert_eq!(report.corrections[0].sale_id, "sale1");
+    assert_eq!(report.corrections[0].new_total_amount_ttc, Money::from_units(109.0), "A missing tax rate should fall back to settings");
+    let deleted_ttc: i64 = sqlx::query_scalar("SELECT total_amount_ttc FROM sales WHERE id = 'sale2'").fetch_one(&pool).await?;
+    assert_eq!(deleted_ttc, 100, "Deleted sales should be left alone");
+    Ok(())
+}
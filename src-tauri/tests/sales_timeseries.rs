@@ -0,0 +1,131 @@
+mod common;
+
+use app_lib::commands::{self, CreateSaleItemRequest, CreateSaleRequest, Money, SoldProductsFilter};
+use chrono::{TimeZone, Utc};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    common::insert_clients(&pool, &[("cli1", "Alpha"), ("cli2", "Beta")]).await?;
+    Ok(pool)
+}
+
+fn item(product_type: &str, width: f64, weight: Option<f64>, quantity: f64, price: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: "Article".to_string(),
+        coil_width: Some(width),
+        coil_weight: weight,
+        quantity,
+        product_type: product_type.to_string(),
+        ..common::coil_item(0.0, price)
+    }
+}
+
+fn sale(client_id: &str, year: i32, month: u32, day: u32, items: Vec<CreateSaleItemRequest>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        client_id: client_id.to_string(),
+        date: Utc.with_ymd_and_hms(year, month, day, 15, 30, 0).unwrap(),
+        transportation_fee: Some(Money::from_units(5000.0)),
+        ..common::sale(items)
+    }
+}
+
+fn period(start: &str, end: &str) -> SoldProductsFilter {
+    SoldProductsFilter {
+        start_date: Some(start.to_string()),
+        end_date: Some(end.to_string()),
+        ..common::no_filter()
+    }
+}
+
+async fn seed(pool: &SqlitePool) -> Result<(), Box<dyn std::error::Error>> {
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    // Alpha: a 2 t coil at 200 000 and 10 sheets of 6 m at 1 000 on 3 June, paid in part on 20 June
+    let june = commands::create_sale(sale("cli1", 2024, 6, 3, vec![
+        item("coil", 1250.0, Some(2.0), 1.0, 200000.0),
+        item("corrugated_sheet", 6.0, None, 10.0, 1000.0),
+    ]), app.state()).await?;
+    commands::create_payment(common::payment(&june.id, 100000.0, "2024-06-20"), app.state()).await?;
+    // Beta: a 1 t coil on the last day of August
+    commands::create_sale(sale("cli2", 2024, 8, 31, vec![item("coil", 1250.0, Some(1.0), 1.0, 100000.0)]), app.state()).await?;
+    // Last year, Beta bought 0.5 t in July
+    commands::create_sale(sale("cli2", 2023, 7, 14, vec![item("coil", 1250.0, Some(0.5), 1.0, 100000.0)]), app.state()).await?;
+    // Deleted sales are left out
+    let deleted = commands::create_sale(sale("cli1", 2024, 7, 1, vec![item("coil", 1250.0, Some(9.0), 1.0, 100000.0)]), app.state()).await?;
+    commands::delete_sale(deleted.id, app.state()).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_monthly_buckets_are_filled_and_compared() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    seed(&pool).await?;
+
+    let series = commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), None, None, Some(true), app.state()).await?;
+    let periods: Vec<&str> = series.buckets.iter().map(|b| b.period.as_str()).collect();
+    assert_eq!(periods, vec!["2024-06", "2024-07", "2024-08"]);
+    let june = &series.buckets[0];
+    assert_eq!(june.revenue_ht, Money::from_units(460000.0), "Transport fees are not item revenue");
+    assert_eq!(june.revenue_ttc, Money::from_units(547400.0));
+    assert!((june.tonnage - 2.0).abs() < 1e-9);
+    assert!((june.quantity - 11.0).abs() < 1e-9);
+    assert_eq!(june.sale_count, 1);
+    assert_eq!(june.collected, Money::from_units(100000.0));
+    assert_eq!(series.buckets[1].sale_count, 0, "July is filled in empty");
+    assert_eq!(series.buckets[2].end_date, "2024-08-31");
+    assert_eq!(series.buckets[2].revenue_ht, Money::from_units(100000.0), "The end date is inclusive");
+
+    let previous = series.previous_year.expect("comparison requested");
+    let previous: Vec<(&str, Money)> = previous.iter().map(|b| (b.period.as_str(), b.revenue_ht)).collect();
+    assert_eq!(previous, vec![
+        ("2023-06", Money::ZERO),
+        ("2023-07", Money::from_units(50000.0)),
+        ("2023-08", Money::ZERO),
+    ]);
+
+    let quarters = commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "quarter".to_string(), None, None, None, app.state()).await?;
+    let quarters: Vec<(&str, i64)> = quarters.buckets.iter().map(|b| (b.period.as_str(), b.sale_count)).collect();
+    assert_eq!(quarters, vec![("2024-Q2", 1), ("2024-Q3", 1)]);
+    assert!(commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), None, None, None, app.state()).await?.previous_year.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_weeks_breakdowns_and_validation() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+    seed(&pool).await?;
+
+    let weeks = commands::get_sales_timeseries(period("2024-06-05", "2024-06-20"), "week".to_string(), None, None, None, app.state()).await?;
+    let weeks: Vec<(&str, &str)> = weeks.buckets.iter().map(|b| (b.period.as_str(), b.start_date.as_str())).collect();
+    assert_eq!(weeks, vec![("2024-W23", "2024-06-03"), ("2024-W24", "2024-06-10"), ("2024-W25", "2024-06-17")]);
+
+    let by_type = commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), Some("revenue_ht".to_string()), Some("product_type".to_string()), None, app.state()).await?;
+    let totals: Vec<(&str, f64)> = by_type.series.iter().map(|s| (s.key.as_str(), s.total)).collect();
+    assert_eq!(totals, vec![("coil", 500000.0), ("corrugated_sheet", 60000.0)]);
+    assert!(by_type.series.iter().all(|s| s.buckets.len() == 3));
+
+    let by_client = commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), Some("tonnage".to_string()), Some("client".to_string()), None, app.state()).await?;
+    let clients: Vec<(&str, &str, f64)> = by_client.series.iter().map(|s| (s.key.as_str(), s.label.as_str(), s.total)).collect();
+    assert_eq!(clients, vec![("cli1", "Alpha", 2.0), ("cli2", "Beta", 1.0)]);
+    assert_eq!(by_client.series[0].buckets[0].collected, Money::from_units(100000.0));
+
+    // Without dates the buckets run from the first to the last matching sale
+    let mut beta = period("", "");
+    beta.client_id = Some("cli2".to_string());
+    let beta = commands::get_sales_timeseries(beta, "quarter".to_string(), None, None, None, app.state()).await?;
+    assert_eq!(beta.buckets.first().map(|b| b.period.as_str()), Some("2023-Q3"));
+    assert_eq!(beta.buckets.last().map(|b| b.period.as_str()), Some("2024-Q3"));
+    assert_eq!(beta.buckets.len(), 5);
+
+    assert!(commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "year".to_string(), None, None, None, app.state()).await.is_err());
+    assert!(commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), Some("margin".to_string()), None, None, app.state()).await.is_err());
+    assert!(commands::get_sales_timeseries(period("2024-06-01", "2024-08-31"), "month".to_string(), None, Some("supplier".to_string()), None, app.state()).await.is_err());
+    assert!(commands::get_sales_timeseries(period("2024-08-31", "2024-06-01"), "month".to_string(), None, None, None, app.state()).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use app_lib::commands::{self, CreateCreditNoteRequest, CreateDeliveryNoteRequest, CreateInvoiceRequest, CreateSaleItemRequest, CreateSaleRequest, Money};
+use chrono::Utc;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    sqlx::migrate!("./migrations").run(&pool).await?;
+    sqlx::query("INSERT INTO clients (id, name) VALUES ('cli1', 'Test Client')")
+        .execute(&pool)
+        .await?;
+    Ok(pool)
+}
+
+async fn count(pool: &SqlitePool, sql: &str) -> i64 {
+    sqlx::query_scalar(sql).fetch_one(pool).await.unwrap()
+}
+
+/// Everything left on the sale, in one trip
+fn delivery_note(sale_id: &str) -> CreateDeliveryNoteRequest {
+    CreateDeliveryNoteRequest {
+        sale_id: sale_id.to_string(),
+        date: "2024-06-10".to_string(),
+        truck: None,
+        driver: None,
+        plate: None,
+        notes: None,
+        lines: vec![],
+    }
+}
+
+fn coil_item(description: &str, weight: f64) -> CreateSaleItemRequest {
+    CreateSaleItemRequest {
+        description: description.to_string(),
+        coil_ref: None,
+        coil_thickness: Some(0.5),
+        coil_width: Some(1000.0),
+        top_coat_ral: None,
+        back_coat_ral: None,
+        coil_weight: Some(weight),
+        quantity: 1.0,
+        price_per_ton: Money::from_units(100.0),
+        total_amount: Money::from_units(100.0 * weight),
+        product_type: "coil".to_string(),
+        unit_cost: None,
+        tax_rate: None,
+    }
+}
+
+fn sale_request(items: Vec<CreateSaleItemRequest>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        client_id: "cli1".to_string(),
+        date: Utc::now(),
+        total_amount: Money::from_units(300.0),
+        total_amount_ttc: Money::from_units(357.0),
+        is_invoiced: false,
+        invoice_id: None,
+        notes: None,
+        payment_method: None,
+        transportation_fee: None,
+        tax_rate: 0.19,
+        tax_exemption: None,
+        is_paid: None,
+        paid_at: None,
+        items,
+        allow_oversell: false,
+    }
+}
+
+#[tokio::test]
+async fn test_create_sale_rolls_back_when_an_item_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    // Inject a failure on the second item insert
+    sqlx::query("CREATE TRIGGER fail_second_item BEFORE INSERT ON sale_items WHEN (SELECT COUNT(*) FROM sale_items WHERE sale_id = NEW.sale_id) >= 1 BEGIN SELECT RAISE(ABORT, 'injected failure'); END;")
+        .execute(&pool)
+        .await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let result = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0), coil_item("Coil B", 2.0)]), app.state()).await;
+    assert!(result.is_err(), "create_sale should fail");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM sales").await, 0, "No sale should be written");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM sale_items").await, 0, "No sale item should be written");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM audit_log").await, 0, "No audit entry should be written");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_sale_keeps_old_items_when_an_item_is_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0)]), app.state()).await?;
+    let mut invalid = coil_item("Coil B", 2.0);
+    invalid.coil_thickness = None;
+    let mut request = sale_request(vec![coil_item("Coil C", 3.0), invalid]);
+    request.notes = Some("edited".to_string());
+
+    let result = commands::update_sale(sale.id.clone(), request, app.state()).await;
+    assert!(result.is_err(), "update_sale should reject the invalid item");
+    let kept = commands::get_sale_by_id(sale.id.clone(), app.state()).await?.expect("sale should still exist");
+    assert_eq!(kept.items.len(), 1, "Old items should be kept");
+    assert_eq!(kept.items[0].description, "Coil A", "Old items should be kept");
+    assert!(kept.notes.is_none(), "Sale header should not be updated");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM audit_log WHERE action = 'update'").await, 0, "No update audit entry should be written");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_sale_rolls_back_when_audit_log_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0)]), app.state()).await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', ?, 'cli1', 5000, '2024-06-12', 'cash', 0)")
+        .bind(&sale.id)
+        .execute(&pool)
+        .await?;
+    // The audit entry is the last write, so every earlier write must be undone
+    sqlx::query("CREATE TRIGGER fail_audit BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'injected failure'); END;")
+        .execute(&pool)
+        .await?;
+
+    let result = commands::delete_sale(sale.id.clone(), app.state()).await;
+    assert!(result.is_err(), "delete_sale should fail");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM sales WHERE is_deleted = 1").await, 0, "Sale should not be soft-deleted");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM payments WHERE is_deleted = 1").await, 0, "Payments should not be soft-deleted");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_invoice_rolls_back_when_a_sale_link_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0)]), app.state()).await?;
+    commands::create_delivery_note(delivery_note(&sale.id), app.state()).await?;
+    let invoice = CreateInvoiceRequest {
+        invoice_number: None,
+        client_id: "cli1".to_string(),
+        date: "2024-06-10".to_string(),
+        due_date: "2024-07-10".to_string(),
+        total_amount_ht: Money::from_units(100.0),
+        total_amount_ttc: Money::from_units(119.0),
+        is_paid: false,
+        paid_at: None,
+        notes: None,
+        // The second sale does not exist, so its invoice_sales link violates the foreign key
+        sales_ids: vec![sale.id.clone(), "missing-sale".to_string()],
+    };
+
+    let result = commands::create_invoice(invoice, app.state()).await;
+    assert!(result.is_err(), "create_invoice should fail");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM invoices").await, 0, "No invoice should be written");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM invoice_sales").await, 0, "No sale should be linked");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM sales WHERE is_invoiced = 1").await, 0, "No sale should be marked invoiced");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restore_sale_rolls_back_when_audit_log_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0)]), app.state()).await?;
+    sqlx::query("INSERT INTO payments (id, sale_id, client_id, amount, date, method, is_deleted) VALUES ('pay1', ?, 'cli1', 5000, '2024-06-12', 'cash', 0)")
+        .bind(&sale.id)
+        .execute(&pool)
+        .await?;
+    commands::delete_sale(sale.id.clone(), app.state()).await?;
+    sqlx::query("CREATE TRIGGER fail_audit BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'injected failure'); END;")
+        .execute(&pool)
+        .await?;
+
+    let result = commands::restore_sale(sale.id.clone(), app.state()).await;
+    assert!(result.is_err(), "restore_sale should fail");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM sales WHERE is_deleted = 1").await, 1, "Sale should stay deleted");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM payments WHERE is_deleted = 1").await, 1, "Payments should stay deleted");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_credit_note_rolls_back_when_audit_log_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let sale = commands::create_sale(sale_request(vec![coil_item("Coil A", 1.0)]), app.state()).await?;
+    commands::create_delivery_note(delivery_note(&sale.id), app.state()).await?;
+    let invoice = CreateInvoiceRequest {
+        invoice_number: None,
+        client_id: "cli1".to_string(),
+        date: "2024-06-10".to_string(),
+        due_date: "2024-07-10".to_string(),
+        total_amount_ht: Money::from_units(100.0),
+        total_amount_ttc: Money::from_units(119.0),
+        is_paid: false,
+        paid_at: None,
+        notes: None,
+        sales_ids: vec![sale.id.clone()],
+    };
+    let invoice = commands::create_invoice(invoice, app.state()).await?;
+    sqlx::query("CREATE TRIGGER fail_audit BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'injected failure'); END;")
+        .execute(&pool)
+        .await?;
+
+    let credit_note = CreateCreditNoteRequest {
+        invoice_id: invoice.id,
+        date: "2024-06-20".to_string(),
+        reason: None,
+        lines: vec![],
+        amount: None,
+    };
+    let result = commands::create_credit_note(credit_note, app.state()).await;
+    assert!(result.is_err(), "create_credit_note should fail");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM credit_notes").await, 0, "No credit note should be written");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM credit_note_items").await, 0, "No credit note line should be written");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM invoices WHERE credited_amount > 0").await, 0, "Invoice should not be credited");
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM document_sequences WHERE document_type = 'credit_note'").await, 0, "No number should be consumed");
+    Ok(())
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use app_lib::commands::{self, CreateSaleItemRequest, CreateSaleRequest, Money, UpdateSettingsRequest};
+use tauri::Manager;
+
+fn coil_item(weight: f64, price_per_ton: f64, tax_rate: Option<f64>) -> CreateSaleItemRequest {
+    CreateSaleItemRequest { tax_rate, ..common::coil_item(weight, price_per_ton) }
+}
+
+fn sale(items: Vec<CreateSaleItemRequest>, payment_method: Option<&str>, tax_exemption: Option<&str>) -> CreateSaleRequest {
+    CreateSaleRequest {
+        payment_method: payment_method.map(str::to_string),
+        transportation_fee: Some(Money::from_units(10000.0)),
+        tax_exemption: tax_exemption.map(str::to_string),
+        ..common::sale(items)
+    }
+}
+
+fn stamp_duty_max(max: f64) -> UpdateSettingsRequest {
+    UpdateSettingsRequest {
+        company_name: None,
+        company_address: None,
+        company_phone: None,
+        company_email: None,
+        company_logo: None,
+        tax_rate: None,
+        currency: None,
+        nif: None,
+        nis: None,
+        rc: None,
+        ai: None,
+        rib: None,
+        language: None,
+        theme: None,
+        notifications: None,
+        dark_mode: None,
+        user_id: None,
+        invoice_number_format: None,
+        credit_note_number_format: None,
+        quote_number_format: None,
+        delivery_note_number_format: None,
+        stamp_duty_rate: None,
+        stamp_duty_min: None,
+        stamp_duty_max: Some(Money::from_units(max)),
+    }
+}
+
+#[tokio::test]
+async fn test_sale_totals_use_item_rates_exemptions_and_stamp_duty() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    // 100 000 at 19% and 50 000 at 9%, plus a 10 000 fee taxed at the sale's 19%
+    let mixed = commands::create_sale(sale(vec![coil_item(1.0, 100000.0, None), coil_item(1.0, 50000.0, Some(0.09))], None, None), app.state()).await?;
+    assert_eq!(mixed.total_amount, Money::from_units(160000.0));
+    assert_eq!(mixed.tax_amount, Money::from_units(20900.0 + 4500.0));
+    assert_eq!(mixed.stamp_duty, Money::ZERO);
+    assert_eq!(mixed.total_amount_ttc, Money::from_units(185400.0));
+    let item_taxes: Vec<(Option<f64>, Money)> = mixed.items.iter().map(|i| (i.tax_rate, i.tax_amount)).collect();
+    assert_eq!(item_taxes, vec![(None, Money::from_units(19000.0)), (Some(0.09), Money::from_units(4500.0))]);
+
+    // Paid in cash: 1% stamp duty on the amount due
+    let cash = commands::update_sale(mixed.id.clone(), sale(vec![coil_item(1.0, 100000.0, None), coil_item(1.0, 50000.0, Some(0.09))], Some("cash"), None), app.state()).await?;
+    assert_eq!(cash.stamp_duty, Money::from_units(1854.0));
+    assert_eq!(cash.total_amount_ttc, Money::from_units(187254.0));
+    assert_eq!(cash.balance_due, cash.total_amount_ttc, "The client owes the stamp duty too");
+
+    commands::update_settings(stamp_duty_max(1000.0), app.state()).await?;
+    let capped = commands::create_sale(sale(vec![coil_item(1.0, 100000.0, None)], Some("cash"), None), app.state()).await?;
+    assert_eq!(capped.stamp_duty, Money::from_units(1000.0));
+
+    let export = commands::create_sale(sale(vec![coil_item(1.0, 100000.0, Some(0.09))], None, Some(" export ")), app.state()).await?;
+    assert_eq!(export.tax_exemption.as_deref(), Some("export"));
+    assert_eq!(export.tax_amount, Money::ZERO);
+    assert_eq!(export.total_amount_ttc, Money::from_units(110000.0));
+    assert_eq!(export.items[0].tax_amount, Money::ZERO);
+    let blank = commands::create_sale(sale(vec![coil_item(1.0, 100000.0, None)], None, Some("  ")), app.state()).await?;
+    assert_eq!(blank.tax_exemption, None, "A blank reason does not exempt the sale");
+
+    assert!(commands::create_sale(sale(vec![coil_item(1.0, 100000.0, Some(9.0))], None, None), app.state()).await.is_err());
+    assert!(commands::update_settings(stamp_duty_max(-1.0), app.state()).await.is_err());
+
+    // Recalculation keeps the stamp duty the sale was charged
+    let report = commands::recalculate_all_sale_totals(Some(true), app.state()).await?;
+    assert!(report.corrections.is_empty(), "Stored totals already follow the model");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_analytics_follow_the_vat_model() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = common::setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let mut standard = sale(vec![coil_item(1.0, 100000.0, None), coil_item(1.0, 50000.0, Some(0.09))], Some("cash"), None);
+    standard.transportation_fee = None;
+    commands::create_sale(standard, app.state()).await?;
+    let mut export = sale(vec![coil_item(2.0, 100000.0, None)], None, Some("export"));
+    export.transportation_fee = None;
+    commands::create_sale(export, app.state()).await?;
+
+    let summary = commands::get_sold_products_summary(common::no_filter(), app.state()).await?;
+    let vat: Vec<(f64, Money, Money)> = summary.vat_breakdown.iter().map(|v| (v.tax_rate, v.taxable_amount, v.tax_amount)).collect();
+    assert_eq!(vat, vec![
+        (0.0, Money::from_units(200000.0), Money::ZERO),
+        (0.09, Money::from_units(50000.0), Money::from_units(4500.0)),
+        (0.19, Money::from_units(100000.0), Money::from_units(19000.0)),
+    ]);
+    assert_eq!(summary.item_total_revenue, Money::from_units(373500.0));
+    assert_eq!(summary.official_total_revenue, Money::from_units(373500.0), "Items and headers agree");
+    assert_eq!(summary.total_stamp_duty, Money::from_units(1735.0));
+
+    let rows = commands::get_sold_products_analytics(common::no_filter(), None, Some(10), app.state()).await?;
+    let mut totals: Vec<Money> = rows.rows.iter().map(|r| r.total_price).collect();
+    totals.sort();
+    assert_eq!(totals, vec![Money::from_units(54500.0), Money::from_units(119000.0), Money::from_units(200000.0)]);
+
+    let stats = commands::get_dashboard_stats(app.state()).await?;
+    assert_eq!(stats.total_revenue, Money::from_units(373500.0), "Stamp duty is not revenue");
+    Ok(())
+}
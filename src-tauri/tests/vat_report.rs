@@ -0,0 +1,134 @@
+mod common;
+
+use app_lib::commands::{self, Money};
+use sqlx::SqlitePool;
+use tauri::Manager;
+
+async fn setup() -> Result<SqlitePool, Box<dyn std::error::Error>> {
+    let pool = common::pool().await?;
+    sqlx::query("INSERT INTO clients (id, name, nif) VALUES ('cli1', 'Alpha', '000111222'), ('cli2', 'Beta', NULL)")
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO settings (company_name, company_address, company_phone, tax_rate, nif, nis, ai, rc) VALUES ('Test Company', 'Address', '000', 0.19, 'NIF-1', 'NIS-1', 'AI-1', 'RC-1')")
+        .execute(&pool)
+        .await?;
+    // sale1: 100 000 at 19% and 50 000 at 9%, invoiced in June, paid in cash with stamp duty
+    // sale2: 200 000 exempt, uninvoiced, in June
+    // sale3: 100 000 at 19%, invoiced in July
+    // sale4: deleted
+    sqlx::query(
+        "INSERT INTO sales (id, client_id, date, total_amount, tax_amount, stamp_duty, total_amount_ttc, tax_rate, tax_exemption, payment_method, is_invoiced, invoice_id, is_deleted) VALUES
+         ('sale1', 'cli1', '2024-06-10', 15000000, 2350000, 173500, 17523500, 0.19, NULL, 'cash', 1, 'inv1', 0),
+         ('sale2', 'cli2', '2024-06-12', 20000000, 0, 0, 20000000, 0.19, 'export', NULL, 0, NULL, 0),
+         ('sale3', 'cli1', '2024-06-28', 10000000, 1900000, 0, 11900000, 0.19, NULL, NULL, 1, 'inv2', 0),
+         ('sale4', 'cli1', '2024-06-15', 10000000, 1900000, 0, 11900000, 0.19, NULL, NULL, 0, NULL, 1)"
+    )
+    .execute(&pool)
+    .await?;
+    sqlx::query(
+        "INSERT INTO sale_items (id, sale_id, description, coil_weight, quantity, price_per_ton, total_amount, tax_rate, tax_amount, product_type) VALUES
+         ('item1', 'sale1', 'Coil', 1, 1, 10000000, 10000000, NULL, 1900000, 'coil'),
+         ('item2', 'sale1', 'Coil', 1, 1, 5000000, 5000000, 0.09, 450000, 'coil'),
+         ('item3', 'sale2', 'Coil', 2, 1, 10000000, 20000000, NULL, 0, 'coil'),
+         ('item4', 'sale3', 'Coil', 1, 1, 10000000, 10000000, NULL, 1900000, 'coil'),
+         ('item5', 'sale4', 'Coil', 1, 1, 10000000, 10000000, NULL, 1900000, 'coil')"
+    )
+    .execute(&pool)
+    .await?;
+    sqlx::query(
+        "INSERT INTO invoices (id, invoice_number, client_id, date, due_date, total_amount_ht, total_amount_ttc, is_paid, is_deleted) VALUES
+         ('inv1', 'F-001', 'cli1', '2024-06-10', '2024-07-10', 15000000, 17523500, 0, 0),
+         ('inv2', 'F-002', 'cli1', '2024-07-02', '2024-08-02', 10000000, 11900000, 0, 0)"
+    )
+    .execute(&pool)
+    .await?;
+    sqlx::query("INSERT INTO invoice_sales (id, invoice_id, sale_id) VALUES ('is1', 'inv1', 'sale1'), ('is2', 'inv2', 'sale3')")
+        .execute(&pool)
+        .await?;
+    // Half of sale1 in June, the other half in July; sale2 in full in June
+    sqlx::query(
+        "INSERT INTO payments (id, sale_id, invoice_id, client_id, amount, date, method, is_deleted) VALUES
+         ('pay1', 'sale1', 'inv1', 'cli1', 8761750, '2024-06-20', 'cash', 0),
+         ('pay2', 'sale1', 'inv1', 'cli1', 8761750, '2024-07-05', 'cash', 0),
+         ('pay3', 'sale2', NULL, 'cli2', 20000000, '2024-06-25', 'transfer', 0),
+         ('pay4', 'sale2', NULL, 'cli2', 20000000, '2024-06-26', 'transfer', 1)"
+    )
+    .execute(&pool)
+    .await?;
+    Ok(pool)
+}
+
+fn vat(report: &commands::VatReport) -> Vec<(f64, Money, Money)> {
+    report.vat_by_rate.iter().map(|v| (v.tax_rate, v.taxable_amount, v.tax_amount)).collect()
+}
+
+#[tokio::test]
+async fn test_vat_report_on_invoiced_basis() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let june = commands::get_vat_report("2024-06".to_string(), "invoiced".to_string(), app.state()).await?;
+    assert_eq!((june.start_date.as_str(), june.end_date.as_str()), ("2024-06-01", "2024-06-30"));
+    assert_eq!((june.nif.as_deref(), june.nis.as_deref(), june.ai.as_deref()), (Some("NIF-1"), Some("NIS-1"), Some("AI-1")));
+    assert_eq!(june.company_name, "Test Company");
+    let rows: Vec<(&str, &str)> = june.rows.iter().map(|r| (r.kind.as_str(), r.id.as_str())).collect();
+    assert_eq!(rows, vec![("invoice", "inv1"), ("sale", "sale2")], "sale3 is declared with its July invoice, sale4 is deleted");
+    assert_eq!(june.rows[0].reference.as_deref(), Some("F-001"));
+    assert_eq!(june.rows[0].client_nif.as_deref(), Some("000111222"));
+    assert_eq!(vat(&june), vec![
+        (0.09, Money::from_units(50000.0), Money::from_units(4500.0)),
+        (0.19, Money::from_units(100000.0), Money::from_units(19000.0)),
+    ]);
+    assert_eq!(june.taxable_amount, Money::from_units(150000.0));
+    assert_eq!(june.tax_amount, Money::from_units(23500.0));
+    assert_eq!(june.exempt_amount, Money::from_units(200000.0));
+    assert_eq!(june.stamp_duty, Money::from_units(1735.0));
+
+    let quarter = commands::get_vat_report("2024-Q3".to_string(), "invoiced".to_string(), app.state()).await?;
+    assert_eq!((quarter.start_date.as_str(), quarter.end_date.as_str()), ("2024-07-01", "2024-09-30"));
+    assert_eq!(quarter.rows.len(), 1);
+    assert_eq!(quarter.tax_amount, Money::from_units(19000.0));
+
+    assert!(commands::get_vat_report("2024-13".to_string(), "invoiced".to_string(), app.state()).await.is_err());
+    assert!(commands::get_vat_report("2024-Q5".to_string(), "invoiced".to_string(), app.state()).await.is_err());
+    assert!(commands::get_vat_report("2024-06".to_string(), "accrual".to_string(), app.state()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_vat_report_on_cash_basis_and_export() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = setup().await?;
+    let app = tauri::test::mock_app();
+    app.manage(pool.clone());
+
+    let june = commands::get_vat_report("2024-06".to_string(), "cash".to_string(), app.state()).await?;
+    let rows: Vec<&str> = june.rows.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(rows, vec!["pay1", "pay3"], "Deleted payments are left out");
+    // pay1 settles half of sale1
+    assert_eq!(vat(&june), vec![
+        (0.09, Money::from_units(25000.0), Money::from_units(2250.0)),
+        (0.19, Money::from_units(50000.0), Money::from_units(9500.0)),
+    ]);
+    assert_eq!(june.rows[0].reference.as_deref(), Some("F-001"));
+    assert_eq!(june.stamp_duty, Money::from_units(867.5));
+    assert_eq!(june.exempt_amount, Money::from_units(200000.0));
+
+    // Payments recorded before is_deleted had a default count as live
+    sqlx::query("UPDATE payments SET is_deleted = NULL WHERE id = 'pay2'").execute(&pool).await?;
+    let july = commands::get_vat_report("2024-07".to_string(), "cash".to_string(), app.state()).await?;
+    assert_eq!(july.tax_amount, Money::from_units(11750.0), "The rest of sale1 is declared when collected");
+
+    let dir = std::env::temp_dir().join(format!("vat_report_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("g50.csv");
+    commands::export_vat_report("2024-06".to_string(), "invoiced".to_string(), path.to_string_lossy().to_string(), app.state()).await?;
+    let csv = std::fs::read_to_string(&path)?;
+    std::fs::remove_dir_all(&dir)?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "company,Test Company");
+    assert_eq!(lines[1], "nif,NIF-1");
+    assert!(lines.contains(&"type,reference,date,client,client_nif,taxable_amount,tax_amount,exempt_amount,stamp_duty,ht 9%,tva 9%,ht 19%,tva 19%"));
+    assert_eq!(lines.last().copied(), Some("Total,,,,,150000.00,23500.00,200000.00,1735.00,50000.00,4500.00,100000.00,19000.00"));
+    Ok(())
+}
@@ -32,7 +32,8 @@ import {
   TableRow,
 } from '@/components/ui/table';
 import { Invoice, Sale, PaymentMethodType } from '@/types/index';
-import { formatCurrency, formatDate, formatDateInput, generateInvoiceNumber } from '../../utils/format';
+import { formatCurrency, formatDate, formatDateInput } from '../../utils/format';
+import { tauriApi } from '@/lib/tauri-api';
 import { toast } from 'sonner';
 import { useLanguage } from '@/context/LanguageContext';
 import Combobox from '@/components/ui/Combobox';
@@ -40,8 +41,8 @@ import Combobox from '@/components/ui/Combobox';
 const paymentMethods = ['cash', 'bank_transfer', 'check', 'term'] as const;
 
 const formSchema = z.object({
-  prefix: z.string().min(1, { message: 'Le préfixe est requis' }),
-  invoiceNumber: z.string().min(1, { message: 'Le numéro de facture est requis' }),
+  // Assigned by the backend from the invoice sequence; only displayed here
+  invoiceNumber: z.string().optional(),
   clientId: z.string().min(1, { message: 'Veuillez sélectionner un client' }),
   date: z.string().min(1, { message: 'La date est requise' }),
   dueDate: z.string().min(1, { message: 'La date d\'échéance est requise' }),
@@ -65,7 +66,7 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
     sales,
     getSalePaymentStatus
   } = useAppContext();
-  const { settings: invoiceSettings, updateSettings: updateInvoiceSettings } = useInvoiceSettings();
+  const { settings: invoiceSettings } = useInvoiceSettings();
   const { t } = useLanguage();
 
   const [clientSales, setClientSales] = useState<Sale[]>([]);
@@ -84,8 +85,7 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
   }, [invoice, selectedSales, sales]);
 
   const defaultValues: FormValues = {
-    prefix: invoice?.invoiceNumber?.split('-')[0] || invoiceSettings.defaultPrefix,
-    invoiceNumber: invoice?.invoiceNumber || generateInvoiceNumber(invoiceSettings.defaultPrefix, invoiceSettings.nextNumber),
+    invoiceNumber: invoice?.invoiceNumber || '',
     clientId: invoice?.clientId || '',
     date: formatDateInput(invoice?.date || new Date()),
     dueDate: formatDateInput(invoice?.dueDate || new Date(new Date().setDate(new Date().getDate() + invoiceSettings.paymentTerms))),
@@ -99,8 +99,6 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
     mode: "onChange",
   });
 
-  const [isInvoiceNumberManuallyEdited, setIsInvoiceNumberManuallyEdited] = useState(!!invoice);
-
   const calculateTotals = useCallback(() => {
     const selectedSalesData = selectedSales
       .map(id => sales.find(s => s.id === id))
@@ -126,13 +124,14 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
     return { totalHT, totalTTC, taxAmount };
   }, [selectedSales, sales]);
 
+  // New invoices show the number the backend will assign for the chosen date
+  const invoiceDate = form.watch('date');
   useEffect(() => {
-    const prefix = form.watch('prefix');
-    if (!invoice && !isInvoiceNumberManuallyEdited && invoiceSettings.autoInvoiceNumber) {
-      const newInvoiceNumber = generateInvoiceNumber(prefix, invoiceSettings.nextNumber);
-      form.setValue('invoiceNumber', newInvoiceNumber);
-    }
-  }, [form, invoice, isInvoiceNumberManuallyEdited, invoiceSettings.nextNumber, form.watch('prefix'), invoiceSettings.autoInvoiceNumber]);
+    if (invoice || !invoiceDate) return;
+    tauriApi.invoices.previewNextNumber(invoiceDate)
+      .then((number) => form.setValue('invoiceNumber', number as string))
+      .catch((error) => console.error('Error previewing invoice number:', error));
+  }, [form, invoice, invoiceDate]);
 
   useEffect(() => {
     const clientId = form.watch('clientId');
@@ -205,7 +204,7 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
       const allSalesPaid = selectedSales.every(saleId => getSalePaymentStatus(saleId)?.isFullyPaid === true);
 
       const invoiceData = {
-        invoiceNumber: data.invoiceNumber,
+        invoiceNumber: data.invoiceNumber ?? '',
         clientId: data.clientId,
         date: emissionDate,
         dueDate: expirationDate,
@@ -219,18 +218,6 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
         isDeleted: false,
       };
 
-      // SMART INCREMENT LOGIC
-      const expectedAutoNumber = generateInvoiceNumber(invoiceSettings.defaultPrefix, invoiceSettings.nextNumber);
-      const manualNumberMatch = data.invoiceNumber.match(/(\d+)$/);
-      const manualNumber = manualNumberMatch ? parseInt(manualNumberMatch[1], 10) : null;
-      if (invoiceSettings.autoInvoiceNumber && data.invoiceNumber === expectedAutoNumber) {
-        // Auto mode: increment nextNumber
-        updateInvoiceSettings({ nextNumber: invoiceSettings.nextNumber + 1 });
-      } else if (!invoiceSettings.autoInvoiceNumber && manualNumber && manualNumber >= invoiceSettings.nextNumber) {
-        // Manual mode: user entered a manual number higher than nextNumber, update nextNumber to manual+1
-        updateInvoiceSettings({ nextNumber: manualNumber + 1 });
-      }
-
       if (invoice) {
         await updateInvoice(invoice.id, invoiceData);
         toast.success('Invoice has been updated');
@@ -252,51 +239,18 @@ const InvoiceForm = ({ invoice, onSuccess }: InvoiceFormProps) => {
       <Form {...form}>
         <form onSubmit={form.handleSubmit(onSubmit)} className="space-y-4">
           {/* Form fields... */}
-          <div className="grid grid-cols-1 gap-4 sm:grid-cols-3">
-            <FormField
-              control={form.control}
-              name="prefix"
-              render={({ field, fieldState }) => (
-                <FormItem>
-                  <FormLabel>Préfixe</FormLabel>
-                  <Select
-                    onValueChange={(value) => {
-                      field.onChange(value);
-                      if (!invoice) setIsInvoiceNumberManuallyEdited(false);
-                    }}
-                    value={field.value}
-                    defaultValue={field.value}
-                  >
-                    <FormControl>
-                      <SelectTrigger aria-invalid={!!fieldState.error}>
-                        <SelectValue placeholder="Sélectionner un préfixe" />
-                      </SelectTrigger>
-                    </FormControl>
-                    <SelectContent>
-                      {invoiceSettings.prefixes.map(p => (
-                        <SelectItem key={p.value} value={p.value}>{p.value}</SelectItem>
-                      ))}
-                    </SelectContent>
-                  </Select>
-                  <FormMessage />
-                </FormItem>
-              )}
-            />
+          <div className="grid grid-cols-1 gap-4">
             <FormField
               control={form.control}
               name="invoiceNumber"
               render={({ field }) => (
-                <FormItem className="sm:col-span-2">
+                <FormItem>
                   <FormLabel>Numéro de Facture</FormLabel>
                   <FormControl>
                     <Input
                       {...field}
-                      onChange={(e) => {
-                        field.onChange(e.target.value);
-                        setIsInvoiceNumberManuallyEdited(true);
-                      }}
-                      readOnly={!!invoiceSettings.autoInvoiceNumber && !invoice}
-                      className={invoiceSettings.autoInvoiceNumber && !invoice ? 'bg-gray-100 cursor-not-allowed' : ''}
+                      readOnly
+                      className="bg-gray-100 cursor-not-allowed"
                     />
                   </FormControl>
                   <FormMessage />
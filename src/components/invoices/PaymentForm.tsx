@@ -27,7 +27,7 @@ import { formatCurrency, formatDateInput } from '@/utils/format'; // Ensure form
 import { useLanguage } from '@/context/LanguageContext';
 
 // Use the shared PaymentMethodType or define specifically if PaymentForm allows different methods
-const paymentFormMethods = ['cash', 'bank_transfer', 'check', 'term', 'deferred'] as const;
+const paymentFormMethods = ['cash', 'bank_transfer', 'check', 'credit', 'term', 'deferred'] as const;
 
 const formSchema = z.object({
   date: z.string().min(1, { message: "Date is required" }),
@@ -203,6 +203,7 @@ export const PaymentForm = ({ saleId, payment, onSuccess, onCancel }: PaymentFor
                   <SelectItem value="cash">{t('payments.methods.cash')}</SelectItem>
                   <SelectItem value="bank_transfer">{t('payments.methods.bank_transfer')}</SelectItem>
                   <SelectItem value="check">{t('payments.methods.check')}</SelectItem>
+                  <SelectItem value="credit">{t('payments.methods.credit')}</SelectItem>
                   {/* <SelectItem value="term">À Terme</SelectItem> 
                   <SelectItem value="deferred">Différé</SelectItem> */}
                 </SelectContent>
@@ -36,7 +36,8 @@ const translations: { [key in Language]: Translations } = {
       'unpaid': 'Unpaid',
       'overdue': 'Overdue',
       'pending': 'Pending',
-      'partiallyPaid': 'Partially Paid'
+      'partiallyPaid': 'Partially Paid',
+      'overpaid': 'Overpaid'
     },
 
     // General
@@ -598,6 +599,7 @@ const translations: { [key in Language]: Translations } = {
         'cash': 'Cash',
         'bank_transfer': 'Bank Transfer',
         'check': 'Check',
+        'credit': 'Client Credit',
         'term': 'Term Payment',
         'credit_card': 'Credit Card'
       },
@@ -704,7 +706,8 @@ const translations: { [key in Language]: Translations } = {
       'unpaid': 'Non Payé',
       'overdue': 'En Retard',
       'pending': 'En Attente',
-      'partiallyPaid': 'Partiellement payé'
+      'partiallyPaid': 'Partiellement payé',
+      'overpaid': 'Trop-perçu'
     },
 
     // General
@@ -1267,6 +1270,7 @@ const translations: { [key in Language]: Translations } = {
         'cash': 'Espèces',
         'bank_transfer': 'Virement bancaire',
         'check': 'Chèque',
+        'credit': 'Avoir client',
         'term': 'À terme',
       },
       'quantity': 'Quantity',
@@ -1,22 +1,44 @@
 import { core } from '@tauri-apps/api';
+import type { ListFilter } from '@/types/index';
 // import * as coreInvoke from '@tauri-apps/api/core';
 
+// The list commands expect snake_case filter keys
+const toBackendListFilter = (filter?: ListFilter) => filter && {
+  search: filter.search || undefined,
+  start_date: filter.startDate || undefined,
+  end_date: filter.endDate || undefined,
+  client_id: filter.clientId || undefined,
+  supplier_id: filter.supplierId || undefined,
+  amount_min: filter.amountMin,
+  amount_max: filter.amountMax,
+  payment_status: filter.paymentStatus,
+  is_invoiced: filter.isInvoiced,
+  sort_by: filter.sortBy,
+  sort_direction: filter.sortDirection,
+};
+
 export const tauriApi = {
   clients: {
-    getClients: (page?: number, pageSize?: number) => core.invoke('get_clients', { page, page_size: pageSize }),
+    getClients: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_clients', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
     getById: (id: string) => core.invoke('get_client_by_id', { id }),
     create: (client: any) => core.invoke('create_client', { client }),
     update: (id: string, client: any) => core.invoke('update_client', { id, client }),
     delete: (id: string) => core.invoke('delete_client', { id }),
+    getCreditLedger: (clientId: string) => core.invoke('get_client_credit_ledger', { clientId }),
+    adjustCredit: (adjustment: any) => core.invoke('create_credit_adjustment', { adjustment }),
+    reconcileCredit: () => core.invoke('reconcile_credit_balances'),
+    getStatement: (clientId: string, from?: string, to?: string) => core.invoke('get_client_statement', { clientId, from, to }),
+    exportStatement: (clientId: string, format: 'pdf' | 'csv', outputPath: string, from?: string, to?: string) => core.invoke('export_client_statement', { clientId, from, to, format, outputPath }),
   },
   sales: {
-    getSales: (page?: number, pageSize?: number) => core.invoke('get_sales', { page, page_size: pageSize }),
+    getSales: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_sales', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
     getById: (id: string) => core.invoke('get_sale_by_id', { id }),
     create: (sale: any) => core.invoke('create_sale', { sale }),
     delete: (id: string) => core.invoke('delete_sale', { id }),
     restore: (id: string) => core.invoke('restore_sale', { id }),
     getDeleted: () => core.invoke('get_deleted_sales'),
     update: (id: string, sale: any) => core.invoke('update_sale', { id, sale }),
+    recalculateTotals: (dryRun?: boolean) => core.invoke('recalculate_all_sale_totals', { dryRun }),
     markInvoiced: async (saleId: string, invoiceId: string) => {
       return await core.invoke('mark_sale_invoiced', {
         saleId,
@@ -30,24 +52,114 @@ export const tauriApi = {
     },
   },
   invoices: {
-    getInvoices: (page?: number, pageSize?: number) => core.invoke('get_invoices', { page, page_size: pageSize }),
+    getInvoices: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_invoices', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
     create: (invoice: any) => core.invoke('create_invoice', { invoice }),
+    update: (id: string, invoice: any) => core.invoke('update_invoice', { id, invoice }),
+    previewNextNumber: (date?: string) => core.invoke('preview_next_invoice_number', { date }),
     delete: (id: string) => core.invoke('delete_invoice', { id }),
     restore: (id: string) => core.invoke('restore_invoice', { id }),
     getDeleted: () => core.invoke('get_deleted_invoices'),
+    generatePdf: (invoiceId: string, outputPath: string) => core.invoke('generate_invoice_pdf', { invoiceId, outputPath }),
+  },
+  creditNotes: {
+    create: (creditNote: any) => core.invoke('create_credit_note', { creditNote }),
+    getCreditNotes: (invoiceId?: string, clientId?: string) => core.invoke('get_credit_notes', { invoiceId, clientId }),
+    getById: (id: string) => core.invoke('get_credit_note', { id }),
+  },
+  quotes: {
+    getQuotes: (page?: number, pageSize?: number, filter?: ListFilter, status?: string) => core.invoke('get_quotes', { page, page_size: pageSize, filter: toBackendListFilter(filter), status }),
+    getById: (id: string) => core.invoke('get_quote_by_id', { id }),
+    create: (quote: any) => core.invoke('create_quote', { quote }),
+    update: (id: string, quote: any) => core.invoke('update_quote', { id, quote }),
+    setStatus: (id: string, status: 'draft' | 'sent' | 'accepted' | 'expired') => core.invoke('set_quote_status', { id, status }),
+    convertToSale: (id: string, date?: string, allowOversell?: boolean) => core.invoke('convert_quote_to_sale', { id, date, allowOversell }),
+    delete: (id: string) => core.invoke('delete_quote', { id }),
+    restore: (id: string) => core.invoke('restore_quote', { id }),
+  },
+  deliveryNotes: {
+    create: (deliveryNote: any) => core.invoke('create_delivery_note', { deliveryNote }),
+    getDeliveryNotes: (saleId?: string, clientId?: string) => core.invoke('get_delivery_notes', { saleId, clientId }),
+    getById: (id: string) => core.invoke('get_delivery_note', { id }),
+    delete: (id: string) => core.invoke('delete_delivery_note', { id }),
+    generatePdf: (deliveryNoteId: string, outputPath: string) => core.invoke('generate_delivery_note_pdf', { deliveryNoteId, outputPath }),
   },
   payments: {
-    getAll: () => core.invoke('get_payments'),
+    getPayments: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_payments', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
     create: (payment: any) => core.invoke('create_payment', { payment }),
     delete: (id: string) => core.invoke('delete_payment', { id }),
     restore: (id: string) => core.invoke('restore_payment', { id }),
     getDeleted: () => core.invoke('get_deleted_payments'),
-    // update: (id: string, payment: any) => core.invoke('update_payment', { id, payment }), // Commented out, not implemented in backend
+    update: (id: string, payment: any) => core.invoke('update_payment', { id, payment }),
+    createBulk: (bulk: any) => core.invoke('create_bulk_payment', { bulk }),
+    getBulk: (clientId?: string) => core.invoke('get_bulk_payments', { clientId }),
+    deleteBulk: (id: string) => core.invoke('delete_bulk_payment', { id }),
+  },
+  paymentInstruments: {
+    getAll: (clientId?: string, status?: string) => core.invoke('get_payment_instruments', { clientId, status }),
+    getDue: (from?: string, to?: string) => core.invoke('get_cheques_due', { from, to }),
+    deposit: (ids: string[], depositBatch: string, date: string) => core.invoke('deposit_payment_instruments', { ids, depositBatch, date }),
+    clear: (id: string, date: string) => core.invoke('clear_payment_instrument', { id, date }),
+    bounce: (id: string, date: string, reason?: string) => core.invoke('bounce_payment_instrument', { id, date, reason }),
+  },
+  bankReconciliation: {
+    importStatement: (path: string) => core.invoke('import_bank_statement', { path }),
+    autoMatch: () => core.invoke('auto_match_bank_transactions'),
+    getTransactions: (status?: string, from?: string, to?: string) => core.invoke('get_bank_transactions', { status, from, to }),
+    match: (id: string, paymentId?: string, bulkPaymentId?: string) => core.invoke('match_bank_transaction', { id, paymentId, bulkPaymentId }),
+    unmatch: (id: string) => core.invoke('unmatch_bank_transaction', { id }),
+    getReport: (from?: string, to?: string) => core.invoke('get_bank_reconciliation', { from, to }),
+  },
+  search: {
+    global: (query: string, limit?: number) => core.invoke('global_search', { query, limit }),
+  },
+  coils: {
+    receive: (coil: any) => core.invoke('receive_coil', { coil }),
+    adjust: (adjustment: any) => core.invoke('adjust_coil_stock', { adjustment }),
+    getAll: (page?: number, pageSize?: number, filter?: ListFilter, inStockOnly?: boolean) => core.invoke('get_coils', { page, page_size: pageSize, filter: toBackendListFilter(filter), in_stock_only: inStockOnly }),
+    getMovements: (coilId: string) => core.invoke('get_coil_movements', { coilId }),
+  },
+  suppliers: {
+    getAll: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_suppliers', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
+    getById: (id: string) => core.invoke('get_supplier_by_id', { id }),
+    create: (supplier: any) => core.invoke('create_supplier', { supplier }),
+    update: (id: string, supplier: any) => core.invoke('update_supplier', { id, supplier }),
+    delete: (id: string) => core.invoke('delete_supplier', { id }),
+    restore: (id: string) => core.invoke('restore_supplier', { id }),
+  },
+  purchaseOrders: {
+    getAll: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_purchase_orders', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
+    getById: (id: string) => core.invoke('get_purchase_order_by_id', { id }),
+    create: (order: any) => core.invoke('create_purchase_order', { order }),
+    update: (id: string, order: any) => core.invoke('update_purchase_order', { id, order }),
+    delete: (id: string) => core.invoke('delete_purchase_order', { id }),
+    restore: (id: string) => core.invoke('restore_purchase_order', { id }),
+    receiveItem: (itemId: string, receipt: any) => core.invoke('receive_purchase_order_item', { itemId, receipt }),
+  },
+  supplierInvoices: {
+    getAll: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_supplier_invoices', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
+    create: (invoice: any) => core.invoke('create_supplier_invoice', { invoice }),
+    update: (id: string, invoice: any) => core.invoke('update_supplier_invoice', { id, invoice }),
+    delete: (id: string) => core.invoke('delete_supplier_invoice', { id }),
+    restore: (id: string) => core.invoke('restore_supplier_invoice', { id }),
+  },
+  supplierPayments: {
+    getAll: (page?: number, pageSize?: number, filter?: ListFilter) => core.invoke('get_supplier_payments', { page, page_size: pageSize, filter: toBackendListFilter(filter) }),
+    create: (payment: any) => core.invoke('create_supplier_payment', { payment }),
+    update: (id: string, payment: any) => core.invoke('update_supplier_payment', { id, payment }),
+    delete: (id: string) => core.invoke('delete_supplier_payment', { id }),
+    restore: (id: string) => core.invoke('restore_supplier_payment', { id }),
   },
   analytics: {
     getSoldProducts: (filter: any, page?: number, pageSize?: number) => core.invoke('get_sold_products_analytics', { filter, page, page_size: pageSize }),
     getSoldProductsSummary: (filter: any) => core.invoke('get_sold_products_summary', { filter }),
+    getMargins: (filter: any) => core.invoke('get_margin_analytics', { filter }),
+    getTimeseries: (filter: any, granularity: 'day' | 'week' | 'month' | 'quarter', metric?: string, breakdown?: 'product_type' | 'client', comparePreviousYear?: boolean) =>
+      core.invoke('get_sales_timeseries', { filter, granularity, metric, breakdown, comparePreviousYear }),
     getUniqueThicknessWidth: () => core.invoke('get_unique_thickness_width'),
+    getArAging: (asOf?: string, bucketEdges?: number[]) => core.invoke('get_ar_aging', { asOf, bucketEdges }),
+    exportArAging: (outputPath: string, asOf?: string, bucketEdges?: number[]) => core.invoke('export_ar_aging', { asOf, bucketEdges, outputPath }),
+    getVatReport: (period: string, basis: 'invoiced' | 'cash') => core.invoke('get_vat_report', { period, basis }),
+    exportVatReport: (period: string, basis: 'invoiced' | 'cash', outputPath: string) => core.invoke('export_vat_report', { period, basis, outputPath }),
   },
   settings: {
     get: () => core.invoke('get_settings'),
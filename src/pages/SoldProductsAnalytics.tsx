@@ -254,13 +254,15 @@ const FilterPanel = ({ filters, setFilters, t, onExport }: {
             {[
               { value: 'all', label: t('general.all'), color: 'bg-gray-300 dark:bg-gray-700' },
               { value: 'paid', label: t('status.paid'), color: 'bg-green-500' },
+              { value: 'partial', label: t('status.partiallyPaid'), color: 'bg-yellow-500' },
               { value: 'unpaid', label: t('status.unpaid'), color: 'bg-red-500' },
+              { value: 'overpaid', label: t('status.overpaid'), color: 'bg-blue-500' },
             ].map(opt => (
               <button
                 key={opt.value}
                 type="button"
                 className={`px-3 py-1 rounded-full border text-xs font-medium transition-colors focus:outline-none focus:ring-2 focus:ring-blue-500 ${filters.paymentStatus === opt.value || (!filters.paymentStatus && opt.value === 'all') ? `${opt.color} text-white border-transparent` : 'bg-white dark:bg-gray-800 border-gray-300 dark:border-gray-700 text-gray-800 dark:text-gray-200'}`}
-                onClick={() => setFilters(f => ({ ...f, paymentStatus: opt.value as 'all' | 'paid' | 'unpaid' }))}
+                onClick={() => setFilters(f => ({ ...f, paymentStatus: opt.value as SoldProductsFilter['paymentStatus'] }))}
               >
                 {opt.label}
               </button>
@@ -0,0 +1,139 @@
+import { tauriApi } from '@/lib/tauri-api';
+import { formatDateInput } from '@/utils/format';
+
+export type BankStatementFormat = 'csv' | 'ofx' | 'camt053';
+
+// Credits are positive, debits negative
+export interface BankTransaction {
+  id: string;
+  sourceFile: string;
+  format: BankStatementFormat;
+  date: Date;
+  amount: number;
+  description?: string;
+  reference?: string;
+  paymentId?: string;
+  bulkPaymentId?: string;
+  matchMethod?: 'auto' | 'manual';
+  matchedAt?: Date;
+  importedAt: Date;
+}
+
+// A bank transfer, cheque or traite the bank has not shown yet
+export interface ReconciliationPayment {
+  paymentId?: string;
+  bulkPaymentId?: string;
+  clientId: string;
+  clientName?: string;
+  date: Date;
+  expectedDate: Date;
+  amount: number;
+  method: string;
+  checkNumber?: string;
+}
+
+export interface BankStatementImport {
+  sourceFile: string;
+  format: BankStatementFormat;
+  imported: number;
+  duplicates: number;
+  autoMatched: number;
+  transactions: BankTransaction[];
+}
+
+export interface BankReconciliation {
+  from?: string;
+  to?: string;
+  matchedCount: number;
+  unmatchedTransactions: BankTransaction[];
+  unmatchedPayments: ReconciliationPayment[];
+  unmatchedTransactionsTotal: number;
+  unmatchedPaymentsTotal: number;
+}
+
+const mapTransaction = (row: any): BankTransaction => ({
+  id: row.id,
+  sourceFile: row.source_file,
+  format: row.format,
+  date: new Date(row.date),
+  amount: row.amount,
+  description: row.description ?? undefined,
+  reference: row.reference ?? undefined,
+  paymentId: row.payment_id ?? undefined,
+  bulkPaymentId: row.bulk_payment_id ?? undefined,
+  matchMethod: row.match_method ?? undefined,
+  matchedAt: row.matched_at ? new Date(row.matched_at) : undefined,
+  importedAt: new Date(row.imported_at),
+});
+
+const mapPayment = (row: any): ReconciliationPayment => ({
+  paymentId: row.payment_id ?? undefined,
+  bulkPaymentId: row.bulk_payment_id ?? undefined,
+  clientId: row.client_id,
+  clientName: row.client_name ?? undefined,
+  date: new Date(row.date),
+  expectedDate: new Date(row.expected_date),
+  amount: row.amount,
+  method: row.method,
+  checkNumber: row.check_number ?? undefined,
+});
+
+// CSV, OFX and CAMT.053 files are recognised from their content
+export const importBankStatement = async (path: string): Promise<BankStatementImport> => {
+  try {
+    const result = await tauriApi.bankReconciliation.importStatement(path) as any;
+    return {
+      sourceFile: result.source_file,
+      format: result.format,
+      imported: result.imported,
+      duplicates: result.duplicates,
+      autoMatched: result.auto_matched,
+      transactions: result.transactions.map(mapTransaction),
+    };
+  } catch (error) {
+    console.error('Error importing bank statement:', error);
+    throw error;
+  }
+};
+
+export const autoMatchBankTransactions = async (): Promise<number> => {
+  return await tauriApi.bankReconciliation.autoMatch() as number;
+};
+
+export const getBankTransactions = async (
+  status?: 'matched' | 'unmatched',
+  from?: Date,
+  to?: Date
+): Promise<BankTransaction[]> => {
+  const rows = await tauriApi.bankReconciliation.getTransactions(
+    status,
+    from ? formatDateInput(from) : undefined,
+    to ? formatDateInput(to) : undefined
+  ) as any[];
+  return rows.map(mapTransaction);
+};
+
+// Exactly one of paymentId / bulkPaymentId
+export const matchBankTransaction = async (id: string, paymentId?: string, bulkPaymentId?: string): Promise<BankTransaction> => {
+  return mapTransaction(await tauriApi.bankReconciliation.match(id, paymentId, bulkPaymentId));
+};
+
+export const unmatchBankTransaction = async (id: string): Promise<BankTransaction> => {
+  return mapTransaction(await tauriApi.bankReconciliation.unmatch(id));
+};
+
+export const getBankReconciliation = async (from?: Date, to?: Date): Promise<BankReconciliation> => {
+  const report = await tauriApi.bankReconciliation.getReport(
+    from ? formatDateInput(from) : undefined,
+    to ? formatDateInput(to) : undefined
+  ) as any;
+  return {
+    from: report.from ?? undefined,
+    to: report.to ?? undefined,
+    matchedCount: report.matched_count,
+    unmatchedTransactions: report.unmatched_transactions.map(mapTransaction),
+    unmatchedPayments: report.unmatched_payments.map(mapPayment),
+    unmatchedTransactionsTotal: report.unmatched_transactions_total,
+    unmatchedPaymentsTotal: report.unmatched_payments_total,
+  };
+};
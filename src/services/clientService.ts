@@ -1,7 +1,7 @@
 // Replace the Supabase import with local data
 // import { supabase } from '@/integrations/supabase/client';
 import { tauriApi } from '@/lib/tauri-api';
-import { Client } from '@/types/index';
+import { Client, ListFilter } from '@/types/index';
 
 export interface PaginatedClientsResult {
   rows: Client[];
@@ -29,9 +29,10 @@ export const getClientById = async (id: string): Promise<Client | null> => {
 
 export const getClientsPaginated = async (
   page: number = 1,
-  pageSize: number = 5
+  pageSize: number = 5,
+  filter?: ListFilter
 ): Promise<PaginatedClientsResult> => {
-  const result = await tauriApi.clients.getClients(page, pageSize) as PaginatedClientsResult;
+  const result = await tauriApi.clients.getClients(page, pageSize, filter) as PaginatedClientsResult;
   return result;
 };
 
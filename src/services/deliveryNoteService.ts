@@ -0,0 +1,105 @@
+import { tauriApi } from '@/lib/tauri-api';
+import { formatDateInput } from '@/utils/format';
+
+// Coils and steel slitting are delivered by weight (tons), other products by quantity
+export interface DeliveryNoteItem {
+  id: string;
+  saleItemId: string;
+  description: string;
+  productType: string;
+  weight?: number;
+  quantity?: number;
+}
+
+export interface DeliveryNote {
+  id: string;
+  deliveryNoteNumber: string;
+  saleId: string;
+  clientId: string;
+  date: Date;
+  truck?: string;
+  driver?: string;
+  plate?: string;
+  notes?: string;
+  createdAt: Date;
+  updatedAt?: Date;
+  items: DeliveryNoteItem[];
+}
+
+export interface CreateDeliveryNoteInput {
+  saleId: string;
+  date: Date;
+  truck?: string;
+  driver?: string;
+  plate?: string;
+  notes?: string;
+  // What is left of each item when its measure is omitted; no lines delivers everything left
+  lines?: { saleItemId: string; weight?: number; quantity?: number }[];
+}
+
+const mapDeliveryNote = (note: any): DeliveryNote => ({
+  id: note.id,
+  deliveryNoteNumber: note.delivery_note_number,
+  saleId: note.sale_id,
+  clientId: note.client_id,
+  date: new Date(note.date),
+  truck: note.truck ?? undefined,
+  driver: note.driver ?? undefined,
+  plate: note.plate ?? undefined,
+  notes: note.notes ?? undefined,
+  createdAt: new Date(note.created_at),
+  updatedAt: note.updated_at ? new Date(note.updated_at) : undefined,
+  items: (note.items ?? []).map((item: any) => ({
+    id: item.id,
+    saleItemId: item.sale_item_id,
+    description: item.description,
+    productType: item.product_type,
+    weight: item.weight ?? undefined,
+    quantity: item.quantity ?? undefined,
+  })),
+});
+
+export const createDeliveryNote = async (input: CreateDeliveryNoteInput): Promise<DeliveryNote> => {
+  try {
+    const created = await tauriApi.deliveryNotes.create({
+      sale_id: input.saleId,
+      date: formatDateInput(input.date),
+      truck: input.truck ?? null,
+      driver: input.driver ?? null,
+      plate: input.plate ?? null,
+      notes: input.notes ?? null,
+      lines: (input.lines ?? []).map(line => ({
+        sale_item_id: line.saleItemId,
+        weight: line.weight ?? null,
+        quantity: line.quantity ?? null,
+      })),
+    });
+    return mapDeliveryNote(created);
+  } catch (error) {
+    console.error('Error creating delivery note:', error);
+    throw error;
+  }
+};
+
+export const getDeliveryNotes = async (saleId?: string, clientId?: string): Promise<DeliveryNote[]> => {
+  try {
+    const notes = await tauriApi.deliveryNotes.getDeliveryNotes(saleId, clientId) as any[];
+    return Array.isArray(notes) ? notes.map(mapDeliveryNote) : [];
+  } catch (error) {
+    console.error('Error fetching delivery notes:', error);
+    throw error;
+  }
+};
+
+export const getDeliveryNoteById = async (id: string): Promise<DeliveryNote | null> => {
+  const note = await tauriApi.deliveryNotes.getById(id) as any;
+  return note ? mapDeliveryNote(note) : null;
+};
+
+export const deleteDeliveryNote = async (id: string): Promise<void> => {
+  await tauriApi.deliveryNotes.delete(id);
+};
+
+export const generateDeliveryNotePdf = async (id: string, outputPath: string): Promise<string> => {
+  return await tauriApi.deliveryNotes.generatePdf(id, outputPath) as string;
+};
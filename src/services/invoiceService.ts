@@ -1,5 +1,5 @@
 import { tauriApi } from '@/lib/tauri-api';
-import { Invoice } from '@/types/index';
+import { Invoice, ListFilter } from '@/types/index';
 import { formatDateInput } from '@/utils/format';
 
 // Interface for data being inserted into Supabase
@@ -65,6 +65,10 @@ const mapDbInvoiceToInvoice = (dbInvoice: DbInvoice, salesIds: string[] = [], ex
   taxRate: typeof extra.tax_rate === 'number' ? extra.tax_rate : 0.19,
   isPaid: dbInvoice.is_paid,
   paidAt: dbInvoice.paid_at ? new Date(dbInvoice.paid_at) : undefined,
+  creditedAmount: extra.credited_amount,
+  amountPaid: extra.amount_paid,
+  balanceDue: extra.balance_due,
+  paymentStatus: extra.payment_status,
   paymentMethod: extra.payment_method,
   transportationFee: extra.transportation_fee,
   transportationFeeTTC: extra.transportation_fee_ttc,
@@ -157,7 +161,8 @@ export const createInvoice = async (
   try {
     // Map camelCase to snake_case for backend
     const backendInvoice = {
-      invoice_number: invoice.invoiceNumber,
+      // The number is assigned by the backend from the invoice sequence
+      invoice_number: null,
       client_id: invoice.clientId,
       date: invoice.date.toISOString(),
       due_date: invoice.dueDate.toISOString(),
@@ -181,9 +186,10 @@ export interface PaginatedInvoicesResult {
 
 export const getInvoicesPaginated = async (
   page: number = 1,
-  pageSize: number = 5
+  pageSize: number = 5,
+  filter?: ListFilter
 ): Promise<PaginatedInvoicesResult> => {
-  const result = await tauriApi.invoices.getInvoices(page, pageSize) as any;
+  const result = await tauriApi.invoices.getInvoices(page, pageSize, filter) as any;
   return {
     rows: result.rows.map((row: any) => {
       // Defensive: ensure sales_ids is always an array
@@ -212,4 +218,89 @@ export const getInvoicesPaginated = async (
   };
 };
 
-// TODO: Implement updateInvoice when backend support is available.
\ No newline at end of file
+// TODO: Implement updateInvoice when backend support is available.
+export interface CreditNoteItem {
+  id: string;
+  saleId: string;
+  saleItemId?: string;
+  description: string;
+  taxRate: number;
+  totalAmount: number;
+  taxAmount: number;
+}
+
+export interface CreditNote {
+  id: string;
+  creditNoteNumber: string;
+  invoiceId: string;
+  invoiceNumber: string;
+  clientId: string;
+  date: Date;
+  reason?: string;
+  totalAmountHT: number;
+  taxAmount: number;
+  stampDuty: number;
+  totalAmountTTC: number;
+  createdAt: Date;
+  items: CreditNoteItem[];
+}
+
+export interface CreateCreditNoteInput {
+  invoiceId: string;
+  date: Date;
+  reason?: string;
+  // Credit these items (HT, the rest of the item when omitted)...
+  lines?: { saleItemId: string; amount?: number }[];
+  // ...or this TTC amount across the invoice; neither credits everything left
+  amount?: number;
+}
+
+const mapCreditNote = (note: any): CreditNote => ({
+  id: note.id,
+  creditNoteNumber: note.credit_note_number,
+  invoiceId: note.invoice_id,
+  invoiceNumber: note.invoice_number,
+  clientId: note.client_id,
+  date: new Date(note.date),
+  reason: note.reason ?? undefined,
+  totalAmountHT: note.total_amount_ht,
+  taxAmount: note.tax_amount,
+  stampDuty: note.stamp_duty,
+  totalAmountTTC: note.total_amount_ttc,
+  createdAt: new Date(note.created_at),
+  items: (note.items ?? []).map((item: any) => ({
+    id: item.id,
+    saleId: item.sale_id,
+    saleItemId: item.sale_item_id ?? undefined,
+    description: item.description,
+    taxRate: item.tax_rate,
+    totalAmount: item.total_amount,
+    taxAmount: item.tax_amount,
+  })),
+});
+
+export const createCreditNote = async (input: CreateCreditNoteInput): Promise<CreditNote> => {
+  try {
+    const created = await tauriApi.creditNotes.create({
+      invoice_id: input.invoiceId,
+      date: formatDateInput(input.date),
+      reason: input.reason ?? null,
+      lines: (input.lines ?? []).map(line => ({ sale_item_id: line.saleItemId, amount: line.amount ?? null })),
+      amount: input.amount ?? null,
+    });
+    return mapCreditNote(created);
+  } catch (error) {
+    console.error('Error creating credit note:', error);
+    throw error;
+  }
+};
+
+export const getCreditNotes = async (invoiceId?: string, clientId?: string): Promise<CreditNote[]> => {
+  try {
+    const notes = await tauriApi.creditNotes.getCreditNotes(invoiceId, clientId) as any[];
+    return Array.isArray(notes) ? notes.map(mapCreditNote) : [];
+  } catch (error) {
+    console.error('Error fetching credit notes:', error);
+    throw error;
+  }
+};
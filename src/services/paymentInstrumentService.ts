@@ -0,0 +1,86 @@
+import { tauriApi } from '@/lib/tauri-api';
+import { formatDateInput } from '@/utils/format';
+
+export type PaymentInstrumentKind = 'check' | 'traite';
+
+// received -> deposited -> cleared or bounced; a bounce takes its payments back
+export type PaymentInstrumentStatus = 'received' | 'deposited' | 'cleared' | 'bounced';
+
+export interface PaymentInstrument {
+  id: string;
+  clientId: string;
+  clientName?: string;
+  kind: PaymentInstrumentKind;
+  number?: string;
+  bank?: string;
+  amount: number;
+  receivedDate: Date;
+  maturityDate: Date;
+  status: PaymentInstrumentStatus;
+  depositBatch?: string;
+  depositedAt?: Date;
+  clearedAt?: Date;
+  bouncedAt?: Date;
+  bounceReason?: string;
+  createdAt: Date;
+  updatedAt?: Date;
+  bulkPaymentId?: string;
+  // Payments made with the instrument, reversed ones included
+  paymentIds: string[];
+}
+
+const optionalDate = (value?: string | null) => (value ? new Date(value) : undefined);
+
+const mapInstrument = (row: any): PaymentInstrument => ({
+  id: row.id,
+  clientId: row.client_id,
+  clientName: row.client_name ?? undefined,
+  kind: row.kind,
+  number: row.number ?? undefined,
+  bank: row.bank ?? undefined,
+  amount: row.amount,
+  receivedDate: new Date(row.received_date),
+  maturityDate: new Date(row.maturity_date),
+  status: row.status,
+  depositBatch: row.deposit_batch ?? undefined,
+  depositedAt: optionalDate(row.deposited_at),
+  clearedAt: optionalDate(row.cleared_at),
+  bouncedAt: optionalDate(row.bounced_at),
+  bounceReason: row.bounce_reason ?? undefined,
+  createdAt: new Date(row.created_at),
+  updatedAt: optionalDate(row.updated_at),
+  bulkPaymentId: row.bulk_payment_id ?? undefined,
+  paymentIds: (row.payments ?? []).map((p: any) => p.id),
+});
+
+export const getPaymentInstruments = async (clientId?: string, status?: PaymentInstrumentStatus): Promise<PaymentInstrument[]> => {
+  const rows = await tauriApi.paymentInstruments.getAll(clientId, status) as any[];
+  return rows.map(mapInstrument);
+};
+
+// Cheques and traites still to be cashed, maturing in the period
+export const getChequesDue = async (from?: Date, to?: Date): Promise<PaymentInstrument[]> => {
+  const rows = await tauriApi.paymentInstruments.getDue(
+    from ? formatDateInput(from) : undefined,
+    to ? formatDateInput(to) : undefined
+  ) as any[];
+  return rows.map(mapInstrument);
+};
+
+export const depositPaymentInstruments = async (ids: string[], depositBatch: string, date: Date): Promise<PaymentInstrument[]> => {
+  const rows = await tauriApi.paymentInstruments.deposit(ids, depositBatch, formatDateInput(date)) as any[];
+  return rows.map(mapInstrument);
+};
+
+export const clearPaymentInstrument = async (id: string, date: Date): Promise<PaymentInstrument> => {
+  return mapInstrument(await tauriApi.paymentInstruments.clear(id, formatDateInput(date)));
+};
+
+export const bouncePaymentInstrument = async (id: string, date: Date, reason?: string): Promise<PaymentInstrument> => {
+  try {
+    return mapInstrument(await tauriApi.paymentInstruments.bounce(id, formatDateInput(date), reason));
+  } catch (error) {
+    console.error('Error recording bounced instrument:', error);
+    throw error;
+  }
+};
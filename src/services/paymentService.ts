@@ -1,5 +1,5 @@
 import { supabase } from '@/integrations/supabase/client';
-import { Payment, PaymentMethodType } from '@/types/index';
+import { ListFilter, Payment, PaymentMethodType } from '@/types/index';
 import { core } from '@tauri-apps/api';
 import { tauriApi } from '@/lib/tauri-api';
 
@@ -49,25 +49,46 @@ const mapDbPaymentToPayment = (dbPayment: DbPaymentResponse): Payment => ({
   checkNumber: dbPayment.check_number || undefined,
 });
 
-export const getPayments = async (): Promise<Payment[]> => {
-  const backendPayments = await tauriApi.payments.getAll() as any[];
-  if (!Array.isArray(backendPayments)) return [];
-  return backendPayments.map((p: any) => ({
-    id: p.id,
-    saleId: p.sale_id,
-    clientId: p.client_id,
-    bulkPaymentId: p.bulk_payment_id,
-    date: new Date(p.date),
-    amount: Number(p.amount),
-    method: p.method,
-    notes: p.notes || undefined,
-    createdAt: new Date(p.created_at),
-    updatedAt: p.updated_at ? new Date(p.updated_at) : undefined,
-    invoiceId: p.invoice_id,
-    isDeleted: !!p.is_deleted,
-    deletedAt: p.deleted_at ? new Date(p.deleted_at) : undefined,
-    checkNumber: p.check_number || undefined,
-  }));
+export interface PaginatedPaymentsResult {
+  rows: Payment[];
+  total: number;
+}
+
+const mapBackendPayment = (p: any): Payment => ({
+  id: p.id,
+  saleId: p.sale_id,
+  clientId: p.client_id,
+  bulkPaymentId: p.bulk_payment_id,
+  date: new Date(p.date),
+  amount: Number(p.amount),
+  method: p.method,
+  notes: p.notes || undefined,
+  createdAt: new Date(p.created_at),
+  updatedAt: p.updated_at ? new Date(p.updated_at) : undefined,
+  invoiceId: p.invoice_id,
+  isDeleted: !!p.is_deleted,
+  deletedAt: p.deleted_at ? new Date(p.deleted_at) : undefined,
+  checkNumber: p.check_number || undefined,
+  instrumentId: p.instrument_id || undefined,
+});
+
+export const getPaymentsPaginated = async (
+  page: number = 1,
+  pageSize: number = 5,
+  filter?: ListFilter
+): Promise<PaginatedPaymentsResult> => {
+  const result = await tauriApi.payments.getPayments(page, pageSize, filter) as any;
+  return {
+    rows: result.rows.map(mapBackendPayment),
+    total: result.total,
+  };
+};
+
+// Every matching payment, for the views that still filter on the client side
+export const getPayments = async (filter?: ListFilter): Promise<Payment[]> => {
+  const first = await getPaymentsPaginated(1, 500, filter);
+  if (first.total <= first.rows.length) return first.rows;
+  return (await getPaymentsPaginated(1, first.total, filter)).rows;
 };
 
 export const getPaymentsBySale = async (saleId: string): Promise<Payment[]> => {
@@ -76,8 +97,7 @@ export const getPaymentsBySale = async (saleId: string): Promise<Payment[]> => {
 };
 
 export const getPaymentsByClient = async (clientId: string): Promise<Payment[]> => {
-  const payments = await getPayments();
-  return payments.filter(p => p.clientId === clientId);
+  return getPayments({ clientId });
 };
 
 export const addPayment = async (payment: Omit<Payment, 'id' | 'createdAt' | 'updatedAt'>): Promise<Payment> => {
@@ -108,6 +128,7 @@ function mapTauriPaymentToPayment(p: any): Payment {
     isDeleted: !!p.is_deleted,
     deletedAt: p.deleted_at ? new Date(p.deleted_at) : undefined,
     checkNumber: p.check_number || undefined,
+    instrumentId: p.instrument_id || undefined,
   };
 }
 
@@ -201,6 +222,7 @@ export const getDeletedPayments = async (): Promise<Payment[]> => {
     isDeleted: !!p.is_deleted,
     deletedAt: p.deleted_at ? new Date(p.deleted_at) : undefined,
     checkNumber: p.check_number || undefined,
+    instrumentId: p.instrument_id || undefined,
   }));
 };
 
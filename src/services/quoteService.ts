@@ -0,0 +1,172 @@
+import { tauriApi } from '@/lib/tauri-api';
+import { ListFilter, Sale, SaleItem } from '@/types/index';
+import { formatDateInput } from '@/utils/format';
+
+export type QuoteStatus = 'draft' | 'sent' | 'accepted' | 'expired';
+
+// Quote lines have the same shape as sale items
+export interface Quote {
+  id: string;
+  quoteNumber: string;
+  clientId: string;
+  date: Date;
+  validUntil: Date;
+  // Draft and sent quotes past validUntil come back as expired
+  status: QuoteStatus;
+  notes?: string;
+  paymentMethod?: string;
+  transportationFee?: number;
+  taxRate: number;
+  taxExemption?: string;
+  totalAmountHT: number;
+  taxAmount: number;
+  stampDuty: number;
+  totalAmountTTC: number;
+  // The sale the quote was converted to
+  saleId?: string;
+  convertedAt?: Date;
+  createdAt: Date;
+  updatedAt?: Date;
+  items: SaleItem[];
+}
+
+export interface QuoteInput {
+  clientId: string;
+  date: Date;
+  validUntil: Date;
+  notes?: string;
+  paymentMethod?: string;
+  transportationFee?: number;
+  taxRate: number;
+  taxExemption?: string;
+  items: Omit<SaleItem, 'id' | 'totalAmountTTC'>[];
+}
+
+export interface PaginatedQuotesResult {
+  rows: Quote[];
+  total: number;
+}
+
+const mapQuote = (row: any): Quote => ({
+  id: row.id,
+  quoteNumber: row.quote_number,
+  clientId: row.client_id,
+  date: new Date(row.date),
+  validUntil: new Date(row.valid_until),
+  status: row.status,
+  notes: row.notes ?? undefined,
+  paymentMethod: row.payment_method ?? undefined,
+  transportationFee: row.transportation_fee ?? undefined,
+  taxRate: row.tax_rate,
+  taxExemption: row.tax_exemption ?? undefined,
+  totalAmountHT: row.total_amount,
+  taxAmount: row.tax_amount,
+  stampDuty: row.stamp_duty,
+  totalAmountTTC: row.total_amount_ttc,
+  saleId: row.sale_id ?? undefined,
+  convertedAt: row.converted_at ? new Date(row.converted_at) : undefined,
+  createdAt: new Date(row.created_at),
+  updatedAt: row.updated_at ? new Date(row.updated_at) : undefined,
+  items: (row.items ?? []).map((item: any) => ({
+    id: item.id,
+    description: item.description,
+    coilRef: item.coil_ref ?? undefined,
+    coilThickness: item.coil_thickness ?? undefined,
+    coilWidth: item.coil_width ?? undefined,
+    topCoatRAL: item.top_coat_ral ?? undefined,
+    backCoatRAL: item.back_coat_ral ?? undefined,
+    coilWeight: item.coil_weight ?? undefined,
+    quantity: item.quantity,
+    pricePerTon: item.price_per_ton,
+    totalAmountHT: item.total_amount,
+    totalAmountTTC: item.total_amount + item.tax_amount,
+    productType: item.product_type,
+    unitCost: item.unit_cost ?? undefined,
+    taxRate: item.tax_rate ?? undefined,
+    taxAmount: item.tax_amount,
+  })),
+});
+
+const toBackendQuote = (quote: QuoteInput) => ({
+  client_id: quote.clientId,
+  date: formatDateInput(quote.date),
+  valid_until: formatDateInput(quote.validUntil),
+  notes: quote.notes ?? null,
+  payment_method: quote.paymentMethod ?? null,
+  transportation_fee: quote.transportationFee ?? null,
+  tax_rate: quote.taxRate,
+  tax_exemption: quote.taxExemption ?? null,
+  items: quote.items.map(item => ({
+    description: item.description,
+    coil_ref: item.coilRef ?? null,
+    coil_thickness: item.coilThickness ?? null,
+    coil_width: item.coilWidth ?? null,
+    top_coat_ral: item.topCoatRAL ?? null,
+    back_coat_ral: item.backCoatRAL ?? null,
+    coil_weight: item.coilWeight ?? null,
+    quantity: item.quantity,
+    price_per_ton: item.pricePerTon,
+    total_amount: item.totalAmountHT ?? 0,
+    product_type: item.productType ?? '',
+    unit_cost: item.unitCost ?? null,
+    tax_rate: item.taxRate ?? null,
+  })),
+});
+
+export const getQuotesPaginated = async (
+  page: number = 1,
+  pageSize: number = 5,
+  filter?: ListFilter,
+  status?: QuoteStatus | 'all'
+): Promise<PaginatedQuotesResult> => {
+  const result = await tauriApi.quotes.getQuotes(page, pageSize, filter, status) as any;
+  return {
+    rows: result.rows.map(mapQuote),
+    total: result.total,
+  };
+};
+
+export const getQuoteById = async (id: string): Promise<Quote | null> => {
+  const row = await tauriApi.quotes.getById(id) as any;
+  return row ? mapQuote(row) : null;
+};
+
+export const createQuote = async (quote: QuoteInput): Promise<Quote> => {
+  try {
+    return mapQuote(await tauriApi.quotes.create(toBackendQuote(quote)));
+  } catch (error) {
+    console.error('Error creating quote:', error);
+    throw error;
+  }
+};
+
+export const updateQuote = async (id: string, quote: QuoteInput): Promise<Quote> => {
+  try {
+    return mapQuote(await tauriApi.quotes.update(id, toBackendQuote(quote)));
+  } catch (error) {
+    console.error('Error updating quote:', error);
+    throw error;
+  }
+};
+
+export const setQuoteStatus = async (id: string, status: QuoteStatus): Promise<Quote> => {
+  return mapQuote(await tauriApi.quotes.setStatus(id, status));
+};
+
+// Creates the sale and its items from the quote; the sale is dated now unless a date is given
+export const convertQuoteToSale = async (id: string, date?: Date, allowOversell?: boolean): Promise<Sale> => {
+  try {
+    return await tauriApi.quotes.convertToSale(id, date?.toISOString(), allowOversell) as Sale;
+  } catch (error) {
+    console.error('Error converting quote:', error);
+    throw error;
+  }
+};
+
+export const deleteQuote = async (id: string): Promise<void> => {
+  await tauriApi.quotes.delete(id);
+};
+
+export const restoreQuote = async (id: string): Promise<void> => {
+  await tauriApi.quotes.restore(id);
+};
@@ -2,7 +2,7 @@ import { v4 as uuidv4 } from 'uuid';
 import type { Sale, Invoice } from '@/types/index';
 import type { Client, Payment, BulkPayment, CreditTransaction, AppSettings } from '@/types/index';
 import { tauriApi } from '@/lib/tauri-api';
-import { Sale, SaleItem } from '@/types/index';
+import { Sale, SaleItem, ListFilter } from '@/types/index';
 
 // Mock data storage
 let mockClients: Client[] = [
@@ -323,6 +323,7 @@ export const localDB = {
           payment_method: sale.paymentMethod ?? null,
           transportation_fee: typeof sale.transportationFee === 'number' ? sale.transportationFee : 0,
           tax_rate: typeof sale.taxRate === 'number' ? sale.taxRate : 0,
+          tax_exemption: sale.taxExemption ?? null,
           is_paid: sale.isPaid ?? false,
           paid_at: paidAtString,
           items: (sale.items || []).map(item => ({
@@ -336,6 +337,8 @@ export const localDB = {
             quantity: typeof item.quantity === 'number' ? item.quantity : 0,
             price_per_ton: typeof item.pricePerTon === 'number' ? item.pricePerTon : 0,
             total_amount: typeof item.totalAmountHT === 'number' ? item.totalAmountHT : 0,
+            unit_cost: typeof item.unitCost === 'number' ? item.unitCost : null,
+            tax_rate: typeof item.taxRate === 'number' ? item.taxRate : null,
           }))
         };
         console.log('[createSale] Payload to backend:', JSON.stringify(backendSale, null, 2));
@@ -659,6 +662,10 @@ export const getDeletedSales = async (): Promise<Sale[]> => {
       pricePerTon: Number(item.price_per_ton),
       totalAmountHT: Number(item.total_amount_ht ?? item.total_amount),
       totalAmountTTC: Number(item.total_amount_ttc),
+      unitCost: item.unit_cost ?? undefined,
+      costAmount: item.cost_amount ?? undefined,
+      taxRate: item.tax_rate ?? undefined,
+      taxAmount: item.tax_amount ?? undefined,
       createdAt: undefined,
       updatedAt: undefined,
       productType:
@@ -677,10 +684,16 @@ export const getDeletedSales = async (): Promise<Sale[]> => {
     paymentMethod: sale.payment_method,
     transportationFee: sale.transportation_fee,
     taxRate: sale.tax_rate,
+    taxAmount: sale.tax_amount,
+    stampDuty: sale.stamp_duty,
+    taxExemption: sale.tax_exemption ?? undefined,
     createdAt: new Date(sale.created_at),
     updatedAt: sale.updated_at ? new Date(sale.updated_at) : undefined,
     isPaid: !!sale.is_paid,
-    paidAt: sale.paid_at ? new Date(sale.paid_at) : undefined
+    paidAt: sale.paid_at ? new Date(sale.paid_at) : undefined,
+    amountPaid: sale.amount_paid,
+    balanceDue: sale.balance_due,
+    paymentStatus: sale.payment_status
   }));
 };
 
@@ -736,6 +749,7 @@ export const createSale = async (sale: Omit<Sale, 'id' | 'createdAt' | 'updatedA
       payment_method: sale.paymentMethod ?? null,
       transportation_fee: typeof sale.transportationFee === 'number' ? sale.transportationFee : 0,
       tax_rate: typeof sale.taxRate === 'number' ? sale.taxRate : 0,
+      tax_exemption: sale.taxExemption ?? null,
       is_paid: sale.isPaid ?? false,
       paid_at: paidAtString,
       items: (sale.items || []).map(item => ({
@@ -749,6 +763,8 @@ export const createSale = async (sale: Omit<Sale, 'id' | 'createdAt' | 'updatedA
         quantity: typeof item.quantity === 'number' ? item.quantity : 0,
         price_per_ton: typeof item.pricePerTon === 'number' ? item.pricePerTon : 0,
         total_amount: typeof item.totalAmountHT === 'number' ? item.totalAmountHT : 0,
+        unit_cost: typeof item.unitCost === 'number' ? item.unitCost : null,
+        tax_rate: typeof item.taxRate === 'number' ? item.taxRate : null,
       }))
     };
     console.log('[createSale] Payload to backend:', JSON.stringify(backendSale, null, 2));
@@ -800,7 +816,9 @@ export const updateSale = async (id: string, sale: Partial<Sale>): Promise<Sale>
       coil_weight: item.coilWeight ?? null,
       quantity: item.quantity,
       price_per_ton: item.pricePerTon,
-      total_amount: item.totalAmountHT
+      total_amount: item.totalAmountHT,
+      unit_cost: item.unitCost ?? null,
+      tax_rate: item.taxRate ?? null
     };
   });
   function flattenAndClean(obj: Record<string, any>) {
@@ -825,6 +843,7 @@ export const updateSale = async (id: string, sale: Partial<Sale>): Promise<Sale>
     payment_method: merged.paymentMethod,
     transportation_fee: merged.transportationFee,
     tax_rate: merged.taxRate,
+    tax_exemption: merged.taxExemption ?? null,
     is_paid: merged.isPaid ?? false,
     paid_at: merged.paidAt ? (merged.paidAt instanceof Date ? merged.paidAt.toISOString() : merged.paidAt) : null,
     items,
@@ -893,6 +912,11 @@ function mapDbSaleToSale(row: any): Sale {
           totalAmountHT: item.total_amount, // backend uses total_amount
           totalAmountTTC: item.total_amount, // fallback, adjust if needed
           productType: item.product_type,
+          unitCost: item.unit_cost ?? undefined,
+          costAmount: item.cost_amount ?? undefined,
+          taxRate: item.tax_rate ?? undefined,
+          taxAmount: item.tax_amount ?? undefined,
+          delivered: item.delivered ?? undefined,
         }))
       : [],
     totalAmountHT: row.total_amount,
@@ -903,20 +927,29 @@ function mapDbSaleToSale(row: any): Sale {
     paymentMethod: row.payment_method,
     transportationFee: row.transportation_fee,
     taxRate: row.tax_rate,
+    taxAmount: row.tax_amount,
+    stampDuty: row.stamp_duty,
+    taxExemption: row.tax_exemption ?? undefined,
     createdAt: row.created_at ? new Date(row.created_at) : new Date(),
     updatedAt: row.updated_at ? new Date(row.updated_at) : undefined,
     isDeleted: false,
     deletedAt: undefined,
     isPaid: row.is_paid,
     paidAt: row.paid_at ? new Date(row.paid_at) : undefined,
+    amountPaid: row.amount_paid,
+    balanceDue: row.balance_due,
+    paymentStatus: row.payment_status,
+    quoteId: row.quote_id ?? undefined,
+    deliveryStatus: row.delivery_status ?? undefined,
   };
 }
 
 export const getSalesPaginated = async (
   page: number = 1,
-  pageSize: number = 5
+  pageSize: number = 5,
+  filter?: ListFilter
 ): Promise<PaginatedSalesResult> => {
-  const result = await tauriApi.sales.getSales(page, pageSize) as any;
+  const result = await tauriApi.sales.getSales(page, pageSize, filter) as any;
   return {
     rows: result.rows.map((row: any) => mapDbSaleToSale(row)),
     total: result.total,
@@ -1,5 +1,5 @@
 // Shared types
-export type PaymentMethodType = 'cash' | 'bank_transfer' | 'check' | 'term';
+export type PaymentMethodType = 'cash' | 'bank_transfer' | 'check' | 'traite' | 'term';
 
 // Domain types
 export interface SaleItem {
@@ -9,7 +9,7 @@ export interface SoldProductsFilter {
   width?: number[];
   unitPriceMin?: number;
   unitPriceMax?: number;
-  paymentStatus?: 'all' | 'paid' | 'unpaid';
+  paymentStatus?: 'all' | 'unpaid' | 'partial' | 'paid' | 'overpaid';
 }
 
 export interface SoldProduct {
@@ -35,6 +35,8 @@ export interface SoldProductsSummary {
   uniqueProducts: number;
   uniqueClients: number;
   averageOrderValue: number;
+  vatBreakdown: { taxRate: number; taxableAmount: number; taxAmount: number }[];
+  totalStampDuty: number;
 }
 
 export interface SoldProductsAnalyticsResult {
@@ -112,6 +114,12 @@ export const getSoldProductsSummary = async (filters: SoldProductsFilter): Promi
       uniqueProducts: data.unique_products ?? 0,
       uniqueClients: data.unique_clients ?? 0,
       averageOrderValue: data.average_order_value ?? 0,
+      vatBreakdown: (data.vat_breakdown ?? []).map((v: any) => ({
+        taxRate: v.tax_rate,
+        taxableAmount: v.taxable_amount,
+        taxAmount: v.tax_amount,
+      })),
+      totalStampDuty: data.total_stamp_duty ?? 0,
     };
   } catch (e) {
     throw new Error((e as Error).message || 'Failed to fetch sold products summary');
@@ -121,4 +129,87 @@ export const getSoldProductsSummary = async (filters: SoldProductsFilter): Promi
 export async function getUniqueThicknessWidth(): Promise<{ thicknesses: number[]; widths: number[] }> {
   const [thicknesses, widths] = await tauriApi.analytics.getUniqueThicknessWidth() as [number[], number[]];
   return { thicknesses, widths };
-} 
\ No newline at end of file
+} 
+export interface VatLine {
+  taxRate: number;
+  taxableAmount: number;
+  taxAmount: number;
+}
+
+export interface VatReportRow {
+  kind: 'invoice' | 'sale' | 'payment';
+  id: string;
+  reference?: string;
+  date: string;
+  clientId: string;
+  clientName: string;
+  clientNif?: string;
+  taxableAmount: number;
+  taxAmount: number;
+  exemptAmount: number;
+  stampDuty: number;
+  vatByRate: VatLine[];
+}
+
+export interface VatReport {
+  period: string;
+  basis: 'invoiced' | 'cash';
+  startDate: string;
+  endDate: string;
+  companyName: string;
+  nif?: string;
+  nis?: string;
+  ai?: string;
+  rc?: string;
+  vatByRate: VatLine[];
+  taxableAmount: number;
+  taxAmount: number;
+  exemptAmount: number;
+  stampDuty: number;
+  rows: VatReportRow[];
+}
+
+const toVatLines = (lines: any[] | undefined): VatLine[] =>
+  (lines ?? []).map((v: any) => ({
+    taxRate: v.tax_rate,
+    taxableAmount: v.taxable_amount,
+    taxAmount: v.tax_amount,
+  }));
+
+export const getVatReport = async (period: string, basis: 'invoiced' | 'cash'): Promise<VatReport> => {
+  try {
+    const data = await tauriApi.analytics.getVatReport(period, basis) as any;
+    return {
+      period: data.period,
+      basis: data.basis,
+      startDate: data.start_date,
+      endDate: data.end_date,
+      companyName: data.company_name ?? '',
+      nif: data.nif ?? undefined,
+      nis: data.nis ?? undefined,
+      ai: data.ai ?? undefined,
+      rc: data.rc ?? undefined,
+      vatByRate: toVatLines(data.vat_by_rate),
+      taxableAmount: data.taxable_amount ?? 0,
+      taxAmount: data.tax_amount ?? 0,
+      exemptAmount: data.exempt_amount ?? 0,
+      stampDuty: data.stamp_duty ?? 0,
+      rows: (data.rows ?? []).map((r: any) => ({
+        kind: r.kind,
+        id: r.id,
+        reference: r.reference ?? undefined,
+        date: r.date,
+        clientId: r.client_id,
+        clientName: r.client_name,
+        clientNif: r.client_nif ?? undefined,
+        taxableAmount: r.taxable_amount,
+        taxAmount: r.tax_amount,
+        exemptAmount: r.exempt_amount,
+        stampDuty: r.stamp_duty,
+        vatByRate: toVatLines(r.vat_by_rate),
+      })),
+    };
+  } catch (e) {
+    throw new Error((e as Error).message || 'Failed to fetch VAT report');
+  }
+};
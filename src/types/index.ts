@@ -1,5 +1,5 @@
 // Define shared types
-export type PaymentMethodType = 'cash' | 'bank_transfer' | 'check' | 'term';
+export type PaymentMethodType = 'cash' | 'bank_transfer' | 'check' | 'traite' | 'term';
 
 // Define the type for a business client
 export interface Client {
@@ -35,6 +35,33 @@ export interface SaleItem {
   totalAmountHT: number;
   totalAmountTTC: number;
   productType?: string;
+  // Cost per pricing unit, entered or taken from coil stock
+  unitCost?: number;
+  costAmount?: number;
+  // VAT rate of this line when it differs from the sale's, and its VAT
+  taxRate?: number;
+  taxAmount?: number;
+  // Tons (coils, steel slitting) or units carried by delivery notes so far
+  delivered?: number;
+}
+
+export type PaymentStatus = 'unpaid' | 'partial' | 'paid' | 'overpaid';
+
+export type DeliveryStatus = 'undelivered' | 'partial' | 'delivered';
+
+// Search, filter and sort options accepted by the paginated list commands
+export interface ListFilter {
+  search?: string;
+  startDate?: string;
+  endDate?: string;
+  clientId?: string;
+  supplierId?: string;
+  amountMin?: number;
+  amountMax?: number;
+  paymentStatus?: PaymentStatus | 'all';
+  isInvoiced?: boolean;
+  sortBy?: string;
+  sortDirection?: 'asc' | 'desc';
 }
 
 // Define the type for a sale
@@ -51,6 +78,18 @@ export interface Sale {
   paymentMethod?: PaymentMethodType;
   transportationFee?: number;
   taxRate: number;
+  taxAmount?: number;
+  // Droit de timbre on cash sales, included in totalAmountTTC
+  stampDuty?: number;
+  // Reason the sale is exempt from VAT (export, ...)
+  taxExemption?: string;
+  amountPaid?: number;
+  balanceDue?: number;
+  paymentStatus?: PaymentStatus;
+  // The quote the sale was converted from
+  quoteId?: string;
+  // Only delivered sales can be invoiced
+  deliveryStatus?: DeliveryStatus;
   createdAt: Date;
   updatedAt?: Date;
   isDeleted: boolean;
@@ -70,6 +109,10 @@ export interface Invoice {
   taxRate: number;
   isPaid: boolean;
   paidAt?: Date;
+  creditedAmount?: number;
+  amountPaid?: number;
+  balanceDue?: number;
+  paymentStatus?: PaymentStatus;
   paymentMethod?: PaymentMethodType;
   transportationFee?: number;
   transportationFeeTTC?: number;
@@ -89,7 +132,7 @@ export interface Payment {
   bulkPaymentId?: string;
   amount: number;
   date: Date;
-  method: 'cash' | 'bank_transfer' | 'check' | 'deferred' | 'term';
+  method: 'cash' | 'bank_transfer' | 'check' | 'traite' | 'credit' | 'deferred' | 'term';
   notes?: string;
   createdAt: Date;
   updatedAt?: Date;
@@ -97,6 +140,8 @@ export interface Payment {
   isDeleted: boolean;
   deletedAt?: Date;
   checkNumber?: string;
+  // The cheque or traite followed until the bank clears it
+  instrumentId?: string;
 }
 
 // Define the type for bulk payments
@@ -124,6 +169,7 @@ export interface DashboardStats {
   new_clients: number;
   overdue_invoices: number;
   unpaid_invoices: number;
+  total_payable: number;
 }
 
 // CompanyProfile is the canonical type for company info
@@ -1,6 +1,7 @@
 import * as XLSX from 'xlsx';
 import { Sale, SaleItem, Client } from '../types';
 import { formatDate, formatCurrency } from './format';
+import type { VatLine, VatReport } from '../services/soldProductsService';
 
 interface ExcelReportOptions {
   dateRange?: {
@@ -287,4 +288,50 @@ export const generateCustomerAnalyticsReport = (sales: Sale[], clients: Client[]
   // Generate buffer
   const excelBuffer = XLSX.write(wb, { bookType: 'xlsx', type: 'array' });
   return new Blob([excelBuffer], { type: 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet' });
-}; 
\ No newline at end of file
+}; 
+export const generateVatReport = (report: VatReport) => {
+  const rates = report.vatByRate.map(line => line.taxRate);
+  const rateLabel = (rate: number) => `${Math.round(rate * 10000) / 100}%`;
+  const rateColumns = (lines: VatLine[]) => rates.flatMap(rate => {
+    const line = lines.find(l => l.taxRate === rate);
+    return [line?.taxableAmount ?? 0, line?.taxAmount ?? 0];
+  });
+
+  // Company header for the G50 return, then the drill-down table
+  const rows: (string | number)[][] = [
+    ['Company', report.companyName],
+    ['NIF', report.nif || ''],
+    ['NIS', report.nis || ''],
+    ['AI', report.ai || ''],
+    ['RC', report.rc || ''],
+    ['Period', report.period],
+    ['Basis', report.basis === 'cash' ? 'Cash' : 'Invoiced'],
+    ['From', report.startDate],
+    ['To', report.endDate],
+    [],
+    [
+      'Type', 'Reference', 'Date', 'Client', 'Client NIF', 'Taxable HT', 'TVA', 'Exempt HT', 'Stamp Duty',
+      ...rates.flatMap(rate => [`HT ${rateLabel(rate)}`, `TVA ${rateLabel(rate)}`])
+    ],
+    ...report.rows.map(row => [
+      row.kind, row.reference || '', row.date, row.clientName, row.clientNif || '',
+      row.taxableAmount, row.taxAmount, row.exemptAmount, row.stampDuty,
+      ...rateColumns(row.vatByRate)
+    ]),
+    [
+      'Total', '', '', '', '',
+      report.taxableAmount, report.taxAmount, report.exemptAmount, report.stampDuty,
+      ...rateColumns(report.vatByRate)
+    ]
+  ];
+
+  const wb = XLSX.utils.book_new();
+  const ws = XLSX.utils.aoa_to_sheet(rows);
+  ws['!cols'] = (rows[10] as string[]).map((header, i) => ({
+    wch: Math.max(header.length, ...rows.slice(11).map(row => String(row[i] ?? '').length))
+  }));
+  XLSX.utils.book_append_sheet(wb, ws, 'TVA');
+
+  const excelBuffer = XLSX.write(wb, { bookType: 'xlsx', type: 'array' });
+  return new Blob([excelBuffer], { type: 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet' });
+};